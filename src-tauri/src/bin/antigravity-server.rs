@@ -0,0 +1,6 @@
+//! 纯服务端入口：反代服务 + 管理 API，不依赖 Tauri / GTK / WebKit
+//! 构建: cargo build --release --no-default-features --bin antigravity-server
+
+fn main() {
+    antigravity_tools_lib::run_headless()
+}
@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+use crate::modules::admin_key_db::{self, AdminKey, AdminScope, CreatedAdminKey};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateAdminKeyRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub scopes: Vec<AdminScope>,
+}
+
+/// 列出所有管理密钥
+#[cfg_attr(feature = "desktop", tauri::command)]
+pub async fn list_admin_keys() -> Result<Vec<AdminKey>, String> {
+    admin_key_db::list_keys()
+}
+
+/// 创建管理密钥 (明文密钥仅在返回值中出现一次)
+#[cfg_attr(feature = "desktop", tauri::command)]
+pub async fn create_admin_key(request: CreateAdminKeyRequest) -> Result<CreatedAdminKey, String> {
+    admin_key_db::create_key(&request.name, request.description, &request.scopes)
+}
+
+/// 吊销管理密钥
+#[cfg_attr(feature = "desktop", tauri::command)]
+pub async fn revoke_admin_key(id: String) -> Result<(), String> {
+    admin_key_db::revoke_key(&id)
+}
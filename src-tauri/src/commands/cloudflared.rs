@@ -1,5 +1,8 @@
+#[cfg(feature = "desktop")]
 use tauri::State;
-use crate::modules::cloudflared::{CloudflaredConfig, CloudflaredManager, CloudflaredStatus};
+use crate::modules::cloudflared::CloudflaredManager;
+#[cfg(feature = "desktop")]
+use crate::modules::cloudflared::{CloudflaredConfig, CloudflaredStatus};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
@@ -28,6 +31,7 @@ impl CloudflaredState {
 }
 
 /// 检查cloudflared是否已安装
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn cloudflared_check(
     state: State<'_, CloudflaredState>,
@@ -50,6 +54,7 @@ pub async fn cloudflared_check(
 }
 
 /// 安装cloudflared
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn cloudflared_install(
     state: State<'_, CloudflaredState>,
@@ -65,6 +70,7 @@ pub async fn cloudflared_install(
 }
 
 /// 启动cloudflared隧道
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn cloudflared_start(
     state: State<'_, CloudflaredState>,
@@ -81,6 +87,7 @@ pub async fn cloudflared_start(
 }
 
 /// 停止cloudflared隧道
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn cloudflared_stop(
     state: State<'_, CloudflaredState>,
@@ -96,6 +103,7 @@ pub async fn cloudflared_stop(
 }
 
 /// 获取cloudflared状态
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn cloudflared_get_status(
     state: State<'_, CloudflaredState>,
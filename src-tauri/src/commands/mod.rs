@@ -1,28 +1,38 @@
+#[cfg(feature = "desktop")]
 use crate::models::{Account, AppConfig, QuotaData};
 use crate::modules;
-use tauri::{Emitter, Manager};
+#[cfg(feature = "desktop")]
+use tauri::Manager;
+#[cfg(feature = "desktop")]
 use tauri_plugin_opener::OpenerExt;
 
 // 导出 proxy 命令
 pub mod proxy;
 // 导出 autostart 命令
+#[cfg(feature = "desktop")]
 pub mod autostart;
 // 导出 cloudflared 命令
 pub mod cloudflared;
 // 导出 security 命令 (IP 监控)
+#[cfg(feature = "desktop")]
 pub mod security;
 // 导出 proxy_pool 命令
+#[cfg(feature = "desktop")]
 pub mod proxy_pool;
 // 导出 user_token 命令
 pub mod user_token;
+// 导出 admin_key 命令 (具名管理密钥)
+pub mod admin_key;
 
 /// 列出所有账号
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn list_accounts() -> Result<Vec<Account>, String> {
     modules::list_accounts()
 }
 
 /// 添加账号
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn add_account(
     app: tauri::AppHandle,
@@ -49,6 +59,7 @@ pub async fn add_account(
 
 /// 删除账号
 /// 删除账号
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn delete_account(
     app: tauri::AppHandle,
@@ -67,6 +78,7 @@ pub async fn delete_account(
 }
 
 /// 批量删除账号
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn delete_accounts(
     app: tauri::AppHandle,
@@ -93,6 +105,7 @@ pub async fn delete_accounts(
 
 /// 重新排序账号列表
 /// 根据传入的账号ID数组顺序更新账号排列
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn reorder_accounts(
     proxy_state: tauri::State<'_, crate::commands::proxy::ProxyServiceState>,
@@ -112,7 +125,37 @@ pub async fn reorder_accounts(
     Ok(())
 }
 
+/// 列出账号分组
+#[cfg(feature = "desktop")]
+#[tauri::command]
+pub async fn list_account_groups() -> Result<Vec<crate::models::AccountGroup>, String> {
+    modules::account::list_account_groups()
+}
+
+/// 创建或更新账号分组 (成员列表整体替换)
+#[cfg(feature = "desktop")]
+#[tauri::command]
+pub async fn save_account_group(
+    request: crate::models::AccountGroup,
+) -> Result<crate::models::AccountGroup, String> {
+    modules::logger::log_info(&format!(
+        "保存账号分组: {} ({} 个账号)",
+        request.name,
+        request.account_ids.len()
+    ));
+    modules::account::save_account_group(request)
+}
+
+/// 删除账号分组
+#[cfg(feature = "desktop")]
+#[tauri::command]
+pub async fn delete_account_group(name: String) -> Result<(), String> {
+    modules::logger::log_info(&format!("删除账号分组: {}", name));
+    modules::account::delete_account_group(&name)
+}
+
 /// 切换账号
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn switch_account(
     app: tauri::AppHandle,
@@ -135,6 +178,7 @@ pub async fn switch_account(
 }
 
 /// 获取当前账号
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn get_current_account() -> Result<Option<Account>, String> {
     // println!("🚀 Backend Command: get_current_account called"); // Commented out to reduce noise for frequent calls, relies on frontend log for frequency
@@ -153,14 +197,17 @@ pub async fn get_current_account() -> Result<Option<Account>, String> {
 }
 
 /// 导出账号（包含 refresh_token）
+#[cfg(feature = "desktop")]
 use crate::models::AccountExportResponse;
 
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn export_accounts(account_ids: Vec<String>) -> Result<AccountExportResponse, String> {
     modules::account::export_accounts_by_ids(&account_ids)
 }
 
 /// 内部辅助功能：在添加或导入账号后自动刷新一次额度
+#[cfg(feature = "desktop")]
 async fn internal_refresh_account_quota(
     app: &tauri::AppHandle,
     account: &mut Account,
@@ -184,6 +231,7 @@ async fn internal_refresh_account_quota(
 }
 
 /// 查询账号配额
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn fetch_account_quota(
     app: tauri::AppHandle,
@@ -215,9 +263,9 @@ pub async fn fetch_account_quota(
 pub use modules::account::RefreshStats;
 
 /// 刷新所有账号配额 (内部实现)
+#[cfg_attr(not(feature = "desktop"), allow(dead_code))] // 无桌面构建中仅由调度器引用
 pub async fn refresh_all_quotas_internal(
     proxy_state: &crate::commands::proxy::ProxyServiceState,
-    app_handle: Option<tauri::AppHandle>,
 ) -> Result<RefreshStats, String> {
     let stats = modules::account::refresh_all_quotas_logic().await?;
 
@@ -227,24 +275,22 @@ pub async fn refresh_all_quotas_internal(
         let _ = instance.token_manager.reload_all_accounts().await;
     }
 
-    // 发送全局刷新事件给 UI (如果需要)
-    if let Some(handle) = app_handle {
-        use tauri::Emitter;
-        let _ = handle.emit("accounts://refreshed", ());
-    }
+    // 发送全局刷新事件给 UI
+    modules::events::emit("accounts://refreshed", ());
 
     Ok(stats)
 }
 
 /// 刷新所有账号配额 (Tauri Command)
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn refresh_all_quotas(
     proxy_state: tauri::State<'_, crate::commands::proxy::ProxyServiceState>,
-    app_handle: tauri::AppHandle,
 ) -> Result<RefreshStats, String> {
-    refresh_all_quotas_internal(&proxy_state, Some(app_handle)).await
+    refresh_all_quotas_internal(&proxy_state).await
 }
 /// 获取设备指纹（当前 storage.json + 账号绑定）
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn get_device_profiles(
     account_id: String,
@@ -253,6 +299,7 @@ pub async fn get_device_profiles(
 }
 
 /// 绑定设备指纹（capture: 采集当前；generate: 生成新指纹），并写入 storage.json
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn bind_device_profile(
     account_id: String,
@@ -262,12 +309,14 @@ pub async fn bind_device_profile(
 }
 
 /// 预览生成一个指纹（不落盘）
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn preview_generate_profile() -> Result<crate::models::DeviceProfile, String> {
     Ok(crate::modules::device::generate_profile())
 }
 
 /// 使用给定指纹直接绑定
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn bind_device_profile_with_profile(
     account_id: String,
@@ -277,6 +326,7 @@ pub async fn bind_device_profile_with_profile(
 }
 
 /// 将账号已绑定的指纹应用到 storage.json
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn apply_device_profile(
     account_id: String,
@@ -285,12 +335,14 @@ pub async fn apply_device_profile(
 }
 
 /// 恢复最早的 storage.json 备份（近似“原始”状态）
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn restore_original_device() -> Result<String, String> {
     modules::restore_original_device()
 }
 
 /// 列出指纹版本
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn list_device_versions(
     account_id: String,
@@ -299,6 +351,7 @@ pub async fn list_device_versions(
 }
 
 /// 按版本恢复指纹
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn restore_device_version(
     account_id: String,
@@ -308,12 +361,14 @@ pub async fn restore_device_version(
 }
 
 /// 删除历史指纹（baseline 不可删）
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn delete_device_version(account_id: String, version_id: String) -> Result<(), String> {
     modules::delete_device_version(&account_id, &version_id)
 }
 
 /// 打开设备存储目录
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn open_device_folder(app: tauri::AppHandle) -> Result<(), String> {
     let dir = modules::device::get_storage_dir()?;
@@ -327,66 +382,46 @@ pub async fn open_device_folder(app: tauri::AppHandle) -> Result<(), String> {
 }
 
 /// 加载配置
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn load_config() -> Result<AppConfig, String> {
     modules::load_app_config()
 }
 
+/// 配置预检：返回字段级校验结果与相对运行中配置的差异，不写盘
+#[cfg(feature = "desktop")]
+#[tauri::command]
+pub async fn validate_config(
+    config: AppConfig,
+) -> Result<modules::config_validation::ConfigValidationReport, String> {
+    Ok(modules::config_validation::dry_run(
+        &crate::proxy::config_reload::running_config(),
+        &config,
+        &crate::proxy::config_reload::known_models(),
+    ))
+}
+
 /// 保存配置
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn save_config(
-    app: tauri::AppHandle,
     proxy_state: tauri::State<'_, crate::commands::proxy::ProxyServiceState>,
     config: AppConfig,
 ) -> Result<(), String> {
+    let issues = crate::proxy::config_reload::validate(&config);
+    if modules::config_validation::has_errors(&issues) {
+        return Err(modules::config_validation::summarize_errors(&issues));
+    }
+
     modules::save_app_config(&config)?;
 
     // 通知托盘配置已更新
-    let _ = app.emit("config://updated", ());
+    modules::events::emit("config://updated", ());
 
     // 热更新正在运行的服务
     let instance_lock = proxy_state.instance.read().await;
     if let Some(instance) = instance_lock.as_ref() {
-        // 更新模型映射
-        instance.axum_server.update_mapping(&config.proxy).await;
-        // 更新上游代理
-        instance
-            .axum_server
-            .update_proxy(config.proxy.upstream_proxy.clone())
-            .await;
-        // 更新安全策略 (auth)
-        instance.axum_server.update_security(&config.proxy).await;
-        // 更新 z.ai 配置
-        instance.axum_server.update_zai(&config.proxy).await;
-        // 更新实验性配置
-        instance
-            .axum_server
-            .update_experimental(&config.proxy)
-            .await;
-        // 更新调试日志配置
-        instance
-            .axum_server
-            .update_debug_logging(&config.proxy)
-            .await;
-        // [NEW] 更新 User-Agent 配置
-        instance.axum_server.update_user_agent(&config.proxy).await;
-        // 更新 Thinking Budget 配置
-        crate::proxy::update_thinking_budget_config(config.proxy.thinking_budget.clone());
-        // [NEW] 更新全局系统提示词配置
-        crate::proxy::update_global_system_prompt_config(config.proxy.global_system_prompt.clone());
-        // [NEW] 更新全局图像思维模式配置
-        crate::proxy::update_image_thinking_mode(config.proxy.image_thinking_mode.clone());
-        // 更新代理池配置
-        instance
-            .axum_server
-            .update_proxy_pool(config.proxy.proxy_pool.clone())
-            .await;
-        // 更新熔断配置
-        instance
-            .token_manager
-            .update_circuit_breaker_config(config.circuit_breaker.clone())
-            .await;
-        tracing::debug!("已同步热更新反代服务配置");
+        instance.axum_server.apply_config(&config).await;
     }
 
     Ok(())
@@ -394,6 +429,7 @@ pub async fn save_config(
 
 // --- OAuth 命令 ---
 
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn start_oauth_login(app_handle: tauri::AppHandle, oauth_client_key: Option<String>) -> Result<Account, String> {
     modules::logger::log_info("开始 OAuth 授权流程...");
@@ -416,6 +452,7 @@ pub async fn start_oauth_login(app_handle: tauri::AppHandle, oauth_client_key: O
 }
 
 /// 完成 OAuth 授权（不自动打开浏览器）
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn complete_oauth_login(app_handle: tauri::AppHandle) -> Result<Account, String> {
     modules::logger::log_info("完成 OAuth 授权流程 (manual)...");
@@ -438,6 +475,7 @@ pub async fn complete_oauth_login(app_handle: tauri::AppHandle) -> Result<Accoun
 }
 
 /// 预生成 OAuth 授权链接 (不打开浏览器)
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn prepare_oauth_url(app_handle: tauri::AppHandle, oauth_client_key: Option<String>) -> Result<String, String> {
     let service = modules::account_service::AccountService::new(
@@ -446,6 +484,7 @@ pub async fn prepare_oauth_url(app_handle: tauri::AppHandle, oauth_client_key: O
     service.prepare_oauth_url(oauth_client_key).await
 }
 
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn cancel_oauth_login() -> Result<(), String> {
     modules::oauth_server::cancel_oauth_flow();
@@ -453,22 +492,26 @@ pub async fn cancel_oauth_login() -> Result<(), String> {
 }
 
 /// 手动提交 OAuth Code (用于 Docker/远程环境无法自动回调时)
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn submit_oauth_code(code: String, state: Option<String>) -> Result<(), String> {
     modules::logger::log_info("收到手动提交 OAuth Code 请求");
     modules::oauth_server::submit_oauth_code(code, state).await
 }
 
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn list_oauth_clients() -> Result<Vec<crate::modules::oauth::OAuthClientDescriptor>, String> {
     crate::modules::oauth::list_oauth_clients()
 }
 
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn get_active_oauth_client() -> Result<String, String> {
     crate::modules::oauth::get_active_oauth_client_key()
 }
 
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn set_active_oauth_client(client_key: String) -> Result<(), String> {
     crate::modules::oauth::set_active_oauth_client_key(&client_key)
@@ -476,6 +519,7 @@ pub async fn set_active_oauth_client(client_key: String) -> Result<(), String> {
 
 // --- 导入命令 ---
 
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn import_v1_accounts(
     app: tauri::AppHandle,
@@ -494,6 +538,7 @@ pub async fn import_v1_accounts(
     Ok(accounts)
 }
 
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn import_from_db(
     app: tauri::AppHandle,
@@ -518,6 +563,7 @@ pub async fn import_from_db(
     Ok(account)
 }
 
+#[cfg(feature = "desktop")]
 #[tauri::command]
 #[allow(dead_code)]
 pub async fn import_custom_db(
@@ -544,6 +590,7 @@ pub async fn import_custom_db(
     Ok(account)
 }
 
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn sync_account_from_db(
     app: tauri::AppHandle,
@@ -581,6 +628,7 @@ pub async fn sync_account_from_db(
     Ok(Some(account))
 }
 
+#[cfg(feature = "desktop")]
 fn validate_path(path: &str) -> Result<(), String> {
     if path.contains("..") {
         return Err("非法路径: 不允许目录遍历".to_string());
@@ -610,6 +658,7 @@ fn validate_path(path: &str) -> Result<(), String> {
 }
 
 /// 保存文本文件 (绕过前端 Scope 限制)
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn save_text_file(path: String, content: String) -> Result<(), String> {
     validate_path(&path)?;
@@ -617,6 +666,7 @@ pub async fn save_text_file(path: String, content: String) -> Result<(), String>
 }
 
 /// 读取文本文件 (绕过前端 Scope 限制)
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn read_text_file(path: String) -> Result<String, String> {
     validate_path(&path)?;
@@ -624,20 +674,20 @@ pub async fn read_text_file(path: String) -> Result<String, String> {
 }
 
 /// 清理日志缓存
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn clear_log_cache() -> Result<(), String> {
     modules::logger::clear_logs()
 }
 
 /// 清理 Antigravity 应用缓存
 /// 用于解决登录失败、版本验证错误等问题
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn clear_antigravity_cache() -> Result<modules::cache::ClearResult, String> {
     modules::cache::clear_antigravity_cache(None)
 }
 
 /// 获取 Antigravity 缓存路径列表（用于预览）
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_antigravity_cache_paths() -> Result<Vec<String>, String> {
     Ok(modules::cache::get_existing_cache_paths()
         .into_iter()
@@ -646,7 +696,7 @@ pub async fn get_antigravity_cache_paths() -> Result<Vec<String>, String> {
 }
 
 /// 打开数据目录
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn open_data_folder() -> Result<(), String> {
     let path = modules::account::get_data_dir()?;
 
@@ -680,6 +730,7 @@ pub async fn open_data_folder() -> Result<(), String> {
 }
 
 /// 获取数据目录绝对路径
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn get_data_dir_path() -> Result<String, String> {
     let path = modules::account::get_data_dir()?;
@@ -687,12 +738,14 @@ pub async fn get_data_dir_path() -> Result<String, String> {
 }
 
 /// 显示主窗口
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn show_main_window(window: tauri::Window) -> Result<(), String> {
     window.show().map_err(|e| e.to_string())
 }
 
 /// 设置窗口主题（用于同步 Windows 标题栏按钮颜色）
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn set_window_theme(window: tauri::Window, theme: String) -> Result<(), String> {
     use tauri::Theme;
@@ -707,7 +760,7 @@ pub async fn set_window_theme(window: tauri::Window, theme: String) -> Result<()
 }
 
 /// 获取 Antigravity 可执行文件路径
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_antigravity_path(bypass_config: Option<bool>) -> Result<String, String> {
     // 1. 优先从配置查询 (除非明确要求绕过)
     if bypass_config != Some(true) {
@@ -728,7 +781,7 @@ pub async fn get_antigravity_path(bypass_config: Option<bool>) -> Result<String,
 }
 
 /// 获取 Antigravity 启动参数
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_antigravity_args() -> Result<Vec<String>, String> {
     match crate::modules::process::get_args_from_running_process() {
         Some(args) => Ok(args),
@@ -737,15 +790,18 @@ pub async fn get_antigravity_args() -> Result<Vec<String>, String> {
 }
 
 /// 检测更新响应结构
+#[cfg(feature = "desktop")]
 pub use crate::modules::update_checker::UpdateInfo;
 
 /// 检测 GitHub releases 更新
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn check_for_updates() -> Result<UpdateInfo, String> {
     modules::logger::log_info("收到前端触发的更新检查请求");
     crate::modules::update_checker::check_for_updates().await
 }
 
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn should_check_updates() -> Result<bool, String> {
     let settings = crate::modules::update_checker::load_update_settings()?;
@@ -754,6 +810,7 @@ pub async fn should_check_updates() -> Result<bool, String> {
     ))
 }
 
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn update_last_check_time() -> Result<(), String> {
     crate::modules::update_checker::update_last_check_time()
@@ -761,12 +818,14 @@ pub async fn update_last_check_time() -> Result<(), String> {
 
 
 /// 检测是否通过 Homebrew Cask 安装
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn check_homebrew_installation() -> Result<bool, String> {
     Ok(crate::modules::update_checker::is_homebrew_installed())
 }
 
 /// 通过 Homebrew Cask 升级应用
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn brew_upgrade_cask() -> Result<String, String> {
     modules::logger::log_info("收到前端触发的 Homebrew 升级请求");
@@ -775,6 +834,7 @@ pub async fn brew_upgrade_cask() -> Result<String, String> {
 
 
 /// 获取更新设置
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn get_update_settings() -> Result<crate::modules::update_checker::UpdateSettings, String>
 {
@@ -782,6 +842,7 @@ pub async fn get_update_settings() -> Result<crate::modules::update_checker::Upd
 }
 
 /// 保存更新设置
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn save_update_settings(
     settings: crate::modules::update_checker::UpdateSettings,
@@ -790,6 +851,7 @@ pub async fn save_update_settings(
 }
 
 /// 切换账号的反代禁用状态
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn toggle_proxy_status(
     app: tauri::AppHandle,
@@ -880,18 +942,19 @@ pub async fn toggle_proxy_status(
 }
 
 /// 预热所有可用账号
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn warm_up_all_accounts() -> Result<String, String> {
     modules::quota::warm_up_all_accounts().await
 }
 
 /// 预热指定账号
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn warm_up_account(account_id: String) -> Result<String, String> {
     modules::quota::warm_up_account(&account_id).await
 }
 
 /// 更新账号自定义标签
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn update_account_label(account_id: String, label: String) -> Result<(), String> {
     // 验证标签长度（按字符数计算，支持中文）
@@ -951,12 +1014,14 @@ pub async fn update_account_label(account_id: String, label: String) -> Result<(
 // ============================================================================
 
 /// 获取 HTTP API 设置
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn get_http_api_settings() -> Result<crate::modules::http_api::HttpApiSettings, String> {
     crate::modules::http_api::load_settings()
 }
 
 /// 保存 HTTP API 设置
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn save_http_api_settings(
     settings: crate::modules::http_api::HttpApiSettings,
@@ -968,33 +1033,40 @@ pub async fn save_http_api_settings(
 // Token Statistics Commands
 // ============================================================================
 
+#[cfg(feature = "desktop")]
 pub use crate::modules::token_stats::{AccountTokenStats, TokenStatsAggregated, TokenStatsSummary};
 
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn get_token_stats_hourly(hours: i64) -> Result<Vec<TokenStatsAggregated>, String> {
     crate::modules::token_stats::get_hourly_stats(hours)
 }
 
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn get_token_stats_daily(days: i64) -> Result<Vec<TokenStatsAggregated>, String> {
     crate::modules::token_stats::get_daily_stats(days)
 }
 
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn get_token_stats_weekly(weeks: i64) -> Result<Vec<TokenStatsAggregated>, String> {
     crate::modules::token_stats::get_weekly_stats(weeks)
 }
 
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn get_token_stats_by_account(hours: i64) -> Result<Vec<AccountTokenStats>, String> {
     crate::modules::token_stats::get_account_stats(hours)
 }
 
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn get_token_stats_summary(hours: i64) -> Result<TokenStatsSummary, String> {
     crate::modules::token_stats::get_summary_stats(hours)
 }
 
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn get_token_stats_by_model(
     hours: i64,
@@ -1002,6 +1074,7 @@ pub async fn get_token_stats_by_model(
     crate::modules::token_stats::get_model_stats(hours)
 }
 
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn get_token_stats_model_trend_hourly(
     hours: i64,
@@ -1009,6 +1082,7 @@ pub async fn get_token_stats_model_trend_hourly(
     crate::modules::token_stats::get_model_trend_hourly(hours)
 }
 
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn get_token_stats_model_trend_daily(
     days: i64,
@@ -1016,6 +1090,7 @@ pub async fn get_token_stats_model_trend_daily(
     crate::modules::token_stats::get_model_trend_daily(days)
 }
 
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn get_token_stats_account_trend_hourly(
     hours: i64,
@@ -1023,9 +1098,48 @@ pub async fn get_token_stats_account_trend_hourly(
     crate::modules::token_stats::get_account_trend_hourly(hours)
 }
 
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn get_token_stats_account_trend_daily(
     days: i64,
 ) -> Result<Vec<crate::modules::token_stats::AccountTrendPoint>, String> {
     crate::modules::token_stats::get_account_trend_daily(days)
 }
+
+// ============================================================================
+// Quota History Commands
+// ============================================================================
+
+#[cfg(feature = "desktop")]
+pub use crate::modules::quota_history::{PoolQuotaForecast, QuotaForecast, QuotaSnapshot};
+
+#[cfg(feature = "desktop")]
+#[tauri::command]
+pub async fn get_quota_history(
+    account_id: Option<String>,
+    model: Option<String>,
+    hours: i64,
+) -> Result<Vec<QuotaSnapshot>, String> {
+    crate::modules::quota_history::get_quota_history(account_id.as_deref(), model.as_deref(), hours)
+}
+
+#[cfg(feature = "desktop")]
+#[tauri::command]
+pub async fn get_quota_forecast(account_id: Option<String>) -> Result<Vec<QuotaForecast>, String> {
+    crate::modules::quota_history::get_quota_forecast(account_id.as_deref())
+}
+
+#[cfg(feature = "desktop")]
+#[tauri::command]
+pub async fn get_pool_quota_forecast() -> Result<Vec<PoolQuotaForecast>, String> {
+    crate::modules::quota_history::get_pool_quota_forecast()
+}
+
+/// 测试示例请求会命中哪条后台任务规则
+#[cfg(feature = "desktop")]
+#[tauri::command]
+pub async fn test_background_task_rules(
+    request: crate::proxy::common::background_task::RuleTestRequest,
+) -> Result<crate::proxy::common::background_task::ClassificationReport, String> {
+    crate::proxy::common::background_task::test_rules(request)
+}
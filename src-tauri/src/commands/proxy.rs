@@ -1,10 +1,18 @@
-use crate::proxy::monitor::{ProxyMonitor, ProxyRequestLog, ProxyStats};
-use crate::proxy::{ProxyConfig, ProxyPoolConfig, TokenManager};
+use crate::proxy::monitor::ProxyMonitor;
+#[cfg(feature = "desktop")]
+use crate::proxy::monitor::ProxyRequestLog;
+#[cfg(feature = "desktop")]
+use crate::proxy::monitor::ProxyStats;
+use crate::proxy::{ProxyConfig, TokenManager};
+#[cfg(feature = "desktop")]
+use crate::proxy::ProxyPoolConfig;
 use serde::{Deserialize, Serialize};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+#[cfg(feature = "desktop")]
 use tauri::State;
 use tokio::sync::RwLock;
+#[cfg(feature = "desktop")]
 use tokio::time::Duration;
 
 /// 反代服务状态
@@ -32,6 +40,7 @@ pub struct AdminServerInstance {
 }
 
 /// 反代服务实例
+#[cfg_attr(not(feature = "desktop"), allow(dead_code))] // 无桌面构建中仅持有实例，字段由 Tauri 命令读取
 pub struct ProxyServiceInstance {
     pub config: ProxyConfig,
     pub token_manager: Arc<TokenManager>,
@@ -52,6 +61,7 @@ impl ProxyServiceState {
 }
 
 /// 启动反代服务 (Tauri 命令)
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn start_proxy_service(
     config: ProxyConfig,
@@ -106,13 +116,7 @@ pub async fn internal_start_proxy_service(
     {
         let mut monitor_lock = state.monitor.write().await;
         if monitor_lock.is_none() {
-            let app_handle =
-                if let crate::modules::integration::SystemManager::Desktop(ref h) = integration {
-                    Some(h.clone())
-                } else {
-                    None
-                };
-            *monitor_lock = Some(Arc::new(ProxyMonitor::new(1000, app_handle)));
+            *monitor_lock = Some(Arc::new(ProxyMonitor::new(1000)));
         }
         // Sync enabled state from config
         if let Some(monitor) = monitor_lock.as_ref() {
@@ -223,13 +227,7 @@ pub async fn ensure_admin_server(
     let monitor = {
         let mut monitor_lock = state.monitor.write().await;
         if monitor_lock.is_none() {
-            let app_handle =
-                if let crate::modules::integration::SystemManager::Desktop(ref h) = integration {
-                    Some(h.clone())
-                } else {
-                    None
-                };
-            *monitor_lock = Some(Arc::new(ProxyMonitor::new(1000, app_handle)));
+            *monitor_lock = Some(Arc::new(ProxyMonitor::new(1000)));
         }
         monitor_lock.as_ref().unwrap().clone()
     };
@@ -262,6 +260,8 @@ pub async fn ensure_admin_server(
         Ok((server, handle)) => (server, handle),
         Err(e) => return Err(format!("启动管理服务器失败: {}", e)),
     };
+    axum_server.update_upstream_endpoints(&config).await;
+    axum_server.update_metrics(&config).await;
 
     *admin_lock = Some(AdminServerInstance {
         axum_server,
@@ -274,11 +274,28 @@ pub async fn ensure_admin_server(
     crate::proxy::update_global_system_prompt_config(config.global_system_prompt.clone());
     // [NEW] 初始化全局图像思维模式配置
     crate::proxy::update_image_thinking_mode(config.image_thinking_mode.clone());
+    // 初始化模型降级链
+    crate::proxy::common::model_fallback::update_model_fallbacks(config.model_fallbacks.clone());
+    // 初始化后台任务识别规则
+    crate::proxy::common::background_task::update_background_task_rules(config.background_task_rules.clone());
+    // 初始化通用上游提供商
+    crate::proxy::providers::compatible::update_providers(config.providers.clone());
+    // 初始化响应缓存配置
+    crate::modules::response_cache::update_response_cache_config(config.response_cache.clone());
+    // 初始化审计日志配置
+    crate::modules::audit_log::update_audit_log_config(config.audit_log.clone());
+    // 初始化 Responses API 响应存储配置
+    crate::modules::response_store::update_response_store_config(config.response_store.clone());
+    // 初始化生成图片存储配置
+    crate::modules::image_store::update_image_store_config(config.image_store.clone());
+    // 初始化结构化输出校验配置
+    crate::proxy::common::structured_output::update_structured_output_config(config.structured_outputs.clone());
 
     Ok(())
 }
 
 /// 停止反代服务
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn stop_proxy_service(state: State<'_, ProxyServiceState>) -> Result<(), String> {
     let mut instance_lock = state.instance.write().await;
@@ -298,6 +315,7 @@ pub async fn stop_proxy_service(state: State<'_, ProxyServiceState>) -> Result<(
 }
 
 /// 获取反代服务状态
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn get_proxy_status(state: State<'_, ProxyServiceState>) -> Result<ProxyStatus, String> {
     // 优先检查启动标志，避免被写锁阻塞
@@ -341,6 +359,7 @@ pub async fn get_proxy_status(state: State<'_, ProxyServiceState>) -> Result<Pro
 }
 
 /// 获取反代服务统计
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn get_proxy_stats(state: State<'_, ProxyServiceState>) -> Result<ProxyStats, String> {
     let monitor_lock = state.monitor.read().await;
@@ -352,6 +371,7 @@ pub async fn get_proxy_stats(state: State<'_, ProxyServiceState>) -> Result<Prox
 }
 
 /// 获取反代请求日志
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn get_proxy_logs(
     state: State<'_, ProxyServiceState>,
@@ -366,6 +386,7 @@ pub async fn get_proxy_logs(
 }
 
 /// 设置监控开启状态
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn set_proxy_monitor_enabled(
     state: State<'_, ProxyServiceState>,
@@ -379,6 +400,7 @@ pub async fn set_proxy_monitor_enabled(
 }
 
 /// 清除反代请求日志
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn clear_proxy_logs(state: State<'_, ProxyServiceState>) -> Result<(), String> {
     let monitor_lock = state.monitor.read().await;
@@ -389,6 +411,7 @@ pub async fn clear_proxy_logs(state: State<'_, ProxyServiceState>) -> Result<(),
 }
 
 /// 获取反代请求日志 (分页)
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn get_proxy_logs_paginated(
     limit: Option<usize>,
@@ -398,18 +421,44 @@ pub async fn get_proxy_logs_paginated(
 }
 
 /// 获取单条日志的完整详情
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn get_proxy_log_detail(log_id: String) -> Result<ProxyRequestLog, String> {
     crate::modules::proxy_db::get_log_detail(&log_id)
 }
 
+/// 重放一条已记录的请求 (可指定账号 / 映射模型 / 流式模式)
+#[cfg(feature = "desktop")]
+#[tauri::command]
+pub async fn replay_proxy_log(
+    state: State<'_, ProxyServiceState>,
+    log_id: String,
+    request: Option<crate::proxy::replay::ReplayOptions>,
+) -> Result<crate::proxy::replay::ReplayResult, String> {
+    let instance_lock = state.instance.read().await;
+    let instance = instance_lock.as_ref().ok_or("Proxy service is not running")?;
+    instance
+        .axum_server
+        .replay_log(&log_id, request.unwrap_or_default())
+        .await
+}
+
+/// 获取某条日志的重放记录
+#[cfg(feature = "desktop")]
+#[tauri::command]
+pub async fn get_proxy_log_replays(log_id: String) -> Result<Vec<crate::proxy::replay::ReplayRecord>, String> {
+    crate::modules::proxy_db::get_replays(&log_id)
+}
+
 /// 获取日志总数
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn get_proxy_logs_count() -> Result<u64, String> {
     crate::modules::proxy_db::get_logs_count()
 }
 
 /// 导出所有日志到指定文件
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn export_proxy_logs(file_path: String) -> Result<usize, String> {
     let logs = crate::modules::proxy_db::get_all_logs_for_export()?;
@@ -423,7 +472,64 @@ pub async fn export_proxy_logs(file_path: String) -> Result<usize, String> {
     Ok(count)
 }
 
+/// 查询管理接口审计日志
+#[cfg(feature = "desktop")]
+#[tauri::command]
+pub async fn get_audit_logs(
+    actor: Option<String>,
+    endpoint: Option<String>,
+    since: Option<i64>,
+    until: Option<i64>,
+    failed_only: Option<bool>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+) -> Result<Vec<crate::modules::audit_log::AuditEntry>, String> {
+    let query = crate::modules::audit_log::AuditQuery {
+        actor,
+        endpoint,
+        since,
+        until,
+        failed_only: failed_only.unwrap_or(false),
+        limit,
+        offset,
+    };
+    tokio::task::spawn_blocking(move || crate::modules::audit_log::query_entries(&query))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// 导出符合条件的审计日志到文件
+#[cfg(feature = "desktop")]
+#[tauri::command]
+pub async fn export_audit_logs(
+    file_path: String,
+    actor: Option<String>,
+    endpoint: Option<String>,
+    since: Option<i64>,
+    until: Option<i64>,
+    failed_only: Option<bool>,
+) -> Result<usize, String> {
+    let query = crate::modules::audit_log::AuditQuery {
+        actor,
+        endpoint,
+        since,
+        until,
+        failed_only: failed_only.unwrap_or(false),
+        ..Default::default()
+    };
+    let entries = crate::modules::audit_log::export_entries(&query)?;
+    let count = entries.len();
+
+    let json = serde_json::to_string_pretty(&entries)
+        .map_err(|e| format!("Failed to serialize audit logs: {}", e))?;
+
+    std::fs::write(&file_path, json).map_err(|e| format!("Failed to write file: {}", e))?;
+
+    Ok(count)
+}
+
 /// 导出指定的日志JSON到文件
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn export_proxy_logs_json(file_path: String, json_data: String) -> Result<usize, String> {
     // Parse to count items
@@ -441,6 +547,7 @@ pub async fn export_proxy_logs_json(file_path: String, json_data: String) -> Res
 }
 
 /// 获取带搜索条件的日志数量
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn get_proxy_logs_count_filtered(
     filter: String,
@@ -450,6 +557,7 @@ pub async fn get_proxy_logs_count_filtered(
 }
 
 /// 获取带搜索条件的分页日志
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn get_proxy_logs_filtered(
     filter: String,
@@ -461,12 +569,14 @@ pub async fn get_proxy_logs_filtered(
 }
 
 /// 生成 API Key
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub fn generate_api_key() -> String {
     format!("sk-{}", uuid::Uuid::new_v4().simple())
 }
 
 /// 重新加载账号（当主应用添加/删除账号时调用）
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn reload_proxy_accounts(state: State<'_, ProxyServiceState>) -> Result<usize, String> {
     let instance_lock = state.instance.read().await;
@@ -490,6 +600,7 @@ pub async fn reload_proxy_accounts(state: State<'_, ProxyServiceState>) -> Resul
 }
 
 /// 更新模型映射表 (热更新)
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn update_model_mapping(
     config: ProxyConfig,
@@ -512,6 +623,7 @@ pub async fn update_model_mapping(
     Ok(())
 }
 
+#[cfg(feature = "desktop")]
 fn join_base_url(base: &str, path: &str) -> String {
     let base = base.trim_end_matches('/');
     let path = if path.starts_with('/') {
@@ -522,6 +634,7 @@ fn join_base_url(base: &str, path: &str) -> String {
     format!("{}{}", base, path)
 }
 
+#[cfg(feature = "desktop")]
 fn extract_model_ids(value: &serde_json::Value) -> Vec<String> {
     let mut out = Vec::new();
 
@@ -571,6 +684,7 @@ fn extract_model_ids(value: &serde_json::Value) -> Vec<String> {
 }
 
 /// Fetch available models from the configured z.ai Anthropic-compatible API (`/v1/models`).
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn fetch_zai_models(
     zai: crate::proxy::ZaiConfig,
@@ -632,6 +746,7 @@ pub async fn fetch_zai_models(
 }
 
 /// 获取当前调度配置
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn get_proxy_scheduling_config(
     state: State<'_, ProxyServiceState>,
@@ -645,6 +760,7 @@ pub async fn get_proxy_scheduling_config(
 }
 
 /// 更新调度配置
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn update_proxy_scheduling_config(
     state: State<'_, ProxyServiceState>,
@@ -660,6 +776,7 @@ pub async fn update_proxy_scheduling_config(
 }
 
 /// 清除所有会话粘性绑定
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn clear_proxy_session_bindings(
     state: State<'_, ProxyServiceState>,
@@ -677,6 +794,7 @@ pub async fn clear_proxy_session_bindings(
 
 /// 设置优先使用的账号（固定账号模式）
 /// 传入 account_id 启用固定模式，传入 null/空字符串恢复轮询模式
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn set_preferred_account(
     state: State<'_, ProxyServiceState>,
@@ -716,6 +834,7 @@ pub async fn set_preferred_account(
 }
 
 /// 获取当前优先使用的账号ID
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn get_preferred_account(
     state: State<'_, ProxyServiceState>,
@@ -729,6 +848,7 @@ pub async fn get_preferred_account(
 }
 
 /// 清除指定账号的限流记录
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn clear_proxy_rate_limit(
     state: State<'_, ProxyServiceState>,
@@ -743,6 +863,7 @@ pub async fn clear_proxy_rate_limit(
 }
 
 /// 清除所有限流记录
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn clear_all_proxy_rate_limits(
     state: State<'_, ProxyServiceState>,
@@ -757,6 +878,7 @@ pub async fn clear_all_proxy_rate_limits(
 }
 
 /// 触发所有代理的健康检查，并返回更新后的配置
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn check_proxy_health(
     state: State<'_, ProxyServiceState>,
@@ -777,6 +899,7 @@ pub async fn check_proxy_health(
 }
 
 /// 获取当前内存中的代理池状态
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn get_proxy_pool_config(
     state: State<'_, ProxyServiceState>,
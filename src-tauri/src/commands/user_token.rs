@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
-use crate::modules::user_token_db::{self, UserToken, TokenIpBinding};
+use crate::modules::user_token_db::{self, BudgetUsage, TokenLimits, UserToken};
+#[cfg(feature = "desktop")]
+use crate::modules::user_token_db::TokenIpBinding;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateTokenRequest {
@@ -10,6 +12,14 @@ pub struct CreateTokenRequest {
     pub curfew_start: Option<String>,
     pub curfew_end: Option<String>,
     pub custom_expires_at: Option<i64>,  // 自定义过期时间戳 (秒)
+    #[serde(default)]
+    pub allowed_models: Vec<String>,
+    #[serde(default)]
+    pub denied_models: Vec<String>,
+    #[serde(default)]
+    pub account_groups: Vec<String>,
+    #[serde(default)]
+    pub limits: TokenLimits,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -20,18 +30,22 @@ pub struct UpdateTokenRequest {
     pub max_ips: Option<i32>,
     pub curfew_start: Option<Option<String>>,
     pub curfew_end: Option<Option<String>>,
+    pub allowed_models: Option<Vec<String>>,
+    pub denied_models: Option<Vec<String>>,
+    pub account_groups: Option<Vec<String>>,
+    pub limits: Option<TokenLimits>,
 }
 
 // 命令实现
 
 /// 列出所有令牌
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn list_user_tokens() -> Result<Vec<UserToken>, String> {
     user_token_db::list_tokens()
 }
 
 /// 创建新令牌
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn create_user_token(request: CreateTokenRequest) -> Result<UserToken, String> {
     user_token_db::create_token(
         request.username,
@@ -41,11 +55,15 @@ pub async fn create_user_token(request: CreateTokenRequest) -> Result<UserToken,
         request.curfew_start,
         request.curfew_end,
         request.custom_expires_at,
+        request.allowed_models,
+        request.denied_models,
+        request.account_groups,
+        request.limits,
     )
 }
 
 /// 更新令牌
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn update_user_token(id: String, request: UpdateTokenRequest) -> Result<(), String> {
     user_token_db::update_token(
         &id,
@@ -55,22 +73,27 @@ pub async fn update_user_token(id: String, request: UpdateTokenRequest) -> Resul
         request.max_ips,
         request.curfew_start,
         request.curfew_end,
+        request.allowed_models,
+        request.denied_models,
+        request.account_groups,
+        request.limits,
     )
 }
 
 /// 删除令牌
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn delete_user_token(id: String) -> Result<(), String> {
     user_token_db::delete_token(&id)
 }
 
 /// 续期令牌
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn renew_user_token(id: String, expires_type: String) -> Result<(), String> {
     user_token_db::renew_token(&id, &expires_type)
 }
 
 /// 获取令牌 IP 绑定
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn get_token_ip_bindings(token_id: String) -> Result<Vec<TokenIpBinding>, String> {
     user_token_db::get_token_ips(&token_id)
@@ -82,10 +105,26 @@ pub struct UserTokenStats {
     pub active_tokens: usize,
     pub total_users: usize,
     pub today_requests: i64,
+    /// 设置了预算或速率限制的令牌用量
+    #[serde(default)]
+    pub budgets: Vec<TokenBudgetSummary>,
+}
+
+/// 单个令牌的预算用量
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokenBudgetSummary {
+    pub token_id: String,
+    pub username: String,
+    pub usage: Vec<BudgetUsage>,
+    pub limits: TokenLimits,
+    /// 当前并发请求数
+    pub in_flight: usize,
+    /// 最近一分钟请求数
+    pub recent_rpm: usize,
 }
 
 /// 获取简单的统计信息
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_user_token_summary() -> Result<UserTokenStats, String> {
     let tokens = user_token_db::list_tokens()?;
     let active_tokens = tokens.iter().filter(|t| t.enabled).count();
@@ -98,11 +137,25 @@ pub async fn get_user_token_summary() -> Result<UserTokenStats, String> {
     
     // 这里简单返回一些数据，请求数最好从数据库聚合查询
     // 目前仅作为演示，请求数暂不精确统计今日的
+
+    let limiter = crate::proxy::middleware::token_limiter::global_limiter();
+    let mut budgets = Vec::new();
+    for t in tokens.iter().filter(|t| t.limits != TokenLimits::default()) {
+        budgets.push(TokenBudgetSummary {
+            token_id: t.id.clone(),
+            username: t.username.clone(),
+            usage: user_token_db::get_budget_usage(t)?,
+            limits: t.limits.clone(),
+            in_flight: limiter.in_flight(&t.id),
+            recent_rpm: limiter.recent_requests(&t.id),
+        });
+    }
     
     Ok(UserTokenStats {
         total_tokens: tokens.len(),
         active_tokens,
         total_users: users.len(),
         today_requests: 0, // TODO: Implement daily stats query
+        budgets,
     })
 }
@@ -6,23 +6,26 @@ mod proxy;  // Proxy service module
 pub mod error;
 pub mod constants;
 
+#[cfg(feature = "desktop")]
 use tauri::Manager;
 use modules::logger;
 use tracing::{info, warn, error};
 use std::sync::Arc;
 
+#[cfg(feature = "desktop")]
 #[derive(Clone, Copy)]
 struct AppRuntimeFlags {
     tray_enabled: bool,
 }
 
+#[cfg(feature = "desktop")]
 fn env_flag_enabled(name: &str) -> bool {
     std::env::var(name)
         .map(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes" | "on"))
         .unwrap_or(false)
 }
 
-#[cfg(target_os = "linux")]
+#[cfg(all(feature = "desktop", target_os = "linux"))]
 fn is_wayland_session() -> bool {
     std::env::var("WAYLAND_DISPLAY")
         .map(|v| !v.trim().is_empty())
@@ -32,6 +35,7 @@ fn is_wayland_session() -> bool {
             .unwrap_or(false)
 }
 
+#[cfg(feature = "desktop")]
 fn should_enable_tray() -> bool {
     if env_flag_enabled("ANTIGRAVITY_DISABLE_TRAY") {
         info!("Tray disabled by ANTIGRAVITY_DISABLE_TRAY");
@@ -51,7 +55,7 @@ fn should_enable_tray() -> bool {
     true
 }
 
-#[cfg(target_os = "linux")]
+#[cfg(all(feature = "desktop", target_os = "linux"))]
 fn configure_linux_gdk_backend() {
     if std::env::var("GDK_BACKEND").is_ok() {
         return;
@@ -100,17 +104,14 @@ fn increase_nofile_limit() {
 }
 
 // Test command
+#[cfg(feature = "desktop")]
 #[tauri::command]
 fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
-    // Check for headless mode
-    let args: Vec<String> = std::env::args().collect();
-    let is_headless = args.iter().any(|arg| arg == "--headless");
-
+/// 桌面版与纯服务端共用的初始化：日志与本地数据库
+fn init_runtime() {
     // Increase file descriptor limit (macOS only)
     #[cfg(target_os = "macos")]
     increase_nofile_limit();
@@ -118,14 +119,41 @@ pub fn run() {
     // Initialize logger
     logger::init_logger();
 
-    #[cfg(target_os = "linux")]
-    configure_linux_gdk_backend();
-
     // Initialize token stats database
     if let Err(e) = modules::token_stats::init_db() {
         error!("Failed to initialize token stats database: {}", e);
     }
 
+    // Initialize quota history database
+    if let Err(e) = modules::quota_history::init_db() {
+        error!("Failed to initialize quota history database: {}", e);
+    }
+
+    // Initialize response cache database
+    if let Err(e) = modules::response_cache::init_db() {
+        error!("Failed to initialize response cache database: {}", e);
+    }
+
+    // Initialize audit log database
+    if let Err(e) = modules::audit_log::init_db() {
+        error!("Failed to initialize audit log database: {}", e);
+    }
+
+    // Initialize Responses API store database
+    if let Err(e) = modules::response_store::init_db() {
+        error!("Failed to initialize response store database: {}", e);
+    }
+
+    // Clean up expired generated images
+    if let Err(e) = modules::image_store::init_store() {
+        error!("Failed to initialize image store: {}", e);
+    }
+
+    // Initialize admin key database
+    if let Err(e) = modules::admin_key_db::init_db() {
+        error!("Failed to initialize admin key database: {}", e);
+    }
+
     // Initialize security database
     if let Err(e) = modules::security_db::init_db() {
         error!("Failed to initialize security database: {}", e);
@@ -136,158 +164,170 @@ pub fn run() {
         error!("Failed to initialize user token database: {}", e);
     }
 
-    if is_headless {
-        info!("Starting in HEADLESS mode...");
-
-        let rt = tokio::runtime::Runtime::new().expect("Failed to create Tokio runtime");
-        rt.block_on(async {
-            // Initialize states manually
-            // [FIX] Initialize log bridge for headless mode
-            // Pass a dummy app handle or None since we don't have a Tauri app handle in headless mode
-            // Actually log_bridge relies on AppHandle to emit events.
-            // In headless mode, we don't emit events, but we still need the buffer.
-            // We need to modify log_bridge to handle missing AppHandle gracefully, which it already does (Option).
-            // But init_log_bridge requires AppHandle.
-            // We'll skip passing AppHandle for now and just leverage the global buffer capability.
-            // Since init_log_bridge takes AppHandle, we might need a separate init for headless or just not call init and rely on lazy init of buffer?
-            // Checking log_bridge code again...
-            // "static LOG_BUFFER: OnceLock<...> = OnceLock::new();" -> lazy init.
-            // So we just need to ensure the tracing layer is added.
-            // And `logger::init_logger()` adds the layer?
-            // Let's check `modules::logger`.
-
-            let proxy_state = commands::proxy::ProxyServiceState::new();
-            let cf_state = Arc::new(commands::cloudflared::CloudflaredState::new());
-
-            // Load config
-            match modules::config::load_app_config() {
-                Ok(mut config) => {
-                    let mut modified = false;
-                    // Headless/docker 默认允许 LAN 访问（绑定 0.0.0.0）
-                    // 若设置 ABV_BIND_LOCAL_ONLY，则仅绑定 127.0.0.1
-                    let bind_local_only = std::env::var("ABV_BIND_LOCAL_ONLY")
-                        .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes" | "on"))
-                        .unwrap_or(false);
-                    if bind_local_only {
-                        config.proxy.allow_lan_access = false;
-                        modified = true;
-                    } else {
-                        config.proxy.allow_lan_access = true;
-                    }
+    // Encrypt plaintext credentials left by older versions
+    if let Err(e) = modules::account::migrate_plaintext_credentials() {
+        error!("Failed to migrate account credentials: {}", e);
+    }
+}
 
-                    // [FIX] Force auth mode to AllExceptHealth in headless mode if it's Off or Auto
-                    // This ensures Web UI login validation works properly
-                    if matches!(config.proxy.auth_mode, crate::proxy::ProxyAuthMode::Off | crate::proxy::ProxyAuthMode::Auto) {
-                        info!("Headless mode: Forcing auth_mode to AllExceptHealth for Web UI security");
-                        config.proxy.auth_mode = crate::proxy::ProxyAuthMode::AllExceptHealth;
+/// 纯服务端入口 (`--headless` / antigravity-server)：仅运行反代服务、管理 API 与后台任务，不创建任何窗口
+pub fn run_headless() {
+    init_runtime();
+
+    info!("Starting in HEADLESS mode...");
+
+    let rt = tokio::runtime::Runtime::new().expect("Failed to create Tokio runtime");
+    rt.block_on(async {
+        // Initialize states manually
+        // 日志缓冲与事件总线均为惰性初始化，Headless 模式下无需 AppHandle
+
+        let proxy_state = commands::proxy::ProxyServiceState::new();
+        let cf_state = Arc::new(commands::cloudflared::CloudflaredState::new());
+
+        // Load config
+        match modules::config::load_app_config() {
+            Ok(mut config) => {
+                let mut modified = false;
+                // Headless/docker 默认允许 LAN 访问（绑定 0.0.0.0）
+                // 若设置 ABV_BIND_LOCAL_ONLY，则仅绑定 127.0.0.1
+                let bind_local_only = std::env::var("ABV_BIND_LOCAL_ONLY")
+                    .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes" | "on"))
+                    .unwrap_or(false);
+                if bind_local_only {
+                    config.proxy.allow_lan_access = false;
+                    modified = true;
+                } else {
+                    config.proxy.allow_lan_access = true;
+                }
+
+                // [FIX] Force auth mode to AllExceptHealth in headless mode if it's Off or Auto
+                // This ensures Web UI login validation works properly
+                if matches!(config.proxy.auth_mode, crate::proxy::ProxyAuthMode::Off | crate::proxy::ProxyAuthMode::Auto) {
+                    info!("Headless mode: Forcing auth_mode to AllExceptHealth for Web UI security");
+                    config.proxy.auth_mode = crate::proxy::ProxyAuthMode::AllExceptHealth;
+                    modified = true;
+                }
+
+                // [NEW] 支持通过环境变量注入 API Key
+                // 优先级：ABV_API_KEY > API_KEY > 配置文件
+                let env_key = std::env::var("ABV_API_KEY")
+                    .or_else(|_| std::env::var("API_KEY"))
+                    .ok();
+
+                if let Some(key) = env_key {
+                    if !key.trim().is_empty() {
+                        info!("Using API Key from environment variable");
+                        config.proxy.api_key = key;
                         modified = true;
                     }
+                }
 
-                    // [NEW] 支持通过环境变量注入 API Key
-                    // 优先级：ABV_API_KEY > API_KEY > 配置文件
-                    let env_key = std::env::var("ABV_API_KEY")
-                        .or_else(|_| std::env::var("API_KEY"))
-                        .ok();
-
-                    if let Some(key) = env_key {
-                        if !key.trim().is_empty() {
-                            info!("Using API Key from environment variable");
-                            config.proxy.api_key = key;
-                            modified = true;
-                        }
-                    }
+                // [NEW] 支持通过环境变量注入 Web UI 密码
+                // 优先级：ABV_WEB_PASSWORD > WEB_PASSWORD > 配置文件
+                let env_web_password = std::env::var("ABV_WEB_PASSWORD")
+                    .or_else(|_| std::env::var("WEB_PASSWORD"))
+                    .ok();
 
-                    // [NEW] 支持通过环境变量注入 Web UI 密码
-                    // 优先级：ABV_WEB_PASSWORD > WEB_PASSWORD > 配置文件
-                    let env_web_password = std::env::var("ABV_WEB_PASSWORD")
-                        .or_else(|_| std::env::var("WEB_PASSWORD"))
-                        .ok();
-
-                    if let Some(pwd) = env_web_password {
-                        if !pwd.trim().is_empty() {
-                            info!("Using Web UI Password from environment variable");
-                            config.proxy.admin_password = Some(pwd);
-                            modified = true;
-                        }
+                if let Some(pwd) = env_web_password {
+                    if !pwd.trim().is_empty() {
+                        info!("Using Web UI Password from environment variable");
+                        config.proxy.admin_password = Some(pwd);
+                        modified = true;
                     }
+                }
 
-                    // [NEW] 支持通过环境变量注入鉴权模式
-                    // 优先级：ABV_AUTH_MODE > AUTH_MODE > 配置文件
-                    let env_auth_mode = std::env::var("ABV_AUTH_MODE")
-                        .or_else(|_| std::env::var("AUTH_MODE"))
-                        .ok();
-
-                    if let Some(mode_str) = env_auth_mode {
-                        let mode = match mode_str.to_lowercase().as_str() {
-                            "off" => Some(crate::proxy::ProxyAuthMode::Off),
-                            "strict" => Some(crate::proxy::ProxyAuthMode::Strict),
-                            "all_except_health" => Some(crate::proxy::ProxyAuthMode::AllExceptHealth),
-                            "auto" => Some(crate::proxy::ProxyAuthMode::Auto),
-                            _ => {
-                                warn!("Invalid AUTH_MODE: {}, ignoring", mode_str);
-                                None
-                            }
-                        };
-                        if let Some(m) = mode {
-                            info!("Using Auth Mode from environment variable: {:?}", m);
-                            config.proxy.auth_mode = m;
-                            modified = true;
+                // [NEW] 支持通过环境变量注入鉴权模式
+                // 优先级：ABV_AUTH_MODE > AUTH_MODE > 配置文件
+                let env_auth_mode = std::env::var("ABV_AUTH_MODE")
+                    .or_else(|_| std::env::var("AUTH_MODE"))
+                    .ok();
+
+                if let Some(mode_str) = env_auth_mode {
+                    let mode = match mode_str.to_lowercase().as_str() {
+                        "off" => Some(crate::proxy::ProxyAuthMode::Off),
+                        "strict" => Some(crate::proxy::ProxyAuthMode::Strict),
+                        "all_except_health" => Some(crate::proxy::ProxyAuthMode::AllExceptHealth),
+                        "auto" => Some(crate::proxy::ProxyAuthMode::Auto),
+                        _ => {
+                            warn!("Invalid AUTH_MODE: {}, ignoring", mode_str);
+                            None
                         }
+                    };
+                    if let Some(m) = mode {
+                        info!("Using Auth Mode from environment variable: {:?}", m);
+                        config.proxy.auth_mode = m;
+                        modified = true;
                     }
+                }
 
-                    info!("--------------------------------------------------");
-                    info!("🚀 Headless mode proxy service starting...");
-                    info!("📍 Port: {}", config.proxy.port);
-                    info!("🔑 Current API Key: {}", config.proxy.api_key);
-                    if let Some(ref pwd) = config.proxy.admin_password {
-                        info!("🔐 Web UI Password: {}", pwd);
+                info!("--------------------------------------------------");
+                info!("🚀 Headless mode proxy service starting...");
+                info!("📍 Port: {}", config.proxy.port);
+                info!("🔑 Current API Key: {}", config.proxy.api_key);
+                if let Some(ref pwd) = config.proxy.admin_password {
+                    info!("🔐 Web UI Password: {}", pwd);
+                } else {
+                    info!("🔐 Web UI Password: (Same as API Key)");
+                }
+                info!("💡 Tips: You can use these keys to login to Web UI and access AI APIs.");
+                info!("💡 Search docker logs or grep gui_config.json to find them.");
+                info!("--------------------------------------------------");
+
+                // [FIX #1460] Persist environment overrides to ensure they are visible in Web UI/load_config
+                if modified {
+                    if let Err(e) = modules::config::save_app_config(&config) {
+                        error!("Failed to persist environment overrides: {}", e);
                     } else {
-                        info!("🔐 Web UI Password: (Same as API Key)");
+                        info!("Environment overrides persisted to gui_config.json");
                     }
-                    info!("💡 Tips: You can use these keys to login to Web UI and access AI APIs.");
-                    info!("💡 Search docker logs or grep gui_config.json to find them.");
-                    info!("--------------------------------------------------");
-
-                    // [FIX #1460] Persist environment overrides to ensure they are visible in Web UI/load_config
-                    if modified {
-                        if let Err(e) = modules::config::save_app_config(&config) {
-                            error!("Failed to persist environment overrides: {}", e);
-                        } else {
-                            info!("Environment overrides persisted to gui_config.json");
-                        }
-                    }
-
-                    // Start proxy service
-                    if let Err(e) = commands::proxy::internal_start_proxy_service(
-                        config.proxy,
-                        &proxy_state,
-                        crate::modules::integration::SystemManager::Headless,
-                        cf_state.clone(),
-                    ).await {
-                        error!("Failed to start proxy service in headless mode: {}", e);
-                        std::process::exit(1);
-                    }
-
-                    info!("Headless proxy service is running.");
-
-                    // [DISABLED] Start smart scheduler (Automatic warmup disabled as per user request)
-                    // modules::scheduler::start_scheduler(None, proxy_state.clone());
-                    info!("Smart scheduler (Automatic Warmup) is DISABLED.");
-                    info!("Smart scheduler started in headless mode.");
                 }
-                Err(e) => {
-                    error!("Failed to load config for headless mode: {}", e);
+
+                // Start proxy service
+                if let Err(e) = commands::proxy::internal_start_proxy_service(
+                    config.proxy,
+                    &proxy_state,
+                    crate::modules::integration::SystemManager::Headless,
+                    cf_state.clone(),
+                ).await {
+                    error!("Failed to start proxy service in headless mode: {}", e);
                     std::process::exit(1);
                 }
+
+                info!("Headless proxy service is running.");
+
+                // [DISABLED] Start smart scheduler (Automatic warmup disabled as per user request)
+                // modules::scheduler::start_scheduler(None, proxy_state.clone());
+                info!("Smart scheduler (Automatic Warmup) is DISABLED.");
+                info!("Smart scheduler started in headless mode.");
             }
+            Err(e) => {
+                error!("Failed to load config for headless mode: {}", e);
+                std::process::exit(1);
+            }
+        }
 
-            // Wait for Ctrl-C
-            tokio::signal::ctrl_c().await.ok();
-            info!("Headless mode shutting down");
-        });
+        // Wait for Ctrl-C
+        tokio::signal::ctrl_c().await.ok();
+        info!("Headless mode shutting down");
+    });
+}
+
+#[cfg(feature = "desktop")]
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    // Check for headless mode
+    let args: Vec<String> = std::env::args().collect();
+    if args.iter().any(|arg| arg == "--headless") {
+        run_headless();
         return;
     }
 
+    init_runtime();
+
+    #[cfg(target_os = "linux")]
+    configure_linux_gdk_backend();
+
+
     let tray_enabled = should_enable_tray();
 
     tauri::Builder::default()
@@ -316,8 +356,8 @@ pub fn run() {
         .setup(|app| {
             info!("Setup starting...");
 
-            // Initialize log bridge with app handle for debug console
-            modules::log_bridge::init_log_bridge(app.handle().clone());
+            // 绑定事件总线，后台事件 (日志、请求监控等) 转发至前端
+            modules::events::attach_app_handle(app.handle().clone());
 
             // Linux: Workaround for transparent window crash/freeze
             // The transparent window feature is unstable on Linux with WebKitGTK
@@ -428,6 +468,9 @@ pub fn run() {
             commands::delete_account,
             commands::delete_accounts,
             commands::reorder_accounts,
+            commands::list_account_groups,
+            commands::save_account_group,
+            commands::delete_account_group,
             commands::switch_account,
             commands::export_accounts,
             // Device fingerprint
@@ -448,6 +491,7 @@ pub fn run() {
             // Config commands
             commands::load_config,
             commands::save_config,
+            commands::validate_config,
             // Additional commands
             commands::prepare_oauth_url,
             commands::start_oauth_login,
@@ -488,9 +532,13 @@ pub fn run() {
             commands::proxy::get_proxy_logs,
             commands::proxy::get_proxy_logs_paginated,
             commands::proxy::get_proxy_log_detail,
+            commands::proxy::replay_proxy_log,
+            commands::proxy::get_proxy_log_replays,
             commands::proxy::get_proxy_logs_count,
             commands::proxy::export_proxy_logs,
             commands::proxy::export_proxy_logs_json,
+            commands::proxy::get_audit_logs,
+            commands::proxy::export_audit_logs,
             commands::proxy::get_proxy_logs_count_filtered,
             commands::proxy::get_proxy_logs_filtered,
             commands::proxy::set_proxy_monitor_enabled,
@@ -535,6 +583,11 @@ pub fn run() {
             commands::get_token_stats_model_trend_daily,
             commands::get_token_stats_account_trend_hourly,
             commands::get_token_stats_account_trend_daily,
+            // 配额历史与耗尽预测
+            commands::get_quota_history,
+            commands::get_quota_forecast,
+            commands::get_pool_quota_forecast,
+            commands::test_background_task_rules,
             proxy::cli_sync::get_cli_sync_status,
             proxy::cli_sync::execute_cli_sync,
             proxy::cli_sync::execute_cli_restore,
@@ -585,6 +638,9 @@ pub fn run() {
             commands::user_token::renew_user_token,
             commands::user_token::get_token_ip_bindings,
             commands::user_token::get_user_token_summary,
+            commands::admin_key::list_admin_keys,
+            commands::admin_key::create_admin_key,
+            commands::admin_key::revoke_admin_key,
         ])
         .build(tauri::generate_context!())
         .expect("error while building tauri application")
@@ -60,6 +60,9 @@ pub struct Account {
     /// 用户自定义标签
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub custom_label: Option<String>,
+    /// 所属账号分组 (索引中分组成员关系的副本，索引损坏重建时据此恢复分组)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<String>,
 }
 
 impl Account {
@@ -89,6 +92,7 @@ impl Account {
             proxy_id: None,
             proxy_bound_at: None,
             custom_label: None,
+            groups: Vec::new(),
         }
     }
 
@@ -107,6 +111,19 @@ pub struct AccountIndex {
     pub version: String,
     pub accounts: Vec<AccountSummary>,
     pub current_account_id: Option<String>,
+    /// 命名账号分组 (用户令牌可绑定到分组，仅从组内账号调度)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<AccountGroup>,
+}
+
+/// 账号分组
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccountGroup {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub account_ids: Vec<String>,
 }
 
 /// 账号摘要信息
@@ -132,6 +149,7 @@ impl AccountIndex {
             version: "2.0".to_string(),
             accounts: Vec::new(),
             current_account_id: None,
+            groups: Vec::new(),
         }
     }
 }
@@ -3,7 +3,7 @@ pub mod token;
 pub mod quota;
 pub mod config;
 
-pub use account::{Account, AccountIndex, AccountSummary, DeviceProfile, DeviceProfileVersion, AccountExportItem, AccountExportResponse};
+pub use account::{Account, AccountGroup, AccountIndex, AccountSummary, DeviceProfile, DeviceProfileVersion, AccountExportItem, AccountExportResponse};
 pub use token::TokenData;
 pub use quota::QuotaData;
 pub use config::{AppConfig, QuotaProtectionConfig, CircuitBreakerConfig};
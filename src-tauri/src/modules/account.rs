@@ -6,7 +6,7 @@ use std::path::PathBuf;
 use uuid::Uuid;
 
 use crate::models::{
-    Account, AccountIndex, AccountSummary, DeviceProfile, DeviceProfileVersion, QuotaData,
+    Account, AccountGroup, AccountIndex, AccountSummary, DeviceProfile, DeviceProfileVersion, QuotaData,
     TokenData,
 };
 use crate::modules;
@@ -215,6 +215,36 @@ mod tests {
         println!("Missing index with existing accounts: successfully recovered {} accounts", index.accounts.len());
     }
 
+    #[test]
+    fn test_missing_index_recovers_groups_from_account_files() {
+        let _guard = TEST_MUTEX.lock().unwrap();
+        let dir = TestDataDir::new();
+
+        create_account_file(dir.path(), "acc-1", "user1@example.com");
+        create_account_file(dir.path(), "acc-2", "user2@example.com");
+        create_account_file(dir.path(), "acc-3", "user3@example.com");
+        for (id, groups) in [("acc-1", vec!["team-a", "team-b"]), ("acc-2", vec!["team-a"])] {
+            let path = dir.path().join("accounts").join(format!("{}.json", id));
+            let mut account = load_account_at_path(&path).unwrap();
+            account.groups = groups.into_iter().map(String::from).collect();
+            fs::write(&path, serde_json::to_string_pretty(&account).unwrap()).unwrap();
+        }
+
+        let index = load_account_index_in_dir(dir.path()).expect("Should recover from accounts directory");
+        let groups: Vec<(String, Vec<String>)> = index
+            .groups
+            .into_iter()
+            .map(|g| (g.name, g.account_ids))
+            .collect();
+        assert_eq!(
+            groups,
+            vec![
+                ("team-a".to_string(), vec!["acc-1".to_string(), "acc-2".to_string()]),
+                ("team-b".to_string(), vec!["acc-1".to_string()]),
+            ]
+        );
+    }
+
     #[test]
     fn test_save_account_index_roundtrip() {
         let _guard = TEST_MUTEX.lock().unwrap();
@@ -247,6 +277,11 @@ mod tests {
                 },
             ],
             current_account_id: Some("acc-1".to_string()),
+            groups: vec![AccountGroup {
+                name: "team-a".to_string(),
+                description: None,
+                account_ids: vec!["acc-1".to_string()],
+            }],
         };
 
         // Save the index
@@ -258,6 +293,11 @@ mod tests {
         // Assert it matches
         assert_eq!(loaded.accounts.len(), 2, "Should have 2 accounts");
         assert_eq!(loaded.current_account_id, Some("acc-1".to_string()), "current_account_id should match");
+        assert_eq!(loaded.groups, index.groups, "groups should roundtrip");
+        assert_eq!(
+            group_memberships_in_dir(dir.path()).get("acc-1").map(|g| g.contains("team-a")),
+            Some(true)
+        );
         
         // Check first account
         let acc1 = loaded.accounts.iter().find(|a| a.id == "acc-1").expect("acc-1 should exist");
@@ -319,6 +359,30 @@ mod tests {
         println!("Backup creation on parse failure: successfully created backup");
     }
 
+    #[test]
+    fn test_migrate_plaintext_credentials_in_dir() {
+        let _guard = TEST_MUTEX.lock().unwrap();
+        let dir = TestDataDir::new();
+        create_account_file(dir.path(), "plain-acc", "plain@example.com");
+
+        let accounts_dir = dir.path().join("accounts");
+        let account_path = accounts_dir.join("plain-acc.json");
+        assert_eq!(migrate_credentials_in_dir(&accounts_dir).unwrap(), 1);
+
+        // 文件中不再包含明文凭据
+        let raw = fs::read_to_string(&account_path).unwrap();
+        assert!(!raw.contains("test_refresh_token"));
+        assert!(!raw.contains("test_access_token"));
+
+        // 透明解密
+        let account = load_account_at_path(&account_path).unwrap();
+        assert_eq!(account.token.refresh_token, "test_refresh_token");
+        assert_eq!(account.token.access_token, "test_access_token");
+
+        // 已迁移的文件不会被重复处理
+        assert_eq!(migrate_credentials_in_dir(&accounts_dir).unwrap(), 0);
+        assert_eq!(fs::read_to_string(&account_path).unwrap(), raw);
+    }
 }
 
 /// Global account write lock to prevent corruption during concurrent operations
@@ -457,6 +521,8 @@ fn save_account_index_in_dir(data_dir: &PathBuf, index: &AccountIndex) -> Result
 fn rebuild_index_from_accounts_in_dir(data_dir: &PathBuf) -> Result<AccountIndex, String> {
     let accounts_dir = data_dir.join(ACCOUNTS_DIR);
     let mut summaries = Vec::new();
+    // 分组名 -> 成员账号 (来自账号文件中的分组副本)
+    let mut groups: std::collections::BTreeMap<String, Vec<String>> = std::collections::BTreeMap::new();
 
     if accounts_dir.exists() {
         if let Ok(entries) = fs::read_dir(&accounts_dir) {
@@ -466,6 +532,9 @@ fn rebuild_index_from_accounts_in_dir(data_dir: &PathBuf) -> Result<AccountIndex
                     if let Some(account_id) = path.file_stem().and_then(|s| s.to_str()) {
                         match load_account_at_path(&path) {
                             Ok(account) => {
+                                    for group in &account.groups {
+                                        groups.entry(group.clone()).or_default().push(account.id.clone());
+                                    }
                                     summaries.push(AccountSummary {
                                         id: account.id,
                                         email: account.email,
@@ -499,15 +568,29 @@ fn rebuild_index_from_accounts_in_dir(data_dir: &PathBuf) -> Result<AccountIndex
 
     let current_account_id = summaries.first().map(|s| s.id.clone());
 
+    let groups: Vec<AccountGroup> = groups
+        .into_iter()
+        .map(|(name, mut account_ids)| {
+            account_ids.sort();
+            AccountGroup {
+                name,
+                description: None,
+                account_ids,
+            }
+        })
+        .collect();
+
     crate::modules::logger::log_info(&format!(
-        "Rebuilt index from accounts directory: {} accounts recovered",
-        summaries.len()
+        "Rebuilt index from accounts directory: {} accounts, {} groups recovered",
+        summaries.len(),
+        groups.len()
     ));
 
     Ok(AccountIndex {
         version: "2.0".to_string(),
         accounts: summaries,
         current_account_id,
+        groups,
     })
 }
 
@@ -515,7 +598,115 @@ fn rebuild_index_from_accounts_in_dir(data_dir: &PathBuf) -> Result<AccountIndex
 fn load_account_at_path(account_path: &PathBuf) -> Result<Account, String> {
     let content = fs::read_to_string(account_path)
         .map_err(|e| format!("failed_to_read_account_data: {}", e))?;
-    serde_json::from_str(&content).map_err(|e| format!("failed_to_parse_account_data: {}", e))
+    let mut value: serde_json::Value = serde_json::from_str(&content)
+        .map_err(|e| format!("failed_to_parse_account_data: {}", e))?;
+    open_account_tokens(&mut value)?;
+    serde_json::from_value(value).map_err(|e| format!("failed_to_parse_account_data: {}", e))
+}
+
+/// 落盘加密的凭据字段
+const SEALED_TOKEN_FIELDS: [&str; 2] = ["access_token", "refresh_token"];
+
+/// 加密账号 JSON 中的 token 字段 (写盘前调用)
+pub fn seal_account_tokens(value: &mut serde_json::Value) -> Result<(), String> {
+    if let Some(token) = value.get_mut("token").and_then(|t| t.as_object_mut()) {
+        for field in SEALED_TOKEN_FIELDS {
+            if let Some(serde_json::Value::String(raw)) = token.get_mut(field) {
+                *raw = crate::utils::crypto::seal_credential(raw)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 解密账号 JSON 中的 token 字段 (兼容旧版明文)
+pub fn open_account_tokens(value: &mut serde_json::Value) -> Result<(), String> {
+    if let Some(token) = value.get_mut("token").and_then(|t| t.as_object_mut()) {
+        for field in SEALED_TOKEN_FIELDS {
+            if let Some(serde_json::Value::String(raw)) = token.get_mut(field) {
+                *raw = crate::utils::crypto::open_credential(raw)
+                    .map_err(|e| format!("failed_to_decrypt_account_{}: {}", field, e))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn account_tokens_need_reseal(value: &serde_json::Value) -> bool {
+    value
+        .get("token")
+        .and_then(|t| t.as_object())
+        .map(|token| {
+            SEALED_TOKEN_FIELDS.iter().any(|field| {
+                token
+                    .get(*field)
+                    .and_then(|v| v.as_str())
+                    .map(crate::utils::crypto::credential_needs_reseal)
+                    .unwrap_or(false)
+            })
+        })
+        .unwrap_or(false)
+}
+
+/// 将目录内明文 (或由其他密钥来源加密) 的账号凭据重新加密，返回处理的文件数
+fn migrate_credentials_in_dir(accounts_dir: &PathBuf) -> Result<usize, String> {
+    let entries = match fs::read_dir(accounts_dir) {
+        Ok(entries) => entries,
+        Err(_) => return Ok(0),
+    };
+
+    let mut migrated = 0;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let Ok(mut value) = serde_json::from_str::<serde_json::Value>(&content) else {
+            continue;
+        };
+        if !account_tokens_need_reseal(&value) {
+            continue;
+        }
+
+        // seal_credential 不会重复加密，需先解密再用当前密钥写回
+        if let Err(e) = open_account_tokens(&mut value).and_then(|_| seal_account_tokens(&mut value)) {
+            modules::logger::log_warn(&format!(
+                "Skipping credential migration for {:?}: {}",
+                path.file_name().unwrap_or_default(),
+                e
+            ));
+            continue;
+        }
+        let content = serde_json::to_string_pretty(&value)
+            .map_err(|e| format!("failed_to_serialize_account_data: {}", e))?;
+        let temp_path = path.with_extension(format!("json.tmp.{}", Uuid::new_v4()));
+        if let Err(e) = fs::write(&temp_path, content) {
+            let _ = fs::remove_file(&temp_path);
+            return Err(format!("failed_to_write_temp_account_file: {}", e));
+        }
+        if let Err(e) = atomic_replace_file(&temp_path, &path) {
+            let _ = fs::remove_file(&temp_path);
+            return Err(format!("failed_to_replace_account_file: {}", e));
+        }
+        migrated += 1;
+    }
+    Ok(migrated)
+}
+
+/// 一次性迁移: 加密现有账号文件中的明文凭据 (启动时调用，已加密的文件不做改动)
+pub fn migrate_plaintext_credentials() -> Result<usize, String> {
+    let accounts_dir = get_accounts_dir()?;
+    let migrated = migrate_credentials_in_dir(&accounts_dir)?;
+    if migrated > 0 {
+        modules::logger::log_info(&format!(
+            "Encrypted stored credentials for {} account(s)",
+            migrated
+        ));
+    }
+    Ok(migrated)
 }
 
 /// Load account index with recovery support
@@ -659,7 +850,10 @@ pub fn save_account(account: &Account) -> Result<(), String> {
     let temp_filename = format!("{}.tmp.{}", account.id, Uuid::new_v4());
     let temp_path = accounts_dir.join(&temp_filename);
 
-    let content = serde_json::to_string_pretty(account)
+    let mut value = serde_json::to_value(account)
+        .map_err(|e| format!("failed_to_serialize_account_data: {}", e))?;
+    seal_account_tokens(&mut value)?;
+    let content = serde_json::to_string_pretty(&value)
         .map_err(|e| format!("failed_to_serialize_account_data: {}", e))?;
 
     if let Err(e) = std::fs::write(&temp_path, content) {
@@ -836,6 +1030,9 @@ pub fn delete_account(account_id: &str) -> Result<(), String> {
     if index.accounts.len() == original_len {
         return Err(format!("Account ID not found: {}", account_id));
     }
+    for group in index.groups.iter_mut() {
+        group.account_ids.retain(|id| id != account_id);
+    }
 
     // Clear current account if it's being deleted
     if index.current_account_id.as_deref() == Some(account_id) {
@@ -871,6 +1068,9 @@ pub fn delete_accounts(account_ids: &[String]) -> Result<(), String> {
     for account_id in account_ids {
         // Remove from index
         index.accounts.retain(|s| &s.id != account_id);
+        for group in index.groups.iter_mut() {
+            group.account_ids.retain(|id| id != account_id);
+        }
 
         // Clear current account if it's being deleted
         if index.current_account_id.as_deref() == Some(account_id) {
@@ -895,6 +1095,134 @@ pub fn delete_accounts(account_ids: &[String]) -> Result<(), String> {
     save_account_index(&index)
 }
 
+/// 分组名仅允许字母、数字、'-'、'_'、'.'
+fn validate_group_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.len() > 64 {
+        return Err("invalid_group_name: must be 1-64 characters".to_string());
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    {
+        return Err(format!(
+            "invalid_group_name: '{}' may only contain letters, digits, '-', '_' and '.'",
+            name
+        ));
+    }
+    Ok(())
+}
+
+/// List account groups
+pub fn list_account_groups() -> Result<Vec<AccountGroup>, String> {
+    Ok(load_account_index()?.groups)
+}
+
+/// Create or update an account group (membership is replaced)
+pub fn save_account_group(mut group: AccountGroup) -> Result<AccountGroup, String> {
+    group.name = group.name.trim().to_string();
+    validate_group_name(&group.name)?;
+
+    let _lock = ACCOUNT_INDEX_LOCK
+        .lock()
+        .map_err(|e| format!("failed_to_acquire_lock: {}", e))?;
+    let mut index = load_account_index()?;
+
+    let mut seen = std::collections::HashSet::new();
+    group.account_ids.retain(|id| seen.insert(id.clone()));
+    if let Some(unknown) = group
+        .account_ids
+        .iter()
+        .find(|id| !index.accounts.iter().any(|s| &s.id == *id))
+    {
+        return Err(format!("Account ID not found: {}", unknown));
+    }
+
+    // 新旧成员都需要刷新 TokenManager 中的分组信息
+    let mut affected: Vec<String> = group.account_ids.clone();
+    match index.groups.iter_mut().find(|g| g.name == group.name) {
+        Some(existing) => {
+            affected.extend(existing.account_ids.drain(..));
+            *existing = group.clone();
+        }
+        None => index.groups.push(group.clone()),
+    }
+
+    save_account_index(&index)?;
+    sync_account_group_memberships(&index, &affected);
+    for account_id in affected {
+        crate::proxy::server::trigger_account_reload(&account_id);
+    }
+
+    Ok(group)
+}
+
+/// Delete an account group
+pub fn delete_account_group(name: &str) -> Result<(), String> {
+    let _lock = ACCOUNT_INDEX_LOCK
+        .lock()
+        .map_err(|e| format!("failed_to_acquire_lock: {}", e))?;
+    let mut index = load_account_index()?;
+
+    let position = index
+        .groups
+        .iter()
+        .position(|g| g.name == name)
+        .ok_or_else(|| format!("Account group not found: {}", name))?;
+    let removed = index.groups.remove(position);
+
+    save_account_index(&index)?;
+    sync_account_group_memberships(&index, &removed.account_ids);
+    for account_id in removed.account_ids {
+        crate::proxy::server::trigger_account_reload(&account_id);
+    }
+
+    Ok(())
+}
+
+/// 将索引中的分组成员关系同步到账号文件 (索引损坏重建时据此恢复分组)
+fn sync_account_group_memberships(index: &AccountIndex, account_ids: &[String]) {
+    for account_id in account_ids {
+        let mut names: Vec<String> = index
+            .groups
+            .iter()
+            .filter(|g| g.account_ids.contains(account_id))
+            .map(|g| g.name.clone())
+            .collect();
+        names.sort();
+
+        let result = load_account(account_id).and_then(|mut account| {
+            if account.groups == names {
+                return Ok(());
+            }
+            account.groups = names;
+            save_account(&account)
+        });
+        if let Err(e) = result {
+            crate::modules::logger::log_warn(&format!(
+                "Failed to sync group memberships to account {}: {}",
+                account_id, e
+            ));
+        }
+    }
+}
+
+/// 账号 -> 所属分组 (供 TokenManager 调度使用；索引不存在或损坏时返回空表)
+pub fn group_memberships_in_dir(data_dir: &std::path::Path) -> HashMap<String, std::collections::HashSet<String>> {
+    let mut memberships: HashMap<String, std::collections::HashSet<String>> = HashMap::new();
+    let index: Option<AccountIndex> = fs::read(data_dir.join(ACCOUNTS_INDEX))
+        .ok()
+        .and_then(|raw| serde_json::from_str(&sanitize_index_content(&raw)).ok());
+    for group in index.map(|i| i.groups).unwrap_or_default() {
+        for account_id in group.account_ids {
+            memberships
+                .entry(account_id)
+                .or_default()
+                .insert(group.name.clone());
+        }
+    }
+    memberships
+}
+
 /// Reorder account list
 /// Update account order in index file based on provided IDs
 pub fn reorder_accounts(account_ids: &[String]) -> Result<(), String> {
@@ -1275,6 +1603,7 @@ fn apply_profile_to_account(
 }
 
 /// List available device profile versions for an account (including baseline)
+#[cfg(feature = "desktop")]
 pub fn list_device_versions(account_id: &str) -> Result<DeviceProfiles, String> {
     get_device_profiles(account_id)
 }
@@ -1326,6 +1655,7 @@ pub fn delete_device_version(account_id: &str, version_id: &str) -> Result<(), S
     Ok(())
 }
 /// Apply account bound device profile to storage.json
+#[cfg(feature = "desktop")]
 pub fn apply_device_profile(account_id: &str) -> Result<DeviceProfile, String> {
     use crate::modules::device;
     let mut account = load_account(account_id)?;
@@ -1376,6 +1706,7 @@ pub fn get_current_account() -> Result<Option<Account>, String> {
 }
 
 /// Set current active account ID
+#[cfg(feature = "desktop")]
 pub fn set_current_account_id(account_id: &str) -> Result<(), String> {
     let _lock = ACCOUNT_INDEX_LOCK
         .lock()
@@ -1454,6 +1785,13 @@ pub fn update_account_quota(account_id: &str, quota: QuotaData) -> Result<(), St
     // Save account first
     save_account(&account)?;
 
+    // 记录配额快照 (用于消耗速率与耗尽预测)
+    if let Some(ref q) = account.quota {
+        if let Err(e) = crate::modules::quota_history::record_quota_snapshot(&account.id, &account.email, q) {
+            crate::modules::logger::log_warn(&format!("[Quota] Failed to record quota history: {}", e));
+        }
+    }
+
     // [FIX] 同时更新索引文件中的摘要信息，确保列表页图标即时刷新
     {
         let _lock = ACCOUNT_INDEX_LOCK
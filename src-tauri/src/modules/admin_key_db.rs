@@ -0,0 +1,400 @@
+//! Admin Key Database Module
+//! 具名管理密钥：每个密钥带一组权限范围，按管理接口路由逐一校验
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const DB_FILE: &str = "admin_keys.db";
+/// 密钥前缀，便于在日志与配置中识别
+pub const KEY_PREFIX: &str = "ak-";
+/// last_used_at 写入节流间隔 (秒)，避免每个管理请求都写库
+const LAST_USED_WRITE_INTERVAL_SECS: i64 = 60;
+
+/// 管理接口权限范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AdminScope {
+    #[serde(rename = "accounts:read")]
+    AccountsRead,
+    #[serde(rename = "accounts:write")]
+    AccountsWrite,
+    #[serde(rename = "config:read")]
+    ConfigRead,
+    #[serde(rename = "config:write")]
+    ConfigWrite,
+    #[serde(rename = "logs:read")]
+    LogsRead,
+    #[serde(rename = "logs:write")]
+    LogsWrite,
+    #[serde(rename = "security:read")]
+    SecurityRead,
+    #[serde(rename = "security:write")]
+    SecurityWrite,
+    #[serde(rename = "user-tokens:read")]
+    UserTokensRead,
+    #[serde(rename = "user-tokens:write")]
+    UserTokensWrite,
+}
+
+impl AdminScope {
+    pub const ALL: [AdminScope; 10] = [
+        AdminScope::AccountsRead,
+        AdminScope::AccountsWrite,
+        AdminScope::ConfigRead,
+        AdminScope::ConfigWrite,
+        AdminScope::LogsRead,
+        AdminScope::LogsWrite,
+        AdminScope::SecurityRead,
+        AdminScope::SecurityWrite,
+        AdminScope::UserTokensRead,
+        AdminScope::UserTokensWrite,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AdminScope::AccountsRead => "accounts:read",
+            AdminScope::AccountsWrite => "accounts:write",
+            AdminScope::ConfigRead => "config:read",
+            AdminScope::ConfigWrite => "config:write",
+            AdminScope::LogsRead => "logs:read",
+            AdminScope::LogsWrite => "logs:write",
+            AdminScope::SecurityRead => "security:read",
+            AdminScope::SecurityWrite => "security:write",
+            AdminScope::UserTokensRead => "user-tokens:read",
+            AdminScope::UserTokensWrite => "user-tokens:write",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|s| s.as_str() == value)
+    }
+
+    /// 写权限隐含同一资源的读权限
+    pub fn implied_read(&self) -> Option<AdminScope> {
+        match self {
+            AdminScope::AccountsWrite => Some(AdminScope::AccountsRead),
+            AdminScope::ConfigWrite => Some(AdminScope::ConfigRead),
+            AdminScope::LogsWrite => Some(AdminScope::LogsRead),
+            AdminScope::SecurityWrite => Some(AdminScope::SecurityRead),
+            AdminScope::UserTokensWrite => Some(AdminScope::UserTokensRead),
+            _ => None,
+        }
+    }
+}
+
+/// 管理密钥 (不含明文密钥，仅保存 SHA-256 摘要)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AdminKey {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub scopes: Vec<AdminScope>,
+    /// 明文密钥的前几位，用于界面识别
+    pub key_hint: String,
+    pub created_at: i64,
+    pub last_used_at: Option<i64>,
+    /// 吊销时间 (吊销后不可再使用，记录保留用于审计)
+    pub revoked_at: Option<i64>,
+}
+
+impl AdminKey {
+    pub fn has_scope(&self, scope: AdminScope) -> bool {
+        self.scopes
+            .iter()
+            .any(|s| *s == scope || s.implied_read() == Some(scope))
+    }
+}
+
+/// 新建的管理密钥 (明文密钥仅在创建时返回一次)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatedAdminKey {
+    #[serde(flatten)]
+    pub key: AdminKey,
+    pub secret: String,
+}
+
+pub(crate) fn get_db_path() -> Result<PathBuf, String> {
+    let data_dir = crate::modules::account::get_data_dir()?;
+    Ok(data_dir.join(DB_FILE))
+}
+
+fn connect_db() -> Result<Connection, String> {
+    connect_db_at(&get_db_path()?)
+}
+
+fn connect_db_at(db_path: &Path) -> Result<Connection, String> {
+    let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
+
+    // Enable WAL mode for better concurrency
+    conn.pragma_update(None, "journal_mode", "WAL")
+        .map_err(|e| e.to_string())?;
+    conn.pragma_update(None, "busy_timeout", 5000)
+        .map_err(|e| e.to_string())?;
+    conn.pragma_update(None, "synchronous", "NORMAL")
+        .map_err(|e| e.to_string())?;
+
+    Ok(conn)
+}
+
+fn create_schema(conn: &Connection) -> Result<(), String> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS admin_keys (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            description TEXT,
+            key_hash TEXT UNIQUE NOT NULL,
+            key_hint TEXT NOT NULL,
+            scopes TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            last_used_at INTEGER,
+            revoked_at INTEGER
+        )",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Initialize the admin key database
+pub fn init_db() -> Result<(), String> {
+    let conn = connect_db()?;
+    create_schema(&conn)
+}
+
+fn hash_key(secret: &str) -> String {
+    use sha2::{Digest, Sha256};
+
+    let mut hasher = Sha256::new();
+    hasher.update(secret.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+fn scopes_to_json(scopes: &[AdminScope]) -> String {
+    serde_json::to_string(scopes).unwrap_or_else(|_| "[]".to_string())
+}
+
+fn key_from_row(row: &rusqlite::Row) -> rusqlite::Result<AdminKey> {
+    let scopes: String = row.get("scopes")?;
+    Ok(AdminKey {
+        id: row.get("id")?,
+        name: row.get("name")?,
+        description: row.get("description")?,
+        // 未知的权限范围 (例如来自更新版本) 直接忽略
+        scopes: serde_json::from_str::<Vec<String>>(&scopes)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|s| AdminScope::parse(s))
+            .collect(),
+        key_hint: row.get("key_hint")?,
+        created_at: row.get("created_at")?,
+        last_used_at: row.get("last_used_at")?,
+        revoked_at: row.get("revoked_at")?,
+    })
+}
+
+const SELECT_COLUMNS: &str =
+    "id, name, description, key_hint, scopes, created_at, last_used_at, revoked_at";
+
+fn create_key_in(
+    conn: &Connection,
+    name: &str,
+    description: Option<String>,
+    scopes: &[AdminScope],
+) -> Result<CreatedAdminKey, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Admin key name is required".to_string());
+    }
+    if scopes.is_empty() {
+        return Err("At least one scope is required".to_string());
+    }
+
+    let mut unique: Vec<AdminScope> = Vec::new();
+    for scope in scopes {
+        if !unique.contains(scope) {
+            unique.push(*scope);
+        }
+    }
+    let secret = format!("{}{}", KEY_PREFIX, uuid::Uuid::new_v4().simple());
+    let key = AdminKey {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: name.to_string(),
+        description,
+        scopes: unique,
+        key_hint: secret.chars().take(KEY_PREFIX.len() + 6).collect(),
+        created_at: chrono::Utc::now().timestamp(),
+        last_used_at: None,
+        revoked_at: None,
+    };
+
+    conn.execute(
+        "INSERT INTO admin_keys (id, name, description, key_hash, key_hint, scopes, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            key.id,
+            key.name,
+            key.description,
+            hash_key(&secret),
+            key.key_hint,
+            scopes_to_json(&key.scopes),
+            key.created_at,
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(CreatedAdminKey { key, secret })
+}
+
+fn list_keys_in(conn: &Connection) -> Result<Vec<AdminKey>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM admin_keys ORDER BY created_at DESC",
+            SELECT_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], key_from_row).map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
+}
+
+fn revoke_key_in(conn: &Connection, id: &str, now: i64) -> Result<(), String> {
+    let updated = conn
+        .execute(
+            "UPDATE admin_keys SET revoked_at = ?2 WHERE id = ?1 AND revoked_at IS NULL",
+            params![id, now],
+        )
+        .map_err(|e| e.to_string())?;
+    if updated == 0 {
+        return Err(format!("Admin key not found or already revoked: {}", id));
+    }
+    Ok(())
+}
+
+fn authenticate_in(conn: &Connection, secret: &str, now: i64) -> Result<Option<AdminKey>, String> {
+    let key = conn
+        .query_row(
+            &format!(
+                "SELECT {} FROM admin_keys WHERE key_hash = ?1 AND revoked_at IS NULL",
+                SELECT_COLUMNS
+            ),
+            params![hash_key(secret)],
+            key_from_row,
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    let stale = |k: &AdminKey| {
+        k.last_used_at
+            .map_or(true, |t| now - t >= LAST_USED_WRITE_INTERVAL_SECS)
+    };
+    if let Some(key) = key.as_ref().filter(|k| stale(k)) {
+        conn.execute(
+            "UPDATE admin_keys SET last_used_at = ?2 WHERE id = ?1",
+            params![key.id, now],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(key)
+}
+
+/// 创建管理密钥
+pub fn create_key(
+    name: &str,
+    description: Option<String>,
+    scopes: &[AdminScope],
+) -> Result<CreatedAdminKey, String> {
+    let conn = connect_db()?;
+    create_key_in(&conn, name, description, scopes)
+}
+
+/// 列出全部管理密钥 (含已吊销)
+pub fn list_keys() -> Result<Vec<AdminKey>, String> {
+    let conn = connect_db()?;
+    list_keys_in(&conn)
+}
+
+/// 吊销管理密钥
+pub fn revoke_key(id: &str) -> Result<(), String> {
+    let conn = connect_db()?;
+    revoke_key_in(&conn, id, chrono::Utc::now().timestamp())
+}
+
+/// 校验明文密钥，返回未吊销的管理密钥并更新最后使用时间
+pub fn authenticate(secret: &str) -> Result<Option<AdminKey>, String> {
+    if !secret.starts_with(KEY_PREFIX) {
+        return Ok(None);
+    }
+    let conn = connect_db()?;
+    authenticate_in(&conn, secret, chrono::Utc::now().timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        create_schema(&conn).unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_scope_serde_and_implied_read() {
+        let json = serde_json::to_string(&[AdminScope::LogsRead, AdminScope::UserTokensWrite]).unwrap();
+        assert_eq!(json, r#"["logs:read","user-tokens:write"]"#);
+        assert_eq!(AdminScope::parse("security:write"), Some(AdminScope::SecurityWrite));
+        assert_eq!(AdminScope::parse("accounts:delete"), None);
+
+        let key = AdminKey {
+            id: "k".to_string(),
+            name: "bot".to_string(),
+            description: None,
+            scopes: vec![AdminScope::AccountsWrite, AdminScope::LogsRead],
+            key_hint: "ak-123456".to_string(),
+            created_at: 0,
+            last_used_at: None,
+            revoked_at: None,
+        };
+        assert!(key.has_scope(AdminScope::AccountsRead));
+        assert!(key.has_scope(AdminScope::LogsRead));
+        assert!(!key.has_scope(AdminScope::LogsWrite));
+        assert!(!key.has_scope(AdminScope::ConfigRead));
+    }
+
+    #[test]
+    fn test_create_authenticate_and_revoke() {
+        let conn = setup();
+        assert!(create_key_in(&conn, " ", None, &[AdminScope::LogsRead]).is_err());
+        assert!(create_key_in(&conn, "dashboard", None, &[]).is_err());
+
+        let created = create_key_in(
+            &conn,
+            "dashboard",
+            Some("Grafana".to_string()),
+            &[AdminScope::LogsRead],
+        )
+        .unwrap();
+        assert!(created.secret.starts_with(KEY_PREFIX));
+        assert!(created.secret.starts_with(&created.key.key_hint));
+
+        let stored: String = conn
+            .query_row("SELECT key_hash FROM admin_keys", [], |r| r.get(0))
+            .unwrap();
+        assert_ne!(stored, created.secret);
+
+        let found = authenticate_in(&conn, &created.secret, 100).unwrap().unwrap();
+        assert_eq!(found.name, "dashboard");
+        assert_eq!(found.scopes, vec![AdminScope::LogsRead]);
+        assert_eq!(list_keys_in(&conn).unwrap()[0].last_used_at, Some(100));
+        // 节流窗口内不重复写入
+        authenticate_in(&conn, &created.secret, 130).unwrap().unwrap();
+        assert_eq!(list_keys_in(&conn).unwrap()[0].last_used_at, Some(100));
+        authenticate_in(&conn, &created.secret, 160).unwrap().unwrap();
+        assert_eq!(list_keys_in(&conn).unwrap()[0].last_used_at, Some(160));
+        assert!(authenticate_in(&conn, "ak-wrong", 100).unwrap().is_none());
+
+        revoke_key_in(&conn, &created.key.id, 200).unwrap();
+        assert!(authenticate_in(&conn, &created.secret, 300).unwrap().is_none());
+        assert!(revoke_key_in(&conn, &created.key.id, 300).is_err());
+        assert_eq!(list_keys_in(&conn).unwrap()[0].revoked_at, Some(200));
+    }
+}
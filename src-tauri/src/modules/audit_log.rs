@@ -0,0 +1,423 @@
+//! Audit Log Module
+//! 管理接口审计日志：只追加记录调用者、来源 IP、接口、脱敏后的请求摘要与结果，按保留天数清理
+
+use rusqlite::{params, Connection, ToSql};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{OnceLock, RwLock};
+
+use crate::proxy::config::AuditLogConfig;
+
+const DB_FILE: &str = "audit_log.db";
+/// 请求摘要的最大长度 (字符)
+const MAX_SUMMARY_CHARS: usize = 4000;
+/// 摘要中单个字符串字段的最大长度
+const MAX_STRING_CHARS: usize = 200;
+/// 摘要中数组保留的最大元素数
+const MAX_ARRAY_ITEMS: usize = 20;
+/// 两次过期清理的最小间隔 (秒)
+const CLEANUP_INTERVAL_SECS: i64 = 3600;
+
+/// 字段名包含这些片段时值被替换为 ***
+const SECRET_FRAGMENTS: &[&str] = &[
+    "password",
+    "secret",
+    "token",
+    "api_key",
+    "apikey",
+    "authorization",
+    "cookie",
+    "credential",
+];
+
+static GLOBAL_AUDIT_CONFIG: OnceLock<RwLock<AuditLogConfig>> = OnceLock::new();
+static LAST_CLEANUP: AtomicI64 = AtomicI64::new(0);
+
+/// 更新全局审计日志配置
+pub fn update_audit_log_config(config: AuditLogConfig) {
+    if let Some(lock) = GLOBAL_AUDIT_CONFIG.get() {
+        if let Ok(mut cfg) = lock.write() {
+            *cfg = config;
+        }
+    } else {
+        let _ = GLOBAL_AUDIT_CONFIG.set(RwLock::new(config));
+    }
+    // 保留天数可能变短，下次写入时立即清理
+    LAST_CLEANUP.store(0, Ordering::Relaxed);
+}
+
+fn current_config() -> AuditLogConfig {
+    GLOBAL_AUDIT_CONFIG
+        .get()
+        .and_then(|lock| lock.read().ok().map(|cfg| cfg.clone()))
+        .unwrap_or_default()
+}
+
+pub fn is_enabled() -> bool {
+    current_config().enabled
+}
+
+/// 单条审计记录
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AuditEntry {
+    #[serde(default)]
+    pub id: i64,
+    /// Unix 秒
+    pub timestamp: i64,
+    /// 调用者 (admin_password / api_key / anonymous / unauthenticated / user_token:<用户名>)
+    pub actor: String,
+    pub source_ip: Option<String>,
+    pub method: String,
+    pub endpoint: String,
+    /// 脱敏后的请求体摘要
+    pub payload: Option<String>,
+    pub status: u16,
+    pub success: bool,
+    pub user_agent: Option<String>,
+}
+
+/// 审计日志查询条件
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditQuery {
+    /// 调用者 (精确匹配)
+    #[serde(default)]
+    pub actor: Option<String>,
+    /// 接口路径 (子串匹配)
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    /// 起始时间 (Unix 秒，含)
+    #[serde(default)]
+    pub since: Option<i64>,
+    /// 截止时间 (Unix 秒，含)
+    #[serde(default)]
+    pub until: Option<i64>,
+    #[serde(default)]
+    pub failed_only: bool,
+    #[serde(default)]
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub offset: Option<usize>,
+}
+
+pub(crate) fn get_db_path() -> Result<PathBuf, String> {
+    let data_dir = crate::modules::account::get_data_dir()?;
+    Ok(data_dir.join(DB_FILE))
+}
+
+fn connect_db() -> Result<Connection, String> {
+    connect_db_at(&get_db_path()?)
+}
+
+fn connect_db_at(db_path: &Path) -> Result<Connection, String> {
+    let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
+
+    // Enable WAL mode for better concurrency
+    conn.pragma_update(None, "journal_mode", "WAL")
+        .map_err(|e| e.to_string())?;
+    conn.pragma_update(None, "busy_timeout", 5000)
+        .map_err(|e| e.to_string())?;
+    conn.pragma_update(None, "synchronous", "NORMAL")
+        .map_err(|e| e.to_string())?;
+
+    Ok(conn)
+}
+
+fn create_schema(conn: &Connection) -> Result<(), String> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS audit_logs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            timestamp INTEGER NOT NULL,
+            actor TEXT NOT NULL,
+            source_ip TEXT,
+            method TEXT NOT NULL,
+            endpoint TEXT NOT NULL,
+            payload TEXT,
+            status INTEGER NOT NULL,
+            success INTEGER NOT NULL,
+            user_agent TEXT
+        )",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_audit_timestamp ON audit_logs (timestamp DESC)",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_audit_actor ON audit_logs (actor)",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Initialize the audit log database
+pub fn init_db() -> Result<(), String> {
+    let conn = connect_db()?;
+    create_schema(&conn)
+}
+
+fn is_secret_key(key: &str) -> bool {
+    let key = key.to_lowercase();
+    SECRET_FRAGMENTS.iter().any(|f| key.contains(f))
+}
+
+fn sanitize_value(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| {
+                    let v = if is_secret_key(k) && !v.is_null() {
+                        Value::String("***".to_string())
+                    } else {
+                        sanitize_value(v)
+                    };
+                    (k.clone(), v)
+                })
+                .collect(),
+        ),
+        Value::Array(arr) => {
+            let mut items: Vec<Value> = arr.iter().take(MAX_ARRAY_ITEMS).map(sanitize_value).collect();
+            if arr.len() > MAX_ARRAY_ITEMS {
+                items.push(Value::String(format!("...({} more)", arr.len() - MAX_ARRAY_ITEMS)));
+            }
+            Value::Array(items)
+        }
+        Value::String(s) if s.chars().count() > MAX_STRING_CHARS => {
+            let head: String = s.chars().take(MAX_STRING_CHARS).collect();
+            Value::String(format!("{}...({} chars)", head, s.chars().count()))
+        }
+        other => other.clone(),
+    }
+}
+
+/// 生成脱敏后的请求体摘要 (敏感字段替换为 ***，长字符串与大数组截断)
+pub fn summarize_payload(body: &[u8]) -> Option<String> {
+    if body.is_empty() {
+        return None;
+    }
+    let summary = match serde_json::from_slice::<Value>(body) {
+        Ok(json) => sanitize_value(&json).to_string(),
+        Err(_) => format!("[{} bytes non-JSON body]", body.len()),
+    };
+    if summary.chars().count() > MAX_SUMMARY_CHARS {
+        let head: String = summary.chars().take(MAX_SUMMARY_CHARS).collect();
+        Some(format!("{}...(truncated)", head))
+    } else {
+        Some(summary)
+    }
+}
+
+fn insert_entry(conn: &Connection, entry: &AuditEntry) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO audit_logs (timestamp, actor, source_ip, method, endpoint, payload, status, success, user_agent)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            entry.timestamp,
+            entry.actor,
+            entry.source_ip,
+            entry.method,
+            entry.endpoint,
+            entry.payload,
+            entry.status,
+            entry.success,
+            entry.user_agent,
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn cleanup_in(conn: &Connection, retention_days: u32, now: i64) -> Result<usize, String> {
+    let cutoff = now - retention_days as i64 * 24 * 3600;
+    conn.execute("DELETE FROM audit_logs WHERE timestamp < ?1", params![cutoff])
+        .map_err(|e| e.to_string())
+}
+
+/// 追加一条审计记录 (审计关闭时忽略)，并按保留天数定期清理过期记录
+pub fn record(entry: &AuditEntry) -> Result<(), String> {
+    let config = current_config();
+    if !config.enabled {
+        return Ok(());
+    }
+    let conn = connect_db()?;
+    insert_entry(&conn, entry)?;
+
+    let now = chrono::Utc::now().timestamp();
+    let last = LAST_CLEANUP.load(Ordering::Relaxed);
+    if config.retention_days > 0 && now - last >= CLEANUP_INTERVAL_SECS {
+        LAST_CLEANUP.store(now, Ordering::Relaxed);
+        let deleted = cleanup_in(&conn, config.retention_days, now)?;
+        if deleted > 0 {
+            tracing::info!("[Audit] Removed {} entries older than {} days", deleted, config.retention_days);
+        }
+    }
+    Ok(())
+}
+
+fn query_in(conn: &Connection, query: &AuditQuery, paged: bool) -> Result<Vec<AuditEntry>, String> {
+    let mut sql = String::from(
+        "SELECT id, timestamp, actor, source_ip, method, endpoint, payload, status, success, user_agent
+         FROM audit_logs WHERE 1 = 1",
+    );
+    let mut args: Vec<Box<dyn ToSql>> = Vec::new();
+    if let Some(actor) = query.actor.as_ref().filter(|a| !a.is_empty()) {
+        args.push(Box::new(actor.clone()));
+        sql.push_str(&format!(" AND actor = ?{}", args.len()));
+    }
+    if let Some(endpoint) = query.endpoint.as_ref().filter(|e| !e.is_empty()) {
+        args.push(Box::new(format!("%{}%", endpoint)));
+        sql.push_str(&format!(" AND endpoint LIKE ?{}", args.len()));
+    }
+    if let Some(since) = query.since {
+        args.push(Box::new(since));
+        sql.push_str(&format!(" AND timestamp >= ?{}", args.len()));
+    }
+    if let Some(until) = query.until {
+        args.push(Box::new(until));
+        sql.push_str(&format!(" AND timestamp <= ?{}", args.len()));
+    }
+    if query.failed_only {
+        sql.push_str(" AND success = 0");
+    }
+    sql.push_str(" ORDER BY timestamp DESC, id DESC");
+    if paged {
+        sql.push_str(&format!(
+            " LIMIT {} OFFSET {}",
+            query.limit.unwrap_or(100),
+            query.offset.unwrap_or(0)
+        ));
+    }
+
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(rusqlite::params_from_iter(args.iter()), |row| {
+            Ok(AuditEntry {
+                id: row.get(0)?,
+                timestamp: row.get(1)?,
+                actor: row.get(2)?,
+                source_ip: row.get(3)?,
+                method: row.get(4)?,
+                endpoint: row.get(5)?,
+                payload: row.get(6)?,
+                status: row.get(7)?,
+                success: row.get(8)?,
+                user_agent: row.get(9)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+
+    rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
+}
+
+/// 分页查询审计记录 (按时间倒序)
+pub fn query_entries(query: &AuditQuery) -> Result<Vec<AuditEntry>, String> {
+    let conn = connect_db()?;
+    query_in(&conn, query, true)
+}
+
+/// 导出符合条件的全部审计记录 (忽略分页参数)
+pub fn export_entries(query: &AuditQuery) -> Result<Vec<AuditEntry>, String> {
+    let conn = connect_db()?;
+    query_in(&conn, query, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(timestamp: i64, actor: &str, endpoint: &str, status: u16) -> AuditEntry {
+        AuditEntry {
+            id: 0,
+            timestamp,
+            actor: actor.to_string(),
+            source_ip: Some("10.0.0.2".to_string()),
+            method: "POST".to_string(),
+            endpoint: endpoint.to_string(),
+            payload: None,
+            status,
+            success: (200..300).contains(&status),
+            user_agent: None,
+        }
+    }
+
+    #[test]
+    fn test_summarize_payload_masks_secrets() {
+        let body = serde_json::json!({
+            "proxy": {"api_key": "sk-live", "admin_password": "hunter2", "port": 8045},
+            "refresh_token": "1//abc",
+            "note": "x".repeat(500),
+            "ids": (0..30).collect::<Vec<_>>(),
+        });
+        let summary = summarize_payload(body.to_string().as_bytes()).unwrap();
+        assert!(!summary.contains("sk-live"));
+        assert!(!summary.contains("hunter2"));
+        assert!(!summary.contains("1//abc"));
+        assert!(summary.contains("\"port\":8045"));
+        assert!(summary.contains("(500 chars)"));
+        assert!(summary.contains("...(10 more)"));
+
+        assert_eq!(summarize_payload(b""), None);
+        assert_eq!(summarize_payload(b"\x00\x01").unwrap(), "[2 bytes non-JSON body]");
+    }
+
+    #[test]
+    fn test_query_filters_and_retention() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_schema(&conn).unwrap();
+        let now = 1_700_000_000;
+        insert_entry(&conn, &entry(now - 40 * 86400, "admin_password", "/api/accounts/a1", 200)).unwrap();
+        insert_entry(&conn, &entry(now - 60, "admin_password", "/api/config", 200)).unwrap();
+        insert_entry(&conn, &entry(now - 30, "unauthenticated", "/api/config", 401)).unwrap();
+        insert_entry(&conn, &entry(now, "api_key", "/api/security/blacklist/clear", 200)).unwrap();
+
+        let all = query_in(&conn, &AuditQuery::default(), true).unwrap();
+        assert_eq!(all.len(), 4);
+        assert_eq!(all[0].actor, "api_key");
+
+        let config_changes = query_in(
+            &conn,
+            &AuditQuery {
+                endpoint: Some("/config".to_string()),
+                ..Default::default()
+            },
+            true,
+        )
+        .unwrap();
+        assert_eq!(config_changes.len(), 2);
+
+        let failed = query_in(
+            &conn,
+            &AuditQuery {
+                failed_only: true,
+                ..Default::default()
+            },
+            true,
+        )
+        .unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].status, 401);
+
+        let by_actor = query_in(
+            &conn,
+            &AuditQuery {
+                actor: Some("admin_password".to_string()),
+                since: Some(now - 3600),
+                ..Default::default()
+            },
+            true,
+        )
+        .unwrap();
+        assert_eq!(by_actor.len(), 1);
+
+        assert_eq!(cleanup_in(&conn, 30, now).unwrap(), 1);
+        assert_eq!(query_in(&conn, &AuditQuery::default(), false).unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,514 @@
+//! Config Validation
+//! gui_config.json 保存/重载前的字段级校验，以及与运行中配置的差异对比
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::models::AppConfig;
+
+/// 校验问题级别：Error 阻止保存，Warning 仅提示
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IssueSeverity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ValidationIssue {
+    /// 字段路径，如 "proxy.proxy_pool.proxies[0].url"
+    pub field: String,
+    pub message: String,
+    pub severity: IssueSeverity,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigChange {
+    pub path: String,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+    /// 该字段无法热更新，需重启反代服务后生效
+    pub requires_restart: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigValidationReport {
+    pub valid: bool,
+    pub issues: Vec<ValidationIssue>,
+    pub changes: Vec<ConfigChange>,
+}
+
+/// 需要重启才能生效的字段 (监听地址/端口)
+const RESTART_REQUIRED_PATHS: &[&str] = &["proxy.port", "proxy.allow_lan_access"];
+
+/// 差异中需要脱敏的字段名
+const SECRET_KEYS: &[&str] = &["api_key", "admin_password", "token", "password"];
+
+struct Issues(Vec<ValidationIssue>);
+
+impl Issues {
+    fn error(&mut self, field: impl Into<String>, message: impl Into<String>) {
+        self.0.push(ValidationIssue {
+            field: field.into(),
+            message: message.into(),
+            severity: IssueSeverity::Error,
+        });
+    }
+
+    fn warning(&mut self, field: impl Into<String>, message: impl Into<String>) {
+        self.0.push(ValidationIssue {
+            field: field.into(),
+            message: message.into(),
+            severity: IssueSeverity::Warning,
+        });
+    }
+
+    fn range<T: PartialOrd + std::fmt::Display>(&mut self, field: &str, value: T, min: T, max: T) {
+        if value < min || value > max {
+            self.error(field, format!("must be between {} and {} (got {})", min, max, value));
+        }
+    }
+
+    fn proxy_url(&mut self, field: &str, raw: &str, schemes: &[&str]) {
+        if raw.trim().is_empty() {
+            self.error(field, "URL must not be empty");
+            return;
+        }
+        match url::Url::parse(raw.trim()) {
+            Ok(u) if !schemes.contains(&u.scheme()) => self.error(
+                field,
+                format!("unsupported scheme '{}', expected one of: {}", u.scheme(), schemes.join(", ")),
+            ),
+            Ok(u) if u.host_str().map_or(true, |h| h.is_empty()) => {
+                self.error(field, "URL has no host")
+            }
+            Ok(_) => {}
+            Err(e) => self.error(field, format!("invalid URL: {}", e)),
+        }
+    }
+}
+
+const PROXY_SCHEMES: &[&str] = &["http", "https", "socks5", "socks5h"];
+const HTTP_SCHEMES: &[&str] = &["http", "https"];
+
+/// 校验完整配置
+///
+/// `known_models` 用于检测永远不会命中的通配符映射 (仅告警)。
+pub fn validate_app_config(config: &AppConfig, known_models: &[String]) -> Vec<ValidationIssue> {
+    let mut issues = Issues(Vec::new());
+    let proxy = &config.proxy;
+
+    // 1. 应用级
+    if config.auto_refresh && config.refresh_interval < 1 {
+        issues.error("refresh_interval", "must be at least 1 minute");
+    }
+    if config.auto_sync && config.sync_interval < 1 {
+        issues.error("sync_interval", "must be at least 1 minute");
+    }
+    if config.quota_protection.enabled {
+        issues.range(
+            "quota_protection.threshold_percentage",
+            config.quota_protection.threshold_percentage,
+            1,
+            99,
+        );
+    }
+    if config.circuit_breaker.enabled {
+        if config.circuit_breaker.backoff_steps.is_empty() {
+            issues.error("circuit_breaker.backoff_steps", "must contain at least one step");
+        }
+        for (i, step) in config.circuit_breaker.backoff_steps.iter().enumerate() {
+            if *step == 0 {
+                issues.error(format!("circuit_breaker.backoff_steps[{}]", i), "must be greater than 0");
+            }
+        }
+    }
+
+    // 2. 反代服务
+    if proxy.port == 0 {
+        issues.error("proxy.port", "must be between 1 and 65535");
+    }
+    issues.range("proxy.request_timeout", proxy.request_timeout, 30, 7200);
+    issues.range("proxy.scheduling.max_wait_seconds", proxy.scheduling.max_wait_seconds, 0, 300);
+
+    if proxy.upstream_proxy.enabled {
+        issues.proxy_url("proxy.upstream_proxy.url", &proxy.upstream_proxy.url, PROXY_SCHEMES);
+    }
+
+    // 3. 代理池
+    let pool = &proxy.proxy_pool;
+    if pool.enabled && pool.health_check_interval < 10 {
+        issues.error("proxy.proxy_pool.health_check_interval", "must be at least 10 seconds");
+    }
+    let mut seen_ids = std::collections::HashSet::new();
+    for (i, entry) in pool.proxies.iter().enumerate() {
+        let base = format!("proxy.proxy_pool.proxies[{}]", i);
+        if !seen_ids.insert(entry.id.as_str()) {
+            issues.error(format!("{}.id", base), format!("duplicate proxy id '{}'", entry.id));
+        }
+        issues.proxy_url(&format!("{}.url", base), &entry.url, PROXY_SCHEMES);
+        if let Some(check_url) = entry.health_check_url.as_deref().filter(|u| !u.trim().is_empty()) {
+            issues.proxy_url(&format!("{}.health_check_url", base), check_url, HTTP_SCHEMES);
+        }
+    }
+
+    // 4. 上游端点
+    for (i, endpoint) in proxy.upstream_endpoints.iter().enumerate() {
+        issues.proxy_url(&format!("proxy.upstream_endpoints[{}].url", i), &endpoint.url, HTTP_SCHEMES);
+    }
+    if !proxy.upstream_endpoints.is_empty() && proxy.upstream_endpoints.iter().all(|e| !e.enabled) {
+        issues.warning(
+            "proxy.upstream_endpoints",
+            "all endpoints are disabled; built-in defaults will be used",
+        );
+    }
+    if proxy.zai.enabled {
+        issues.proxy_url("proxy.zai.base_url", &proxy.zai.base_url, HTTP_SCHEMES);
+    }
+    let mut provider_ids = std::collections::HashSet::new();
+    for (i, provider) in proxy.providers.iter().enumerate() {
+        let field = format!("proxy.providers[{}]", i);
+        if provider.id.trim().is_empty() {
+            issues.error(format!("{}.id", field), "provider id must not be empty");
+        } else if !provider_ids.insert(provider.id.as_str()) {
+            issues.error(format!("{}.id", field), format!("duplicate provider id '{}'", provider.id));
+        }
+        if provider.enabled {
+            issues.proxy_url(&format!("{}.base_url", field), &provider.base_url, HTTP_SCHEMES);
+        }
+    }
+
+    // 5. 受信任代理 (IP / CIDR)
+    for (i, pattern) in proxy.security_monitor.trusted_proxies.iter().enumerate() {
+        if !crate::proxy::middleware::ip_filter::is_valid_ip_pattern(pattern) {
+            issues.error(
+                format!("proxy.security_monitor.trusted_proxies[{}]", i),
+                format!("'{}' is not a valid IP address or CIDR range", pattern),
+            );
+        }
+    }
+
+    // 6. 模型映射
+    let mut patterns: Vec<_> = proxy.custom_mapping.iter().collect();
+    patterns.sort();
+    for (pattern, target) in patterns {
+        let field = format!("proxy.custom_mapping[\"{}\"]", pattern);
+        if pattern.trim().is_empty() {
+            issues.error(&field, "mapping key must not be empty");
+            continue;
+        }
+        if target.trim().is_empty() {
+            issues.error(&field, "mapping target must not be empty");
+        }
+        if pattern.contains('*')
+            && !known_models
+                .iter()
+                .any(|m| crate::proxy::common::model_mapping::wildcard_match(pattern, m))
+        {
+            issues.warning(
+                &field,
+                "wildcard does not match any known model (matching is case-sensitive)",
+            );
+        }
+    }
+
+    // 7. 模型降级链
+    let mut chains: Vec<_> = proxy.model_fallbacks.iter().collect();
+    chains.sort();
+    for (model, chain) in chains {
+        let field = format!("proxy.model_fallbacks[\"{}\"]", model);
+        if model.trim().is_empty() {
+            issues.error(&field, "fallback key must not be empty");
+            continue;
+        }
+        if chain.is_empty() {
+            issues.warning(&field, "fallback chain is empty");
+        }
+        for (i, target) in chain.iter().enumerate() {
+            if target.trim().is_empty() {
+                issues.error(format!("{}[{}]", field, i), "fallback model must not be empty");
+            } else if target == model {
+                issues.warning(format!("{}[{}]", field, i), "model falls back to itself and will be skipped");
+            }
+        }
+    }
+
+    // 8. 后台任务识别规则
+    let mut rule_names = std::collections::HashSet::new();
+    for (i, rule) in proxy.background_task_rules.iter().enumerate() {
+        let field = format!("proxy.background_task_rules[{}]", i);
+        if rule.name.trim().is_empty() {
+            issues.error(format!("{}.name", field), "rule name must not be empty");
+        } else if !rule_names.insert(rule.name.as_str()) {
+            issues.error(format!("{}.name", field), format!("duplicate rule name '{}'", rule.name));
+        }
+        let regex = rule.regex.as_deref().filter(|r| !r.is_empty());
+        if let Some(Err(e)) = regex.map(regex::Regex::new) {
+            issues.error(format!("{}.regex", field), format!("invalid regex: {}", e));
+        }
+        if regex.is_none() && rule.keywords.iter().all(|k| k.is_empty()) {
+            issues.error(&field, "rule needs at least one keyword or a regex");
+        }
+        if rule.target_model.trim().is_empty() {
+            issues.error(format!("{}.target_model", field), "target model must not be empty");
+        }
+        if rule.max_length == 0 {
+            issues.warning(format!("{}.max_length", field), "max_length is 0; rule will never match");
+        }
+    }
+
+    // 9. 上下文压缩阈值
+    let exp = &proxy.experimental;
+    let thresholds = [
+        ("proxy.experimental.context_compression_threshold_l1", exp.context_compression_threshold_l1),
+        ("proxy.experimental.context_compression_threshold_l2", exp.context_compression_threshold_l2),
+        ("proxy.experimental.context_compression_threshold_l3", exp.context_compression_threshold_l3),
+    ];
+    for (field, value) in thresholds {
+        if !(value > 0.0 && value <= 1.0) {
+            issues.error(field, format!("must be within (0, 1] (got {})", value));
+        }
+    }
+    if exp.context_compression_threshold_l1 > exp.context_compression_threshold_l2
+        || exp.context_compression_threshold_l2 > exp.context_compression_threshold_l3
+    {
+        issues.error(
+            "proxy.experimental.context_compression_threshold_l1",
+            "thresholds must satisfy L1 <= L2 <= L3",
+        );
+    }
+
+    // 10. 响应缓存
+    let cache = &proxy.response_cache;
+    if cache.enabled {
+        if cache.ttl_secs == 0 {
+            issues.error("proxy.response_cache.ttl_secs", "ttl must be at least 1 second");
+        }
+        if cache.max_size_mb == 0 {
+            issues.error("proxy.response_cache.max_size_mb", "size cap must be at least 1 MB");
+        }
+    }
+
+    // 11. 生成图片存储
+    let images = &proxy.image_store;
+    if images.enabled {
+        if images.ttl_secs == 0 {
+            issues.error("proxy.image_store.ttl_secs", "ttl must be at least 1 second");
+        }
+        if images.max_size_mb == 0 {
+            issues.error("proxy.image_store.max_size_mb", "size cap must be at least 1 MB");
+        }
+        if !images.public_base_url.trim().is_empty() {
+            issues.proxy_url("proxy.image_store.public_base_url", &images.public_base_url, HTTP_SCHEMES);
+        }
+    }
+
+    issues.0
+}
+
+pub fn has_errors(issues: &[ValidationIssue]) -> bool {
+    issues.iter().any(|i| i.severity == IssueSeverity::Error)
+}
+
+/// 将错误汇总为单行文本 (用于 Tauri 命令的 Err(String))
+pub fn summarize_errors(issues: &[ValidationIssue]) -> String {
+    let errors: Vec<String> = issues
+        .iter()
+        .filter(|i| i.severity == IssueSeverity::Error)
+        .map(|i| format!("{}: {}", i.field, i.message))
+        .collect();
+    format!("invalid_config: {}", errors.join("; "))
+}
+
+fn is_secret_key(path: &str) -> bool {
+    let key = path.rsplit('.').next().unwrap_or(path);
+    SECRET_KEYS.contains(&key)
+}
+
+fn flatten(prefix: &str, value: &Value, out: &mut std::collections::BTreeMap<String, Value>) {
+    match value {
+        Value::Object(map) => {
+            // 空对象不产生叶子，新增键时直接显示为 "path.key"
+            for (k, v) in map {
+                let path = if prefix.is_empty() { k.clone() } else { format!("{}.{}", prefix, k) };
+                flatten(&path, v, out);
+            }
+        }
+        _ => {
+            out.insert(prefix.to_string(), value.clone());
+        }
+    }
+}
+
+/// 计算两个配置之间的叶子字段差异 (数组整体比较，敏感字段脱敏)
+pub fn diff_configs(before: &AppConfig, after: &AppConfig) -> Vec<ConfigChange> {
+    let mut old = std::collections::BTreeMap::new();
+    let mut new = std::collections::BTreeMap::new();
+    flatten("", &serde_json::to_value(before).unwrap_or(Value::Null), &mut old);
+    flatten("", &serde_json::to_value(after).unwrap_or(Value::Null), &mut new);
+
+    let mask = |path: &str, v: Option<&Value>| -> Option<Value> {
+        v.map(|v| {
+            if is_secret_key(path) && !v.is_null() {
+                Value::String("***".to_string())
+            } else {
+                v.clone()
+            }
+        })
+    };
+
+    let paths: std::collections::BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    paths
+        .into_iter()
+        .filter(|p| old.get(*p) != new.get(*p))
+        .map(|p| ConfigChange {
+            path: p.clone(),
+            before: mask(p, old.get(p)),
+            after: mask(p, new.get(p)),
+            requires_restart: RESTART_REQUIRED_PATHS.contains(&p.as_str()),
+        })
+        .collect()
+}
+
+/// 校验候选配置并与运行中的配置对比 (不写盘)
+pub fn dry_run(running: &AppConfig, candidate: &AppConfig, known_models: &[String]) -> ConfigValidationReport {
+    let issues = validate_app_config(candidate, known_models);
+    ConfigValidationReport {
+        valid: !has_errors(&issues),
+        changes: diff_configs(running, candidate),
+        issues,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::config::ProxyEntry;
+
+    fn pool_entry(id: &str, url: &str) -> ProxyEntry {
+        ProxyEntry {
+            id: id.to_string(),
+            name: id.to_string(),
+            url: url.to_string(),
+            auth: None,
+            enabled: true,
+            priority: 0,
+            tags: Vec::new(),
+            max_accounts: None,
+            health_check_url: None,
+            last_check_time: None,
+            is_healthy: true,
+            latency: None,
+            weight: 1,
+        }
+    }
+
+    fn provider(id: &str, base_url: &str) -> crate::proxy::config::UpstreamProviderConfig {
+        serde_json::from_value(serde_json::json!({ "id": id, "base_url": base_url })).unwrap()
+    }
+
+    fn fields(issues: &[ValidationIssue], severity: IssueSeverity) -> Vec<String> {
+        issues
+            .iter()
+            .filter(|i| i.severity == severity)
+            .map(|i| i.field.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_default_config_is_valid() {
+        let issues = validate_app_config(&AppConfig::default(), &[]);
+        assert!(!has_errors(&issues), "{:?}", issues);
+
+        // 配额保护未启用时不校验阈值
+        let mut config = AppConfig::default();
+        config.quota_protection.enabled = false;
+        config.quota_protection.threshold_percentage = 0;
+        assert!(!has_errors(&validate_app_config(&config, &[])));
+    }
+
+    #[test]
+    fn test_field_level_errors() {
+        let mut config = AppConfig::default();
+        config.proxy.request_timeout = 5;
+        config.proxy.upstream_proxy.enabled = true;
+        config.proxy.upstream_proxy.url = "ftp://proxy.local".to_string();
+        config.proxy.proxy_pool.proxies = vec![
+            pool_entry("p1", "socks5://10.0.0.1:1080"),
+            pool_entry("p1", "not a url"),
+        ];
+        config.proxy.security_monitor.trusted_proxies =
+            vec!["10.0.0.0/8".to_string(), "10.0.0.0/33".to_string(), "proxy.local".to_string()];
+        config.proxy.custom_mapping.insert("gpt-4*".to_string(), "gemini-2.5-flash".to_string());
+        config.proxy.custom_mapping.insert("GPT-*".to_string(), "gemini-2.5-flash".to_string());
+        config.proxy.model_fallbacks.insert(
+            "claude-opus-4-6-thinking".to_string(),
+            vec!["claude-opus-4-6-thinking".to_string(), "".to_string(), "gemini-3-pro-high".to_string()],
+        );
+        config.proxy.providers = vec![
+            provider("local", "http://127.0.0.1:8000/v1"),
+            provider("local", "127.0.0.1:8000"),
+        ];
+        config.proxy.background_task_rules.truncate(2);
+        config.proxy.background_task_rules[1].name = "system_message".to_string();
+        config.proxy.background_task_rules[1].regex = Some("(unclosed".to_string());
+        config.proxy.background_task_rules[1].max_length = 0;
+        config.proxy.response_cache.enabled = true;
+        config.proxy.response_cache.ttl_secs = 0;
+        config.proxy.image_store.public_base_url = "proxy.example.com".to_string();
+        config.quota_protection.enabled = true;
+        config.quota_protection.threshold_percentage = 0;
+
+        let known = vec!["gpt-4o".to_string(), "claude-sonnet-4-5".to_string()];
+        let issues = validate_app_config(&config, &known);
+        let errors = fields(&issues, IssueSeverity::Error);
+        assert_eq!(
+            errors,
+            vec![
+                "quota_protection.threshold_percentage",
+                "proxy.request_timeout",
+                "proxy.upstream_proxy.url",
+                "proxy.proxy_pool.proxies[1].id",
+                "proxy.proxy_pool.proxies[1].url",
+                "proxy.providers[1].id",
+                "proxy.providers[1].base_url",
+                "proxy.security_monitor.trusted_proxies[1]",
+                "proxy.security_monitor.trusted_proxies[2]",
+                "proxy.model_fallbacks[\"claude-opus-4-6-thinking\"][1]",
+                "proxy.background_task_rules[1].name",
+                "proxy.background_task_rules[1].regex",
+                "proxy.response_cache.ttl_secs",
+                "proxy.image_store.public_base_url",
+            ]
+        );
+        assert_eq!(
+            fields(&issues, IssueSeverity::Warning),
+            vec![
+                "proxy.custom_mapping[\"GPT-*\"]",
+                "proxy.model_fallbacks[\"claude-opus-4-6-thinking\"][0]",
+                "proxy.background_task_rules[1].max_length",
+            ]
+        );
+        assert!(summarize_errors(&issues).contains("proxy.request_timeout"));
+    }
+
+    #[test]
+    fn test_diff_masks_secrets_and_flags_restart() {
+        let before = AppConfig::default();
+        let mut after = before.clone();
+        after.proxy.port = 9000;
+        after.proxy.api_key = "sk-new".to_string();
+        after.proxy.custom_mapping.insert("a".to_string(), "b".to_string());
+
+        let report = dry_run(&before, &after, &[]);
+        assert!(report.valid);
+        let paths: Vec<&str> = report.changes.iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(paths, vec!["proxy.api_key", "proxy.custom_mapping.a", "proxy.port"]);
+
+        let api_key = &report.changes[0];
+        assert_eq!(api_key.after, Some(Value::String("***".to_string())));
+        assert!(!api_key.requires_restart);
+        assert!(report.changes[2].requires_restart);
+        assert!(diff_configs(&before, &before).is_empty());
+    }
+}
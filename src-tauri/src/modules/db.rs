@@ -1,4 +1,6 @@
+#[cfg(feature = "desktop")]
 use crate::utils::protobuf;
+#[cfg(feature = "desktop")]
 use rusqlite::Connection;
 use std::path::PathBuf;
 
@@ -62,6 +64,7 @@ pub fn get_db_path() -> Result<PathBuf, String> {
 }
 
 /// Inject Token and Email into database
+#[cfg(feature = "desktop")]
 pub fn inject_token(
     db_path: &PathBuf,
     access_token: &str,
@@ -142,6 +145,7 @@ pub fn inject_token(
 }
 
 /// New format injection (>= 1.16.5)
+#[cfg(feature = "desktop")]
 fn inject_new_format(
     db_path: &PathBuf,
     access_token: &str,
@@ -181,6 +185,7 @@ fn inject_new_format(
     Ok("Token injection successful (new format)".to_string())
 }
 
+#[cfg(feature = "desktop")]
 fn inject_user_status(conn: &Connection, email: &str) -> Result<(), String> {
     let payload = protobuf::create_minimal_user_status_payload(email);
     let entry_b64 = protobuf::create_unified_state_entry("userStatusSentinelKey", &payload);
@@ -194,6 +199,7 @@ fn inject_user_status(conn: &Connection, email: &str) -> Result<(), String> {
     Ok(())
 }
 
+#[cfg(feature = "desktop")]
 fn inject_enterprise_project_preference(conn: &Connection, project_id: &str) -> Result<(), String> {
     let payload = protobuf::create_string_value_payload(project_id);
     let entry_b64 = protobuf::create_unified_state_entry("enterpriseGcpProjectId", &payload);
@@ -210,6 +216,7 @@ fn inject_enterprise_project_preference(conn: &Connection, project_id: &str) ->
     Ok(())
 }
 
+#[cfg(feature = "desktop")]
 fn clear_enterprise_project_preference(conn: &Connection) -> Result<(), String> {
     conn.execute(
         "DELETE FROM ItemTable WHERE key = ?",
@@ -221,6 +228,7 @@ fn clear_enterprise_project_preference(conn: &Connection) -> Result<(), String>
 }
 
 /// Old format injection (< 1.16.5)
+#[cfg(feature = "desktop")]
 fn inject_old_format(
     db_path: &PathBuf,
     access_token: &str,
@@ -145,6 +145,7 @@ pub fn read_profile(storage_path: &Path) -> Result<DeviceProfile, String> {
 }
 
 /// Write device profile to storage.json
+#[cfg(feature = "desktop")]
 pub fn write_profile(storage_path: &Path, profile: &DeviceProfile) -> Result<(), String> {
     if !storage_path.exists() {
         return Err(format!("storage_json_missing: {:?}", storage_path));
@@ -0,0 +1,83 @@
+//! App Events - 进程内事件总线
+//! 后台模块统一通过广播通道发布事件；桌面构建下额外转发给 Tauri 前端。
+
+use serde::Serialize;
+use std::sync::OnceLock;
+use tokio::sync::broadcast;
+
+/// 广播通道容量 (慢订阅者超出后会丢弃最旧的事件)
+const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// 事件名与前端 `listen()` 使用的名称保持一致，如 `proxy://request`
+#[derive(Debug, Clone, Serialize)]
+pub struct AppEvent {
+    pub name: String,
+    pub payload: serde_json::Value,
+}
+
+static EVENT_TX: OnceLock<broadcast::Sender<AppEvent>> = OnceLock::new();
+
+#[cfg(feature = "desktop")]
+static APP_HANDLE: OnceLock<tauri::AppHandle> = OnceLock::new();
+
+fn sender() -> &'static broadcast::Sender<AppEvent> {
+    EVENT_TX.get_or_init(|| broadcast::channel(EVENT_CHANNEL_CAPACITY).0)
+}
+
+/// 订阅全部事件
+pub fn subscribe() -> broadcast::Receiver<AppEvent> {
+    sender().subscribe()
+}
+
+/// 绑定 Tauri AppHandle，之后发布的事件同时推送给桌面前端 (在 setup 中调用一次)
+#[cfg(feature = "desktop")]
+pub fn attach_app_handle(app_handle: tauri::AppHandle) {
+    let _ = APP_HANDLE.set(app_handle);
+    tracing::debug!("[Events] Attached Tauri app handle");
+}
+
+/// 发布事件；没有订阅者时直接丢弃
+pub fn emit<S: Serialize>(name: &str, payload: S) {
+    let payload = match serde_json::to_value(payload) {
+        Ok(v) => v,
+        Err(e) => {
+            tracing::warn!("[Events] Failed to serialize payload for {}: {}", name, e);
+            return;
+        }
+    };
+
+    #[cfg(feature = "desktop")]
+    if let Some(handle) = APP_HANDLE.get() {
+        use tauri::Emitter;
+        let _ = handle.emit(name, &payload);
+    }
+
+    let _ = sender().send(AppEvent {
+        name: name.to_string(),
+        payload,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_emit_reaches_subscribers() {
+        let mut rx = subscribe();
+        emit("test://event", serde_json::json!({ "id": 1 }));
+
+        // 其他测试可能并发发布事件，按名称过滤
+        loop {
+            let event = rx.recv().await.unwrap();
+            if event.name == "test://event" {
+                assert_eq!(event.payload["id"], 1);
+                break;
+            }
+        }
+
+        // 无订阅者时不应 panic
+        drop(rx);
+        emit("test://unit", ());
+    }
+}
@@ -482,7 +482,12 @@ pub async fn start_server(port: u16, integration: crate::modules::integration::S
 /// Start HTTP API server in background (non-blocking)
 pub fn spawn_server(port: u16, integration: crate::modules::integration::SystemManager) {
     // Use tauri::async_runtime::spawn to ensure running within Tauri's runtime
-    tauri::async_runtime::spawn(async move {
+    #[cfg(feature = "desktop")]
+    use tauri::async_runtime::spawn;
+    #[cfg(not(feature = "desktop"))]
+    use tokio::spawn;
+
+    spawn(async move {
         if let Err(e) = start_server(port, integration).await {
             logger::log_error(&format!("[HTTP API] Failed to start server: {}", e));
         }
@@ -0,0 +1,246 @@
+//! Image Store Module
+//! 生成图片的本地存储：response_format=url 时图片写入数据目录，经 /v1/files/images/:id 以短期 URL 提供
+//! 过期时间按文件修改时间计算，总大小超出配额时按最旧优先淘汰
+
+use base64::Engine as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{OnceLock, RwLock};
+use std::time::{Duration, SystemTime};
+
+use crate::proxy::config::ImageStoreConfig;
+
+const IMAGE_DIR: &str = "images";
+/// 支持的图片类型 (扩展名, MIME)
+const IMAGE_TYPES: &[(&str, &str)] = &[
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("webp", "image/webp"),
+    ("gif", "image/gif"),
+];
+
+static GLOBAL_IMAGE_STORE_CONFIG: OnceLock<RwLock<ImageStoreConfig>> = OnceLock::new();
+
+/// 更新全局图片存储配置
+pub fn update_image_store_config(config: ImageStoreConfig) {
+    if let Some(lock) = GLOBAL_IMAGE_STORE_CONFIG.get() {
+        if let Ok(mut cfg) = lock.write() {
+            *cfg = config;
+        }
+    } else {
+        let _ = GLOBAL_IMAGE_STORE_CONFIG.set(RwLock::new(config));
+    }
+}
+
+pub fn current_config() -> ImageStoreConfig {
+    GLOBAL_IMAGE_STORE_CONFIG
+        .get()
+        .and_then(|lock| lock.read().ok().map(|cfg| cfg.clone()))
+        .unwrap_or_default()
+}
+
+/// 读取到的图片
+#[derive(Debug, Clone)]
+pub struct StoredImage {
+    pub bytes: Vec<u8>,
+    pub mime_type: &'static str,
+    /// 距过期的剩余秒数
+    pub expires_in: u64,
+}
+
+pub fn get_image_dir() -> Result<PathBuf, String> {
+    let data_dir = crate::modules::account::get_data_dir()?;
+    Ok(data_dir.join(IMAGE_DIR))
+}
+
+fn extension_for(mime_type: &str) -> &'static str {
+    IMAGE_TYPES
+        .iter()
+        .find(|(_, mime)| mime.eq_ignore_ascii_case(mime_type))
+        .map(|(ext, _)| *ext)
+        .unwrap_or("png")
+}
+
+/// 图片 ID 为 32 位十六进制 (UUID simple)，其余输入一律拒绝以防路径穿越
+fn is_valid_id(id: &str) -> bool {
+    id.len() == 32 && id.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// 列出目录内的图片文件 (路径, 大小, 修改时间)
+fn list_images(dir: &Path) -> Result<Vec<(PathBuf, u64, SystemTime)>, String> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.to_string()),
+    };
+    let mut images = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let known = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|ext| IMAGE_TYPES.iter().any(|(known, _)| *known == ext))
+            .unwrap_or(false);
+        if !known {
+            continue;
+        }
+        if let Ok(meta) = entry.metadata() {
+            let modified = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            images.push((path, meta.len(), modified));
+        }
+    }
+    Ok(images)
+}
+
+fn is_expired(modified: SystemTime, ttl_secs: u64, now: SystemTime) -> bool {
+    modified + Duration::from_secs(ttl_secs) <= now
+}
+
+/// 删除过期图片，并在总大小超出配额 (预留 `reserve` 字节) 时按最旧优先淘汰，返回删除数量
+fn cleanup_at(dir: &Path, config: &ImageStoreConfig, reserve: u64, now: SystemTime) -> Result<usize, String> {
+    let mut images = list_images(dir)?;
+    let mut removed = 0;
+
+    images.retain(|(path, _, modified)| {
+        if is_expired(*modified, config.ttl_secs, now) {
+            if fs::remove_file(path).is_ok() {
+                removed += 1;
+            }
+            false
+        } else {
+            true
+        }
+    });
+
+    let max_bytes = config.max_size_mb.saturating_mul(1024 * 1024);
+    let mut total: u64 = images.iter().map(|(_, size, _)| size).sum::<u64>() + reserve;
+    if total > max_bytes {
+        images.sort_by_key(|(_, _, modified)| *modified);
+        for (path, size, _) in images {
+            if total <= max_bytes {
+                break;
+            }
+            if fs::remove_file(&path).is_ok() {
+                total -= size;
+                removed += 1;
+            }
+        }
+    }
+    Ok(removed)
+}
+
+fn save_at(dir: &Path, config: &ImageStoreConfig, mime_type: &str, bytes: &[u8], now: SystemTime) -> Result<String, String> {
+    let max_bytes = config.max_size_mb.saturating_mul(1024 * 1024);
+    if bytes.len() as u64 > max_bytes {
+        return Err(format!(
+            "Image ({} bytes) exceeds the image store quota of {} MB",
+            bytes.len(),
+            config.max_size_mb
+        ));
+    }
+
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create image directory: {}", e))?;
+    cleanup_at(dir, config, bytes.len() as u64, now)?;
+
+    let id = uuid::Uuid::new_v4().simple().to_string();
+    let path = dir.join(format!("{}.{}", id, extension_for(mime_type)));
+    fs::write(&path, bytes).map_err(|e| format!("Failed to write image: {}", e))?;
+    Ok(id)
+}
+
+fn load_at(dir: &Path, id: &str, ttl_secs: u64, now: SystemTime) -> Result<Option<StoredImage>, String> {
+    if !is_valid_id(id) {
+        return Ok(None);
+    }
+    for (ext, mime_type) in IMAGE_TYPES {
+        let path = dir.join(format!("{}.{}", id, ext));
+        let meta = match fs::metadata(&path) {
+            Ok(meta) => meta,
+            Err(_) => continue,
+        };
+        let modified = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+        if is_expired(modified, ttl_secs, now) {
+            let _ = fs::remove_file(&path);
+            return Ok(None);
+        }
+        let bytes = fs::read(&path).map_err(|e| format!("Failed to read image: {}", e))?;
+        let expires_in = (modified + Duration::from_secs(ttl_secs))
+            .duration_since(now)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        return Ok(Some(StoredImage {
+            bytes,
+            mime_type,
+            expires_in,
+        }));
+    }
+    Ok(None)
+}
+
+/// 启动时清理过期图片
+pub fn init_store() -> Result<(), String> {
+    let removed = cleanup_at(&get_image_dir()?, &current_config(), 0, SystemTime::now())?;
+    if removed > 0 {
+        tracing::info!("[Image-Store] Removed {} expired images", removed);
+    }
+    Ok(())
+}
+
+/// 保存 base64 编码的图片，返回图片 ID
+pub fn save_base64(mime_type: &str, data: &str) -> Result<String, String> {
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(data)
+        .map_err(|e| format!("Invalid base64 image data: {}", e))?;
+    save_at(&get_image_dir()?, &current_config(), mime_type, &bytes, SystemTime::now())
+}
+
+/// 读取未过期的图片
+pub fn load(id: &str) -> Result<Option<StoredImage>, String> {
+    load_at(&get_image_dir()?, id, current_config().ttl_secs, SystemTime::now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(ttl_secs: u64, max_size_mb: u64) -> ImageStoreConfig {
+        ImageStoreConfig {
+            enabled: true,
+            ttl_secs,
+            max_size_mb,
+            public_base_url: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_save_load_expiry_and_quota() {
+        let dir = std::env::temp_dir().join(format!("antigravity-image-store-test-{}", uuid::Uuid::new_v4()));
+        let cfg = config(60, 1);
+        let now = SystemTime::now();
+
+        let id = save_at(&dir, &cfg, "image/jpeg", b"jpeg-bytes", now).unwrap();
+        assert!(dir.join(format!("{}.jpg", id)).exists());
+        let image = load_at(&dir, &id, cfg.ttl_secs, now).unwrap().unwrap();
+        assert_eq!(image.bytes, b"jpeg-bytes");
+        assert_eq!(image.mime_type, "image/jpeg");
+        assert!(image.expires_in <= 60);
+
+        // 非法 ID 与过期图片均不可读取，过期文件被删除
+        assert!(load_at(&dir, "../../etc/passwd", 60, now).unwrap().is_none());
+        let later = now + Duration::from_secs(120);
+        assert!(load_at(&dir, &id, cfg.ttl_secs, later).unwrap().is_none());
+        assert!(!dir.join(format!("{}.jpg", id)).exists());
+
+        // 超出配额时淘汰最旧的图片
+        let big = vec![0u8; 600 * 1024];
+        let first = save_at(&dir, &cfg, "image/png", &big, now).unwrap();
+        let second = save_at(&dir, &cfg, "image/png", &big, now).unwrap();
+        assert!(load_at(&dir, &first, cfg.ttl_secs, now).unwrap().is_none());
+        assert!(load_at(&dir, &second, cfg.ttl_secs, now).unwrap().is_some());
+
+        // 单张超过配额的图片直接拒绝
+        assert!(save_at(&dir, &cfg, "image/png", &vec![0u8; 2 * 1024 * 1024], now).is_err());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -1,5 +1,7 @@
+#[cfg(feature = "desktop")]
 use crate::modules::{process, db, device};
 use crate::models::Account;
+#[cfg(feature = "desktop")]
 use std::fs;
 
 pub trait SystemIntegration: Send + Sync {
@@ -7,6 +9,7 @@ pub trait SystemIntegration: Send + Sync {
     async fn on_account_switch(&self, account: &crate::models::Account) -> Result<(), String>;
     
     /// 更新系统托盘（如果适用）
+    #[cfg(feature = "desktop")]
     fn update_tray(&self);
     
     /// 发送系统通知
@@ -14,10 +17,12 @@ pub trait SystemIntegration: Send + Sync {
 }
 
 /// 桌面版实现：包含完整的进程控制和 UI 同步
+#[cfg(feature = "desktop")]
 pub struct DesktopIntegration {
     pub app_handle: tauri::AppHandle,
 }
 
+#[cfg(feature = "desktop")]
 impl SystemIntegration for DesktopIntegration {
     async fn on_account_switch(&self, account: &crate::models::Account) -> Result<(), String> {
         crate::modules::logger::log_info(&format!("[Desktop] Executing system switch for: {}", account.email));
@@ -82,6 +87,7 @@ impl SystemIntegration for HeadlessIntegration {
         Ok(())
     }
 
+    #[cfg(feature = "desktop")]
     fn update_tray(&self) {
         // No-op
     }
@@ -93,6 +99,7 @@ impl SystemIntegration for HeadlessIntegration {
 /// 系统集成管理器：替代 Arc<dyn SystemIntegration> 以解决 async trait 的 dyn 兼容性问题
 #[derive(Clone)]
 pub enum SystemManager {
+    #[cfg(feature = "desktop")]
     Desktop(tauri::AppHandle),
     Headless,
 }
@@ -100,6 +107,7 @@ pub enum SystemManager {
 impl SystemManager {
     pub async fn on_account_switch(&self, account: &Account) -> Result<(), String> {
         match self {
+            #[cfg(feature = "desktop")]
             SystemManager::Desktop(handle) => {
                 let integration = DesktopIntegration { app_handle: handle.clone() };
                 integration.on_account_switch(account).await
@@ -112,14 +120,34 @@ impl SystemManager {
     }
 
     pub fn update_tray(&self) {
+        #[cfg(feature = "desktop")]
         if let SystemManager::Desktop(handle) = self {
             let integration = DesktopIntegration { app_handle: handle.clone() };
             integration.update_tray();
         }
     }
 
+    /// 在系统默认浏览器中打开链接；Headless 模式下仅记录日志，由用户手动打开
+    pub fn open_url(&self, url: &str) -> Result<(), String> {
+        match self {
+            #[cfg(feature = "desktop")]
+            SystemManager::Desktop(handle) => {
+                use tauri_plugin_opener::OpenerExt;
+                handle
+                    .opener()
+                    .open_url(url, None::<String>)
+                    .map_err(|e| format!("failed_to_open_browser: {}", e))
+            }
+            SystemManager::Headless => {
+                crate::modules::logger::log_info(&format!("[Headless] Open this URL in a browser: {}", url));
+                Ok(())
+            }
+        }
+    }
+
     pub fn show_notification(&self, title: &str, body: &str) {
         match self {
+            #[cfg(feature = "desktop")]
             SystemManager::Desktop(handle) => {
                 let integration = DesktopIntegration { app_handle: handle.clone() };
                 integration.show_notification(title, body);
@@ -135,6 +163,7 @@ impl SystemManager {
 impl SystemIntegration for SystemManager {
     async fn on_account_switch(&self, account: &crate::models::Account) -> Result<(), String> {
         match self {
+            #[cfg(feature = "desktop")]
             SystemManager::Desktop(handle) => {
                 let integration = DesktopIntegration { app_handle: handle.clone() };
                 integration.on_account_switch(account).await
@@ -146,8 +175,10 @@ impl SystemIntegration for SystemManager {
         }
     }
 
+    #[cfg(feature = "desktop")]
     fn update_tray(&self) {
         match self {
+            #[cfg(feature = "desktop")]
             SystemManager::Desktop(handle) => {
                 let integration = DesktopIntegration { app_handle: handle.clone() };
                 integration.update_tray();
@@ -161,6 +192,7 @@ impl SystemIntegration for SystemManager {
 
     fn show_notification(&self, title: &str, body: &str) {
         match self {
+            #[cfg(feature = "desktop")]
             SystemManager::Desktop(handle) => {
                 let integration = DesktopIntegration { app_handle: handle.clone() };
                 integration.show_notification(title, body);
@@ -1,12 +1,11 @@
-//! Log Module Bridge - Captures tracing logs and emits them to the frontend via Tauri Events.
-//! Uses a global ring buffer that can be attached to Tauri after app initialization.
+//! Log Module Bridge - Captures tracing logs and publishes them on the app event bus.
+//! Uses a global ring buffer so logs captured before the UI connects are not lost.
 
 use parking_lot::RwLock;
 use serde::Serialize;
 use std::collections::VecDeque;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, OnceLock};
-use tauri::Emitter;
 use tracing::field::{Field, Visit};
 use tracing::{Event, Level, Subscriber};
 use tracing_subscriber::layer::Context;
@@ -21,9 +20,6 @@ static LOG_BRIDGE_ENABLED: AtomicBool = AtomicBool::new(false);
 /// Atomic counter for unique log IDs
 static LOG_ID_COUNTER: AtomicU64 = AtomicU64::new(0);
 
-/// Global app handle for emitting events (set once during setup)
-static APP_HANDLE: OnceLock<tauri::AppHandle> = OnceLock::new();
-
 /// Global log buffer for storing logs before UI connects
 static LOG_BUFFER: OnceLock<Arc<RwLock<VecDeque<LogEntry>>>> = OnceLock::new();
 
@@ -43,21 +39,15 @@ pub struct LogEntry {
     pub fields: std::collections::HashMap<String, String>,
 }
 
-/// Initialize the log bridge with app handle (call from setup)
-pub fn init_log_bridge(app_handle: tauri::AppHandle) {
-    let _ = APP_HANDLE.set(app_handle);
-    tracing::debug!("[LogBridge] Initialized with app handle");
-}
-
 /// Enable log bridging and emit buffered logs
 pub fn enable_log_bridge() {
     LOG_BRIDGE_ENABLED.store(true, Ordering::SeqCst);
 
     // Emit all buffered logs to frontend
-    if let Some(handle) = APP_HANDLE.get() {
+    {
         let buffer = get_log_buffer().read();
         for entry in buffer.iter() {
-            let _ = handle.emit("log-event", entry.clone());
+            crate::modules::events::emit("log-event", entry);
         }
     }
 
@@ -88,10 +78,8 @@ pub fn clear_log_buffer() {
 /// Emit accounts://refreshed event to notify the frontend of account state changes
 /// This is used by background tasks (e.g. warmup 403 handling) that cannot access AppHandle directly.
 pub fn emit_accounts_refreshed() {
-    if let Some(handle) = APP_HANDLE.get() {
-        let _ = handle.emit("accounts://refreshed", ());
-        tracing::debug!("[LogBridge] Emitted accounts://refreshed event to frontend");
-    }
+    crate::modules::events::emit("accounts://refreshed", ());
+    tracing::debug!("[LogBridge] Emitted accounts://refreshed event to frontend");
 }
 
 /// Visitor to extract fields from tracing events
@@ -211,9 +199,7 @@ where
         }
 
         // Emit to frontend
-        if let Some(handle) = APP_HANDLE.get() {
-            let _ = handle.emit("log-event", entry);
-        }
+        crate::modules::events::emit("log-event", entry);
     }
 }
 
@@ -221,26 +207,31 @@ where
 // Tauri Commands
 // ============================================================================
 
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub fn enable_debug_console() {
     enable_log_bridge();
 }
 
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub fn disable_debug_console() {
     disable_log_bridge();
 }
 
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub fn is_debug_console_enabled() -> bool {
     is_log_bridge_enabled()
 }
 
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub fn get_debug_console_logs() -> Vec<LogEntry> {
     get_buffered_logs()
 }
 
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub fn clear_debug_console_logs() {
     clear_log_buffer();
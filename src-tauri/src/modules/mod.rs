@@ -1,21 +1,31 @@
 pub mod account;
 pub mod quota;
 pub mod config;
+pub mod config_validation;
 pub mod logger;
 pub mod db;
 pub mod process;
 pub mod oauth;
 pub mod oauth_server;
 pub mod migration;
+#[cfg(feature = "desktop")]
 pub mod tray;
+#[cfg(feature = "desktop")]
 pub mod i18n;
 pub mod proxy_db;
+pub mod rate_limit_db;
 pub mod device;
 pub mod update_checker;
 pub mod scheduler;
 pub mod token_stats;
+pub mod quota_history;
+pub mod response_cache;
+pub mod audit_log;
+pub mod response_store;
+pub mod image_store;
 pub mod cloudflared;
 pub mod integration;
+pub mod events;
 pub mod account_service;
 #[allow(dead_code)]
 pub mod http_api;
@@ -23,6 +33,7 @@ pub mod cache;
 pub mod log_bridge;
 pub mod security_db;
 pub mod user_token_db;
+pub mod admin_key_db;
 pub mod version;
 
 use crate::models;
@@ -31,6 +42,7 @@ use crate::models;
 pub use account::*;
 #[allow(unused_imports)]
 pub use quota::*;
+#[cfg(feature = "desktop")]
 pub use config::*;
 #[allow(unused_imports)]
 pub use logger::*;
@@ -3,7 +3,7 @@ use tokio::net::TcpListener;
 use tokio::sync::mpsc;
 use tokio::sync::watch;
 use std::sync::{Mutex, OnceLock};
-use tauri::Url;
+use url::Url;
 use crate::modules::oauth;
 
 struct OAuthFlowState {
@@ -44,7 +44,7 @@ fn oauth_fail_html() -> &'static str {
     </html>"
 }
 
-async fn ensure_oauth_flow_prepared(app_handle: Option<tauri::AppHandle>, requested_client_key: Option<String>) -> Result<String, String> {
+async fn ensure_oauth_flow_prepared(requested_client_key: Option<String>) -> Result<String, String> {
     if let Ok(mut state) = get_oauth_flow_state().lock() {
         if let Some(s) = state.as_mut() {
             if let Some(requested_key) = requested_client_key.as_ref() {
@@ -145,12 +145,9 @@ async fn ensure_oauth_flow_prepared(app_handle: Option<tauri::AppHandle>, reques
 
     // Start listeners immediately: even if the user authorizes before clicking "Start OAuth",
     // the browser can still hit our callback and finish the flow.
-    let app_handle_for_tasks = app_handle.clone();
-
     if let Some(l4) = ipv4_listener {
         let tx = code_tx.clone();
         let mut rx = cancel_rx.clone();
-        let app_handle = app_handle_for_tasks.clone();
         tokio::spawn(async move {
             if let Ok((mut stream, _)) = tokio::select! {
                 res = l4.accept() => res.map_err(|e| format!("failed_to_accept_connection: {}", e)),
@@ -224,10 +221,7 @@ async fn ensure_oauth_flow_prepared(app_handle: Option<tauri::AppHandle>, reques
                 let _ = stream.write_all(response_html.as_bytes()).await;
                 let _ = stream.flush().await;
 
-                if let Some(h) = app_handle {
-                    use tauri::Emitter;
-                    let _ = h.emit("oauth-callback-received", ());
-                }
+                crate::modules::events::emit("oauth-callback-received", ());
                 let _ = tx.send(result).await;
             }
         });
@@ -236,7 +230,6 @@ async fn ensure_oauth_flow_prepared(app_handle: Option<tauri::AppHandle>, reques
     if let Some(l6) = ipv6_listener {
         let tx = code_tx.clone();
         let mut rx = cancel_rx;
-        let app_handle = app_handle_for_tasks;
         tokio::spawn(async move {
             if let Ok((mut stream, _)) = tokio::select! {
                 res = l6.accept() => res.map_err(|e| format!("failed_to_accept_connection: {}", e)),
@@ -306,10 +299,7 @@ async fn ensure_oauth_flow_prepared(app_handle: Option<tauri::AppHandle>, reques
                 let _ = stream.write_all(response_html.as_bytes()).await;
                 let _ = stream.flush().await;
 
-                if let Some(h) = app_handle {
-                    use tauri::Emitter;
-                    let _ = h.emit("oauth-callback-received", ());
-                }
+                crate::modules::events::emit("oauth-callback-received", ());
                 let _ = tx.send(result).await;
             }
         });
@@ -329,17 +319,14 @@ async fn ensure_oauth_flow_prepared(app_handle: Option<tauri::AppHandle>, reques
     }
 
     // Send event to frontend (for display/copying link)
-    if let Some(h) = app_handle {
-        use tauri::Emitter;
-        let _ = h.emit("oauth-url-generated", &auth_url);
-    }
+    crate::modules::events::emit("oauth-url-generated", &auth_url);
 
     Ok(auth_url)
 }
 
 /// Pre-generate OAuth URL (does not open browser, does not block waiting for callback)
-pub async fn prepare_oauth_url(app_handle: Option<tauri::AppHandle>, oauth_client_key: Option<String>) -> Result<String, String> {
-    ensure_oauth_flow_prepared(app_handle, oauth_client_key).await
+pub async fn prepare_oauth_url(oauth_client_key: Option<String>) -> Result<String, String> {
+    ensure_oauth_flow_prepared(oauth_client_key).await
 }
 
 /// Cancel current OAuth flow
@@ -353,17 +340,15 @@ pub fn cancel_oauth_flow() {
 }
 
 /// Start OAuth flow and wait for callback, then exchange token
-pub async fn start_oauth_flow(app_handle: Option<tauri::AppHandle>, oauth_client_key: Option<String>) -> Result<oauth::TokenResponse, String> {
+pub async fn start_oauth_flow(
+    integration: &crate::modules::integration::SystemManager,
+    oauth_client_key: Option<String>,
+) -> Result<oauth::TokenResponse, String> {
     // Ensure URL + listener are ready (this way if the user authorizes first, it won't get stuck)
-    let auth_url = ensure_oauth_flow_prepared(app_handle.clone(), oauth_client_key).await?;
-
-    if let Some(h) = app_handle {
-        // Open default browser
-        use tauri_plugin_opener::OpenerExt;
-        h.opener()
-            .open_url(&auth_url, None::<String>)
-            .map_err(|e| format!("failed_to_open_browser: {}", e))?;
-    }
+    let auth_url = ensure_oauth_flow_prepared(oauth_client_key).await?;
+
+    // Open default browser
+    integration.open_url(&auth_url)?;
 
     // Take code_rx to wait for it
     let (mut code_rx, redirect_uri, client_key) = {
@@ -399,9 +384,9 @@ pub async fn start_oauth_flow(app_handle: Option<tauri::AppHandle>, oauth_client
 /// Завершить OAuth flow без открытия браузера.
 /// Предполагается, что пользователь открыл ссылку вручную (или ранее была открыта),
 /// а мы только ждём callback и обмениваем code на token.
-pub async fn complete_oauth_flow(app_handle: Option<tauri::AppHandle>) -> Result<oauth::TokenResponse, String> {
+pub async fn complete_oauth_flow() -> Result<oauth::TokenResponse, String> {
     // Ensure URL + listeners exist
-    let _ = ensure_oauth_flow_prepared(app_handle, None).await?;
+    let _ = ensure_oauth_flow_prepared(None).await?;
 
     // Take receiver to wait for code
     let (mut code_rx, redirect_uri, client_key) = {
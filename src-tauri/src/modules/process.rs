@@ -1,5 +1,8 @@
+#[cfg(feature = "desktop")]
 use std::process::Command;
+#[cfg(feature = "desktop")]
 use std::thread;
+#[cfg(feature = "desktop")]
 use std::time::Duration;
 use sysinfo::System;
 
@@ -14,6 +17,7 @@ fn get_current_exe_path() -> Option<std::path::PathBuf> {
 }
 
 /// Check if Antigravity is running
+#[cfg(feature = "desktop")]
 pub fn is_antigravity_running() -> bool {
     let mut system = System::new();
     system.refresh_processes(sysinfo::ProcessesToUpdate::All);
@@ -136,6 +140,7 @@ pub fn is_antigravity_running() -> bool {
     false
 }
 
+#[cfg(feature = "desktop")]
 #[cfg(target_os = "linux")]
 /// Get PID set of current process and all direct relatives (ancestors + descendants)
 fn get_self_family_pids(system: &sysinfo::System) -> std::collections::HashSet<u32> {
@@ -191,6 +196,7 @@ fn get_self_family_pids(system: &sysinfo::System) -> std::collections::HashSet<u
 }
 
 /// Get PIDs of all Antigravity processes (including main and helper processes)
+#[cfg(feature = "desktop")]
 fn get_antigravity_pids() -> Vec<u32> {
     let mut system = System::new();
     system.refresh_processes(sysinfo::ProcessesToUpdate::All);
@@ -353,6 +359,7 @@ fn get_antigravity_pids() -> Vec<u32> {
 }
 
 /// Close Antigravity processes
+#[cfg(feature = "desktop")]
 pub fn close_antigravity(#[allow(unused_variables)] timeout_secs: u64) -> Result<(), String> {
     crate::modules::logger::log_info("Closing Antigravity...");
 
@@ -701,6 +708,7 @@ pub fn close_antigravity(#[allow(unused_variables)] timeout_secs: u64) -> Result
 }
 
 /// Start Antigravity
+#[cfg(feature = "desktop")]
 #[allow(unused_mut)]
 pub fn start_antigravity() -> Result<(), String> {
     crate::modules::logger::log_info("Starting Antigravity...");
@@ -1,6 +1,7 @@
 use rusqlite::{params, Connection};
 use std::path::PathBuf;
 use crate::proxy::monitor::ProxyRequestLog;
+use crate::proxy::replay::ReplayRecord;
 
 pub fn get_proxy_db_path() -> Result<PathBuf, String> {
     let data_dir = crate::modules::account::get_data_dir()?;
@@ -51,6 +52,7 @@ pub fn init_db() -> Result<(), String> {
     let _ = conn.execute("ALTER TABLE request_logs ADD COLUMN protocol TEXT", []);
     let _ = conn.execute("ALTER TABLE request_logs ADD COLUMN client_ip TEXT", []);
     let _ = conn.execute("ALTER TABLE request_logs ADD COLUMN username TEXT", []);
+    let _ = conn.execute("ALTER TABLE request_logs ADD COLUMN cache_hit INTEGER DEFAULT 0", []);
 
     conn.execute(
         "CREATE INDEX IF NOT EXISTS idx_timestamp ON request_logs (timestamp DESC)",
@@ -63,6 +65,23 @@ pub fn init_db() -> Result<(), String> {
         [],
     ).map_err(|e| e.to_string())?;
 
+    // Replay records (replay_id is the request_logs id of the replayed request)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS request_replays (
+            replay_id TEXT PRIMARY KEY,
+            original_id TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            options TEXT,
+            diff TEXT
+        )",
+        [],
+    ).map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_replays_original ON request_replays (original_id)",
+        [],
+    ).map_err(|e| e.to_string())?;
+
     Ok(())
 }
 
@@ -70,8 +89,8 @@ pub fn save_log(log: &ProxyRequestLog) -> Result<(), String> {
     let conn = connect_db()?;
 
     conn.execute(
-        "INSERT INTO request_logs (id, timestamp, method, url, status, duration, model, error, request_body, response_body, input_tokens, output_tokens, account_email, mapped_model, protocol, client_ip, username)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)",
+        "INSERT INTO request_logs (id, timestamp, method, url, status, duration, model, error, request_body, response_body, input_tokens, output_tokens, account_email, mapped_model, protocol, client_ip, username, cache_hit)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
         params![
             log.id,
             log.timestamp,
@@ -90,6 +109,7 @@ pub fn save_log(log: &ProxyRequestLog) -> Result<(), String> {
             log.protocol,
             log.client_ip,
             log.username,
+            log.cache_hit,
         ],
     ).map_err(|e| e.to_string())?;
 
@@ -103,7 +123,7 @@ pub fn get_logs_summary(limit: usize, offset: usize) -> Result<Vec<ProxyRequestL
     let mut stmt = conn.prepare(
         "SELECT id, timestamp, method, url, status, duration, model, error, 
                 NULL as request_body, NULL as response_body,
-                input_tokens, output_tokens, account_email, mapped_model, protocol, client_ip
+                input_tokens, output_tokens, account_email, mapped_model, protocol, client_ip, username, cache_hit
          FROM request_logs 
          ORDER BY timestamp DESC 
          LIMIT ?1 OFFSET ?2"
@@ -128,6 +148,7 @@ pub fn get_logs_summary(limit: usize, offset: usize) -> Result<Vec<ProxyRequestL
             protocol: row.get(14).unwrap_or(None),
             client_ip: row.get(15).unwrap_or(None),
             username: row.get(16).unwrap_or(None),
+            cache_hit: row.get::<_, Option<bool>>(17).ok().flatten().unwrap_or(false),
         })
 
     }).map_err(|e| e.to_string())?;
@@ -173,7 +194,7 @@ pub fn get_log_detail(log_id: &str) -> Result<ProxyRequestLog, String> {
     let mut stmt = conn.prepare(
         "SELECT id, timestamp, method, url, status, duration, model, error,
                 request_body, response_body, input_tokens, output_tokens,
-                account_email, mapped_model, protocol, client_ip, username
+                account_email, mapped_model, protocol, client_ip, username, cache_hit
          FROM request_logs
          WHERE id = ?1"
     ).map_err(|e| e.to_string())?;
@@ -197,6 +218,7 @@ pub fn get_log_detail(log_id: &str) -> Result<ProxyRequestLog, String> {
             protocol: row.get(14).unwrap_or(None),
             client_ip: row.get(15).unwrap_or(None),
             username: row.get(16).unwrap_or(None),
+            cache_hit: row.get::<_, Option<bool>>(17).ok().flatten().unwrap_or(false),
         })
     }).map_err(|e| e.to_string())
 }
@@ -238,10 +260,63 @@ pub fn limit_max_logs(max_count: usize) -> Result<usize, String> {
 pub fn clear_logs() -> Result<(), String> {
     let conn = connect_db()?;
     conn.execute("DELETE FROM request_logs", []).map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM request_replays", []).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Save a replay record linking a replayed request to its original log
+pub fn save_replay(record: &ReplayRecord) -> Result<(), String> {
+    let conn = connect_db()?;
+    conn.execute(
+        "INSERT OR REPLACE INTO request_replays (replay_id, original_id, created_at, options, diff)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            record.replay_id,
+            record.original_id,
+            record.created_at,
+            serde_json::to_string(&record.options).map_err(|e| e.to_string())?,
+            serde_json::to_string(&record.diff).map_err(|e| e.to_string())?,
+        ],
+    ).map_err(|e| e.to_string())?;
     Ok(())
 }
 
+/// Get replay records of a logged request (newest first)
+pub fn get_replays(original_id: &str) -> Result<Vec<ReplayRecord>, String> {
+    let conn = connect_db()?;
+    let mut stmt = conn.prepare(
+        "SELECT replay_id, original_id, created_at, options, diff
+         FROM request_replays
+         WHERE original_id = ?1
+         ORDER BY created_at DESC"
+    ).map_err(|e| e.to_string())?;
+
+    let rows = stmt.query_map([original_id], |row| {
+        let options: Option<String> = row.get(3)?;
+        let diff: Option<String> = row.get(4)?;
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, i64>(2)?, options, diff))
+    }).map_err(|e| e.to_string())?;
+
+    let mut records = Vec::new();
+    for row in rows {
+        let (replay_id, original_id, created_at, options, diff) = row.map_err(|e| e.to_string())?;
+        let diff = match diff.and_then(|d| serde_json::from_str(&d).ok()) {
+            Some(d) => d,
+            None => continue,
+        };
+        records.push(ReplayRecord {
+            replay_id,
+            original_id,
+            created_at,
+            options: options.and_then(|o| serde_json::from_str(&o).ok()).unwrap_or_default(),
+            diff,
+        });
+    }
+    Ok(records)
+}
+
 /// Get total count of logs in database
+#[cfg(feature = "desktop")]
 pub fn get_logs_count() -> Result<u64, String> {
     let conn = connect_db()?;
     
@@ -293,7 +368,7 @@ pub fn get_logs_filtered(filter: &str, errors_only: bool, limit: usize, offset:
     let sql = if errors_only {
         "SELECT id, timestamp, method, url, status, duration, model, error,
                 NULL as request_body, NULL as response_body,
-                input_tokens, output_tokens, account_email, mapped_model, protocol, client_ip, username
+                input_tokens, output_tokens, account_email, mapped_model, protocol, client_ip, username, cache_hit
          FROM request_logs
          WHERE (status < 200 OR status >= 400)
          ORDER BY timestamp DESC
@@ -301,14 +376,14 @@ pub fn get_logs_filtered(filter: &str, errors_only: bool, limit: usize, offset:
     } else if filter.is_empty() {
         "SELECT id, timestamp, method, url, status, duration, model, error,
                 NULL as request_body, NULL as response_body,
-                input_tokens, output_tokens, account_email, mapped_model, protocol, client_ip, username
+                input_tokens, output_tokens, account_email, mapped_model, protocol, client_ip, username, cache_hit
          FROM request_logs
          ORDER BY timestamp DESC
          LIMIT ?1 OFFSET ?2"
     } else {
         "SELECT id, timestamp, method, url, status, duration, model, error,
                 NULL as request_body, NULL as response_body,
-                input_tokens, output_tokens, account_email, mapped_model, protocol, client_ip, username
+                input_tokens, output_tokens, account_email, mapped_model, protocol, client_ip, username, cache_hit
          FROM request_logs
          WHERE (url LIKE ?3 OR method LIKE ?3 OR model LIKE ?3 OR CAST(status AS TEXT) LIKE ?3 OR account_email LIKE ?3 OR client_ip LIKE ?3)
          ORDER BY timestamp DESC
@@ -336,6 +411,7 @@ pub fn get_logs_filtered(filter: &str, errors_only: bool, limit: usize, offset:
                 protocol: row.get(14).unwrap_or(None),
                 client_ip: row.get(15).unwrap_or(None),
                 username: row.get(16).unwrap_or(None),
+                cache_hit: row.get::<_, Option<bool>>(17).ok().flatten().unwrap_or(false),
             })
 
         }).map_err(|e| e.to_string())?;
@@ -361,6 +437,7 @@ pub fn get_logs_filtered(filter: &str, errors_only: bool, limit: usize, offset:
                 protocol: row.get(14).unwrap_or(None),
                 client_ip: row.get(15).unwrap_or(None),
                 username: row.get(16).unwrap_or(None),
+                cache_hit: row.get::<_, Option<bool>>(17).ok().flatten().unwrap_or(false),
             })
 
         }).map_err(|e| e.to_string())?;
@@ -386,6 +463,7 @@ pub fn get_logs_filtered(filter: &str, errors_only: bool, limit: usize, offset:
                 protocol: row.get(14).unwrap_or(None),
                 client_ip: row.get(15).unwrap_or(None),
                 username: row.get(16).unwrap_or(None),
+                cache_hit: row.get::<_, Option<bool>>(17).ok().flatten().unwrap_or(false),
             })
 
         }).map_err(|e| e.to_string())?;
@@ -396,13 +474,14 @@ pub fn get_logs_filtered(filter: &str, errors_only: bool, limit: usize, offset:
 }
 
 /// Get all logs with full details for export
+#[cfg(feature = "desktop")]
 pub fn get_all_logs_for_export() -> Result<Vec<ProxyRequestLog>, String> {
     let conn = connect_db()?;
 
     let mut stmt = conn.prepare(
         "SELECT id, timestamp, method, url, status, duration, model, error,
                 request_body, response_body, input_tokens, output_tokens,
-                account_email, mapped_model, protocol, client_ip, username
+                account_email, mapped_model, protocol, client_ip, username, cache_hit
          FROM request_logs
          ORDER BY timestamp DESC"
     ).map_err(|e| e.to_string())?;
@@ -426,6 +505,7 @@ pub fn get_all_logs_for_export() -> Result<Vec<ProxyRequestLog>, String> {
             protocol: row.get(14).unwrap_or(None),
             client_ip: row.get(15).unwrap_or(None),
             username: row.get(16).unwrap_or(None),
+            cache_hit: row.get::<_, Option<bool>>(17).ok().flatten().unwrap_or(false),
         })
 
     }).map_err(|e| e.to_string())?;
@@ -4,13 +4,6 @@ use serde_json::json;
 use crate::models::QuotaData;
 use crate::modules::config;
 
-// Quota API endpoints (fallback order: Sandbox → Daily → Prod)
-const QUOTA_API_ENDPOINTS: [&str; 3] = [
-    "https://daily-cloudcode-pa.sandbox.googleapis.com/v1internal:fetchAvailableModels",
-    "https://daily-cloudcode-pa.googleapis.com/v1internal:fetchAvailableModels",
-    "https://cloudcode-pa.googleapis.com/v1internal:fetchAvailableModels",
-];
-
 /// Critical retry threshold: considered near recovery when quota reaches 95%
 const NEAR_READY_THRESHOLD: i32 = 95;
 const MAX_RETRIES: u32 = 3;
@@ -111,15 +104,15 @@ async fn create_long_standard_client(account_id: Option<&str>) -> rquest::Client
     }
 }
 
-const CLOUD_CODE_BASE_URL: &str = "https://daily-cloudcode-pa.sandbox.googleapis.com";
-
 /// Fetch project ID and subscription tier
 async fn fetch_project_id(access_token: &str, email: &str, account_id: Option<&str>) -> (Option<String>, Option<String>) {
     let client = create_standard_client(account_id).await;
     let meta = json!({"metadata": {"ideType": "ANTIGRAVITY"}});
 
+    // 与代理共用端点配置 (默认 Sandbox 优先，可被 upstream_endpoints / ABV_UPSTREAM_ENDPOINTS 覆盖)
+    let base_url = crate::proxy::upstream::client::configured_primary_base_url();
     let res = client
-        .post(format!("{}/v1internal:loadCodeAssist", CLOUD_CODE_BASE_URL))
+        .post(format!("{}:loadCodeAssist", base_url))
         .header(rquest::header::AUTHORIZATION, format!("Bearer {}", access_token))
         .header(rquest::header::CONTENT_TYPE, "application/json")
         .header(rquest::header::USER_AGENT, crate::constants::NATIVE_OAUTH_USER_AGENT.as_str())
@@ -214,11 +207,17 @@ pub async fn fetch_quota_with_cache(
     
     let mut last_error: Option<AppError> = None;
 
-    for (ep_idx, ep_url) in QUOTA_API_ENDPOINTS.iter().enumerate() {
-        let has_next = ep_idx + 1 < QUOTA_API_ENDPOINTS.len();
+    // Quota API endpoints follow the proxy's upstream endpoint order (default: Sandbox → Daily → Prod)
+    let quota_endpoints: Vec<String> = crate::proxy::upstream::client::configured_base_urls()
+        .into_iter()
+        .map(|base| format!("{}:fetchAvailableModels", base))
+        .collect();
+
+    for (ep_idx, ep_url) in quota_endpoints.iter().enumerate() {
+        let has_next = ep_idx + 1 < quota_endpoints.len();
 
         match client
-            .post(*ep_url)
+            .post(ep_url)
             .bearer_auth(access_token)
             .header(rquest::header::USER_AGENT, crate::constants::NATIVE_OAUTH_USER_AGENT.as_str())
             .json(&payload)
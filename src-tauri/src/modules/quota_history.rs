@@ -0,0 +1,437 @@
+//! Quota History Module
+//! 配额快照时间序列：每次配额刷新按账号/模型落盘，用于计算消耗速率与预测耗尽时间
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::models::QuotaData;
+
+/// 快照保留天数
+const RETENTION_DAYS: i64 = 30;
+/// 计算消耗速率时回看的时间窗口
+const FORECAST_WINDOW_HOURS: i64 = 6;
+/// 少于该时间跨度的样本不计算速率，避免连续刷新造成的噪声
+const MIN_SPAN_SECS: i64 = 60;
+
+/// 单次配额快照
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuotaSnapshot {
+    pub timestamp: i64,
+    pub account_id: String,
+    pub email: String,
+    pub model: String,
+    pub percentage: i32,
+    /// 配额刷新时间 (Unix 秒)
+    pub reset_time: Option<i64>,
+}
+
+/// 单个账号单个模型的消耗预测
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuotaForecast {
+    pub account_id: String,
+    pub email: String,
+    pub model: String,
+    pub current_percentage: i32,
+    /// 每小时消耗的百分点 (无消耗时为 0)
+    pub burn_rate_per_hour: f64,
+    /// 按当前速率预计耗尽的时间 (Unix 秒)
+    pub projected_exhaustion: Option<i64>,
+    pub reset_time: Option<i64>,
+    /// 预计在配额刷新前耗尽
+    pub exhausts_before_reset: bool,
+    pub sample_count: usize,
+}
+
+/// 整个账号池单个模型的消耗预测
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolQuotaForecast {
+    pub model: String,
+    pub account_count: usize,
+    /// 各账号剩余百分比之和 (100 = 一个满额账号)
+    pub total_remaining_percentage: i64,
+    pub total_burn_rate_per_hour: f64,
+    /// 按当前总速率预计整个账号池耗尽的时间 (Unix 秒)
+    pub projected_exhaustion: Option<i64>,
+    /// 最近一次账号配额刷新时间
+    pub next_reset: Option<i64>,
+    pub accounts_exhausting_before_reset: usize,
+}
+
+const DB_FILE: &str = "quota_history.db";
+
+pub(crate) fn get_db_path() -> Result<PathBuf, String> {
+    let data_dir = crate::modules::account::get_data_dir()?;
+    Ok(data_dir.join(DB_FILE))
+}
+
+fn connect_db() -> Result<Connection, String> {
+    connect_db_at(&get_db_path()?)
+}
+
+fn connect_db_at(db_path: &Path) -> Result<Connection, String> {
+    let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
+
+    // Enable WAL mode for better concurrency
+    conn.pragma_update(None, "journal_mode", "WAL")
+        .map_err(|e| e.to_string())?;
+    conn.pragma_update(None, "busy_timeout", 5000)
+        .map_err(|e| e.to_string())?;
+    conn.pragma_update(None, "synchronous", "NORMAL")
+        .map_err(|e| e.to_string())?;
+
+    Ok(conn)
+}
+
+fn create_schema(conn: &Connection) -> Result<(), String> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS quota_snapshots (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            timestamp INTEGER NOT NULL,
+            account_id TEXT NOT NULL,
+            email TEXT NOT NULL,
+            model TEXT NOT NULL,
+            percentage INTEGER NOT NULL,
+            reset_time INTEGER
+        )",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_quota_account_model ON quota_snapshots (account_id, model, timestamp)",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_quota_timestamp ON quota_snapshots (timestamp)",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Initialize the quota history database (and prune expired snapshots)
+pub fn init_db() -> Result<(), String> {
+    let conn = connect_db()?;
+    create_schema(&conn)?;
+
+    let cutoff = chrono::Utc::now().timestamp() - RETENTION_DAYS * 24 * 3600;
+    conn.execute("DELETE FROM quota_snapshots WHERE timestamp < ?1", params![cutoff])
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+fn parse_reset_time(raw: &str) -> Option<i64> {
+    if raw.is_empty() {
+        return None;
+    }
+    chrono::DateTime::parse_from_rfc3339(raw)
+        .ok()
+        .map(|dt| dt.timestamp())
+}
+
+fn insert_snapshots(
+    conn: &Connection,
+    account_id: &str,
+    email: &str,
+    quota: &QuotaData,
+    timestamp: i64,
+) -> Result<(), String> {
+    for model in &quota.models {
+        conn.execute(
+            "INSERT INTO quota_snapshots (timestamp, account_id, email, model, percentage, reset_time)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                timestamp,
+                account_id,
+                email,
+                model.name,
+                model.percentage,
+                parse_reset_time(&model.reset_time),
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// 记录一次配额刷新 (403 / 空结果不记录，避免污染速率计算)
+pub fn record_quota_snapshot(account_id: &str, email: &str, quota: &QuotaData) -> Result<(), String> {
+    record_quota_snapshot_at(&get_db_path()?, account_id, email, quota)
+}
+
+pub(crate) fn record_quota_snapshot_at(
+    db_path: &Path,
+    account_id: &str,
+    email: &str,
+    quota: &QuotaData,
+) -> Result<(), String> {
+    if quota.is_forbidden || quota.models.is_empty() {
+        return Ok(());
+    }
+    let conn = connect_db_at(db_path)?;
+    create_schema(&conn)?;
+    insert_snapshots(&conn, account_id, email, quota, quota.last_updated)
+}
+
+fn query_snapshots(
+    conn: &Connection,
+    account_id: Option<&str>,
+    model: Option<&str>,
+    since: i64,
+) -> Result<Vec<QuotaSnapshot>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT timestamp, account_id, email, model, percentage, reset_time
+             FROM quota_snapshots
+             WHERE timestamp >= ?1
+               AND (?2 IS NULL OR account_id = ?2)
+               AND (?3 IS NULL OR model = ?3)
+             ORDER BY account_id, model, timestamp ASC",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let rows = stmt
+        .query_map(params![since, account_id, model], |row| {
+            Ok(QuotaSnapshot {
+                timestamp: row.get(0)?,
+                account_id: row.get(1)?,
+                email: row.get(2)?,
+                model: row.get(3)?,
+                percentage: row.get(4)?,
+                reset_time: row.get(5)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+
+    let mut result = Vec::new();
+    for row in rows {
+        result.push(row.map_err(|e| e.to_string())?);
+    }
+    Ok(result)
+}
+
+/// Get quota snapshots for the last `hours`, optionally filtered by account / model
+pub fn get_quota_history(
+    account_id: Option<&str>,
+    model: Option<&str>,
+    hours: i64,
+) -> Result<Vec<QuotaSnapshot>, String> {
+    let conn = connect_db()?;
+    let since = chrono::Utc::now().timestamp() - hours * 3600;
+    query_snapshots(&conn, account_id, model, since)
+}
+
+/// 根据同一账号同一模型按时间升序的样本计算预测
+///
+/// 仅使用当前配额周期内的样本：刷新时间变化或百分比回升都视为周期重置。
+fn forecast_series(samples: &[QuotaSnapshot]) -> Option<QuotaForecast> {
+    let latest = samples.last()?;
+
+    let mut cycle_start = samples.len() - 1;
+    while cycle_start > 0 {
+        let prev = &samples[cycle_start - 1];
+        let next = &samples[cycle_start];
+        if prev.reset_time != latest.reset_time || prev.percentage < next.percentage {
+            break;
+        }
+        cycle_start -= 1;
+    }
+    let first = &samples[cycle_start];
+
+    let span = latest.timestamp - first.timestamp;
+    let burn_rate_per_hour = if span >= MIN_SPAN_SECS {
+        ((first.percentage - latest.percentage) as f64 / (span as f64 / 3600.0)).max(0.0)
+    } else {
+        0.0
+    };
+
+    let projected_exhaustion = if latest.percentage <= 0 {
+        Some(latest.timestamp)
+    } else if burn_rate_per_hour > 0.0 {
+        Some(latest.timestamp + (latest.percentage as f64 / burn_rate_per_hour * 3600.0) as i64)
+    } else {
+        None
+    };
+    let exhausts_before_reset = match (projected_exhaustion, latest.reset_time) {
+        (Some(exhaust), Some(reset)) => exhaust < reset,
+        (Some(_), None) => true,
+        (None, _) => false,
+    };
+
+    Some(QuotaForecast {
+        account_id: latest.account_id.clone(),
+        email: latest.email.clone(),
+        model: latest.model.clone(),
+        current_percentage: latest.percentage,
+        burn_rate_per_hour,
+        projected_exhaustion,
+        reset_time: latest.reset_time,
+        exhausts_before_reset,
+        sample_count: samples.len() - cycle_start,
+    })
+}
+
+fn forecasts_from_snapshots(snapshots: Vec<QuotaSnapshot>) -> Vec<QuotaForecast> {
+    let mut series: BTreeMap<(String, String), Vec<QuotaSnapshot>> = BTreeMap::new();
+    for snapshot in snapshots {
+        series
+            .entry((snapshot.account_id.clone(), snapshot.model.clone()))
+            .or_default()
+            .push(snapshot);
+    }
+    series.values().filter_map(|s| forecast_series(s)).collect()
+}
+
+fn pool_from_forecasts(forecasts: &[QuotaForecast], now: i64) -> Vec<PoolQuotaForecast> {
+    let mut by_model: BTreeMap<&str, Vec<&QuotaForecast>> = BTreeMap::new();
+    for forecast in forecasts {
+        by_model.entry(forecast.model.as_str()).or_default().push(forecast);
+    }
+
+    by_model
+        .into_iter()
+        .map(|(model, items)| {
+            let total_remaining_percentage: i64 =
+                items.iter().map(|f| f.current_percentage.max(0) as i64).sum();
+            let total_burn_rate_per_hour: f64 = items.iter().map(|f| f.burn_rate_per_hour).sum();
+            let projected_exhaustion = if total_remaining_percentage == 0 {
+                Some(now)
+            } else if total_burn_rate_per_hour > 0.0 {
+                Some(now + (total_remaining_percentage as f64 / total_burn_rate_per_hour * 3600.0) as i64)
+            } else {
+                None
+            };
+            PoolQuotaForecast {
+                model: model.to_string(),
+                account_count: items.len(),
+                total_remaining_percentage,
+                total_burn_rate_per_hour,
+                projected_exhaustion,
+                next_reset: items.iter().filter_map(|f| f.reset_time).filter(|r| *r > now).min(),
+                accounts_exhausting_before_reset: items.iter().filter(|f| f.exhausts_before_reset).count(),
+            }
+        })
+        .collect()
+}
+
+/// Get per-account / per-model forecasts (optionally for a single account)
+pub fn get_quota_forecast(account_id: Option<&str>) -> Result<Vec<QuotaForecast>, String> {
+    let conn = connect_db()?;
+    let since = chrono::Utc::now().timestamp() - FORECAST_WINDOW_HOURS * 3600;
+    Ok(forecasts_from_snapshots(query_snapshots(&conn, account_id, None, since)?))
+}
+
+/// Get pool-wide forecasts per model
+pub fn get_pool_quota_forecast() -> Result<Vec<PoolQuotaForecast>, String> {
+    let forecasts = get_quota_forecast(None)?;
+    Ok(pool_from_forecasts(&forecasts, chrono::Utc::now().timestamp()))
+}
+
+/// 预计在刷新前耗尽的模型 (标准 ID)，供 TokenManager 排序降权使用
+pub fn exhausting_models(data_dir: &Path, account_id: &str) -> HashSet<String> {
+    let db_path = data_dir.join(DB_FILE);
+    if !db_path.exists() {
+        return HashSet::new();
+    }
+    let since = chrono::Utc::now().timestamp() - FORECAST_WINDOW_HOURS * 3600;
+    let forecasts = connect_db_at(&db_path)
+        .and_then(|conn| query_snapshots(&conn, Some(account_id), None, since))
+        .map(forecasts_from_snapshots);
+    match forecasts {
+        Ok(forecasts) => forecasts
+            .into_iter()
+            .filter(|f| f.exhausts_before_reset)
+            .map(|f| {
+                crate::proxy::common::model_mapping::normalize_to_standard_id(&f.model)
+                    .unwrap_or(f.model)
+            })
+            .collect(),
+        Err(e) => {
+            tracing::debug!("[QuotaHistory] Forecast unavailable for {}: {}", account_id, e);
+            HashSet::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quota(percentage: i32, reset_time: &str) -> QuotaData {
+        let mut q = QuotaData::new();
+        q.add_model(
+            serde_json::from_value(serde_json::json!({
+                "name": "claude-sonnet-4-5",
+                "percentage": percentage,
+                "reset_time": reset_time,
+            }))
+            .unwrap(),
+        );
+        q
+    }
+
+    #[test]
+    fn test_forecast_from_recorded_snapshots() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_schema(&conn).unwrap();
+
+        let t0 = 1_700_000_000;
+        let reset = "2023-11-15T12:00:00Z"; // t0 + ~13.8h
+        // 上一个周期的残留样本 (刷新时间不同)，不参与速率计算
+        insert_snapshots(&conn, "a1", "a@x", &quota(5, "2023-11-14T12:00:00Z"), t0 - 3600).unwrap();
+        insert_snapshots(&conn, "a1", "a@x", &quota(100, reset), t0).unwrap();
+        insert_snapshots(&conn, "a1", "a@x", &quota(80, reset), t0 + 3600).unwrap();
+        insert_snapshots(&conn, "a1", "a@x", &quota(60, reset), t0 + 7200).unwrap();
+        // 无消耗的账号
+        insert_snapshots(&conn, "a2", "b@x", &quota(90, reset), t0).unwrap();
+        insert_snapshots(&conn, "a2", "b@x", &quota(90, reset), t0 + 7200).unwrap();
+
+        let history = query_snapshots(&conn, Some("a1"), None, 0).unwrap();
+        assert_eq!(history.len(), 4);
+
+        let forecasts = forecasts_from_snapshots(query_snapshots(&conn, None, None, 0).unwrap());
+        assert_eq!(forecasts.len(), 2);
+
+        let a1 = &forecasts[0];
+        assert_eq!(a1.sample_count, 3);
+        assert!((a1.burn_rate_per_hour - 20.0).abs() < 1e-9);
+        assert_eq!(a1.projected_exhaustion, Some(t0 + 7200 + 3 * 3600));
+        assert!(a1.exhausts_before_reset);
+
+        let a2 = &forecasts[1];
+        assert_eq!(a2.burn_rate_per_hour, 0.0);
+        assert_eq!(a2.projected_exhaustion, None);
+        assert!(!a2.exhausts_before_reset);
+
+        let now = t0 + 7200;
+        let pool = pool_from_forecasts(&forecasts, now);
+        assert_eq!(pool.len(), 1);
+        assert_eq!(pool[0].total_remaining_percentage, 150);
+        assert_eq!(pool[0].projected_exhaustion, Some(now + 7 * 3600 + 1800));
+        assert_eq!(pool[0].accounts_exhausting_before_reset, 1);
+    }
+
+    #[test]
+    fn test_refill_starts_new_cycle() {
+        let snap = |ts: i64, pct: i32| QuotaSnapshot {
+            timestamp: ts,
+            account_id: "a1".to_string(),
+            email: "a@x".to_string(),
+            model: "gemini-3-pro-high".to_string(),
+            percentage: pct,
+            reset_time: None,
+        };
+        // 百分比回升 (刷新时间缺失时) 同样视为新周期
+        let series = vec![snap(0, 40), snap(3600, 10), snap(7200, 100), snap(10800, 90)];
+        let f = forecast_series(&series).unwrap();
+        assert_eq!(f.sample_count, 2);
+        assert!((f.burn_rate_per_hour - 10.0).abs() < 1e-9);
+        assert!(f.exhausts_before_reset);
+    }
+}
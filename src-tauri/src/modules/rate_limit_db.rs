@@ -0,0 +1,187 @@
+//! Rate Limit Database Module
+//! 限流锁定持久化 (跨进程重启保留 QUOTA_EXHAUSTED 等锁定状态)
+//! 写操作接收已打开的连接，由 RateLimitTracker 的后台写线程复用同一连接执行
+
+use rusqlite::{params, Connection};
+use std::path::Path;
+
+/// 持久化的限流锁定记录
+#[derive(Debug, Clone)]
+pub struct PersistedLockout {
+    /// 限流 Key ("account_id" 或 "account_id:model")
+    pub key: String,
+    pub account_id: String,
+    pub model: Option<String>,
+    pub reason: String,
+    /// 锁定到期时间 (Unix 秒)
+    pub reset_time: i64,
+    /// 检测时间 (Unix 秒)
+    pub detected_at: i64,
+}
+
+/// 持久化的连续失败计数
+#[derive(Debug, Clone)]
+pub struct PersistedFailureCount {
+    pub account_id: String,
+    pub count: u32,
+    /// 最近一次失败时间 (Unix 秒)
+    pub last_failure: i64,
+}
+
+pub fn connect_db(db_path: &Path) -> Result<Connection, String> {
+    let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
+
+    // Enable WAL mode for better concurrency
+    conn.pragma_update(None, "journal_mode", "WAL").map_err(|e| e.to_string())?;
+
+    // Set busy timeout to 5000ms to avoid "database is locked" errors
+    conn.pragma_update(None, "busy_timeout", 5000).map_err(|e| e.to_string())?;
+
+    // Synchronous NORMAL is faster and safe enough for WAL
+    conn.pragma_update(None, "synchronous", "NORMAL").map_err(|e| e.to_string())?;
+
+    Ok(conn)
+}
+
+pub fn init_db(db_path: &Path) -> Result<(), String> {
+    let conn = connect_db(db_path)?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS rate_limit_lockouts (
+            key TEXT PRIMARY KEY,
+            account_id TEXT NOT NULL,
+            model TEXT,
+            reason TEXT NOT NULL,
+            reset_time INTEGER NOT NULL,
+            detected_at INTEGER NOT NULL
+        )",
+        [],
+    ).map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_lockouts_reset_time ON rate_limit_lockouts (reset_time)",
+        [],
+    ).map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS rate_limit_failures (
+            account_id TEXT PRIMARY KEY,
+            count INTEGER NOT NULL,
+            last_failure INTEGER NOT NULL
+        )",
+        [],
+    ).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+pub fn save_lockout(conn: &Connection, lockout: &PersistedLockout) -> Result<(), String> {
+    conn.execute(
+        "INSERT OR REPLACE INTO rate_limit_lockouts (key, account_id, model, reason, reset_time, detected_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            lockout.key,
+            lockout.account_id,
+            lockout.model,
+            lockout.reason,
+            lockout.reset_time,
+            lockout.detected_at,
+        ],
+    ).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+pub fn delete_lockout(conn: &Connection, key: &str) -> Result<(), String> {
+    conn.execute("DELETE FROM rate_limit_lockouts WHERE key = ?1", params![key])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// 加载尚未过期的锁定记录
+pub fn load_active_lockouts(db_path: &Path, now: i64) -> Result<Vec<PersistedLockout>, String> {
+    let conn = connect_db(db_path)?;
+
+    let mut stmt = conn.prepare(
+        "SELECT key, account_id, model, reason, reset_time, detected_at
+         FROM rate_limit_lockouts
+         WHERE reset_time > ?1",
+    ).map_err(|e| e.to_string())?;
+
+    let rows = stmt.query_map(params![now], |row| {
+        Ok(PersistedLockout {
+            key: row.get(0)?,
+            account_id: row.get(1)?,
+            model: row.get(2)?,
+            reason: row.get(3)?,
+            reset_time: row.get(4)?,
+            detected_at: row.get(5)?,
+        })
+    }).map_err(|e| e.to_string())?;
+
+    let mut lockouts = Vec::new();
+    for row in rows {
+        lockouts.push(row.map_err(|e| e.to_string())?);
+    }
+    Ok(lockouts)
+}
+
+/// 删除已过期的锁定记录，以及早于 `failure_expiry_before` 的失败计数
+pub fn prune_expired(conn: &Connection, now: i64, failure_expiry_before: i64) -> Result<usize, String> {
+    let lockouts = conn.execute(
+        "DELETE FROM rate_limit_lockouts WHERE reset_time <= ?1",
+        params![now],
+    ).map_err(|e| e.to_string())?;
+
+    let failures = conn.execute(
+        "DELETE FROM rate_limit_failures WHERE last_failure < ?1",
+        params![failure_expiry_before],
+    ).map_err(|e| e.to_string())?;
+
+    Ok(lockouts + failures)
+}
+
+pub fn save_failure_count(conn: &Connection, account_id: &str, count: u32, last_failure: i64) -> Result<(), String> {
+    conn.execute(
+        "INSERT OR REPLACE INTO rate_limit_failures (account_id, count, last_failure)
+         VALUES (?1, ?2, ?3)",
+        params![account_id, count, last_failure],
+    ).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+pub fn delete_failure_count(conn: &Connection, account_id: &str) -> Result<(), String> {
+    conn.execute("DELETE FROM rate_limit_failures WHERE account_id = ?1", params![account_id])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+pub fn load_failure_counts(db_path: &Path) -> Result<Vec<PersistedFailureCount>, String> {
+    let conn = connect_db(db_path)?;
+
+    let mut stmt = conn.prepare(
+        "SELECT account_id, count, last_failure FROM rate_limit_failures",
+    ).map_err(|e| e.to_string())?;
+
+    let rows = stmt.query_map([], |row| {
+        Ok(PersistedFailureCount {
+            account_id: row.get(0)?,
+            count: row.get(1)?,
+            last_failure: row.get(2)?,
+        })
+    }).map_err(|e| e.to_string())?;
+
+    let mut counts = Vec::new();
+    for row in rows {
+        counts.push(row.map_err(|e| e.to_string())?);
+    }
+    Ok(counts)
+}
+
+/// 清空所有锁定记录 (与内存中的乐观重置保持一致)
+pub fn clear_lockouts(conn: &Connection) -> Result<(), String> {
+    conn.execute("DELETE FROM rate_limit_lockouts", [])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
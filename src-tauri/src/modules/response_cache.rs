@@ -0,0 +1,471 @@
+//! Response Cache Module
+//! 精确匹配响应缓存：相同的 (映射后模型, 消息, 工具, 生成参数) 在 TTL 内直接回放上次的上游 SSE 响应
+//! 缓存内容为原始的 Gemini SSE 文本，命中后仍经过 create_claude_sse_stream / create_openai_sse_stream 转换，
+//! 因此流式与非流式客户端共享同一条缓存
+
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{OnceLock, RwLock};
+
+use crate::proxy::config::ResponseCacheConfig;
+
+/// 响应头：HIT 表示响应来自本地缓存 (监控中间件据此标记 ProxyRequestLog.cache_hit)
+pub const CACHE_HEADER: &str = "X-Cache";
+
+/// 不参与缓存键计算的请求字段 (不影响上游生成结果)
+const VOLATILE_FIELDS: &[&str] = &["model", "stream", "stream_options", "metadata", "user"];
+
+const DB_FILE: &str = "response_cache.db";
+
+static GLOBAL_RESPONSE_CACHE_CONFIG: OnceLock<RwLock<ResponseCacheConfig>> = OnceLock::new();
+
+/// 更新全局响应缓存配置
+pub fn update_response_cache_config(config: ResponseCacheConfig) {
+    if let Some(lock) = GLOBAL_RESPONSE_CACHE_CONFIG.get() {
+        if let Ok(mut cfg) = lock.write() {
+            *cfg = config;
+        }
+    } else {
+        let _ = GLOBAL_RESPONSE_CACHE_CONFIG.set(RwLock::new(config));
+    }
+}
+
+fn current_config() -> ResponseCacheConfig {
+    GLOBAL_RESPONSE_CACHE_CONFIG
+        .get()
+        .and_then(|lock| lock.read().ok().map(|cfg| cfg.clone()))
+        .unwrap_or_default()
+}
+
+/// 缓存是否生效 (重放请求始终绕过缓存)
+pub fn is_enabled() -> bool {
+    current_config().enabled && !crate::proxy::replay::is_active()
+}
+
+/// 一条缓存的上游响应
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    pub model: String,
+    /// 原始 Gemini SSE 文本
+    pub body: String,
+}
+
+pub(crate) fn get_db_path() -> Result<PathBuf, String> {
+    let data_dir = crate::modules::account::get_data_dir()?;
+    Ok(data_dir.join(DB_FILE))
+}
+
+fn connect_db() -> Result<Connection, String> {
+    connect_db_at(&get_db_path()?)
+}
+
+fn connect_db_at(db_path: &Path) -> Result<Connection, String> {
+    let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
+
+    // Enable WAL mode for better concurrency
+    conn.pragma_update(None, "journal_mode", "WAL")
+        .map_err(|e| e.to_string())?;
+    conn.pragma_update(None, "busy_timeout", 5000)
+        .map_err(|e| e.to_string())?;
+    conn.pragma_update(None, "synchronous", "NORMAL")
+        .map_err(|e| e.to_string())?;
+
+    Ok(conn)
+}
+
+fn create_schema(conn: &Connection) -> Result<(), String> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS response_cache (
+            key TEXT PRIMARY KEY,
+            protocol TEXT NOT NULL,
+            model TEXT NOT NULL,
+            body TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            created_at INTEGER NOT NULL,
+            expires_at INTEGER NOT NULL,
+            last_hit_at INTEGER NOT NULL,
+            hits INTEGER NOT NULL DEFAULT 0
+        )",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_response_cache_last_hit ON response_cache (last_hit_at)",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Initialize the response cache database (and drop expired entries)
+pub fn init_db() -> Result<(), String> {
+    let conn = connect_db()?;
+    create_schema(&conn)?;
+
+    conn.execute(
+        "DELETE FROM response_cache WHERE expires_at <= ?1",
+        params![chrono::Utc::now().timestamp()],
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// 对象键按字母序排列，保证语义相同的请求得到相同的序列化结果
+fn canonicalize(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            let mut sorted = serde_json::Map::new();
+            for k in keys {
+                if !map[k].is_null() {
+                    sorted.insert(k.clone(), canonicalize(&map[k]));
+                }
+            }
+            Value::Object(sorted)
+        }
+        Value::Array(arr) => Value::Array(arr.iter().map(canonicalize).collect()),
+        other => other.clone(),
+    }
+}
+
+/// 是否为确定性请求 (显式 temperature > 0 的请求每次结果不同，不缓存)
+pub fn is_cacheable(temperature: Option<f64>) -> bool {
+    temperature.map_or(true, |t| t <= 0.0)
+}
+
+/// 计算缓存键：调用方 + 协议 + 映射后模型 + 归一化后的请求体 (消息、工具、生成参数)
+///
+/// caller 为用户令牌 ID (主 API Key 为 None)，不同租户之间不共享缓存
+pub fn cache_key(caller: Option<&str>, protocol: &str, mapped_model: &str, request: &Value) -> String {
+    use sha2::{Digest, Sha256};
+
+    let mut body = request.clone();
+    if let Some(obj) = body.as_object_mut() {
+        for field in VOLATILE_FIELDS {
+            obj.remove(*field);
+        }
+    }
+
+    let mut hasher = Sha256::new();
+    hasher.update(caller.unwrap_or("").as_bytes());
+    hasher.update([0u8]);
+    hasher.update(protocol.as_bytes());
+    hasher.update([0u8]);
+    hasher.update(mapped_model.as_bytes());
+    hasher.update([0u8]);
+    hasher.update(canonicalize(&body).to_string().as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+fn lookup_in(conn: &Connection, key: &str, now: i64) -> Result<Option<CachedResponse>, String> {
+    let entry = conn
+        .query_row(
+            "SELECT model, body FROM response_cache WHERE key = ?1 AND expires_at > ?2",
+            params![key, now],
+            |row| {
+                Ok(CachedResponse {
+                    model: row.get(0)?,
+                    body: row.get(1)?,
+                })
+            },
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    if entry.is_some() {
+        conn.execute(
+            "UPDATE response_cache SET hits = hits + 1, last_hit_at = ?2 WHERE key = ?1",
+            params![key, now],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    Ok(entry)
+}
+
+fn store_in(
+    conn: &Connection,
+    key: &str,
+    protocol: &str,
+    model: &str,
+    body: &str,
+    now: i64,
+    config: &ResponseCacheConfig,
+) -> Result<(), String> {
+    let max_bytes = config.max_size_mb.saturating_mul(1024 * 1024) as i64;
+    let size = body.len() as i64;
+    if size > max_bytes {
+        return Ok(());
+    }
+
+    conn.execute(
+        "INSERT OR REPLACE INTO response_cache (key, protocol, model, body, size, created_at, expires_at, last_hit_at, hits)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?6, 0)",
+        params![key, protocol, model, body, size, now, now + config.ttl_secs as i64],
+    )
+    .map_err(|e| e.to_string())?;
+
+    conn.execute("DELETE FROM response_cache WHERE expires_at <= ?1", params![now])
+        .map_err(|e| e.to_string())?;
+
+    // 超出总大小上限时按最近使用时间淘汰
+    let total: i64 = conn
+        .query_row("SELECT COALESCE(SUM(size), 0) FROM response_cache", [], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    if total > max_bytes {
+        let mut stmt = conn
+            .prepare("SELECT key, size FROM response_cache ORDER BY last_hit_at ASC, created_at ASC")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))
+            .map_err(|e| e.to_string())?;
+
+        let mut excess = total - max_bytes;
+        let mut evict = Vec::new();
+        for row in rows {
+            if excess <= 0 {
+                break;
+            }
+            let (k, s) = row.map_err(|e| e.to_string())?;
+            excess -= s;
+            evict.push(k);
+        }
+        for k in evict {
+            conn.execute("DELETE FROM response_cache WHERE key = ?1", params![k])
+                .map_err(|e| e.to_string())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// 查询未过期的缓存条目 (缓存未启用时返回 None；SQLite 读写在阻塞线程池执行)
+pub async fn lookup(key: &str) -> Option<CachedResponse> {
+    if !is_enabled() {
+        return None;
+    }
+    let key = key.to_string();
+    let result = tokio::task::spawn_blocking(move || {
+        connect_db().and_then(|conn| lookup_in(&conn, &key, chrono::Utc::now().timestamp()))
+    })
+    .await
+    .map_err(|e| e.to_string())
+    .and_then(|r| r);
+    match result {
+        Ok(entry) => entry,
+        Err(e) => {
+            tracing::debug!("[Response-Cache] Lookup failed: {}", e);
+            None
+        }
+    }
+}
+
+/// 写入缓存条目 (缓存未启用时忽略)
+pub fn store(key: &str, protocol: &str, model: &str, body: &str) -> Result<(), String> {
+    let config = current_config();
+    if !config.enabled {
+        return Ok(());
+    }
+    let conn = connect_db()?;
+    store_in(&conn, key, protocol, model, body, chrono::Utc::now().timestamp(), &config)
+}
+
+/// 待写入的缓存条目 (上游流完整结束后落盘)
+#[derive(Debug, Clone)]
+pub struct PendingEntry {
+    pub key: String,
+    pub protocol: &'static str,
+    pub model: String,
+}
+
+/// SSE 文本中是否包含带 finishReason 的终止块 (没有终止块说明上游流被截断)
+fn has_finish_reason(body: &str) -> bool {
+    body.lines()
+        .filter_map(|line| line.trim().strip_prefix("data:"))
+        .filter_map(|data| serde_json::from_str::<Value>(data.trim()).ok())
+        .any(|chunk| {
+            let inner = chunk.get("response").unwrap_or(&chunk);
+            inner
+                .get("candidates")
+                .and_then(|c| c.as_array())
+                .map(|candidates| {
+                    candidates
+                        .iter()
+                        .any(|c| c.get("finishReason").map(|r| !r.is_null()).unwrap_or(false))
+                })
+                .unwrap_or(false)
+        })
+}
+
+/// 包装上游 SSE 流：原样透传，同时累积内容，流正常结束且出现 finishReason 终止块后写入缓存
+/// 流中出现错误、被提前丢弃 (客户端断开、重试换号) 或缺少终止块时不写入
+pub fn tee_stream<S, E>(
+    mut stream: Pin<Box<S>>,
+    entry: PendingEntry,
+) -> Pin<Box<dyn Stream<Item = Result<Bytes, E>> + Send>>
+where
+    S: Stream<Item = Result<Bytes, E>> + Send + ?Sized + 'static,
+    E: Send + 'static,
+{
+    let max_bytes = current_config().max_size_mb.saturating_mul(1024 * 1024) as usize;
+
+    Box::pin(async_stream::stream! {
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut complete = true;
+        while let Some(item) = stream.next().await {
+            match &item {
+                Ok(bytes) => {
+                    if complete {
+                        buffer.extend_from_slice(bytes);
+                        if buffer.len() > max_bytes {
+                            complete = false;
+                            buffer = Vec::new();
+                        }
+                    }
+                }
+                Err(_) => complete = false,
+            }
+            yield item;
+        }
+
+        if complete && !buffer.is_empty() {
+            tokio::task::spawn_blocking(move || {
+                let body = String::from_utf8_lossy(&buffer).into_owned();
+                if !has_finish_reason(&body) {
+                    tracing::debug!("[Response-Cache] Stream ended without finishReason, not caching");
+                    return;
+                }
+                if let Err(e) = store(&entry.key, entry.protocol, &entry.model, &body) {
+                    tracing::debug!("[Response-Cache] Store failed: {}", e);
+                }
+            });
+        }
+    })
+}
+
+/// 将缓存的 SSE 文本还原为上游字节流
+pub fn replay_stream(body: String) -> Pin<Box<dyn Stream<Item = Result<Bytes, String>> + Send>> {
+    Box::pin(futures::stream::once(async move { Ok(Bytes::from(body)) }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn config(max_size_mb: u64) -> ResponseCacheConfig {
+        ResponseCacheConfig {
+            enabled: true,
+            ttl_secs: 60,
+            max_size_mb,
+        }
+    }
+
+    #[test]
+    fn test_cache_key_normalization() {
+        let a = json!({
+            "model": "claude-sonnet-4-5",
+            "stream": true,
+            "messages": [{"role": "user", "content": "hi"}],
+            "temperature": 0.0,
+            "metadata": {"user_id": "u1"},
+        });
+        let b = json!({
+            "temperature": 0.0,
+            "messages": [{"content": "hi", "role": "user"}],
+            "stream": false,
+            "model": "claude-opus-4-5",
+            "tools": null,
+        });
+        // 流式标志、客户端模型名、metadata 与字段顺序不影响缓存键
+        assert_eq!(
+            cache_key(None, "anthropic", "gemini-3-flash", &a),
+            cache_key(None, "anthropic", "gemini-3-flash", &b)
+        );
+
+        // 映射后模型、协议与生成参数都参与计算
+        assert_ne!(
+            cache_key(None, "anthropic", "gemini-3-flash", &a),
+            cache_key(None, "anthropic", "gemini-3-pro-high", &a)
+        );
+        assert_ne!(
+            cache_key(None, "anthropic", "gemini-3-flash", &a),
+            cache_key(None, "openai", "gemini-3-flash", &a)
+        );
+        let mut c = a.clone();
+        c["temperature"] = json!(0.7);
+        assert_ne!(
+            cache_key(None, "anthropic", "gemini-3-flash", &a),
+            cache_key(None, "anthropic", "gemini-3-flash", &c)
+        );
+
+        // 不同用户令牌之间不共享缓存
+        assert_ne!(
+            cache_key(Some("token-a"), "anthropic", "gemini-3-flash", &a),
+            cache_key(Some("token-b"), "anthropic", "gemini-3-flash", &a)
+        );
+        assert_ne!(
+            cache_key(Some("token-a"), "anthropic", "gemini-3-flash", &a),
+            cache_key(None, "anthropic", "gemini-3-flash", &a)
+        );
+    }
+
+    #[test]
+    fn test_is_cacheable() {
+        assert!(is_cacheable(None));
+        assert!(is_cacheable(Some(0.0)));
+        assert!(!is_cacheable(Some(0.7)));
+    }
+
+    #[test]
+    fn test_has_finish_reason() {
+        let finished = "data: {\"response\":{\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"hi\"}]}}]}}\n\n\
+                        data: {\"response\":{\"candidates\":[{\"finishReason\":\"STOP\"}]}}\n\n";
+        assert!(has_finish_reason(finished));
+
+        // 截断的流 (没有终止块) 不应缓存
+        let truncated = "data: {\"response\":{\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"hi\"}]}}]}}\n\n";
+        assert!(!has_finish_reason(truncated));
+        assert!(!has_finish_reason(""));
+    }
+
+    #[test]
+    fn test_store_lookup_ttl_and_eviction() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_schema(&conn).unwrap();
+        let cfg = config(1);
+        let now = 1_700_000_000;
+
+        store_in(&conn, "k1", "anthropic", "m", "data: {}\n\n", now, &cfg).unwrap();
+        let hit = lookup_in(&conn, "k1", now + 10).unwrap().unwrap();
+        assert_eq!(hit.model, "m");
+        assert_eq!(hit.body, "data: {}\n\n");
+        let hits: i64 = conn
+            .query_row("SELECT hits FROM response_cache WHERE key = 'k1'", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(hits, 1);
+
+        // 过期后不再命中
+        assert!(lookup_in(&conn, "k1", now + 61).unwrap().is_none());
+
+        // 超出总大小上限时淘汰最久未使用的条目
+        let big = "x".repeat(600 * 1024);
+        store_in(&conn, "a", "anthropic", "m", &big, now, &cfg).unwrap();
+        store_in(&conn, "b", "anthropic", "m", &big, now + 1, &cfg).unwrap();
+        assert!(lookup_in(&conn, "a", now + 2).unwrap().is_none());
+        assert!(lookup_in(&conn, "b", now + 2).unwrap().is_some());
+
+        // 单条超过上限的响应不缓存
+        let huge = "x".repeat(2 * 1024 * 1024);
+        store_in(&conn, "c", "anthropic", "m", &huge, now, &cfg).unwrap();
+        assert!(lookup_in(&conn, "c", now + 1).unwrap().is_none());
+    }
+}
@@ -0,0 +1,429 @@
+//! Response Store Module
+//! Responses API 服务端状态：保存每次响应的输入项与输出项 (带 TTL)，
+//! 客户端只发送增量 input + previous_response_id 时，沿响应链还原完整对话
+
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{OnceLock, RwLock};
+
+use crate::proxy::config::ResponseStoreConfig;
+
+const DB_FILE: &str = "responses.db";
+/// previous_response_id 链的最大回溯深度 (防止异常数据导致死循环)
+const MAX_CHAIN_DEPTH: usize = 512;
+/// 两次过期清理的最小间隔 (秒)
+const CLEANUP_INTERVAL_SECS: i64 = 3600;
+
+static GLOBAL_RESPONSE_STORE_CONFIG: OnceLock<RwLock<ResponseStoreConfig>> = OnceLock::new();
+static LAST_CLEANUP: AtomicI64 = AtomicI64::new(0);
+
+/// 更新全局响应存储配置
+pub fn update_response_store_config(config: ResponseStoreConfig) {
+    if let Some(lock) = GLOBAL_RESPONSE_STORE_CONFIG.get() {
+        if let Ok(mut cfg) = lock.write() {
+            *cfg = config;
+        }
+    } else {
+        let _ = GLOBAL_RESPONSE_STORE_CONFIG.set(RwLock::new(config));
+    }
+}
+
+fn current_config() -> ResponseStoreConfig {
+    GLOBAL_RESPONSE_STORE_CONFIG
+        .get()
+        .and_then(|lock| lock.read().ok().map(|cfg| cfg.clone()))
+        .unwrap_or_default()
+}
+
+pub fn is_enabled() -> bool {
+    current_config().enabled
+}
+
+/// 一条已保存的响应
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredResponse {
+    /// 响应 ID (resp-...)
+    pub id: String,
+    pub previous_response_id: Option<String>,
+    /// 创建者的用户令牌 ID (无用户令牌时为 None)，只有同一调用方可以读取/续接
+    pub owner: Option<String>,
+    /// 本次请求自带的 input 项 (不含从链上还原的历史)
+    pub input: Vec<Value>,
+    /// 返回给客户端的完整 Response 对象
+    pub response: Value,
+    pub created_at: i64,
+    pub expires_at: i64,
+}
+
+pub(crate) fn get_db_path() -> Result<PathBuf, String> {
+    let data_dir = crate::modules::account::get_data_dir()?;
+    Ok(data_dir.join(DB_FILE))
+}
+
+fn connect_db() -> Result<Connection, String> {
+    connect_db_at(&get_db_path()?)
+}
+
+fn connect_db_at(db_path: &Path) -> Result<Connection, String> {
+    let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
+
+    // Enable WAL mode for better concurrency
+    conn.pragma_update(None, "journal_mode", "WAL")
+        .map_err(|e| e.to_string())?;
+    conn.pragma_update(None, "busy_timeout", 5000)
+        .map_err(|e| e.to_string())?;
+    conn.pragma_update(None, "synchronous", "NORMAL")
+        .map_err(|e| e.to_string())?;
+
+    Ok(conn)
+}
+
+fn create_schema(conn: &Connection) -> Result<(), String> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS responses (
+            id TEXT PRIMARY KEY,
+            previous_response_id TEXT,
+            owner TEXT,
+            input TEXT NOT NULL,
+            response TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            expires_at INTEGER NOT NULL
+        )",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_responses_expires_at ON responses (expires_at)",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Initialize the response store database (and drop expired entries)
+pub fn init_db() -> Result<(), String> {
+    let conn = connect_db()?;
+    create_schema(&conn)?;
+    cleanup_in(&conn, chrono::Utc::now().timestamp())?;
+    Ok(())
+}
+
+fn cleanup_in(conn: &Connection, now: i64) -> Result<usize, String> {
+    conn.execute("DELETE FROM responses WHERE expires_at <= ?1", params![now])
+        .map_err(|e| e.to_string())
+}
+
+/// 将 Responses 请求的 input (字符串或项数组) 统一为项数组
+pub fn input_items(input: Option<&Value>) -> Vec<Value> {
+    match input {
+        Some(Value::String(text)) => vec![json!({
+            "type": "message",
+            "role": "user",
+            "content": [{ "type": "input_text", "text": text }]
+        })],
+        Some(Value::Array(items)) => items.clone(),
+        Some(Value::Null) | None => Vec::new(),
+        Some(other) => vec![other.clone()],
+    }
+}
+
+fn save_in(conn: &Connection, entry: &StoredResponse) -> Result<(), String> {
+    conn.execute(
+        "INSERT OR REPLACE INTO responses (id, previous_response_id, owner, input, response, created_at, expires_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            entry.id,
+            entry.previous_response_id,
+            entry.owner,
+            Value::Array(entry.input.clone()).to_string(),
+            entry.response.to_string(),
+            entry.created_at,
+            entry.expires_at,
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn get_in(
+    conn: &Connection,
+    id: &str,
+    owner: Option<&str>,
+    now: i64,
+) -> Result<Option<StoredResponse>, String> {
+    let row = conn
+        .query_row(
+            "SELECT id, previous_response_id, owner, input, response, created_at, expires_at
+             FROM responses WHERE id = ?1 AND owner IS ?2 AND expires_at > ?3",
+            params![id, owner, now],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, String>(4)?,
+                    row.get::<_, i64>(5)?,
+                    row.get::<_, i64>(6)?,
+                ))
+            },
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    let Some((id, previous_response_id, owner, input, response, created_at, expires_at)) = row
+    else {
+        return Ok(None);
+    };
+    let input: Vec<Value> = serde_json::from_str(&input).map_err(|e| e.to_string())?;
+    let response: Value = serde_json::from_str(&response).map_err(|e| e.to_string())?;
+    Ok(Some(StoredResponse {
+        id,
+        previous_response_id,
+        owner,
+        input,
+        response,
+        created_at,
+        expires_at,
+    }))
+}
+
+fn delete_in(conn: &Connection, id: &str, owner: Option<&str>) -> Result<bool, String> {
+    let deleted = conn
+        .execute(
+            "DELETE FROM responses WHERE id = ?1 AND owner IS ?2",
+            params![id, owner],
+        )
+        .map_err(|e| e.to_string())?;
+    Ok(deleted > 0)
+}
+
+/// 沿 previous_response_id 回溯，按时间顺序返回 (input 项 + output 项) 组成的完整历史
+fn history_in(
+    conn: &Connection,
+    previous_response_id: &str,
+    owner: Option<&str>,
+    now: i64,
+) -> Result<Vec<Value>, String> {
+    let mut chain = Vec::new();
+    let mut next = Some(previous_response_id.to_string());
+    while let Some(id) = next {
+        if chain.len() >= MAX_CHAIN_DEPTH {
+            return Err(format!(
+                "Response chain exceeds the maximum depth of {}.",
+                MAX_CHAIN_DEPTH
+            ));
+        }
+        let entry = get_in(conn, &id, owner, now)?
+            .ok_or_else(|| format!("Previous response with id '{}' not found.", id))?;
+        next = entry.previous_response_id.clone();
+        chain.push(entry);
+    }
+
+    let mut items = Vec::new();
+    for entry in chain.into_iter().rev() {
+        items.extend(entry.input);
+        if let Some(output) = entry.response.get("output").and_then(|o| o.as_array()) {
+            items.extend(output.iter().cloned());
+        }
+    }
+    Ok(items)
+}
+
+/// 在阻塞线程池中执行数据库操作，避免占用异步运行时
+async fn run_blocking<T, F>(f: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce(&Connection) -> Result<T, String> + Send + 'static,
+{
+    tokio::task::spawn_blocking(move || connect_db().and_then(|conn| f(&conn)))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// 还原 previous_response_id 之前的完整对话项
+pub async fn load_history(previous_response_id: &str, owner: Option<&str>) -> Result<Vec<Value>, String> {
+    let id = previous_response_id.to_string();
+    let owner = owner.map(String::from);
+    run_blocking(move |conn| {
+        history_in(conn, &id, owner.as_deref(), chrono::Utc::now().timestamp())
+    })
+    .await
+}
+
+/// 读取一条未过期的响应
+pub async fn get(id: &str, owner: Option<&str>) -> Result<Option<StoredResponse>, String> {
+    let id = id.to_string();
+    let owner = owner.map(String::from);
+    run_blocking(move |conn| get_in(conn, &id, owner.as_deref(), chrono::Utc::now().timestamp()))
+        .await
+}
+
+/// 删除一条响应，返回是否存在
+pub async fn delete(id: &str, owner: Option<&str>) -> Result<bool, String> {
+    let id = id.to_string();
+    let owner = owner.map(String::from);
+    run_blocking(move |conn| delete_in(conn, &id, owner.as_deref())).await
+}
+
+/// 待保存的响应上下文 (响应对象生成后补全)
+#[derive(Debug, Clone)]
+pub struct PendingResponse {
+    pub previous_response_id: Option<String>,
+    pub owner: Option<String>,
+    pub input: Vec<Value>,
+}
+
+impl PendingResponse {
+    /// 保存响应对象 (存储未启用或响应缺少 id 时忽略)
+    pub fn save(self, mut response: Value) -> Result<(), String> {
+        let config = current_config();
+        if !config.enabled {
+            return Ok(());
+        }
+        let Some(id) = response.get("id").and_then(|v| v.as_str()).map(String::from) else {
+            return Ok(());
+        };
+        response["previous_response_id"] = json!(self.previous_response_id);
+        response["store"] = json!(true);
+
+        let now = chrono::Utc::now().timestamp();
+        let conn = connect_db()?;
+        save_in(
+            &conn,
+            &StoredResponse {
+                id,
+                previous_response_id: self.previous_response_id,
+                owner: self.owner,
+                input: self.input,
+                response,
+                created_at: now,
+                expires_at: now + config.ttl_secs as i64,
+            },
+        )?;
+
+        let last = LAST_CLEANUP.load(Ordering::Relaxed);
+        if now - last >= CLEANUP_INTERVAL_SECS {
+            LAST_CLEANUP.store(now, Ordering::Relaxed);
+            let deleted = cleanup_in(&conn, now)?;
+            if deleted > 0 {
+                tracing::debug!("[Response-Store] Removed {} expired responses", deleted);
+            }
+        }
+        Ok(())
+    }
+
+    /// 后台保存，不阻塞响应返回
+    pub fn save_in_background(self, response: Value) {
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) = self.save(response) {
+                tracing::warn!("[Response-Store] Failed to save response: {}", e);
+            }
+        });
+    }
+}
+
+/// 从 Responses SSE 事件行中提取 response.completed 携带的响应对象
+fn completed_response(line: &str) -> Option<Value> {
+    let data = line.trim().strip_prefix("data:")?.trim();
+    let event: Value = serde_json::from_str(data).ok()?;
+    if event.get("type").and_then(|t| t.as_str()) != Some("response.completed") {
+        return None;
+    }
+    event.get("response").cloned()
+}
+
+/// 包装 Responses SSE 流：原样透传，收到 response.completed 事件时保存响应
+pub fn record_stream<S, E>(
+    mut stream: Pin<Box<S>>,
+    pending: PendingResponse,
+) -> Pin<Box<dyn Stream<Item = Result<Bytes, E>> + Send>>
+where
+    S: Stream<Item = Result<Bytes, E>> + Send + ?Sized + 'static,
+    E: Send + 'static,
+{
+    Box::pin(async_stream::stream! {
+        let mut pending = Some(pending);
+        let mut buffer: Vec<u8> = Vec::new();
+        while let Some(item) = stream.next().await {
+            if let (Ok(bytes), true) = (&item, pending.is_some()) {
+                buffer.extend_from_slice(bytes);
+                while let Some(pos) = buffer.iter().position(|&b| b == b'\n') {
+                    let line: Vec<u8> = buffer.drain(..=pos).collect();
+                    if let Some(response) = completed_response(&String::from_utf8_lossy(&line)) {
+                        if let Some(pending) = pending.take() {
+                            pending.save_in_background(response);
+                        }
+                        buffer.clear();
+                        break;
+                    }
+                }
+            }
+            yield item;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str, previous: Option<&str>, input: Vec<Value>, output: Vec<Value>, now: i64) -> StoredResponse {
+        StoredResponse {
+            id: id.to_string(),
+            previous_response_id: previous.map(String::from),
+            owner: None,
+            input,
+            response: json!({ "id": id, "object": "response", "output": output }),
+            created_at: now,
+            expires_at: now + 60,
+        }
+    }
+
+    #[test]
+    fn test_history_rebuilds_chain_with_tool_calls() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_schema(&conn).unwrap();
+        let now = 1_700_000_000;
+
+        let call = json!({"type": "function_call", "call_id": "call_1", "name": "shell", "arguments": "{\"command\":[\"ls\"]}"});
+        let call_output = json!({"type": "function_call_output", "call_id": "call_1", "output": "a.txt"});
+        let answer = json!({"type": "message", "role": "assistant", "content": [{"type": "output_text", "text": "Found a.txt"}]});
+
+        save_in(&conn, &entry("resp-1", None, input_items(Some(&json!("list files"))), vec![call.clone()], now)).unwrap();
+        save_in(&conn, &entry("resp-2", Some("resp-1"), vec![call_output.clone()], vec![answer.clone()], now)).unwrap();
+
+        let history = history_in(&conn, "resp-2", None, now + 1).unwrap();
+        assert_eq!(history.len(), 4);
+        assert_eq!(history[0]["content"][0]["text"], "list files");
+        assert_eq!(history[1], call);
+        assert_eq!(history[2], call_output);
+        assert_eq!(history[3], answer);
+
+        // 其他调用方看不到该链
+        assert!(history_in(&conn, "resp-2", Some("token-a"), now + 1).is_err());
+
+        // 链上任一响应过期或被删除都会报错
+        let err = history_in(&conn, "resp-2", None, now + 61).unwrap_err();
+        assert!(err.contains("resp-2"));
+        assert!(delete_in(&conn, "resp-1", None).unwrap());
+        assert!(!delete_in(&conn, "resp-1", None).unwrap());
+        let err = history_in(&conn, "resp-2", None, now + 1).unwrap_err();
+        assert!(err.contains("'resp-1' not found"));
+    }
+
+    #[test]
+    fn test_completed_response_extraction() {
+        assert!(completed_response("data: {\"type\":\"response.output_text.delta\",\"delta\":\"hi\"}").is_none());
+        assert!(completed_response(": ping").is_none());
+        let resp = completed_response("data: {\"type\":\"response.completed\",\"response\":{\"id\":\"resp-x\",\"output\":[]}}\n").unwrap();
+        assert_eq!(resp["id"], "resp-x");
+    }
+}
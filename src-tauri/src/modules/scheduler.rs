@@ -7,6 +7,12 @@ use crate::modules::{config, logger, quota, account};
 use crate::models::Account;
 use std::path::PathBuf;
 
+// 桌面版在 setup 阶段 (尚无 Tokio 上下文) 启动，需借助 Tauri 的全局运行时
+#[cfg(feature = "desktop")]
+use tauri::async_runtime::spawn;
+#[cfg(not(feature = "desktop"))]
+use tokio::spawn;
+
 // Warmup history: key = "email:model_name:100", value = warmup timestamp
 static WARMUP_HISTORY: Lazy<Mutex<HashMap<String, i64>>> = Lazy::new(|| Mutex::new(load_warmup_history()));
 
@@ -51,8 +57,8 @@ pub fn check_cooldown(key: &str, cooldown_seconds: i64) -> bool {
     }
 }
 
-pub fn start_scheduler(app_handle: Option<tauri::AppHandle>, proxy_state: crate::commands::proxy::ProxyServiceState) {
-    tauri::async_runtime::spawn(async move {
+pub fn start_scheduler(proxy_state: crate::commands::proxy::ProxyServiceState) {
+    spawn(async move {
         logger::log_info("Smart Warmup Scheduler started. Monitoring quota at 100%...");
         
         // Scan every 10 minutes
@@ -182,7 +188,6 @@ pub fn start_scheduler(app_handle: Option<tauri::AppHandle>, proxy_state: crate:
                     total
                 ));
 
-                let handle_for_warmup = app_handle.clone();
                 let state_for_warmup = proxy_state.clone();
 
                 tokio::spawn(async move {
@@ -237,7 +242,7 @@ pub fn start_scheduler(app_handle: Option<tauri::AppHandle>, proxy_state: crate:
 
                     // Refresh quota
                     tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
-                    let _ = crate::commands::refresh_all_quotas_internal(&state_for_warmup, handle_for_warmup).await;
+                    let _ = crate::commands::refresh_all_quotas_internal(&state_for_warmup).await;
                 });
             } else if skipped_cooldown > 0 {
                 logger::log_info(&format!(
@@ -248,13 +253,12 @@ pub fn start_scheduler(app_handle: Option<tauri::AppHandle>, proxy_state: crate:
                 logger::log_info("[Scheduler] Scan completed, no models with 100% quota need warmup");
             }
 
-            // Sync to frontend if handle exists
-            if let Some(handle) = app_handle.as_ref() {
-                let handle_inner = handle.clone();
+            // Sync to frontend (via app event bus)
+            {
                 let state_inner = proxy_state.clone();
                 tokio::spawn(async move {
                     tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-                    let _ = crate::commands::refresh_all_quotas_internal(&state_inner, Some(handle_inner)).await;
+                    let _ = crate::commands::refresh_all_quotas_internal(&state_inner).await;
                     logger::log_info("[Scheduler] Quota data synced to frontend");
                 });
             }
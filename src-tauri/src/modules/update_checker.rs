@@ -356,6 +356,7 @@ pub fn update_last_check_time() -> Result<(), String> {
 }
 
 /// Detect if the app was installed via Homebrew Cask (macOS only)
+#[cfg(feature = "desktop")]
 pub fn is_homebrew_installed() -> bool {
     #[cfg(target_os = "macos")]
     {
@@ -376,6 +377,7 @@ pub fn is_homebrew_installed() -> bool {
 }
 
 /// Execute `brew upgrade --cask antigravity-tools` with timeout (macOS only)
+#[cfg(feature = "desktop")]
 #[cfg(not(target_os = "macos"))]
 pub async fn brew_upgrade_cask() -> Result<String, String> {
     Err("brew_not_supported".to_string())
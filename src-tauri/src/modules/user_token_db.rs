@@ -8,7 +8,7 @@ use rusqlite::{params, Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use uuid::Uuid;
-use chrono::{Utc, Local, Timelike, FixedOffset};
+use chrono::{Utc, Local, Timelike, FixedOffset, Datelike, TimeZone};
 
 /// 用户令牌结构体
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,6 +28,108 @@ pub struct UserToken {
     pub last_used_at: Option<i64>,
     pub total_requests: i64,
     pub total_tokens_used: i64,
+    /// 允许使用的模型 (支持 * 通配符，为空表示不限制)
+    #[serde(default)]
+    pub allowed_models: Vec<String>,
+    /// 禁止使用的模型 (支持 * 通配符，优先于 allowed_models)
+    #[serde(default)]
+    pub denied_models: Vec<String>,
+    /// 可调度的账号分组 (为空表示使用全部账号)
+    #[serde(default)]
+    pub account_groups: Vec<String>,
+    /// Token 预算与速率限制
+    #[serde(default)]
+    pub limits: TokenLimits,
+}
+
+/// 令牌的预算与速率限制 (所有字段 0 = 不限制)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TokenLimits {
+    /// 每日 Token 预算 (按服务器本地时间自然日)
+    #[serde(default)]
+    pub daily_token_limit: i64,
+    /// 每周 Token 预算 (周一 00:00 重置)
+    #[serde(default)]
+    pub weekly_token_limit: i64,
+    /// 每月 Token 预算 (每月 1 日 00:00 重置)
+    #[serde(default)]
+    pub monthly_token_limit: i64,
+    /// 每分钟最大请求数
+    #[serde(default)]
+    pub max_rpm: i32,
+    /// 最大并发请求数
+    #[serde(default)]
+    pub max_concurrent: i32,
+}
+
+/// 预算周期
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetPeriod {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl BudgetPeriod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BudgetPeriod::Daily => "daily",
+            BudgetPeriod::Weekly => "weekly",
+            BudgetPeriod::Monthly => "monthly",
+        }
+    }
+
+    /// 返回当前周期的 (开始时间戳, 下次重置时间戳)
+    pub fn bounds(&self, now: chrono::DateTime<Local>) -> (i64, i64) {
+        let today = now.date_naive();
+        let (start, end) = match self {
+            BudgetPeriod::Daily => (today, today + chrono::Duration::days(1)),
+            BudgetPeriod::Weekly => {
+                let start = today - chrono::Duration::days(today.weekday().num_days_from_monday() as i64);
+                (start, start + chrono::Duration::weeks(1))
+            }
+            BudgetPeriod::Monthly => {
+                let start = today.with_day(1).unwrap_or(today);
+                let end = if start.month() == 12 {
+                    chrono::NaiveDate::from_ymd_opt(start.year() + 1, 1, 1)
+                } else {
+                    chrono::NaiveDate::from_ymd_opt(start.year(), start.month() + 1, 1)
+                }
+                .unwrap_or(start + chrono::Duration::days(31));
+                (start, end)
+            }
+        };
+        let to_ts = |d: chrono::NaiveDate| {
+            let naive = d.and_hms_opt(0, 0, 0).unwrap_or_default();
+            Local
+                .from_local_datetime(&naive)
+                .earliest()
+                .map(|dt| dt.timestamp())
+                .unwrap_or_else(|| naive.and_utc().timestamp())
+        };
+        (to_ts(start), to_ts(end))
+    }
+}
+
+/// 单个预算周期的用量
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetUsage {
+    pub period: BudgetPeriod,
+    pub used: i64,
+    pub limit: i64,
+    /// 周期重置时间 (Unix 秒)
+    pub resets_at: i64,
+}
+
+/// 超出预算的详情
+#[derive(Debug, Clone)]
+pub struct BudgetExceeded {
+    pub period: BudgetPeriod,
+    pub used: i64,
+    pub limit: i64,
+    /// 距离周期重置的秒数
+    pub retry_after: i64,
 }
 
 /// 令牌 IP 绑定结构体
@@ -55,6 +157,29 @@ pub struct TokenUsageLog {
     pub status: u16,
 }
 
+/// 模型规则列表以 JSON 数组形式存储
+fn model_list_to_json(list: &[String]) -> String {
+    serde_json::to_string(list).unwrap_or_else(|_| "[]".to_string())
+}
+
+fn limits_from_row(row: &rusqlite::Row) -> TokenLimits {
+    TokenLimits {
+        daily_token_limit: row.get::<_, Option<i64>>("daily_token_limit").ok().flatten().unwrap_or(0),
+        weekly_token_limit: row.get::<_, Option<i64>>("weekly_token_limit").ok().flatten().unwrap_or(0),
+        monthly_token_limit: row.get::<_, Option<i64>>("monthly_token_limit").ok().flatten().unwrap_or(0),
+        max_rpm: row.get::<_, Option<i32>>("max_rpm").ok().flatten().unwrap_or(0),
+        max_concurrent: row.get::<_, Option<i32>>("max_concurrent").ok().flatten().unwrap_or(0),
+    }
+}
+
+fn model_list_from_row(row: &rusqlite::Row, column: &str) -> Vec<String> {
+    row.get::<_, Option<String>>(column)
+        .ok()
+        .flatten()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
 /// 获取数据库路径
 pub fn get_db_path() -> Result<PathBuf, String> {
     let mut path = crate::modules::account::get_data_dir()?;
@@ -91,7 +216,15 @@ pub fn init_db() -> Result<(), String> {
             total_requests INTEGER NOT NULL DEFAULT 0,
             total_tokens_used INTEGER NOT NULL DEFAULT 0,
             curfew_start TEXT,
-            curfew_end TEXT
+            curfew_end TEXT,
+            allowed_models TEXT,
+            denied_models TEXT,
+            account_groups TEXT,
+            daily_token_limit INTEGER NOT NULL DEFAULT 0,
+            weekly_token_limit INTEGER NOT NULL DEFAULT 0,
+            monthly_token_limit INTEGER NOT NULL DEFAULT 0,
+            max_rpm INTEGER NOT NULL DEFAULT 0,
+            max_concurrent INTEGER NOT NULL DEFAULT 0
         )",
         [],
     ).map_err(|e| format!("Failed to create user_tokens table: {}", e))?;
@@ -105,6 +238,14 @@ pub fn init_db() -> Result<(), String> {
     let _ = conn.execute("ALTER TABLE user_tokens ADD COLUMN last_used_at INTEGER", []);
     let _ = conn.execute("ALTER TABLE user_tokens ADD COLUMN curfew_start TEXT", []);
     let _ = conn.execute("ALTER TABLE user_tokens ADD COLUMN curfew_end TEXT", []);
+    let _ = conn.execute("ALTER TABLE user_tokens ADD COLUMN allowed_models TEXT", []);
+    let _ = conn.execute("ALTER TABLE user_tokens ADD COLUMN denied_models TEXT", []);
+    let _ = conn.execute("ALTER TABLE user_tokens ADD COLUMN daily_token_limit INTEGER DEFAULT 0", []);
+    let _ = conn.execute("ALTER TABLE user_tokens ADD COLUMN weekly_token_limit INTEGER DEFAULT 0", []);
+    let _ = conn.execute("ALTER TABLE user_tokens ADD COLUMN monthly_token_limit INTEGER DEFAULT 0", []);
+    let _ = conn.execute("ALTER TABLE user_tokens ADD COLUMN max_rpm INTEGER DEFAULT 0", []);
+    let _ = conn.execute("ALTER TABLE user_tokens ADD COLUMN max_concurrent INTEGER DEFAULT 0", []);
+    let _ = conn.execute("ALTER TABLE user_tokens ADD COLUMN account_groups TEXT", []);
 
     // 创建 token_ip_bindings 表
     conn.execute(
@@ -161,7 +302,11 @@ pub fn create_token(
     max_ips: i32,
     curfew_start: Option<String>,
     curfew_end: Option<String>,
-    custom_expires_at: Option<i64>  // 自定义过期时间戳 (秒)
+    custom_expires_at: Option<i64>,  // 自定义过期时间戳 (秒)
+    allowed_models: Vec<String>,
+    denied_models: Vec<String>,
+    account_groups: Vec<String>,
+    limits: TokenLimits,
 ) -> Result<UserToken, String> {
     let conn = connect_db()?;
     let id = Uuid::new_v4().to_string();
@@ -192,14 +337,20 @@ pub fn create_token(
         last_used_at: None,
         total_requests: 0,
         total_tokens_used: 0,
+        allowed_models,
+        denied_models,
+        account_groups,
+        limits,
     };
 
     conn.execute(
         "INSERT INTO user_tokens (
             id, token, username, description, enabled, expires_type, expires_at, max_ips,
             curfew_start, curfew_end,
-            created_at, updated_at, total_requests, total_tokens_used
-        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            created_at, updated_at, total_requests, total_tokens_used,
+            allowed_models, denied_models, account_groups,
+            daily_token_limit, weekly_token_limit, monthly_token_limit, max_rpm, max_concurrent
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22)",
         params![
             user_token.id,
             user_token.token,
@@ -215,6 +366,14 @@ pub fn create_token(
             user_token.updated_at,
             user_token.total_requests,
             user_token.total_tokens_used,
+            model_list_to_json(&user_token.allowed_models),
+            model_list_to_json(&user_token.denied_models),
+            model_list_to_json(&user_token.account_groups),
+            user_token.limits.daily_token_limit,
+            user_token.limits.weekly_token_limit,
+            user_token.limits.monthly_token_limit,
+            user_token.limits.max_rpm,
+            user_token.limits.max_concurrent,
         ],
     ).map_err(|e| format!("Failed to insert user token: {}", e))?;
 
@@ -244,6 +403,10 @@ pub fn list_tokens() -> Result<Vec<UserToken>, String> {
             last_used_at: row.get("last_used_at").unwrap_or(None),
             total_requests: row.get("total_requests").unwrap_or(0),
             total_tokens_used: row.get("total_tokens_used").unwrap_or(0),
+            allowed_models: model_list_from_row(row, "allowed_models"),
+            denied_models: model_list_from_row(row, "denied_models"),
+            account_groups: model_list_from_row(row, "account_groups"),
+            limits: limits_from_row(row),
         })
     }).map_err(|e| format!("Failed to query tokens: {}", e))?;
 
@@ -278,6 +441,10 @@ pub fn get_token_by_id(id: &str) -> Result<Option<UserToken>, String> {
             last_used_at: row.get("last_used_at")?,
             total_requests: row.get("total_requests")?,
             total_tokens_used: row.get("total_tokens_used")?,
+            allowed_models: model_list_from_row(row, "allowed_models"),
+            denied_models: model_list_from_row(row, "denied_models"),
+            account_groups: model_list_from_row(row, "account_groups"),
+            limits: limits_from_row(row),
         })
     }).optional().map_err(|e| format!("Failed to query token: {}", e))?;
     
@@ -307,6 +474,10 @@ pub fn get_token_by_value(token: &str) -> Result<Option<UserToken>, String> {
             last_used_at: row.get("last_used_at")?,
             total_requests: row.get("total_requests")?,
             total_tokens_used: row.get("total_tokens_used")?,
+            allowed_models: model_list_from_row(row, "allowed_models"),
+            denied_models: model_list_from_row(row, "denied_models"),
+            account_groups: model_list_from_row(row, "account_groups"),
+            limits: limits_from_row(row),
         })
     }).optional().map_err(|e| format!("Failed to query token: {}", e))?;
     
@@ -321,7 +492,11 @@ pub fn update_token(
     enabled: Option<bool>,
     max_ips: Option<i32>,
     curfew_start: Option<Option<String>>,
-    curfew_end: Option<Option<String>>
+    curfew_end: Option<Option<String>>,
+    allowed_models: Option<Vec<String>>,
+    denied_models: Option<Vec<String>>,
+    account_groups: Option<Vec<String>>,
+    limits: Option<TokenLimits>,
 ) -> Result<(), String> {
     let conn = connect_db()?;
     let now = Utc::now().timestamp();
@@ -366,6 +541,37 @@ pub fn update_token(
         param_idx += 1;
     }
 
+    if let Some(models) = allowed_models {
+        query.push_str(&format!(", allowed_models = ?{}", param_idx));
+        params_vec.push(Box::new(model_list_to_json(&models)));
+        param_idx += 1;
+    }
+
+    if let Some(models) = denied_models {
+        query.push_str(&format!(", denied_models = ?{}", param_idx));
+        params_vec.push(Box::new(model_list_to_json(&models)));
+        param_idx += 1;
+    }
+
+    if let Some(groups) = account_groups {
+        query.push_str(&format!(", account_groups = ?{}", param_idx));
+        params_vec.push(Box::new(model_list_to_json(&groups)));
+        param_idx += 1;
+    }
+
+    if let Some(l) = limits {
+        query.push_str(&format!(
+            ", daily_token_limit = ?{}, weekly_token_limit = ?{}, monthly_token_limit = ?{}, max_rpm = ?{}, max_concurrent = ?{}",
+            param_idx, param_idx + 1, param_idx + 2, param_idx + 3, param_idx + 4
+        ));
+        params_vec.push(Box::new(l.daily_token_limit));
+        params_vec.push(Box::new(l.weekly_token_limit));
+        params_vec.push(Box::new(l.monthly_token_limit));
+        params_vec.push(Box::new(l.max_rpm));
+        params_vec.push(Box::new(l.max_concurrent));
+        param_idx += 5;
+    }
+
     query.push_str(&format!(" WHERE id = ?{}", param_idx));
     params_vec.push(Box::new(id.to_string()));
 
@@ -567,6 +773,58 @@ pub fn validate_token(token_str: &str, ip: &str) -> Result<(bool, Option<String>
     }
 }
 
+/// 统计令牌自某时间点以来消耗的 Token 数
+pub fn get_tokens_used_since(token_id: &str, since: i64) -> Result<i64, String> {
+    let conn = connect_db()?;
+    conn.query_row(
+        "SELECT COALESCE(SUM(COALESCE(input_tokens, 0) + COALESCE(output_tokens, 0)), 0)
+         FROM token_usage_logs WHERE token_id = ?1 AND request_time >= ?2",
+        params![token_id, since],
+        |row| row.get(0),
+    ).map_err(|e| format!("Failed to query token usage: {}", e))
+}
+
+/// 获取令牌在各预算周期内的用量 (仅包含已设置上限的周期)
+pub fn get_budget_usage(token: &UserToken) -> Result<Vec<BudgetUsage>, String> {
+    let now = Local::now();
+    let periods = [
+        (BudgetPeriod::Daily, token.limits.daily_token_limit),
+        (BudgetPeriod::Weekly, token.limits.weekly_token_limit),
+        (BudgetPeriod::Monthly, token.limits.monthly_token_limit),
+    ];
+
+    let mut usage = Vec::new();
+    for (period, limit) in periods {
+        if limit <= 0 {
+            continue;
+        }
+        let (start, resets_at) = period.bounds(now);
+        usage.push(BudgetUsage {
+            period,
+            used: get_tokens_used_since(&token.id, start)?,
+            limit,
+            resets_at,
+        });
+    }
+    Ok(usage)
+}
+
+/// 检查令牌是否超出 Token 预算，返回第一个超限的周期
+pub fn check_token_budget(token: &UserToken) -> Result<Option<BudgetExceeded>, String> {
+    let now_ts = Utc::now().timestamp();
+    for usage in get_budget_usage(token)? {
+        if usage.used >= usage.limit {
+            return Ok(Some(BudgetExceeded {
+                period: usage.period,
+                used: usage.used,
+                limit: usage.limit,
+                retry_after: (usage.resets_at - now_ts).max(1),
+            }));
+        }
+    }
+    Ok(None)
+}
+
 /// 获取 IP 关联的用户名 (用于 IP 管理页面)
 /// 返回最近一次使用该 IP 的 Token 所属的用户名
 pub fn get_username_for_ip(ip: &str) -> Result<Option<String>, String> {
@@ -595,7 +853,7 @@ mod tests {
         
         // Use a random username to avoid collisions in existing DB runs during dev
         let username = format!("TestUser_{}", Uuid::new_v4());
-        let token_res = create_token(username.clone(), "day".to_string(), Some("Test token".to_string()), 0, None, None, None);
+        let token_res = create_token(username.clone(), "day".to_string(), Some("Test token".to_string()), 0, None, None, None, Vec::new(), Vec::new(), Vec::new(), TokenLimits::default());
         assert!(token_res.is_ok());
 
         let token = token_res.unwrap();
@@ -606,4 +864,79 @@ mod tests {
         assert!(fetched.is_ok());
         assert_eq!(fetched.unwrap().unwrap().username, username);
     }
+
+    #[test]
+    fn test_token_model_rules_roundtrip() {
+        let _ = init_db();
+
+        let username = format!("TestUser_{}", Uuid::new_v4());
+        let token = create_token(
+            username,
+            "day".to_string(),
+            None,
+            0,
+            None,
+            None,
+            None,
+            vec!["gemini-*".to_string()],
+            vec!["*-image*".to_string()],
+            vec!["team-a".to_string()],
+            TokenLimits::default(),
+        ).unwrap();
+
+        let fetched = get_token_by_value(&token.token).unwrap().unwrap();
+        assert_eq!(fetched.allowed_models, vec!["gemini-*"]);
+        assert_eq!(fetched.denied_models, vec!["*-image*"]);
+        assert_eq!(fetched.account_groups, vec!["team-a"]);
+
+        update_token(&token.id, None, None, None, None, None, None, Some(Vec::new()), None, None, None).unwrap();
+        let fetched = get_token_by_id(&token.id).unwrap().unwrap();
+        assert!(fetched.allowed_models.is_empty());
+        assert_eq!(fetched.denied_models, vec!["*-image*"]);
+        assert_eq!(fetched.account_groups, vec!["team-a"]);
+
+        update_token(&token.id, None, None, None, None, None, None, None, None, Some(Vec::new()), None).unwrap();
+        let fetched = get_token_by_id(&token.id).unwrap().unwrap();
+        assert!(fetched.account_groups.is_empty());
+
+        let _ = delete_token(&token.id);
+    }
+
+    #[test]
+    fn test_budget_period_bounds() {
+        let now = Local.with_ymd_and_hms(2026, 12, 17, 15, 30, 0).unwrap(); // 周四
+        let day = |y, m, d| Local.with_ymd_and_hms(y, m, d, 0, 0, 0).unwrap().timestamp();
+
+        assert_eq!(BudgetPeriod::Daily.bounds(now), (day(2026, 12, 17), day(2026, 12, 18)));
+        assert_eq!(BudgetPeriod::Weekly.bounds(now), (day(2026, 12, 14), day(2026, 12, 21)));
+        assert_eq!(BudgetPeriod::Monthly.bounds(now), (day(2026, 12, 1), day(2027, 1, 1)));
+    }
+
+    #[test]
+    fn test_token_budget_enforcement() {
+        let _ = init_db();
+
+        let token = create_token(
+            format!("TestUser_{}", Uuid::new_v4()),
+            "day".to_string(),
+            None,
+            0,
+            None,
+            None,
+            None,
+            Vec::new(),
+            Vec::new(),
+            Vec::new(),
+            TokenLimits { daily_token_limit: 1000, ..Default::default() },
+        ).unwrap();
+        assert!(check_token_budget(&token).unwrap().is_none());
+
+        record_token_usage_and_ip(&token.id, "127.0.0.1", "gemini-2.5-flash", 600, 500, 200, None).unwrap();
+        let exceeded = check_token_budget(&token).unwrap().expect("budget should be exceeded");
+        assert_eq!(exceeded.period, BudgetPeriod::Daily);
+        assert_eq!(exceeded.used, 1100);
+        assert!(exceeded.retry_after > 0 && exceeded.retry_after <= 86400 + 3600);
+
+        let _ = delete_token(&token.id);
+    }
 }
@@ -190,11 +190,13 @@ fn get_version_linux(exe_path: &PathBuf) -> Result<AntigravityVersion, String> {
 }
 
 /// 判断是否为新版本 (>= 1.16.5)
+#[cfg(feature = "desktop")]
 pub fn is_new_version(version: &AntigravityVersion) -> bool {
     compare_version(&version.short_version, "1.16.5") >= std::cmp::Ordering::Equal
 }
 
 /// 比较版本号
+#[cfg(feature = "desktop")]
 fn compare_version(v1: &str, v2: &str) -> std::cmp::Ordering {
     let parts1: Vec<u32> = v1
         .split('.')
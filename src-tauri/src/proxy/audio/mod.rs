@@ -1,9 +1,19 @@
 use base64::{engine::general_purpose, Engine as _};
 use std::path::Path;
 
+pub mod segment;
+pub mod transcript;
+
+use segment::AudioSegment;
+
 pub struct AudioProcessor;
 
 impl AudioProcessor {
+    /// 单次请求内联音频的大小上限 (15MB)
+    pub const MAX_INLINE_SIZE: usize = 15 * 1024 * 1024;
+    /// 单段音频的时长上限 (秒)，过长的音频即使体积不大也会分段，避免单次输出被截断
+    pub const MAX_SEGMENT_SECS: f64 = 600.0;
+
     /// 检测音频 MIME 类型
     pub fn detect_mime_type(filename: &str) -> Result<String, String> {
         let ext = Path::new(filename)
@@ -29,8 +39,12 @@ impl AudioProcessor {
 
     /// 判断文件是否超过大小限制
     pub fn exceeds_size_limit(size_bytes: usize) -> bool {
-        const MAX_SIZE: usize = 15 * 1024 * 1024; // 15MB
-        size_bytes > MAX_SIZE
+        size_bytes > Self::MAX_INLINE_SIZE
+    }
+
+    /// 按大小与时长上限切分音频 (仅 WAV/FLAC 支持分段，其余格式超限时返回错误)
+    pub fn split_segments(mime_type: &str, audio_data: &[u8]) -> Result<Vec<AudioSegment>, String> {
+        segment::split_audio(mime_type, audio_data, Self::MAX_INLINE_SIZE, Self::MAX_SEGMENT_SECS)
     }
 }
 
@@ -0,0 +1,446 @@
+// 长音频分段
+// WAV 按 PCM 块对齐切分并为每段重写 RIFF 头；FLAC 在帧边界切分 (帧头 CRC-8、采样号连续性与帧尾 CRC-16 校验)，每段复用 STREAMINFO。
+// 其余格式无法在不解码的情况下安全切分，只能整段处理。
+
+/// 单段音频
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioSegment {
+    pub data: Vec<u8>,
+    /// 在原始音频中的起始时间 (秒)
+    pub start_secs: f64,
+    /// 时长 (秒)，无法从文件头得知时为 None
+    pub duration_secs: Option<f64>,
+}
+
+/// 将音频切分为不超过 `max_bytes` 且不长于 `max_secs` 的若干段
+///
+/// 不需要切分时返回原始数据的单段；非 WAV/FLAC 且超过大小上限时返回错误。
+pub fn split_audio(
+    mime_type: &str,
+    data: &[u8],
+    max_bytes: usize,
+    max_secs: f64,
+) -> Result<Vec<AudioSegment>, String> {
+    let parsed = match mime_type {
+        "audio/wav" => split_wav(data, max_bytes, max_secs),
+        "audio/flac" => split_flac(data, max_bytes, max_secs),
+        _ => Err(format!("{} 不支持分段处理", mime_type)),
+    };
+    match parsed {
+        Ok(segments) => Ok(segments),
+        Err(_) if data.len() <= max_bytes => Ok(vec![AudioSegment {
+            data: data.to_vec(),
+            start_secs: 0.0,
+            duration_secs: None,
+        }]),
+        Err(e) => Err(e),
+    }
+}
+
+fn read_u16_le(data: &[u8], pos: usize) -> Option<u16> {
+    data.get(pos..pos + 2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn read_u32_le(data: &[u8], pos: usize) -> Option<u32> {
+    data.get(pos..pos + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+// ===== WAV =====
+
+fn split_wav(data: &[u8], max_bytes: usize, max_secs: f64) -> Result<Vec<AudioSegment>, String> {
+    if data.len() < 12 || &data[0..4] != b"RIFF" || &data[8..12] != b"WAVE" {
+        return Err("无效的 WAV 文件头".to_string());
+    }
+
+    let mut fmt: Option<&[u8]> = None;
+    let mut pcm: Option<&[u8]> = None;
+    let mut pos = 12;
+    while pos + 8 <= data.len() {
+        let id = &data[pos..pos + 4];
+        let declared = read_u32_le(data, pos + 4).unwrap_or(0) as usize;
+        let body_start = pos + 8;
+        let remaining = data.len() - body_start;
+        let len = declared.min(remaining);
+        match id {
+            b"fmt " => fmt = Some(&data[body_start..body_start + len]),
+            b"data" => {
+                // 流式写出的 WAV 常见 data 块长度为 0 或 0xFFFFFFFF，按剩余字节处理
+                let len = if declared == 0 { remaining } else { len };
+                pcm = Some(&data[body_start..body_start + len]);
+                break;
+            }
+            _ => {}
+        }
+        pos = body_start + len + (len & 1);
+    }
+
+    let fmt = fmt.ok_or("WAV 缺少 fmt 块")?;
+    let pcm = pcm.ok_or("WAV 缺少 data 块")?;
+    let byte_rate = read_u32_le(fmt, 8).unwrap_or(0) as usize;
+    let block_align = read_u16_le(fmt, 12).unwrap_or(0).max(1) as usize;
+    if byte_rate == 0 {
+        return Err("WAV byte rate 无效".to_string());
+    }
+
+    let header_len = 12 + 8 + fmt.len() + (fmt.len() & 1) + 8;
+    let by_size = max_bytes.saturating_sub(header_len);
+    let by_time = (max_secs * byte_rate as f64) as usize;
+    let chunk = (by_size.min(by_time) / block_align) * block_align;
+    if chunk == 0 {
+        return Err("分段上限过小".to_string());
+    }
+
+    Ok(pcm
+        .chunks(chunk)
+        .enumerate()
+        .map(|(i, samples)| AudioSegment {
+            data: wav_with_header(fmt, samples),
+            start_secs: (i * chunk) as f64 / byte_rate as f64,
+            duration_secs: Some(samples.len() as f64 / byte_rate as f64),
+        })
+        .collect())
+}
+
+fn wav_with_header(fmt: &[u8], samples: &[u8]) -> Vec<u8> {
+    let fmt_padded = fmt.len() + (fmt.len() & 1);
+    let riff_len = 4 + 8 + fmt_padded + 8 + samples.len();
+    let mut out = Vec::with_capacity(8 + riff_len);
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&(riff_len as u32).to_le_bytes());
+    out.extend_from_slice(b"WAVE");
+    out.extend_from_slice(b"fmt ");
+    out.extend_from_slice(&(fmt.len() as u32).to_le_bytes());
+    out.extend_from_slice(fmt);
+    if fmt.len() & 1 == 1 {
+        out.push(0);
+    }
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&(samples.len() as u32).to_le_bytes());
+    out.extend_from_slice(samples);
+    out
+}
+
+// ===== FLAC =====
+
+struct StreamInfo {
+    /// STREAMINFO 块内容 (34 字节)
+    raw: [u8; 34],
+    max_block_size: u64,
+    sample_rate: u64,
+    total_samples: u64,
+}
+
+fn crc8(bytes: &[u8]) -> u8 {
+    let mut crc: u8 = 0;
+    for &b in bytes {
+        crc ^= b;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 { (crc << 1) ^ 0x07 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+/// 帧尾 CRC-16 (多项式 0x8005，覆盖帧头至帧尾 CRC 之前的全部字节)
+fn crc16(bytes: &[u8]) -> u16 {
+    let mut crc: u16 = 0;
+    for &b in bytes {
+        crc ^= (b as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x8005 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+/// `data[start..end]` 是否恰好是一个完整的帧 (以正确的 CRC-16 结尾)
+fn is_complete_frame(data: &[u8], start: usize, end: usize) -> bool {
+    end >= start + 8
+        && crc16(&data[start..end - 2]) == u16::from_be_bytes([data[end - 2], data[end - 1]])
+}
+
+/// 合法帧头中的位置信息
+#[derive(Debug, Clone, Copy)]
+struct FrameHeader {
+    /// 帧起始采样
+    sample: u64,
+    /// 帧内采样数
+    block_size: u64,
+}
+
+/// 解析 pos 处的帧头，合法时返回该帧的起始采样与块大小
+fn parse_frame_header(data: &[u8], pos: usize, info: &StreamInfo) -> Option<FrameHeader> {
+    let h = data.get(pos..)?;
+    if h.len() < 6 || h[0] != 0xFF || (h[1] & 0xFE) != 0xF8 {
+        return None;
+    }
+    let variable_block = h[1] & 0x01 == 1;
+    let block_code = h[2] >> 4;
+    let rate_code = h[2] & 0x0F;
+    let channels = h[3] >> 4;
+    let size_code = (h[3] >> 1) & 0x07;
+    if block_code == 0 || rate_code == 0x0F || channels > 10 || size_code == 3 || h[3] & 0x01 != 0 {
+        return None;
+    }
+
+    // UTF-8 风格编码的帧号/采样号
+    let first = h[4];
+    let extra = match first.leading_ones() {
+        0 => 0,
+        n @ 2..=7 => n as usize - 1,
+        _ => return None,
+    };
+    let mut number = if extra == 0 { first as u64 } else { (first & (0x7F >> (extra + 1))) as u64 };
+    let mut idx = 5;
+    for _ in 0..extra {
+        let b = *h.get(idx)?;
+        if b & 0xC0 != 0x80 {
+            return None;
+        }
+        number = (number << 6) | (b & 0x3F) as u64;
+        idx += 1;
+    }
+
+    let block_size = match block_code {
+        1 => 192,
+        2..=5 => 576 << (block_code - 2),
+        6 => *h.get(idx)? as u64 + 1,
+        7 => u16::from_be_bytes([*h.get(idx)?, *h.get(idx + 1)?]) as u64 + 1,
+        _ => 256 << (block_code - 8),
+    };
+    idx += match block_code {
+        6 => 1,
+        7 => 2,
+        _ => 0,
+    };
+    idx += match rate_code {
+        12 => 1,
+        13 | 14 => 2,
+        _ => 0,
+    };
+    let crc = *h.get(idx)?;
+    if crc8(&h[..idx]) != crc {
+        return None;
+    }
+
+    let sample = if variable_block { number } else { number * info.max_block_size };
+    Some(FrameHeader { sample, block_size })
+}
+
+fn split_flac(data: &[u8], max_bytes: usize, max_secs: f64) -> Result<Vec<AudioSegment>, String> {
+    if data.len() < 8 || &data[0..4] != b"fLaC" {
+        return Err("无效的 FLAC 文件头".to_string());
+    }
+
+    let mut info: Option<StreamInfo> = None;
+    let mut pos = 4;
+    loop {
+        let header = data.get(pos..pos + 4).ok_or("FLAC 元数据不完整")?;
+        let is_last = header[0] & 0x80 != 0;
+        let block_type = header[0] & 0x7F;
+        let len = ((header[1] as usize) << 16) | ((header[2] as usize) << 8) | header[3] as usize;
+        let body = data.get(pos + 4..pos + 4 + len).ok_or("FLAC 元数据不完整")?;
+        if block_type == 0 && len == 34 {
+            let mut raw = [0u8; 34];
+            raw.copy_from_slice(body);
+            let packed = u64::from_be_bytes([raw[10], raw[11], raw[12], raw[13], raw[14], raw[15], raw[16], raw[17]]);
+            info = Some(StreamInfo {
+                raw,
+                max_block_size: u16::from_be_bytes([raw[2], raw[3]]) as u64,
+                sample_rate: packed >> 44,
+                total_samples: packed & 0x0F_FFFF_FFFF,
+            });
+        }
+        pos += 4 + len;
+        if is_last {
+            break;
+        }
+    }
+    let info = info.ok_or("FLAC 缺少 STREAMINFO")?;
+    if info.sample_rate == 0 {
+        return Err("FLAC 采样率无效".to_string());
+    }
+
+    // 每段重写 STREAMINFO：总采样数未知、MD5 置零 (分段后原值不再成立)
+    let mut segment_info = info.raw;
+    segment_info[13] &= 0xF0;
+    segment_info[14..34].fill(0);
+    let mut header = Vec::with_capacity(42);
+    header.extend_from_slice(b"fLaC");
+    header.extend_from_slice(&[0x80, 0, 0, 34]);
+    header.extend_from_slice(&segment_info);
+
+    let audio_start = pos;
+    let first = parse_frame_header(data, audio_start, &info).ok_or("FLAC 首帧无效")?;
+    let budget = max_bytes.saturating_sub(header.len());
+    if budget == 0 {
+        return Err("分段上限过小".to_string());
+    }
+
+    // 找出所有切分点 (帧起始偏移, 起始采样)
+    // 帧数据中可能出现通过 CRC-8 的伪同步码：只接受采样号紧接上一帧、且上一帧以正确 CRC-16 结尾的帧头
+    let max_samples = (max_secs * info.sample_rate as f64) as u64;
+    let mut cuts = vec![(audio_start, first.sample)];
+    let mut scan = audio_start + 1;
+    let (mut seg_start, mut seg_sample) = (audio_start, first.sample);
+    let mut last_frame = (audio_start, first);
+    while scan < data.len() {
+        if data[scan] == 0xFF {
+            let expected = last_frame.1.sample + last_frame.1.block_size;
+            if let Some(frame) = parse_frame_header(data, scan, &info)
+                .filter(|f| f.sample == expected && is_complete_frame(data, last_frame.0, scan))
+            {
+                // 加上当前帧会超限时切在上一帧开头
+                if (scan - seg_start > budget || frame.sample - seg_sample > max_samples) && last_frame.0 > seg_start {
+                    cuts.push((last_frame.0, last_frame.1.sample));
+                    (seg_start, seg_sample) = (last_frame.0, last_frame.1.sample);
+                }
+                last_frame = (scan, frame);
+            }
+        }
+        scan += 1;
+    }
+    if data.len() - seg_start > budget && last_frame.0 > seg_start {
+        cuts.push((last_frame.0, last_frame.1.sample));
+    }
+
+    let total_secs = (info.total_samples > 0).then(|| info.total_samples as f64 / info.sample_rate as f64);
+    let mut segments = Vec::with_capacity(cuts.len());
+    for (i, &(offset, sample)) in cuts.iter().enumerate() {
+        let end = cuts.get(i + 1).map(|c| c.0).unwrap_or(data.len());
+        let start_secs = sample as f64 / info.sample_rate as f64;
+        let end_secs = cuts
+            .get(i + 1)
+            .map(|c| c.1 as f64 / info.sample_rate as f64)
+            .or(total_secs);
+        let mut segment = header.clone();
+        segment.extend_from_slice(&data[offset..end]);
+        if segment.len() > max_bytes {
+            return Err(format!(
+                "FLAC 分段 {} 大小 {} 字节超过上限 {} 字节 (单帧过大或无法定位帧边界)",
+                i,
+                segment.len(),
+                max_bytes
+            ));
+        }
+        segments.push(AudioSegment {
+            data: segment,
+            start_secs,
+            duration_secs: end_secs.map(|e| (e - start_secs).max(0.0)),
+        });
+    }
+    Ok(segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wav(seconds: usize, byte_rate: u32) -> Vec<u8> {
+        let mut fmt = Vec::new();
+        fmt.extend_from_slice(&1u16.to_le_bytes()); // PCM
+        fmt.extend_from_slice(&1u16.to_le_bytes()); // mono
+        fmt.extend_from_slice(&(byte_rate / 2).to_le_bytes());
+        fmt.extend_from_slice(&byte_rate.to_le_bytes());
+        fmt.extend_from_slice(&2u16.to_le_bytes()); // block align
+        fmt.extend_from_slice(&16u16.to_le_bytes());
+        let samples: Vec<u8> = (0..seconds * byte_rate as usize).map(|i| (i % 251) as u8).collect();
+        wav_with_header(&fmt, &samples)
+    }
+
+    #[test]
+    fn test_split_wav_by_duration_and_size() {
+        let data = wav(25, 1000);
+        let segments = split_audio("audio/wav", &data, 1 << 20, 10.0).unwrap();
+        assert_eq!(segments.len(), 3);
+        assert_eq!(segments[1].start_secs, 10.0);
+        assert_eq!(segments[2].duration_secs, Some(5.0));
+        // 每段都是完整的 WAV，拼接后还原原始 PCM
+        let pcm: Vec<u8> = segments.iter().flat_map(|s| s.data[44..].to_vec()).collect();
+        assert_eq!(pcm, data[44..].to_vec());
+        assert_eq!(&segments[0].data[0..4], b"RIFF");
+
+        // 按大小切分时保持块对齐
+        let by_size = split_audio("audio/wav", &data, 44 + 4001, 600.0).unwrap();
+        assert!(by_size.iter().all(|s| s.data.len() <= 44 + 4001 && (s.data.len() - 44) % 2 == 0));
+    }
+
+    #[test]
+    fn test_split_non_segmentable_formats() {
+        let mp3 = vec![0u8; 100];
+        let single = split_audio("audio/mp3", &mp3, 1000, 600.0).unwrap();
+        assert_eq!(single.len(), 1);
+        assert_eq!(single[0].duration_secs, None);
+        assert!(split_audio("audio/mp3", &mp3, 50, 600.0).is_err());
+    }
+
+    fn flac_frame(frame_number: u8, payload_len: usize) -> Vec<u8> {
+        // 固定块大小 4096 (block code 12)，采样率 44.1k (code 9)，单声道 16bit
+        flac_frame_with_payload(frame_number, (0..payload_len).map(|i| (i % 200) as u8 + 1).collect())
+    }
+
+    fn flac_frame_with_payload(frame_number: u8, payload: Vec<u8>) -> Vec<u8> {
+        let mut frame = vec![0xFF, 0xF8, 0xC9, 0x08, frame_number];
+        frame.push(crc8(&frame));
+        frame.extend(payload);
+        let crc = crc16(&frame);
+        frame.extend_from_slice(&crc.to_be_bytes());
+        frame
+    }
+
+    fn flac_stream(frames: impl IntoIterator<Item = Vec<u8>>) -> Vec<u8> {
+        let mut info = [0u8; 34];
+        info[0..2].copy_from_slice(&4096u16.to_be_bytes());
+        info[2..4].copy_from_slice(&4096u16.to_be_bytes());
+        let total_samples: u64 = 10 * 4096;
+        let packed: u64 = (44100u64 << 44) | (1 << 41) | (15 << 36) | total_samples;
+        info[10..18].copy_from_slice(&packed.to_be_bytes());
+
+        let mut data = b"fLaC".to_vec();
+        data.extend_from_slice(&[0x80, 0, 0, 34]);
+        data.extend_from_slice(&info);
+        for frame in frames {
+            data.extend(frame);
+        }
+        data
+    }
+
+    #[test]
+    fn test_split_flac_on_frame_boundaries() {
+        let data = flac_stream((0..10).map(|n| flac_frame(n, 1000)));
+
+        let segments = split_audio("audio/flac", &data, 42 + 3100, 600.0).unwrap();
+        assert_eq!(segments.len(), 4);
+        assert!(segments.iter().all(|s| s.data.len() <= 42 + 3100 && &s.data[0..4] == b"fLaC"));
+        assert_eq!(segments[1].start_secs, 3.0 * 4096.0 / 44100.0);
+        // 最后一段时长由 STREAMINFO 总采样数推出
+        let last = segments.last().unwrap();
+        assert!((last.start_secs + last.duration_secs.unwrap() - 10.0 * 4096.0 / 44100.0).abs() < 1e-9);
+        // 帧数据完整保留
+        let frames: usize = segments.iter().map(|s| s.data.len() - 42).sum();
+        assert_eq!(frames, 10 * 1008);
+    }
+
+    #[test]
+    fn test_split_flac_ignores_false_sync_in_frame_data() {
+        // 帧 1 的数据中嵌入一个 CRC-8 合法、采样号很大的伪帧头
+        let mut fake = vec![0xFF, 0xF8, 0xC9, 0x08, 0x7F];
+        fake.push(crc8(&fake));
+        let mut payload: Vec<u8> = (0..500).map(|i| (i % 200) as u8 + 1).collect();
+        payload.extend(&fake);
+        payload.extend((0..494).map(|i| (i % 200) as u8 + 1));
+
+        let frames = (0..10).map(|n| if n == 1 { flac_frame_with_payload(n, payload.clone()) } else { flac_frame(n, 1000) });
+        let data = flac_stream(frames);
+        let segments = split_audio("audio/flac", &data, 42 + 3100, 600.0).unwrap();
+        assert_eq!(segments.len(), 4);
+        assert!(segments.iter().all(|s| s.data.len() <= 42 + 3100));
+        assert_eq!(segments[1].start_secs, 3.0 * 4096.0 / 44100.0);
+    }
+
+    #[test]
+    fn test_split_flac_rejects_oversized_frame() {
+        let data = flac_stream((0..4).map(|n| flac_frame(n, if n == 2 { 4000 } else { 1000 })));
+        assert!(split_audio("audio/flac", &data, 42 + 3100, 600.0).is_err());
+    }
+}
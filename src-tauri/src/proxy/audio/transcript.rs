@@ -0,0 +1,388 @@
+// 转录结果的拼接与输出格式 (OpenAI Whisper API 兼容: json / text / srt / vtt / verbose_json)
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// 转录任务类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioTask {
+    /// /v1/audio/transcriptions
+    Transcribe,
+    /// /v1/audio/translations (翻译为英文)
+    Translate,
+}
+
+impl AudioTask {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AudioTask::Transcribe => "transcribe",
+            AudioTask::Translate => "translate",
+        }
+    }
+}
+
+/// response_format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TranscriptFormat {
+    Json,
+    Text,
+    Srt,
+    Vtt,
+    VerboseJson,
+}
+
+impl TranscriptFormat {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.trim() {
+            "" | "json" => Ok(Self::Json),
+            "text" => Ok(Self::Text),
+            "srt" => Ok(Self::Srt),
+            "vtt" => Ok(Self::Vtt),
+            "verbose_json" => Ok(Self::VerboseJson),
+            other => Err(format!(
+                "Unsupported response_format '{}'. Expected one of: json, text, srt, vtt, verbose_json",
+                other
+            )),
+        }
+    }
+
+    /// 是否需要分句时间戳
+    pub fn needs_timestamps(&self) -> bool {
+        matches!(self, Self::Srt | Self::Vtt | Self::VerboseJson)
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Json | Self::VerboseJson => "application/json",
+            Self::Text | Self::Srt => "text/plain; charset=utf-8",
+            Self::Vtt => "text/vtt; charset=utf-8",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TranscriptSegment {
+    pub id: usize,
+    /// 所在音频分段的起始时间 (秒)
+    pub seek: f64,
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TranscriptWord {
+    pub word: String,
+    pub start: f64,
+    pub end: f64,
+}
+
+/// 多个音频分段拼接后的完整转录结果
+#[derive(Debug, Clone, Default)]
+pub struct Transcript {
+    pub language: Option<String>,
+    texts: Vec<String>,
+    pub segments: Vec<TranscriptSegment>,
+    pub words: Vec<TranscriptWord>,
+    /// 已处理音频的结束时间 (秒)
+    pub duration: f64,
+}
+
+/// 请求模型输出分句时间戳时使用的 responseSchema
+pub fn timestamp_schema(with_words: bool) -> Value {
+    let mut segment = json!({
+        "type": "object",
+        "properties": {
+            "start": { "type": "number", "description": "Start time in seconds from the beginning of this audio clip" },
+            "end": { "type": "number", "description": "End time in seconds from the beginning of this audio clip" },
+            "text": { "type": "string" }
+        },
+        "required": ["start", "end", "text"]
+    });
+    if with_words {
+        segment["properties"]["words"] = json!({
+            "type": "array",
+            "items": {
+                "type": "object",
+                "properties": {
+                    "word": { "type": "string" },
+                    "start": { "type": "number" },
+                    "end": { "type": "number" }
+                },
+                "required": ["word", "start", "end"]
+            }
+        });
+    }
+    json!({
+        "type": "object",
+        "properties": {
+            "language": { "type": "string", "description": "Spoken language in lowercase English, e.g. \"english\"" },
+            "segments": { "type": "array", "items": segment }
+        },
+        "required": ["segments"]
+    })
+}
+
+/// 时间值：秒数，或 "MM:SS(.mmm)" / "HH:MM:SS(.mmm)" 字符串
+fn parse_time(value: Option<&Value>) -> Option<f64> {
+    match value? {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => {
+            let mut total = 0.0;
+            for part in s.trim().replace(',', ".").split(':') {
+                total = total * 60.0 + part.trim().parse::<f64>().ok()?;
+            }
+            Some(total)
+        }
+        _ => None,
+    }
+}
+
+fn strip_code_fence(text: &str) -> &str {
+    let trimmed = text.trim();
+    trimmed
+        .strip_prefix("```json")
+        .or_else(|| trimmed.strip_prefix("```"))
+        .and_then(|s| s.strip_suffix("```"))
+        .map(str::trim)
+        .unwrap_or(trimmed)
+}
+
+impl Transcript {
+    /// 追加一段音频的模型输出
+    ///
+    /// `offset` 为该段在原始音频中的起始时间；`clip_duration` 已知时用于修正越界的时间戳。
+    /// 需要时间戳但输出无法解析为 JSON 时，整段文本作为一个分句。
+    pub fn append(&mut self, output: &str, with_timestamps: bool, offset: f64, clip_duration: Option<f64>) {
+        let clip_end = clip_duration.map(|d| offset + d);
+        let clamp = |t: f64| {
+            let t = offset + t.max(0.0);
+            clip_end.map(|end| t.min(end)).unwrap_or(t)
+        };
+
+        let parsed: Option<Value> = if with_timestamps {
+            serde_json::from_str(strip_code_fence(output)).ok()
+        } else {
+            None
+        };
+
+        match parsed {
+            Some(value) => {
+                if self.language.is_none() {
+                    self.language = value
+                        .get("language")
+                        .and_then(|l| l.as_str())
+                        .filter(|l| !l.trim().is_empty())
+                        .map(|l| l.trim().to_lowercase());
+                }
+                let mut clip_texts = Vec::new();
+                for seg in value.get("segments").and_then(|s| s.as_array()).into_iter().flatten() {
+                    let text = seg.get("text").and_then(|t| t.as_str()).unwrap_or("").trim().to_string();
+                    if text.is_empty() {
+                        continue;
+                    }
+                    let start = clamp(parse_time(seg.get("start")).unwrap_or(0.0));
+                    let end = clamp(parse_time(seg.get("end")).unwrap_or(0.0)).max(start);
+                    for word in seg.get("words").and_then(|w| w.as_array()).into_iter().flatten() {
+                        if let Some(w) = word.get("word").and_then(|w| w.as_str()).filter(|w| !w.trim().is_empty()) {
+                            let w_start = clamp(parse_time(word.get("start")).unwrap_or(0.0));
+                            self.words.push(TranscriptWord {
+                                word: w.trim().to_string(),
+                                start: w_start,
+                                end: clamp(parse_time(word.get("end")).unwrap_or(0.0)).max(w_start),
+                            });
+                        }
+                    }
+                    self.segments.push(TranscriptSegment {
+                        id: self.segments.len(),
+                        seek: offset,
+                        start,
+                        end,
+                        text: text.clone(),
+                    });
+                    self.duration = self.duration.max(end);
+                    clip_texts.push(text);
+                }
+                if !clip_texts.is_empty() {
+                    self.texts.push(clip_texts.join(" "));
+                }
+            }
+            None => {
+                let text = output.trim();
+                if !text.is_empty() {
+                    self.texts.push(text.to_string());
+                    if with_timestamps {
+                        let start = offset;
+                        self.segments.push(TranscriptSegment {
+                            id: self.segments.len(),
+                            seek: offset,
+                            start,
+                            end: clip_end.unwrap_or(start),
+                            text: text.to_string(),
+                        });
+                    }
+                }
+            }
+        }
+
+        if let Some(end) = clip_end {
+            self.duration = self.duration.max(end);
+        }
+    }
+
+    /// 拼接后的全文
+    pub fn text(&self) -> String {
+        self.texts.join(" ")
+    }
+
+    /// 最近的转录文本 (作为下一段音频的上下文，保持衔接)
+    pub fn tail(&self, max_chars: usize) -> Option<String> {
+        let text = self.texts.last()?;
+        let count = text.chars().count();
+        Some(text.chars().skip(count.saturating_sub(max_chars)).collect())
+    }
+
+    /// 按 response_format 输出响应体
+    pub fn render(&self, format: TranscriptFormat, task: AudioTask, include_words: bool) -> String {
+        match format {
+            TranscriptFormat::Json => json!({ "text": self.text() }).to_string(),
+            TranscriptFormat::Text => self.text(),
+            TranscriptFormat::Srt => self
+                .segments
+                .iter()
+                .enumerate()
+                .map(|(i, s)| {
+                    format!(
+                        "{}\n{} --> {}\n{}\n",
+                        i + 1,
+                        format_timestamp(s.start, ','),
+                        format_timestamp(s.end, ','),
+                        s.text
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+            TranscriptFormat::Vtt => {
+                let mut out = String::from("WEBVTT\n");
+                for s in &self.segments {
+                    out.push_str(&format!(
+                        "\n{} --> {}\n{}\n",
+                        format_timestamp(s.start, '.'),
+                        format_timestamp(s.end, '.'),
+                        s.text
+                    ));
+                }
+                out
+            }
+            TranscriptFormat::VerboseJson => {
+                let language = match task {
+                    AudioTask::Translate => Some("english".to_string()),
+                    AudioTask::Transcribe => self.language.clone(),
+                };
+                let mut body = json!({
+                    "task": task.as_str(),
+                    "language": language,
+                    "duration": round_ms(self.duration),
+                    "text": self.text(),
+                    "segments": self.segments.iter().map(|s| json!({
+                        "id": s.id,
+                        "seek": round_ms(s.seek),
+                        "start": round_ms(s.start),
+                        "end": round_ms(s.end),
+                        "text": s.text,
+                    })).collect::<Vec<_>>(),
+                });
+                if include_words {
+                    body["words"] = json!(self
+                        .words
+                        .iter()
+                        .map(|w| json!({ "word": w.word, "start": round_ms(w.start), "end": round_ms(w.end) }))
+                        .collect::<Vec<_>>());
+                }
+                body.to_string()
+            }
+        }
+    }
+}
+
+fn round_ms(secs: f64) -> f64 {
+    (secs * 1000.0).round() / 1000.0
+}
+
+/// HH:MM:SS,mmm (SRT) / HH:MM:SS.mmm (VTT)
+fn format_timestamp(secs: f64, separator: char) -> String {
+    let total_ms = (secs.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        total_ms / 3_600_000,
+        (total_ms / 60_000) % 60,
+        (total_ms / 1000) % 60,
+        separator,
+        total_ms % 1000
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stitch_segments_with_offsets() {
+        let mut transcript = Transcript::default();
+        transcript.append(
+            r#"{"language":"English","segments":[{"start":0,"end":2.5,"text":"Hello there."},{"start":"00:03.1","end":9.0,"text":" General Kenobi. "}]}"#,
+            true,
+            0.0,
+            Some(8.0),
+        );
+        transcript.append(
+            "```json\n{\"segments\":[{\"start\":1,\"end\":2,\"text\":\"Next part.\",\"words\":[{\"word\":\"Next\",\"start\":1,\"end\":1.4}]}]}\n```",
+            true,
+            8.0,
+            Some(8.0),
+        );
+
+        assert_eq!(transcript.language.as_deref(), Some("english"));
+        assert_eq!(transcript.text(), "Hello there. General Kenobi. Next part.");
+        let times: Vec<(f64, f64)> = transcript.segments.iter().map(|s| (s.start, s.end)).collect();
+        // 越界的结束时间被限制在所在分段内，第二段整体偏移 8 秒
+        assert_eq!(times, vec![(0.0, 2.5), (3.1, 8.0), (9.0, 10.0)]);
+        assert_eq!(transcript.words[0].start, 9.0);
+        assert_eq!(transcript.duration, 16.0);
+
+        let srt = transcript.render(TranscriptFormat::Srt, AudioTask::Transcribe, false);
+        assert!(srt.starts_with("1\n00:00:00,000 --> 00:00:02,500\nHello there.\n\n2\n"));
+        let vtt = transcript.render(TranscriptFormat::Vtt, AudioTask::Transcribe, false);
+        assert!(vtt.starts_with("WEBVTT\n\n00:00:00.000 --> 00:00:02.500\nHello there.\n"));
+        assert!(vtt.contains("00:00:09.000 --> 00:00:10.000\nNext part."));
+
+        let verbose: Value = serde_json::from_str(&transcript.render(
+            TranscriptFormat::VerboseJson,
+            AudioTask::Transcribe,
+            true,
+        ))
+        .unwrap();
+        assert_eq!(verbose["task"], "transcribe");
+        assert_eq!(verbose["segments"][2]["seek"], 8.0);
+        assert_eq!(verbose["words"][0]["word"], "Next");
+    }
+
+    #[test]
+    fn test_plain_text_and_fallback() {
+        let mut transcript = Transcript::default();
+        transcript.append("  first half ", false, 0.0, Some(600.0));
+        transcript.append("second half", false, 600.0, None);
+        assert_eq!(transcript.render(TranscriptFormat::Text, AudioTask::Transcribe, false), "first half second half");
+        assert!(transcript.segments.is_empty());
+        assert_eq!(transcript.tail(4).as_deref(), Some("half"));
+
+        // 需要时间戳但模型未返回 JSON：整段作为一个分句
+        let mut fallback = Transcript::default();
+        fallback.append("just text", true, 30.0, Some(10.0));
+        assert_eq!(fallback.segments[0].start, 30.0);
+        assert_eq!(fallback.segments[0].end, 40.0);
+
+        assert!(TranscriptFormat::parse("xml").is_err());
+        assert_eq!(TranscriptFormat::parse("").unwrap(), TranscriptFormat::Json);
+    }
+}
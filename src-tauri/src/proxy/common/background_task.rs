@@ -0,0 +1,372 @@
+// 后台任务识别 - 按可配置规则把客户端的标题生成、摘要、环境探测等后台请求降级到轻量模型
+
+use axum::http::{header, HeaderMap, HeaderValue};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::{OnceLock, RwLock};
+
+use crate::proxy::config::BackgroundTaskRule;
+use crate::proxy::mappers::claude::models::{ClaudeRequest, ContentBlock, MessageContent};
+
+/// 所有后台任务默认使用的虚拟模型 ID (可通过自定义模型映射改写)
+pub const INTERNAL_BACKGROUND_TASK: &str = "internal-background-task";
+
+/// 未匹配到任何客户端适配器时使用的客户端名
+pub const DEFAULT_CLIENT: &str = "default";
+
+/// 关键词与正则只匹配消息开头的这部分字符
+const PREVIEW_CHARS: usize = 500;
+
+/// 内置规则 (与历史硬编码行为一致，按顺序匹配)
+pub fn default_rules() -> Vec<BackgroundTaskRule> {
+    let rule = |name: &str, keywords: &[&str]| BackgroundTaskRule {
+        name: name.to_string(),
+        enabled: true,
+        keywords: keywords.iter().map(|k| k.to_string()).collect(),
+        regex: None,
+        max_length: crate::proxy::config::default_background_max_length(),
+        clients: Vec::new(),
+        target_model: INTERNAL_BACKGROUND_TASK.to_string(),
+    };
+    vec![
+        rule("system_message", &["Warmup", "<system-reminder>", "This is a system message"]),
+        rule(
+            "title_generation",
+            &[
+                "write a 5-10 word title",
+                "Please write a 5-10 word title",
+                "Respond with the title",
+                "Generate a title for",
+                "Create a brief title",
+                "title for the conversation",
+                "conversation title",
+                "生成标题",
+                "为对话起个标题",
+            ],
+        ),
+        rule("simple_summary", &["in under 50 characters"]),
+        rule(
+            "context_compression",
+            &[
+                "Summarize this coding conversation",
+                "Summarize the conversation",
+                "Concise summary",
+                "compress the context",
+                "Provide a concise summary",
+                "condense the previous messages",
+                "shorten the conversation history",
+                "extract key points from",
+            ],
+        ),
+        rule(
+            "prompt_suggestion",
+            &[
+                "prompt suggestion generator",
+                "suggest next prompts",
+                "what should I ask next",
+                "generate follow-up questions",
+                "recommend next steps",
+                "possible next actions",
+            ],
+        ),
+        rule(
+            "environment_probe",
+            &["check current directory", "list available tools", "verify environment", "test connection"],
+        ),
+    ]
+}
+
+struct CompiledRule {
+    rule: BackgroundTaskRule,
+    /// None 表示未配置正则；Some(Err) 表示正则无效 (规则不会命中)
+    regex: Option<Result<Regex, String>>,
+}
+
+fn compile(rules: &[BackgroundTaskRule]) -> Vec<CompiledRule> {
+    rules
+        .iter()
+        .map(|rule| CompiledRule {
+            rule: rule.clone(),
+            regex: rule
+                .regex
+                .as_deref()
+                .filter(|p| !p.is_empty())
+                .map(|p| Regex::new(p).map_err(|e| e.to_string())),
+        })
+        .collect()
+}
+
+static GLOBAL_BACKGROUND_RULES: OnceLock<RwLock<Vec<CompiledRule>>> = OnceLock::new();
+
+fn global_rules() -> &'static RwLock<Vec<CompiledRule>> {
+    GLOBAL_BACKGROUND_RULES.get_or_init(|| RwLock::new(compile(&default_rules())))
+}
+
+/// 更新全局后台任务规则
+pub fn update_background_task_rules(rules: Vec<BackgroundTaskRule>) {
+    let compiled = compile(&rules);
+    for c in &compiled {
+        if let Some(Err(e)) = &c.regex {
+            tracing::warn!("[Background-Task] Rule '{}' has invalid regex and will never match: {}", c.rule.name, e);
+        }
+    }
+    if let Ok(mut current) = global_rules().write() {
+        *current = compiled;
+    }
+    tracing::debug!("[Background-Task] {} classifier rule(s) configured", rules.len());
+}
+
+/// 单条规则的判定结果
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleOutcome {
+    Matched,
+    Disabled,
+    ClientMismatch,
+    TooLong,
+    InvalidRegex,
+    NoMatch,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleEvaluation {
+    pub rule: String,
+    pub outcome: RuleOutcome,
+    /// 命中的关键词或正则
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matched: Option<String>,
+}
+
+/// 命中的规则
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BackgroundTaskMatch {
+    pub rule: String,
+    pub target_model: String,
+}
+
+fn evaluate_rule(c: &CompiledRule, message: &str, preview: &str, client: &str) -> RuleEvaluation {
+    let rule = &c.rule;
+    let result = |outcome, matched| RuleEvaluation { rule: rule.name.clone(), outcome, matched };
+
+    if !rule.enabled {
+        return result(RuleOutcome::Disabled, None);
+    }
+    if !rule.clients.is_empty() && !rule.clients.iter().any(|c| c.eq_ignore_ascii_case(client)) {
+        return result(RuleOutcome::ClientMismatch, None);
+    }
+    if message.chars().count() > rule.max_length {
+        return result(RuleOutcome::TooLong, None);
+    }
+    if let Some(kw) = rule.keywords.iter().find(|kw| !kw.is_empty() && preview.contains(kw.as_str())) {
+        return result(RuleOutcome::Matched, Some(kw.clone()));
+    }
+    match &c.regex {
+        Some(Ok(re)) => match re.find(preview) {
+            Some(m) => result(RuleOutcome::Matched, Some(m.as_str().to_string())),
+            None => result(RuleOutcome::NoMatch, None),
+        },
+        Some(Err(_)) => result(RuleOutcome::InvalidRegex, None),
+        None => result(RuleOutcome::NoMatch, None),
+    }
+}
+
+fn evaluate_all(rules: &[CompiledRule], message: &str, client: &str) -> Vec<RuleEvaluation> {
+    let preview: String = message.chars().take(PREVIEW_CHARS).collect();
+    rules
+        .iter()
+        .map(|c| evaluate_rule(c, message, &preview, client))
+        .collect()
+}
+
+/// 用当前生效的规则识别后台任务 (按顺序取第一条命中的规则)
+pub fn classify(message: &str, client: &str) -> Option<BackgroundTaskMatch> {
+    let rules = global_rules().read().ok()?;
+    let preview: String = message.chars().take(PREVIEW_CHARS).collect();
+    rules.iter().find_map(|c| {
+        (evaluate_rule(c, message, &preview, client).outcome == RuleOutcome::Matched).then(|| {
+            BackgroundTaskMatch { rule: c.rule.name.clone(), target_model: c.rule.target_model.clone() }
+        })
+    })
+}
+
+/// 规则测试报告
+#[derive(Debug, Clone, Serialize)]
+pub struct ClassificationReport {
+    pub client: String,
+    /// 参与检测的最后一条用户消息 (None 表示没有可检测的消息)
+    pub message: Option<String>,
+    pub matched: Option<BackgroundTaskMatch>,
+    pub evaluations: Vec<RuleEvaluation>,
+}
+
+/// 当前生效的规则
+pub fn current_rules() -> Vec<BackgroundTaskRule> {
+    global_rules()
+        .read()
+        .map(|r| r.iter().map(|c| c.rule.clone()).collect())
+        .unwrap_or_default()
+}
+
+/// 对示例请求逐条评估规则；`rules` 为 None 时使用当前生效的规则
+pub fn explain(request: &ClaudeRequest, client: &str, rules: Option<Vec<BackgroundTaskRule>>) -> ClassificationReport {
+    let rules = rules.unwrap_or_else(current_rules);
+    let message = last_user_message(request);
+    let evaluations = message
+        .as_deref()
+        .map(|msg| evaluate_all(&compile(&rules), msg, client))
+        .unwrap_or_default();
+    let matched = evaluations
+        .iter()
+        .position(|e| e.outcome == RuleOutcome::Matched)
+        .map(|i| BackgroundTaskMatch {
+            rule: rules[i].name.clone(),
+            target_model: rules[i].target_model.clone(),
+        });
+    ClassificationReport { client: client.to_string(), message, matched, evaluations }
+}
+
+/// 规则测试请求
+#[derive(Debug, Clone, Deserialize)]
+pub struct RuleTestRequest {
+    /// Anthropic Messages 请求体
+    pub request: serde_json::Value,
+    /// 模拟的 User-Agent (用于匹配客户端适配器)
+    #[serde(default)]
+    pub user_agent: Option<String>,
+    /// 待测试的规则草稿；为空时使用当前生效的规则
+    #[serde(default)]
+    pub rules: Option<Vec<BackgroundTaskRule>>,
+}
+
+/// 测试示例请求会命中哪条规则
+pub fn test_rules(req: RuleTestRequest) -> Result<ClassificationReport, String> {
+    let request: ClaudeRequest =
+        serde_json::from_value(req.request).map_err(|e| format!("invalid request: {}", e))?;
+    let mut headers = HeaderMap::new();
+    if let Some(ua) = req.user_agent.as_deref().and_then(|ua| HeaderValue::from_str(ua).ok()) {
+        headers.insert(header::USER_AGENT, ua);
+    }
+    let client = crate::proxy::common::client_adapter::CLIENT_ADAPTERS
+        .iter()
+        .find(|a| a.matches(&headers))
+        .map(|a| a.name())
+        .unwrap_or(DEFAULT_CLIENT);
+    Ok(explain(&request, client, req.rules))
+}
+
+/// 提取最后一条可检测的用户消息 (跳过空消息、Warmup 与 system-reminder)
+pub fn last_user_message(request: &ClaudeRequest) -> Option<String> {
+    request.messages.iter().rev()
+        .filter(|m| m.role == "user")
+        .find_map(|m| {
+            let content = match &m.content {
+                MessageContent::String(s) => s.to_string(),
+                MessageContent::Array(arr) => arr
+                    .iter()
+                    .filter_map(|block| match block {
+                        ContentBlock::Text { text } => Some(text.as_str()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            };
+
+            if content.trim().is_empty()
+                || content.starts_with("Warmup")
+                || content.contains("<system-reminder>")
+            {
+                None
+            } else {
+                Some(content)
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(name: &str, keywords: &[&str], regex: Option<&str>) -> BackgroundTaskRule {
+        BackgroundTaskRule {
+            name: name.to_string(),
+            enabled: true,
+            keywords: keywords.iter().map(|k| k.to_string()).collect(),
+            regex: regex.map(|r| r.to_string()),
+            max_length: 800,
+            clients: Vec::new(),
+            target_model: "gemini-2.5-flash-lite".to_string(),
+        }
+    }
+
+    fn first_match(rules: &[BackgroundTaskRule], message: &str, client: &str) -> Option<String> {
+        evaluate_all(&compile(rules), message, client)
+            .into_iter()
+            .find(|e| e.outcome == RuleOutcome::Matched)
+            .map(|e| e.rule)
+    }
+
+    #[test]
+    fn test_default_rules_preserve_builtin_order() {
+        let rules = default_rules();
+        let title = "Please write a 5-10 word title for the conversation";
+        assert_eq!(first_match(&rules, title, DEFAULT_CLIENT).as_deref(), Some("title_generation"));
+        let short = "Summarize the conversation in under 50 characters";
+        assert_eq!(first_match(&rules, short, DEFAULT_CLIENT).as_deref(), Some("simple_summary"));
+        let long = format!("Summarize the conversation {}", "x".repeat(900));
+        assert_eq!(first_match(&rules, &long, DEFAULT_CLIENT), None);
+        assert_eq!(first_match(&rules, "Fix the failing test in main.rs", DEFAULT_CLIENT), None);
+    }
+
+    #[test]
+    fn test_regex_client_scope_and_limits() {
+        let mut scoped = rule("opencode_title", &[], Some(r"(?i)generate a short title"));
+        scoped.clients = vec!["opencode".to_string()];
+        let mut short_only = rule("tiny", &["ping"], None);
+        short_only.max_length = 10;
+        let mut disabled = rule("off", &["Generate"], None);
+        disabled.enabled = false;
+        let bad = rule("bad", &[], Some("("));
+        let rules = vec![disabled, bad, scoped, short_only];
+
+        let evals = evaluate_all(&compile(&rules), "GENERATE A SHORT TITLE please", "opencode");
+        let outcomes: Vec<_> = evals.iter().map(|e| e.outcome).collect();
+        assert_eq!(
+            outcomes,
+            vec![RuleOutcome::Disabled, RuleOutcome::InvalidRegex, RuleOutcome::Matched, RuleOutcome::TooLong]
+        );
+        assert_eq!(evals[2].matched.as_deref(), Some("GENERATE A SHORT TITLE"));
+
+        assert_eq!(first_match(&rules, "generate a short title", DEFAULT_CLIENT), None);
+        assert_eq!(first_match(&rules, "ping", DEFAULT_CLIENT).as_deref(), Some("tiny"));
+    }
+
+    #[test]
+    fn test_rules_report_uses_client_adapter() {
+        let mut scoped = rule("opencode_title", &["Generate a title for"], None);
+        scoped.clients = vec!["opencode".to_string()];
+        let req = |ua: &str| RuleTestRequest {
+            request: serde_json::json!({
+                "model": "claude-sonnet-4-5",
+                "messages": [{ "role": "user", "content": "Generate a title for this chat" }]
+            }),
+            user_agent: Some(ua.to_string()),
+            rules: Some(vec![scoped.clone()]),
+        };
+
+        let report = test_rules(req("opencode/1.2.0")).unwrap();
+        assert_eq!(report.client, "opencode");
+        assert_eq!(report.message.as_deref(), Some("Generate a title for this chat"));
+        assert_eq!(
+            report.matched,
+            Some(BackgroundTaskMatch {
+                rule: "opencode_title".to_string(),
+                target_model: "gemini-2.5-flash-lite".to_string()
+            })
+        );
+
+        let report = test_rules(req("claude-cli/2.0")).unwrap();
+        assert_eq!(report.client, DEFAULT_CLIENT);
+        assert!(report.matched.is_none());
+        assert_eq!(report.evaluations[0].outcome, RuleOutcome::ClientMismatch);
+    }
+}
@@ -21,6 +21,11 @@ pub trait ClientAdapter: Send + Sync {
     /// # Returns
     /// 如果匹配返回 true，否则返回 false
     fn matches(&self, headers: &HeaderMap) -> bool;
+
+    /// 适配器名称 (用于按客户端限定后台任务规则等配置)
+    fn name(&self) -> &'static str {
+        "unknown"
+    }
     
     /// 是否绕过签名校验
     /// 
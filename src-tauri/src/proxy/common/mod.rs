@@ -3,11 +3,15 @@
 // pub mod error;
 // pub mod rate_limiter;
 pub mod model_mapping;
+pub mod model_fallback;
+pub mod background_task;
 pub mod utils;
 pub mod json_schema;
 pub mod tool_adapter;
 pub mod tool_adapters;
 pub mod schema_cache;
+pub mod structured_output;
+pub mod token_count;
 pub mod client_adapter;
 pub mod client_adapters;
 pub mod session; // [ADDED v4.1.24] Tools for deriving stable session identifiers
@@ -0,0 +1,227 @@
+// 模型降级链 - 某模型的全部账号均被限流/配额保护时，按配置顺序切换到备用模型
+
+use axum::http::HeaderValue;
+use axum::response::Response;
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+
+use crate::proxy::middleware::auth::UserTokenIdentity;
+use crate::proxy::token_manager::TokenManager;
+
+/// 发生降级时附加的响应头，值为原始映射模型 (降级后的模型见 X-Mapped-Model)
+pub const FALLBACK_FROM_HEADER: &str = "X-Model-Fallback-From";
+
+static GLOBAL_MODEL_FALLBACKS: OnceLock<RwLock<HashMap<String, Vec<String>>>> = OnceLock::new();
+
+/// 更新全局模型降级链配置
+pub fn update_model_fallbacks(fallbacks: HashMap<String, Vec<String>>) {
+    let count = fallbacks.len();
+    if let Some(lock) = GLOBAL_MODEL_FALLBACKS.get() {
+        if let Ok(mut cfg) = lock.write() {
+            *cfg = fallbacks;
+        }
+    } else {
+        let _ = GLOBAL_MODEL_FALLBACKS.set(RwLock::new(fallbacks));
+    }
+    tracing::debug!("[Model-Fallback] {} fallback chain(s) configured", count);
+}
+
+/// 查找降级链：优先匹配客户端请求的模型名，其次匹配映射后的模型名
+fn fallback_chain(client_model: &str, mapped_model: &str) -> Vec<String> {
+    GLOBAL_MODEL_FALLBACKS
+        .get()
+        .and_then(|lock| lock.read().ok())
+        .and_then(|cfg| cfg.get(client_model).or_else(|| cfg.get(mapped_model)).cloned())
+        .unwrap_or_default()
+}
+
+/// 一次降级替换
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelFallback {
+    pub from: String,
+    pub to: String,
+}
+
+/// 按顺序选出第一个可用的候选模型 (跳过与原模型相同的条目)
+async fn first_available<F, Fut>(mapped_model: &str, candidates: &[String], is_available: F) -> Option<String>
+where
+    F: Fn(String) -> Fut,
+    Fut: std::future::Future<Output = bool>,
+{
+    for candidate in candidates {
+        if candidate == mapped_model {
+            continue;
+        }
+        if is_available(candidate.clone()).await {
+            return Some(candidate.clone());
+        }
+    }
+    None
+}
+
+/// 过滤掉调用方令牌无权访问的候选模型，返回映射后的模型名
+///
+/// candidates 为 (降级链条目, 映射后的模型)。ACL 只在原模型上检查过，
+/// 每个降级目标需要按自身名称重新判断，不能借用原模型名通过白名单
+fn permitted_candidates(identity: Option<&UserTokenIdentity>, candidates: Vec<(String, String)>) -> Vec<String> {
+    candidates
+        .into_iter()
+        .filter(|(entry, mapped)| identity.map_or(true, |id| id.is_model_permitted(entry, mapped)))
+        .map(|(_, mapped)| mapped)
+        .collect()
+}
+
+/// 原模型没有可用账号时，返回降级链中第一个有可用账号且调用方有权访问的模型；无需降级或链路全部耗尽时返回 None
+pub async fn select_fallback(
+    token_manager: &TokenManager,
+    custom_mapping: &HashMap<String, String>,
+    identity: Option<&UserTokenIdentity>,
+    client_model: &str,
+    mapped_model: &str,
+) -> Option<ModelFallback> {
+    let chain = fallback_chain(client_model, mapped_model);
+    let account_groups = UserTokenIdentity::account_groups_of(identity);
+    if chain.is_empty() || token_manager.has_available_account("", mapped_model, &account_groups).await {
+        return None;
+    }
+
+    // 候选项同样经过模型映射，允许在降级链中使用别名
+    let candidates: Vec<(String, String)> = chain
+        .into_iter()
+        .map(|m| {
+            let mapped = super::model_mapping::resolve_model_route(&m, custom_mapping);
+            (m, mapped)
+        })
+        .collect();
+    let candidates = permitted_candidates(identity, candidates);
+
+    let groups = account_groups.as_slice();
+    let target = first_available(mapped_model, &candidates, |model| async move {
+        token_manager.has_available_account("", &model, groups).await
+    })
+    .await;
+
+    match target {
+        Some(to) => {
+            tracing::warn!(
+                "[Model-Fallback] All accounts exhausted for {}, degrading to {}",
+                mapped_model,
+                to
+            );
+            Some(ModelFallback {
+                from: mapped_model.to_string(),
+                to,
+            })
+        }
+        None => {
+            tracing::warn!(
+                "[Model-Fallback] All accounts exhausted for {} and every fallback model",
+                mapped_model
+            );
+            None
+        }
+    }
+}
+
+/// 在响应上标记降级来源
+pub fn annotate_response(mut response: Response, fallback: Option<&ModelFallback>) -> Response {
+    if let Some(fb) = fallback {
+        if let Ok(v) = HeaderValue::from_str(&fb.from) {
+            response.headers_mut().insert(FALLBACK_FROM_HEADER, v);
+        }
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::response::IntoResponse;
+
+    #[tokio::test]
+    async fn test_first_available_respects_order() {
+        let chain = vec![
+            "claude-opus-4-6-thinking".to_string(),
+            "claude-sonnet-4-6-thinking".to_string(),
+            "gemini-3-pro-high".to_string(),
+        ];
+        let available = ["gemini-3-pro-high", "claude-opus-4-6-thinking"];
+
+        // 跳过原模型自身，选出第一个可用项
+        let picked = first_available("claude-opus-4-6-thinking", &chain, |m| async move {
+            available.contains(&m.as_str())
+        })
+        .await;
+        assert_eq!(picked.as_deref(), Some("gemini-3-pro-high"));
+
+        let none = first_available("claude-opus-4-6-thinking", &chain, |_| async { false }).await;
+        assert!(none.is_none());
+    }
+
+    fn unmapped(models: &[&str]) -> Vec<(String, String)> {
+        models.iter().map(|m| (m.to_string(), m.to_string())).collect()
+    }
+
+    #[tokio::test]
+    async fn test_denied_fallback_is_skipped() {
+        let chain = unmapped(&["gemini-3-pro-high", "gemini-3-flash"]);
+        let identity = UserTokenIdentity {
+            token_id: "t1".to_string(),
+            token: "sk-user".to_string(),
+            username: "alice".to_string(),
+            allowed_models: Vec::new(),
+            denied_models: vec!["gemini-3-pro-high".to_string()],
+            account_groups: Vec::new(),
+        };
+
+        // 第一个可用的候选被令牌拒绝，应选出下一个
+        let candidates = permitted_candidates(Some(&identity), chain.clone());
+        let picked = first_available("claude-opus-4-6-thinking", &candidates, |_| async { true }).await;
+        assert_eq!(picked.as_deref(), Some("gemini-3-flash"));
+
+        // 无用户令牌时不过滤
+        assert_eq!(
+            permitted_candidates(None, chain),
+            vec!["gemini-3-pro-high".to_string(), "gemini-3-flash".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_fallback_outside_allow_list_is_skipped() {
+        let identity = UserTokenIdentity {
+            token_id: "t1".to_string(),
+            token: "sk-user".to_string(),
+            username: "alice".to_string(),
+            allowed_models: vec!["claude-opus-*".to_string()],
+            denied_models: Vec::new(),
+            account_groups: Vec::new(),
+        };
+
+        // 原模型在白名单内不代表降级目标也在白名单内
+        let chain = unmapped(&["gemini-3-pro-high", "claude-opus-4-5"]);
+        assert_eq!(
+            permitted_candidates(Some(&identity), chain),
+            vec!["claude-opus-4-5".to_string()]
+        );
+
+        // 降级链条目是白名单内的别名时，按条目名放行
+        let aliased = vec![("claude-opus-latest".to_string(), "gemini-3-pro-high".to_string())];
+        assert_eq!(
+            permitted_candidates(Some(&identity), aliased),
+            vec!["gemini-3-pro-high".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_annotate_response() {
+        let fb = ModelFallback {
+            from: "claude-opus-4-6-thinking".to_string(),
+            to: "gemini-3-pro-high".to_string(),
+        };
+        let resp = annotate_response("ok".into_response(), Some(&fb));
+        assert_eq!(resp.headers()[FALLBACK_FROM_HEADER], "claude-opus-4-6-thinking");
+
+        let resp = annotate_response("ok".into_response(), None);
+        assert!(resp.headers().get(FALLBACK_FROM_HEADER).is_none());
+    }
+}
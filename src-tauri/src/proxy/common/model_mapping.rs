@@ -81,6 +81,11 @@ static CLAUDE_TO_GEMINI: Lazy<HashMap<&'static str, &'static str>> = Lazy::new(|
     m.insert("gemini-3.1-flash-image", "gemini-3.1-flash-image");
     m.insert("gemini-3-pro-image", "gemini-3-pro-image");
 
+    // OpenAI Embeddings 协议映射表
+    m.insert("text-embedding-3-small", "gemini-embedding-001");
+    m.insert("text-embedding-3-large", "gemini-embedding-001");
+    m.insert("text-embedding-ada-002", "gemini-embedding-001");
+
     // [New] Unified Virtual ID for Background Tasks (Title, Summary, etc.)
     // Allows users to override all background tasks via custom_mapping
     m.insert("internal-background-task", "gemini-2.5-flash");
@@ -166,6 +171,11 @@ pub async fn get_all_dynamic_models(
         }
     }
 
+    // 4. 通用上游提供商显式声明的模型
+    for provider_model in crate::proxy::providers::compatible::listed_models() {
+        model_ids.insert(provider_model);
+    }
+
     // 5. 确保包含常用的 Gemini/画画模型 ID
     model_ids.insert("gemini-3.1-pro-low".to_string());
     
@@ -207,7 +217,7 @@ pub async fn get_all_dynamic_models(
 /// - `claude-*-sonnet-*` matches `claude-3-5-sonnet-20241022` ✓
 /// - `*-thinking` matches `claude-opus-4-5-thinking` ✓
 /// - `a*b*c` matches `a123b456c` ✓
-fn wildcard_match(pattern: &str, text: &str) -> bool {
+pub(crate) fn wildcard_match(pattern: &str, text: &str) -> bool {
     let parts: Vec<&str> = pattern.split('*').collect();
 
     // No wildcard - exact match
@@ -257,6 +267,11 @@ pub fn resolve_model_route(
     original_model: &str,
     custom_mapping: &std::collections::HashMap<String, String>,
 ) -> String {
+    // 请求重放可指定映射模型 (仅作用于重放任务)
+    if let Some(model) = crate::proxy::replay::mapped_model_override() {
+        return model;
+    }
+
     // 0. API 热更新废弃模型转发 (最高物理优先级，强制纠正)
     // 如果用户非要用已经被移除的模型，并且官方下发了 fallback path，我们在此拦截并纠正
     if let Some(forwarded) = DYNAMIC_MODEL_FORWARDING_RULES.get(original_model) {
@@ -336,6 +351,29 @@ pub fn normalize_to_standard_id(model_name: &str) -> Option<String> {
     None
 }
 
+/// 检查模型是否在令牌的允许范围内 (通配符语义同 `wildcard_match`)
+///
+/// - 原始模型或路由后的模型命中任一 denied 规则即拒绝 (denied 优先)
+/// - allowed 为空表示不限制；否则原始模型或路由后的模型须命中其中一条
+pub fn is_model_permitted(
+    allowed: &[String],
+    denied: &[String],
+    original_model: &str,
+    mapped_model: &str,
+) -> bool {
+    let matches_any = |patterns: &[String]| {
+        patterns.iter().any(|p| {
+            let p = p.trim();
+            !p.is_empty() && (wildcard_match(p, original_model) || wildcard_match(p, mapped_model))
+        })
+    };
+
+    if matches_any(denied) {
+        return false;
+    }
+    allowed.iter().all(|p| p.trim().is_empty()) || matches_any(allowed)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -490,4 +528,30 @@ mod tests {
         // Multi-wildcard: "a*b*c" (3)
         assert_eq!(resolve_model_route("a-test-b-foo-c", &custom), "multi-wild");
     }
+
+    #[test]
+    fn test_model_permission() {
+        let allow = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        // 未配置规则时全部放行
+        assert!(is_model_permitted(&[], &[], "claude-opus-4-5", "claude-opus-4-5-thinking"));
+
+        // denied 命中原始或路由后的模型都会拒绝
+        let denied = allow(&["claude-opus-*"]);
+        assert!(!is_model_permitted(&[], &denied, "claude-opus-4-5", "claude-opus-4-5-thinking"));
+        assert!(!is_model_permitted(&[], &denied, "gpt-4", "claude-opus-4-5-thinking"));
+        assert!(is_model_permitted(&[], &denied, "claude-sonnet-4-5", "claude-sonnet-4-5"));
+
+        // allowed 非空时必须命中
+        let allowed = allow(&["gemini-*-flash*", "gpt-4o"]);
+        assert!(is_model_permitted(&allowed, &[], "gemini-2.5-flash", "gemini-2.5-flash"));
+        assert!(is_model_permitted(&allowed, &[], "gpt-4o", "gemini-2.5-flash"));
+        assert!(!is_model_permitted(&allowed, &[], "gemini-3-pro-image", "gemini-3-pro-image"));
+
+        // denied 优先于 allowed
+        assert!(!is_model_permitted(&allow(&["*"]), &allow(&["*-image*"]), "gemini-3-pro-image", "gemini-3-pro-image"));
+
+        // 空白规则被忽略
+        assert!(is_model_permitted(&allow(&[" "]), &allow(&[""]), "any-model", "any-model"));
+    }
 }
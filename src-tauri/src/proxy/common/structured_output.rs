@@ -0,0 +1,316 @@
+// 结构化输出 (OpenAI response_format.json_schema / Claude output_config.format)
+// 将 JSON Schema 映射为 Gemini responseSchema，并可选地在返回前校验输出是否符合原始 Schema
+
+use serde_json::{json, Value};
+use std::sync::{OnceLock, RwLock};
+
+use crate::proxy::config::StructuredOutputConfig;
+
+/// 校验失败时最多列出的问题数
+const MAX_REPORTED_VIOLATIONS: usize = 5;
+const MAX_VALIDATION_DEPTH: usize = 32;
+
+static STRUCTURED_OUTPUT_CONFIG: OnceLock<RwLock<StructuredOutputConfig>> = OnceLock::new();
+
+/// 更新全局结构化输出配置
+pub fn update_structured_output_config(config: StructuredOutputConfig) {
+    if let Some(lock) = STRUCTURED_OUTPUT_CONFIG.get() {
+        if let Ok(mut cfg) = lock.write() {
+            *cfg = config;
+        }
+    } else {
+        let _ = STRUCTURED_OUTPUT_CONFIG.set(RwLock::new(config));
+    }
+}
+
+fn current_config() -> StructuredOutputConfig {
+    STRUCTURED_OUTPUT_CONFIG
+        .get()
+        .and_then(|lock| lock.read().ok().map(|cfg| cfg.clone()))
+        .unwrap_or_default()
+}
+
+/// 校验重试次数；未开启校验时返回 None
+pub fn validation_retries() -> Option<u32> {
+    let config = current_config();
+    config.validate.then_some(config.max_retries)
+}
+
+/// 设置 generationConfig 的 JSON 输出模式与 responseSchema (Schema 经 clean_json_schema 清洗)
+pub fn apply_response_schema(gen_config: &mut Value, schema: &Value) {
+    let mut cleaned = schema.clone();
+    crate::proxy::common::json_schema::clean_json_schema(&mut cleaned);
+    gen_config["responseMimeType"] = json!("application/json");
+    gen_config["responseSchema"] = cleaned;
+}
+
+/// 检查模型输出文本是否为符合 Schema 的 JSON，失败时返回可直接展示给调用方的错误说明
+pub fn check_output(schema: &Value, text: &str) -> Result<(), String> {
+    let trimmed = text.trim();
+    // 个别模型仍会包裹 Markdown 代码块
+    let trimmed = trimmed
+        .strip_prefix("```json")
+        .or_else(|| trimmed.strip_prefix("```"))
+        .and_then(|s| s.strip_suffix("```"))
+        .map(str::trim)
+        .unwrap_or(trimmed);
+
+    let value: Value = serde_json::from_str(trimmed)
+        .map_err(|e| format!("Model output is not valid JSON: {}", e))?;
+    let violations = validate(schema, &value);
+    if violations.is_empty() {
+        return Ok(());
+    }
+    let shown: Vec<&str> = violations
+        .iter()
+        .take(MAX_REPORTED_VIOLATIONS)
+        .map(String::as_str)
+        .collect();
+    let more = violations.len().saturating_sub(MAX_REPORTED_VIOLATIONS);
+    Err(format!(
+        "Model output does not match the requested JSON schema: {}{}",
+        shown.join("; "),
+        if more > 0 { format!(" (and {} more)", more) } else { String::new() }
+    ))
+}
+
+/// 按 JSON Schema 校验值，返回所有不符合项 (支持常用关键字与本地 $ref)
+pub fn validate(schema: &Value, value: &Value) -> Vec<String> {
+    let mut violations = Vec::new();
+    validate_at(schema, schema, value, "$", 0, &mut violations);
+    violations
+}
+
+fn resolve_ref<'a>(root: &'a Value, reference: &str) -> Option<&'a Value> {
+    let pointer = reference.strip_prefix('#')?;
+    root.pointer(pointer)
+}
+
+fn type_matches(expected: &str, value: &Value) -> bool {
+    match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        "number" => value.is_number(),
+        "integer" => value.as_i64().is_some() || value.as_u64().is_some()
+            || value.as_f64().map(|f| f.fract() == 0.0).unwrap_or(false),
+        _ => true,
+    }
+}
+
+fn validate_at(
+    root: &Value,
+    schema: &Value,
+    value: &Value,
+    path: &str,
+    depth: usize,
+    out: &mut Vec<String>,
+) {
+    let Some(obj) = schema.as_object() else {
+        return;
+    };
+    if depth > MAX_VALIDATION_DEPTH {
+        return;
+    }
+
+    if let Some(target) = obj.get("$ref").and_then(|r| r.as_str()) {
+        if let Some(resolved) = resolve_ref(root, target) {
+            validate_at(root, resolved, value, path, depth + 1, out);
+        }
+        return;
+    }
+
+    // type (字符串或数组)
+    if let Some(ty) = obj.get("type") {
+        let types: Vec<&str> = match ty {
+            Value::String(s) => vec![s.as_str()],
+            Value::Array(arr) => arr.iter().filter_map(|t| t.as_str()).collect(),
+            _ => Vec::new(),
+        };
+        if !types.is_empty() && !types.iter().any(|t| type_matches(t, value)) {
+            out.push(format!("{}: expected {}", path, types.join(" or ")));
+            return;
+        }
+    }
+
+    if let Some(options) = obj.get("enum").and_then(|e| e.as_array()) {
+        if !options.contains(value) {
+            out.push(format!("{}: value is not one of the allowed enum values", path));
+        }
+    }
+    if let Some(expected) = obj.get("const") {
+        if expected != value {
+            out.push(format!("{}: value does not equal the required constant", path));
+        }
+    }
+
+    for key in ["anyOf", "oneOf"] {
+        if let Some(branches) = obj.get(key).and_then(|b| b.as_array()) {
+            // 分支在同一根 Schema 下求值并继承深度，自引用的分支也不会无限递归
+            let matched = branches
+                .iter()
+                .filter(|branch| {
+                    let mut scratch = Vec::new();
+                    validate_at(root, branch, value, path, depth + 1, &mut scratch);
+                    scratch.is_empty()
+                })
+                .count();
+            let ok = if key == "oneOf" { matched == 1 } else { matched > 0 };
+            if !ok {
+                out.push(format!("{}: value does not match {}", path, key));
+            }
+        }
+    }
+    if let Some(branches) = obj.get("allOf").and_then(|b| b.as_array()) {
+        for branch in branches {
+            validate_at(root, branch, value, path, depth + 1, out);
+        }
+    }
+
+    match value {
+        Value::Object(map) => {
+            let properties = obj.get("properties").and_then(|p| p.as_object());
+            if let Some(required) = obj.get("required").and_then(|r| r.as_array()) {
+                for name in required.iter().filter_map(|r| r.as_str()) {
+                    if !map.contains_key(name) {
+                        out.push(format!("{}: missing required property '{}'", path, name));
+                    }
+                }
+            }
+            for (name, child) in map {
+                let child_path = format!("{}.{}", path, name);
+                match properties.and_then(|p| p.get(name)) {
+                    Some(child_schema) => validate_at(root, child_schema, child, &child_path, depth + 1, out),
+                    None => match obj.get("additionalProperties") {
+                        Some(Value::Bool(false)) => {
+                            out.push(format!("{}: unexpected property", child_path));
+                        }
+                        Some(extra @ Value::Object(_)) => {
+                            validate_at(root, extra, child, &child_path, depth + 1, out)
+                        }
+                        _ => {}
+                    },
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(min) = obj.get("minItems").and_then(|m| m.as_u64()) {
+                if (items.len() as u64) < min {
+                    out.push(format!("{}: expected at least {} items", path, min));
+                }
+            }
+            if let Some(max) = obj.get("maxItems").and_then(|m| m.as_u64()) {
+                if (items.len() as u64) > max {
+                    out.push(format!("{}: expected at most {} items", path, max));
+                }
+            }
+            if let Some(item_schema) = obj.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    validate_at(root, item_schema, item, &format!("{}[{}]", path, i), depth + 1, out);
+                }
+            }
+        }
+        Value::String(s) => {
+            let len = s.chars().count() as u64;
+            if let Some(min) = obj.get("minLength").and_then(|m| m.as_u64()) {
+                if len < min {
+                    out.push(format!("{}: shorter than {} characters", path, min));
+                }
+            }
+            if let Some(max) = obj.get("maxLength").and_then(|m| m.as_u64()) {
+                if len > max {
+                    out.push(format!("{}: longer than {} characters", path, max));
+                }
+            }
+        }
+        Value::Number(n) => {
+            let n = n.as_f64().unwrap_or(0.0);
+            if let Some(min) = obj.get("minimum").and_then(|m| m.as_f64()) {
+                if n < min {
+                    out.push(format!("{}: less than minimum {}", path, min));
+                }
+            }
+            if let Some(max) = obj.get("maximum").and_then(|m| m.as_f64()) {
+                if n > max {
+                    out.push(format!("{}: greater than maximum {}", path, max));
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn person_schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "name": {"type": "string", "minLength": 1},
+                "age": {"type": "integer", "minimum": 0},
+                "role": {"enum": ["admin", "user"]},
+                "tags": {"type": "array", "items": {"$ref": "#/$defs/tag"}, "maxItems": 2},
+                "nickname": {"anyOf": [{"type": "string"}, {"type": "null"}]}
+            },
+            "required": ["name", "age"],
+            "additionalProperties": false,
+            "$defs": {"tag": {"type": "string"}}
+        })
+    }
+
+    #[test]
+    fn test_validate_accepts_conforming_output() {
+        let schema = person_schema();
+        assert!(check_output(&schema, r#"{"name":"Ada","age":36,"role":"admin","tags":["x"],"nickname":null}"#).is_ok());
+        assert!(check_output(&schema, "```json\n{\"name\":\"Ada\",\"age\":36}\n```").is_ok());
+    }
+
+    #[test]
+    fn test_validate_reports_violations() {
+        let schema = person_schema();
+        let violations = validate(
+            &schema,
+            &json!({"name": "", "age": 1.5, "role": "root", "tags": [1, "a", "b"], "extra": true}),
+        );
+        assert!(violations.contains(&"$.name: shorter than 1 characters".to_string()));
+        assert!(violations.contains(&"$.age: expected integer".to_string()));
+        assert!(violations.iter().any(|v| v.starts_with("$.role")));
+        assert!(violations.contains(&"$.tags: expected at most 2 items".to_string()));
+        assert!(violations.contains(&"$.tags[0]: expected string".to_string()));
+        assert!(violations.contains(&"$.extra: unexpected property".to_string()));
+
+        let err = check_output(&schema, r#"{"age": 3}"#).unwrap_err();
+        assert!(err.contains("missing required property 'name'"));
+        assert!(check_output(&schema, "not json").unwrap_err().contains("not valid JSON"));
+    }
+
+    #[test]
+    fn test_validate_recursive_branches_terminate() {
+        // 自引用的 anyOf 分支受深度上限约束，不会栈溢出
+        let schema = json!({"$defs": {"a": {"anyOf": [{"$ref": "#/$defs/a"}]}}, "$ref": "#/$defs/a"});
+        let _ = validate(&schema, &json!({"x": 1}));
+
+        // 分支中的 #/... 引用按真实根解析
+        let schema = json!({
+            "$defs": {"id": {"type": "integer"}},
+            "properties": {"v": {"oneOf": [{"$ref": "#/$defs/id"}, {"type": "null"}]}}
+        });
+        assert!(validate(&schema, &json!({"v": 3})).is_empty());
+        assert_eq!(validate(&schema, &json!({"v": "x"})), vec!["$.v: value does not match oneOf".to_string()]);
+    }
+
+    #[test]
+    fn test_apply_response_schema_cleans_schema() {
+        let mut gen_config = json!({"temperature": 0.2});
+        apply_response_schema(&mut gen_config, &person_schema());
+        assert_eq!(gen_config["responseMimeType"], "application/json");
+        let cleaned = &gen_config["responseSchema"];
+        assert!(cleaned.get("additionalProperties").is_none());
+        assert!(cleaned.get("$defs").is_none());
+        assert_eq!(cleaned["properties"]["tags"]["items"]["type"], "string");
+    }
+}
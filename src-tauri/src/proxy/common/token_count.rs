@@ -0,0 +1,236 @@
+// Token 计数 (countTokens / count_tokens)
+// 优先经账号池调用上游 v1internal:countTokens；不可用时回退到本地估算 (经 EstimationCalibrator 校准)
+// 计数接口按估算器各自维护校准器，不写入对话请求使用的全局校准器
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::sync::OnceLock;
+
+use crate::proxy::mappers::context_manager::estimate_tokens_from_str;
+use crate::proxy::mappers::estimation_calibrator::EstimationCalibrator;
+use crate::proxy::server::AppState;
+use crate::proxy::token_manager::ProxyToken;
+
+/// 响应头：本次计数的来源 (upstream / estimated)
+pub const COUNT_METHOD_HEADER: &str = "X-Token-Count-Method";
+
+/// 单张图片/文件在 Gemini 中的近似 token 开销
+const INLINE_DATA_TOKENS: u32 = 258;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CountMethod {
+    /// 上游 countTokens 返回的真实值
+    Upstream,
+    /// 本地估算 (已按校准系数修正)
+    Estimated,
+}
+
+impl CountMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CountMethod::Upstream => "upstream",
+            CountMethod::Estimated => "estimated",
+        }
+    }
+}
+
+/// 计数接口使用的本地估算器，每种估算器的误差独立校准
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CountEstimator {
+    /// ContextManager 对 Claude 原始请求的估算
+    ContextManager,
+    /// estimate_gemini_request 对 Gemini 请求的估算
+    GeminiRequest,
+}
+
+impl CountEstimator {
+    fn calibrator(self) -> &'static EstimationCalibrator {
+        static CONTEXT_MANAGER: OnceLock<EstimationCalibrator> = OnceLock::new();
+        static GEMINI_REQUEST: OnceLock<EstimationCalibrator> = OnceLock::new();
+        match self {
+            CountEstimator::ContextManager => CONTEXT_MANAGER.get_or_init(EstimationCalibrator::new),
+            CountEstimator::GeminiRequest => GEMINI_REQUEST.get_or_init(EstimationCalibrator::new),
+        }
+    }
+}
+
+/// 构建 v1internal countTokens 请求体
+///
+/// countTokens 只接受 contents，因此系统提示词与工具声明以文本形式并入开头的 user 消息，
+/// 使计数覆盖模型实际看到的全部输入。
+pub fn build_count_request(model: &str, request: &Value) -> Value {
+    let mut preamble: Vec<Value> = Vec::new();
+    if let Some(parts) = request
+        .get("systemInstruction")
+        .and_then(|s| s.get("parts"))
+        .and_then(|p| p.as_array())
+    {
+        preamble.extend(parts.iter().filter(|p| p.get("text").is_some()).cloned());
+    }
+    if let Some(tools) = request.get("tools").filter(|t| !t.is_null()) {
+        preamble.push(json!({ "text": tools.to_string() }));
+    }
+
+    let mut contents: Vec<Value> = Vec::new();
+    if !preamble.is_empty() {
+        contents.push(json!({ "role": "user", "parts": preamble }));
+    }
+    if let Some(arr) = request.get("contents").and_then(|c| c.as_array()) {
+        contents.extend(arr.iter().cloned());
+    }
+
+    json!({
+        "request": {
+            "model": format!("models/{}", model),
+            "contents": contents,
+        }
+    })
+}
+
+fn estimate_value(value: &Value) -> u32 {
+    match value {
+        Value::String(s) => estimate_tokens_from_str(s),
+        Value::Array(arr) => arr.iter().map(estimate_value).sum(),
+        Value::Object(map) => map
+            .iter()
+            .map(|(k, v)| match k.as_str() {
+                // 二进制内容按固定开销计，不按 base64 长度估算
+                "inlineData" | "fileData" => INLINE_DATA_TOKENS,
+                "thoughtSignature" => 0,
+                _ => estimate_value(v),
+            })
+            .sum(),
+        _ => 0,
+    }
+}
+
+/// 本地估算 Gemini 请求体 (contents / systemInstruction / tools) 的输入 token 数 (未校准)
+pub fn estimate_gemini_request(request: &Value) -> u32 {
+    let mut total = 0;
+    if let Some(contents) = request.get("contents").and_then(|c| c.as_array()) {
+        for content in contents {
+            // 与 ContextManager 一致：每条消息 4 token 开销
+            total += 4 + content.get("parts").map(estimate_value).unwrap_or(0);
+        }
+    }
+    for key in ["systemInstruction", "tools"] {
+        if let Some(v) = request.get(key) {
+            total += estimate_value(v);
+        }
+    }
+    total
+}
+
+/// 经账号池调用上游 countTokens
+///
+/// `build_request` 接收选中账号的 (project_id, account_id, token)，返回 generateContent 格式的请求体。
+pub async fn count_upstream<F>(
+    state: &AppState,
+    model: &str,
+    account_groups: &[String],
+    build_request: F,
+) -> Result<u32, String>
+where
+    F: FnOnce(&str, &str, Option<&ProxyToken>) -> Result<Value, String>,
+{
+    let (access_token, project_id, email, account_id, _wait_ms) = state
+        .token_manager
+        .get_token("gemini", false, None, model, account_groups)
+        .await?;
+    let token = state.token_manager.get_token_by_id(&account_id);
+    let request = build_request(&project_id, &account_id, token.as_ref())?;
+    let body = build_count_request(model, &request);
+
+    let result = state
+        .upstream
+        .call_v1_internal("countTokens", &access_token, body, None, Some(account_id.as_str()))
+        .await?;
+    let status = result.response.status();
+    if !status.is_success() {
+        let text = result.response.text().await.unwrap_or_default();
+        return Err(format!(
+            "countTokens returned {} ({}): {}",
+            status,
+            crate::proxy::upstream::client::mask_email(&email),
+            text.chars().take(200).collect::<String>()
+        ));
+    }
+
+    let json: Value = result
+        .response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse countTokens response: {}", e))?;
+    json.get("totalTokens")
+        .and_then(|v| v.as_u64())
+        .map(|v| v as u32)
+        .ok_or_else(|| format!("countTokens response missing totalTokens: {}", json))
+}
+
+/// 计数入口：上游成功时同时用于校准对应估算器，失败时返回校准后的估算值
+pub async fn count_tokens<F>(
+    state: &AppState,
+    model: &str,
+    account_groups: &[String],
+    estimator: CountEstimator,
+    raw_estimate: u32,
+    build_request: F,
+) -> (u32, CountMethod)
+where
+    F: FnOnce(&str, &str, Option<&ProxyToken>) -> Result<Value, String>,
+{
+    let calibrator = estimator.calibrator();
+    match count_upstream(state, model, account_groups, build_request).await {
+        Ok(actual) => {
+            calibrator.record(raw_estimate, actual);
+            (actual, CountMethod::Upstream)
+        }
+        Err(e) => {
+            tracing::warn!("[CountTokens] Upstream count failed for {}, using estimate: {}", model, e);
+            (calibrator.calibrate(raw_estimate), CountMethod::Estimated)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_count_request_folds_system_and_tools() {
+        let request = json!({
+            "systemInstruction": {"parts": [{"text": "You are terse."}]},
+            "tools": [{"functionDeclarations": [{"name": "ls"}]}],
+            "contents": [{"role": "user", "parts": [{"text": "hi"}]}],
+            "generationConfig": {"maxOutputTokens": 10}
+        });
+        let body = build_count_request("gemini-2.5-flash", &request);
+        assert_eq!(body["request"]["model"], "models/gemini-2.5-flash");
+        let contents = body["request"]["contents"].as_array().unwrap();
+        assert_eq!(contents.len(), 2);
+        assert_eq!(contents[0]["parts"][0]["text"], "You are terse.");
+        assert!(contents[0]["parts"][1]["text"].as_str().unwrap().contains("\"ls\""));
+        assert_eq!(contents[1]["parts"][0]["text"], "hi");
+        assert!(body["request"].get("generationConfig").is_none());
+
+        let plain = build_count_request("m", &json!({"contents": [{"role": "user", "parts": [{"text": "x"}]}]}));
+        assert_eq!(plain["request"]["contents"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_estimate_gemini_request() {
+        assert_eq!(estimate_gemini_request(&json!({})), 0);
+
+        let text_only = json!({"contents": [{"role": "user", "parts": [{"text": "a".repeat(400)}]}]});
+        let base = estimate_gemini_request(&text_only);
+        assert_eq!(base, 4 + estimate_tokens_from_str(&"a".repeat(400)));
+
+        // 图片按固定开销计算，不受 base64 长度影响
+        let with_image = json!({"contents": [{"role": "user", "parts": [
+            {"text": "a".repeat(400)},
+            {"inlineData": {"mimeType": "image/png", "data": "A".repeat(100_000)}}
+        ]}]});
+        assert_eq!(estimate_gemini_request(&with_image), base + INLINE_DATA_TOKENS);
+    }
+}
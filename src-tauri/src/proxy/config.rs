@@ -255,6 +255,89 @@ impl Default for ZaiConfig {
     }
 }
 
+/// 通用上游提供商协议
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderProtocol {
+    /// OpenAI Chat Completions 兼容 (vLLM / llama.cpp / Ollama 等)，base_url 通常以 /v1 结尾
+    #[default]
+    Openai,
+    /// Anthropic Messages 兼容，base_url 不含 /v1
+    Anthropic,
+}
+
+/// 通用上游提供商调度模式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderDispatchMode {
+    /// 匹配的模型全部交给该提供商
+    #[default]
+    Exclusive,
+    /// 作为账号池中的一个额外槽位参与轮询
+    Pooled,
+    /// 仅在 Google 账号池对该模型不可用时使用
+    Fallback,
+}
+
+/// 通用上游提供商 (OpenAI / Anthropic 兼容后端)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UpstreamProviderConfig {
+    /// 唯一标识 (用于日志与响应头 X-Upstream-Provider)
+    pub id: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub protocol: ProviderProtocol,
+    pub base_url: String,
+    /// 为空时不发送鉴权头 (本地服务)
+    #[serde(default)]
+    pub api_key: String,
+    /// 该提供商服务的模型 (支持 * 通配符)；为空表示匹配全部模型
+    #[serde(default)]
+    pub models: Vec<String>,
+    /// 可选的上游模型名映射 (key: 客户端模型名/通配符, value: 上游模型名)
+    #[serde(default)]
+    pub model_mapping: HashMap<String, String>,
+    #[serde(default)]
+    pub dispatch_mode: ProviderDispatchMode,
+}
+
+/// 后台任务识别规则 (命中后请求改写到 target_model，并移除工具与 thinking 配置)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BackgroundTaskRule {
+    /// 规则名 (用于日志与规则测试)
+    pub name: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// 关键词 (区分大小写的子串匹配，任一命中即可)
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    /// 正则表达式 (与关键词任一命中即可)
+    #[serde(default)]
+    pub regex: Option<String>,
+    /// 最后一条用户消息超过该字符数时不视为后台任务
+    #[serde(default = "default_background_max_length")]
+    pub max_length: usize,
+    /// 生效的客户端适配器 (如 "opencode"，"default" 表示未匹配适配器的客户端)；为空表示全部客户端
+    #[serde(default)]
+    pub clients: Vec<String>,
+    /// 改写的目标模型 (仍会经过自定义模型映射)
+    #[serde(default = "default_background_target_model")]
+    pub target_model: String,
+}
+
+pub fn default_background_max_length() -> usize {
+    800
+}
+
+fn default_background_target_model() -> String {
+    crate::proxy::common::background_task::INTERNAL_BACKGROUND_TASK.to_string()
+}
+
+fn default_background_task_rules() -> Vec<BackgroundTaskRule> {
+    crate::proxy::common::background_task::default_rules()
+}
+
 /// 实验性功能配置 (Feature Flags)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExperimentalConfig {
@@ -443,6 +526,12 @@ pub struct SecurityMonitorConfig {
     /// IP 白名单配置
     #[serde(default)]
     pub whitelist: IpWhitelistConfig,
+
+    /// 受信任的反向代理 (IP 或 CIDR, 如 "127.0.0.1", "10.0.0.0/8", "fd00::/8")
+    /// 仅当 TCP 对端地址命中该列表时才解析 X-Forwarded-For / Forwarded / X-Real-IP,
+    /// 否则一律使用 TCP 对端地址，防止伪造转发头绕过黑名单和令牌 IP 绑定
+    #[serde(default)]
+    pub trusted_proxies: Vec<String>,
 }
 
 impl Default for SecurityMonitorConfig {
@@ -450,6 +539,7 @@ impl Default for SecurityMonitorConfig {
         Self {
             blacklist: IpBlacklistConfig::default(),
             whitelist: IpWhitelistConfig::default(),
+            trusted_proxies: Vec::new(),
         }
     }
 }
@@ -490,6 +580,19 @@ pub struct ProxyConfig {
     #[serde(default)]
     pub custom_mapping: std::collections::HashMap<String, String>,
 
+    /// 模型降级链 (key: 模型名, value: 按顺序尝试的备用模型)
+    /// 该模型的全部账号被限流或配额保护时，自动切换到第一个有可用账号的备用模型
+    #[serde(default)]
+    pub model_fallbacks: std::collections::HashMap<String, Vec<String>>,
+
+    /// 通用上游提供商 (按顺序匹配)
+    #[serde(default)]
+    pub providers: Vec<UpstreamProviderConfig>,
+
+    /// 后台任务识别规则 (按顺序匹配，第一条命中的规则生效；为空表示关闭识别)
+    #[serde(default = "default_background_task_rules")]
+    pub background_task_rules: Vec<BackgroundTaskRule>,
+
     /// API 请求超时时间(秒)
     #[serde(default = "default_request_timeout")]
     pub request_timeout: u64,
@@ -555,6 +658,204 @@ pub struct ProxyConfig {
     /// 代理池配置
     #[serde(default)]
     pub proxy_pool: ProxyPoolConfig,
+
+    /// 上游 v1internal 端点 (按顺序降级)
+    /// 可通过环境变量 ABV_UPSTREAM_ENDPOINTS (逗号分隔) 覆盖，便于指向本地 Mock 服务
+    #[serde(default = "default_upstream_endpoints")]
+    pub upstream_endpoints: Vec<UpstreamEndpoint>,
+
+    /// Prometheus 指标导出配置
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+
+    /// 精确匹配响应缓存 (默认关闭)
+    #[serde(default)]
+    pub response_cache: ResponseCacheConfig,
+
+    /// 管理接口审计日志 (默认开启)
+    #[serde(default)]
+    pub audit_log: AuditLogConfig,
+
+    /// 结构化输出 (json_schema) 校验
+    #[serde(default)]
+    pub structured_outputs: StructuredOutputConfig,
+
+    /// Responses API 服务端状态 (previous_response_id / store)
+    #[serde(default)]
+    pub response_store: ResponseStoreConfig,
+
+    /// 生成图片的本地存储 (图片接口 response_format=url)
+    #[serde(default)]
+    pub image_store: ImageStoreConfig,
+}
+
+/// Prometheus 指标导出配置
+/// 启用后在反代端口提供 GET /metrics (遵循 auth_mode 鉴权)
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct MetricsConfig {
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+/// 精确匹配响应缓存配置
+/// 相同的 (映射后模型, 消息, 工具, 生成参数) 在 TTL 内直接回放上次的上游响应，不消耗配额
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ResponseCacheConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// 缓存有效期 (秒)
+    #[serde(default = "default_response_cache_ttl")]
+    pub ttl_secs: u64,
+    /// 缓存总大小上限 (MB)，超出后按最近使用时间淘汰
+    #[serde(default = "default_response_cache_max_size_mb")]
+    pub max_size_mb: u64,
+}
+
+impl Default for ResponseCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ttl_secs: default_response_cache_ttl(),
+            max_size_mb: default_response_cache_max_size_mb(),
+        }
+    }
+}
+
+fn default_response_cache_ttl() -> u64 {
+    3600
+}
+
+fn default_response_cache_max_size_mb() -> u64 {
+    64
+}
+
+/// 管理接口审计日志配置
+/// 记录管理 API 的变更操作 (调用者、来源 IP、接口、脱敏后的请求摘要、结果)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AuditLogConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// 保留天数 (0 表示永久保留)
+    #[serde(default = "default_audit_retention_days")]
+    pub retention_days: u32,
+}
+
+impl Default for AuditLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            retention_days: default_audit_retention_days(),
+        }
+    }
+}
+
+fn default_audit_retention_days() -> u32 {
+    90
+}
+
+/// 结构化输出配置
+/// 开启校验后，非流式 json_schema 请求的输出会按原始 Schema 校验，不符合时换号重试，重试用尽后返回错误
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StructuredOutputConfig {
+    #[serde(default)]
+    pub validate: bool,
+    /// 校验失败后的最大重试次数
+    #[serde(default = "default_structured_output_retries")]
+    pub max_retries: u32,
+}
+
+impl Default for StructuredOutputConfig {
+    fn default() -> Self {
+        Self {
+            validate: false,
+            max_retries: default_structured_output_retries(),
+        }
+    }
+}
+
+fn default_structured_output_retries() -> u32 {
+    1
+}
+
+/// Responses API 响应存储配置
+/// 保存 /v1/responses 的输入与输出，支持 previous_response_id 续接与 GET/DELETE /v1/responses/{id}
+/// 会在本地落盘完整对话内容，默认关闭
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ResponseStoreConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// 保存时长 (秒)，过期后无法再被续接或读取
+    #[serde(default = "default_response_store_ttl")]
+    pub ttl_secs: u64,
+}
+
+impl Default for ResponseStoreConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ttl_secs: default_response_store_ttl(),
+        }
+    }
+}
+
+fn default_response_store_ttl() -> u64 {
+    30 * 24 * 3600
+}
+
+/// 生成图片存储配置
+/// 开启后图片接口的 response_format=url 返回 /v1/files/images/{id} 短期链接 (与反代接口相同鉴权)，关闭时返回 data URI
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ImageStoreConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// 图片有效期 (秒)
+    #[serde(default = "default_image_store_ttl")]
+    pub ttl_secs: u64,
+    /// 磁盘占用上限 (MB)，超出后淘汰最旧的图片
+    #[serde(default = "default_image_store_max_size_mb")]
+    pub max_size_mb: u64,
+    /// 链接的外部访问地址 (如 https://proxy.example.com)，留空时根据请求的 Host 推断
+    #[serde(default)]
+    pub public_base_url: String,
+}
+
+impl Default for ImageStoreConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            ttl_secs: default_image_store_ttl(),
+            max_size_mb: default_image_store_max_size_mb(),
+            public_base_url: String::new(),
+        }
+    }
+}
+
+fn default_image_store_ttl() -> u64 {
+    3600
+}
+
+fn default_image_store_max_size_mb() -> u64 {
+    512
+}
+
+/// 上游 v1internal 端点
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UpstreamEndpoint {
+    /// 基础 URL (包含 /v1internal 路径)
+    pub url: String,
+    /// 是否启用
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+pub fn default_upstream_endpoints() -> Vec<UpstreamEndpoint> {
+    crate::proxy::upstream::client::DEFAULT_V1_INTERNAL_BASE_URLS
+        .iter()
+        .map(|url| UpstreamEndpoint {
+            url: url.to_string(),
+            enabled: true,
+        })
+        .collect()
 }
 
 /// 上游代理配置
@@ -577,6 +878,9 @@ impl Default for ProxyConfig {
             admin_password: None,
             auto_start: false,
             custom_mapping: std::collections::HashMap::new(),
+            model_fallbacks: std::collections::HashMap::new(),
+            providers: Vec::new(),
+            background_task_rules: default_background_task_rules(),
             request_timeout: default_request_timeout(),
             enable_logging: true, // 默认开启，支持 token 统计功能
             debug_logging: DebugLoggingConfig::default(),
@@ -592,6 +896,13 @@ impl Default for ProxyConfig {
             global_system_prompt: GlobalSystemPromptConfig::default(),
             proxy_pool: ProxyPoolConfig::default(),
             image_thinking_mode: None,
+            upstream_endpoints: default_upstream_endpoints(),
+            metrics: MetricsConfig::default(),
+            response_cache: ResponseCacheConfig::default(),
+            audit_log: AuditLogConfig::default(),
+            structured_outputs: StructuredOutputConfig::default(),
+            response_store: ResponseStoreConfig::default(),
+            image_store: ImageStoreConfig::default(),
         }
     }
 }
@@ -655,6 +966,12 @@ pub struct ProxyEntry {
     pub last_check_time: Option<i64>,     // 上次检查时间
     pub is_healthy: bool,                 // 健康状态
     pub latency: Option<u64>,             // 延迟 (毫秒) [NEW]
+    #[serde(default = "default_proxy_weight")]
+    pub weight: u32,                      // 权重 (加权策略使用, 0 = 不参与加权选择)
+}
+
+fn default_proxy_weight() -> u32 {
+    1
 }
 
 /// 代理池配置
@@ -695,10 +1012,12 @@ pub enum ProxySelectionStrategy {
     Random,
     /// 优先级: 按 priority 字段排序
     Priority,
-    /// 最少连接: 选择当前使用最少的代理
+    /// 最少连接: 选择当前在途请求最少的代理
     LeastConnections,
-    /// 加权轮询: 根据健康状态和优先级
+    /// 加权随机: 按 weight 字段比例随机选择
     WeightedRoundRobin,
+    /// 延迟感知: 综合健康检查延迟、在途请求数与权重打分
+    LatencyAware,
 }
 
 #[cfg(test)]
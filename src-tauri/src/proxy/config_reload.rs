@@ -0,0 +1,206 @@
+//! Config Reload
+//! 运行中配置快照、统一热更新入口，以及 gui_config.json 变更 / SIGHUP 触发的重载
+
+use std::sync::{OnceLock, RwLock};
+use std::time::{Duration, SystemTime};
+
+use crate::models::AppConfig;
+use crate::modules::config_validation::{self, ValidationIssue};
+use crate::proxy::server::AppState;
+
+/// 配置文件轮询间隔
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+static RUNNING_CONFIG: OnceLock<RwLock<Option<AppConfig>>> = OnceLock::new();
+
+fn running_slot() -> &'static RwLock<Option<AppConfig>> {
+    RUNNING_CONFIG.get_or_init(|| RwLock::new(None))
+}
+
+/// 当前生效的配置 (尚未记录时回退到磁盘上的配置)
+pub fn running_config() -> AppConfig {
+    if let Some(config) = running_slot().read().ok().and_then(|c| c.clone()) {
+        return config;
+    }
+    crate::modules::config::load_app_config().unwrap_or_default()
+}
+
+pub fn set_running_config(config: &AppConfig) {
+    if let Ok(mut slot) = running_slot().write() {
+        *slot = Some(config.clone());
+    }
+}
+
+/// 用于检测无效通配符映射的已知模型列表
+pub fn known_models() -> Vec<String> {
+    crate::proxy::common::model_mapping::get_supported_models()
+}
+
+/// 校验候选配置，返回全部问题 (含告警)
+pub fn validate(config: &AppConfig) -> Vec<ValidationIssue> {
+    config_validation::validate_app_config(config, &known_models())
+}
+
+/// 将配置热更新到运行中的服务 (监听地址与端口除外)
+pub async fn apply_to_state(state: &AppState, config: &AppConfig) {
+    let proxy = &config.proxy;
+
+    {
+        let mut mapping = state.custom_mapping.write().await;
+        *mapping = proxy.custom_mapping.clone();
+    }
+    {
+        let mut upstream_proxy = state.upstream_proxy.write().await;
+        *upstream_proxy = proxy.upstream_proxy.clone();
+    }
+    {
+        let mut security = state.security.write().await;
+        *security = crate::proxy::ProxySecurityConfig::from_proxy_config(proxy);
+    }
+    {
+        let mut zai = state.zai.write().await;
+        *zai = proxy.zai.clone();
+    }
+    {
+        let mut exp = state.experimental.write().await;
+        *exp = proxy.experimental.clone();
+    }
+    {
+        let mut dbg_cfg = state.debug_logging.write().await;
+        *dbg_cfg = proxy.debug_logging.clone();
+    }
+    {
+        let mut pool = state.proxy_pool_state.write().await;
+        *pool = proxy.proxy_pool.clone();
+    }
+
+    state
+        .upstream
+        .set_user_agent_override(proxy.user_agent_override.clone())
+        .await;
+    state.upstream.set_endpoints(&proxy.upstream_endpoints).await;
+    crate::proxy::metrics::set_enabled(proxy.metrics.enabled);
+    crate::proxy::update_thinking_budget_config(proxy.thinking_budget.clone());
+    crate::proxy::update_global_system_prompt_config(proxy.global_system_prompt.clone());
+    crate::proxy::update_image_thinking_mode(proxy.image_thinking_mode.clone());
+    crate::proxy::common::model_fallback::update_model_fallbacks(proxy.model_fallbacks.clone());
+    crate::proxy::common::background_task::update_background_task_rules(proxy.background_task_rules.clone());
+    crate::proxy::providers::compatible::update_providers(proxy.providers.clone());
+    crate::modules::response_cache::update_response_cache_config(proxy.response_cache.clone());
+    crate::modules::audit_log::update_audit_log_config(proxy.audit_log.clone());
+    crate::modules::response_store::update_response_store_config(proxy.response_store.clone());
+    crate::modules::image_store::update_image_store_config(proxy.image_store.clone());
+    crate::proxy::common::structured_output::update_structured_output_config(proxy.structured_outputs.clone());
+    state
+        .token_manager
+        .update_circuit_breaker_config(config.circuit_breaker.clone())
+        .await;
+
+    set_running_config(config);
+    tracing::debug!("已同步热更新反代服务配置");
+}
+
+/// 从磁盘重新加载配置；校验失败时保留当前配置
+pub async fn reload_from_disk(state: &AppState, trigger: &str) -> Result<usize, String> {
+    let config = crate::modules::config::load_app_config()?;
+
+    let issues = validate(&config);
+    if config_validation::has_errors(&issues) {
+        let summary = config_validation::summarize_errors(&issues);
+        tracing::error!("[ConfigReload] 配置重载被拒绝 ({}): {}", trigger, summary);
+        return Err(summary);
+    }
+
+    let changes = config_validation::diff_configs(&running_config(), &config);
+    if changes.is_empty() {
+        // 应用自身保存配置时也会触发文件变更，内容一致则忽略
+        return Ok(0);
+    }
+
+    apply_to_state(state, &config).await;
+
+    for change in changes.iter().filter(|c| c.requires_restart) {
+        tracing::warn!("[ConfigReload] {} 已变更，需重启反代服务后生效", change.path);
+    }
+    tracing::info!(
+        "[ConfigReload] 已从磁盘重载配置 ({}): {} 项变更",
+        trigger,
+        changes.len()
+    );
+
+    crate::modules::events::emit("config://updated", ());
+
+    Ok(changes.len())
+}
+
+fn config_mtime() -> Option<SystemTime> {
+    let path = crate::modules::account::get_data_dir()
+        .ok()?
+        .join(crate::modules::config::CONFIG_FILE);
+    std::fs::metadata(path).ok()?.modified().ok()
+}
+
+/// 配置监听任务句柄，随所属服务器停止 (或被丢弃) 时中止
+pub struct ConfigWatcher {
+    tasks: Vec<tokio::task::JoinHandle<()>>,
+}
+
+impl ConfigWatcher {
+    pub fn abort(&self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+impl Drop for ConfigWatcher {
+    fn drop(&mut self) {
+        self.abort();
+    }
+}
+
+/// 启动配置文件监听与 SIGHUP 处理，重载结果作用于传入的 `state`
+pub fn spawn_config_watcher(state: AppState) -> ConfigWatcher {
+    let mut tasks = Vec::new();
+
+    // 以启动时的磁盘配置作为基线，避免首次变更被误判为无差异
+    if running_slot().read().map(|c| c.is_none()).unwrap_or(false) {
+        if let Ok(config) = crate::modules::config::load_app_config() {
+            set_running_config(&config);
+        }
+    }
+
+    let watch_state = state.clone();
+    tasks.push(tokio::spawn(async move {
+        let mut last_mtime = config_mtime();
+        loop {
+            tokio::time::sleep(WATCH_INTERVAL).await;
+            let mtime = config_mtime();
+            if mtime.is_some() && mtime != last_mtime {
+                last_mtime = mtime;
+                let _ = reload_from_disk(&watch_state, "file change").await;
+            }
+        }
+    }));
+
+    #[cfg(unix)]
+    tasks.push(tokio::spawn(async move {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut hangup = match signal(SignalKind::hangup()) {
+            Ok(s) => s,
+            Err(e) => {
+                tracing::warn!("[ConfigReload] 无法注册 SIGHUP 处理: {}", e);
+                return;
+            }
+        };
+        while hangup.recv().await.is_some() {
+            tracing::info!("[ConfigReload] 收到 SIGHUP，重新加载配置");
+            let _ = reload_from_disk(&state, "SIGHUP").await;
+        }
+    }));
+
+    #[cfg(not(unix))]
+    drop(state);
+
+    ConfigWatcher { tasks }
+}
@@ -1,24 +1,61 @@
 use axum::{
     extract::{Multipart, State},
-    http::StatusCode,
-    response::IntoResponse,
-    Json,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
 };
 use serde_json::{json, Value};
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 use uuid::Uuid;
 
+use crate::proxy::audio::transcript::{timestamp_schema, AudioTask, Transcript, TranscriptFormat};
+use crate::proxy::handlers::common::{check_model_access, ClientProtocol};
+use crate::proxy::middleware::auth::UserTokenIdentity;
 use crate::proxy::{audio::AudioProcessor, server::AppState};
 
+/// 单段音频的最大尝试次数 (失败时换号重试)
+const MAX_SEGMENT_ATTEMPTS: usize = 2;
+/// 传给下一段的上文长度 (字符)
+const CONTEXT_TAIL_CHARS: usize = 300;
+
+/// Whisper 兼容的表单参数
+struct AudioForm {
+    audio_data: Vec<u8>,
+    file_name: String,
+    model: String,
+    prompt: Option<String>,
+    language: Option<String>,
+    temperature: Option<f64>,
+    response_format: TranscriptFormat,
+    word_timestamps: bool,
+}
+
 /// 处理音频转录请求 (OpenAI Whisper API 兼容)
 pub async fn handle_audio_transcription(
     State(state): State<AppState>,
-    mut multipart: Multipart,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+    identity: Option<axum::Extension<UserTokenIdentity>>,
+    multipart: Multipart,
+) -> Result<Response, (StatusCode, String)> {
+    handle_audio(state, identity, multipart, AudioTask::Transcribe).await
+}
+
+/// 处理音频翻译请求 (OpenAI Whisper API 兼容，输出英文)
+pub async fn handle_audio_translation(
+    State(state): State<AppState>,
+    identity: Option<axum::Extension<UserTokenIdentity>>,
+    multipart: Multipart,
+) -> Result<Response, (StatusCode, String)> {
+    handle_audio(state, identity, multipart, AudioTask::Translate).await
+}
+
+async fn parse_form(mut multipart: Multipart) -> Result<AudioForm, (StatusCode, String)> {
     let mut audio_data: Option<Vec<u8>> = None;
     let mut filename: Option<String> = None;
     let mut model = "gemini-2.0-flash-exp".to_string();
-    let mut prompt = "Generate a transcript of the speech.".to_string();
+    let mut prompt = None;
+    let mut language = None;
+    let mut temperature = None;
+    let mut response_format = TranscriptFormat::Json;
+    let mut word_timestamps = false;
 
     // 1. 解析 multipart/form-data
     while let Some(field) = multipart
@@ -43,68 +80,222 @@ pub async fn handle_audio_transcription(
                 model = field.text().await.unwrap_or(model);
             }
             "prompt" => {
-                prompt = field.text().await.unwrap_or(prompt);
+                prompt = field.text().await.ok().filter(|p| !p.trim().is_empty());
+            }
+            "language" => {
+                language = field.text().await.ok().filter(|l| !l.trim().is_empty());
+            }
+            "temperature" => {
+                temperature = field.text().await.ok().and_then(|t| t.trim().parse().ok());
+            }
+            "response_format" => {
+                let value = field.text().await.unwrap_or_default();
+                response_format =
+                    TranscriptFormat::parse(&value).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+            }
+            "timestamp_granularities[]" | "timestamp_granularities" => {
+                let value = field.text().await.unwrap_or_default();
+                if value.split(',').any(|g| g.trim() == "word") {
+                    word_timestamps = true;
+                }
             }
             _ => {}
         }
     }
 
-    let audio_bytes = audio_data.ok_or((StatusCode::BAD_REQUEST, "缺少音频文件".to_string()))?;
+    Ok(AudioForm {
+        audio_data: audio_data.ok_or((StatusCode::BAD_REQUEST, "缺少音频文件".to_string()))?,
+        file_name: filename.ok_or((StatusCode::BAD_REQUEST, "无法获取文件名".to_string()))?,
+        model,
+        prompt,
+        language,
+        temperature,
+        response_format,
+        // 与 Whisper 一致：逐词时间戳只在 verbose_json 中返回
+        word_timestamps: word_timestamps && response_format == TranscriptFormat::VerboseJson,
+    })
+}
+
+/// 构建单段音频的指令
+fn build_instruction(form: &AudioForm, task: AudioTask, previous: Option<&str>) -> String {
+    let mut lines = vec![match task {
+        AudioTask::Transcribe => {
+            "Transcribe the speech in this audio verbatim, in the language it is spoken.".to_string()
+        }
+        AudioTask::Translate => "Translate the speech in this audio into English.".to_string(),
+    }];
+    if task == AudioTask::Transcribe {
+        if let Some(language) = &form.language {
+            lines.push(format!("The spoken language is '{}' (ISO-639-1).", language.trim()));
+        }
+    }
+    if form.response_format.needs_timestamps() {
+        lines.push(
+            "Return JSON with `language` and `segments`: consecutive utterances in order, each with `start` and `end` in seconds from the beginning of this audio clip and its `text`."
+                .to_string(),
+        );
+        if form.word_timestamps {
+            lines.push("Include `words` with per-word `start` and `end` times for every segment.".to_string());
+        }
+    } else {
+        lines.push("Output only the text, without timestamps, speaker labels or commentary.".to_string());
+    }
+    if let Some(prompt) = &form.prompt {
+        lines.push(format!("Context (spelling and vocabulary hints): {}", prompt.trim()));
+    }
+    if let Some(previous) = previous {
+        lines.push(format!(
+            "This clip continues a longer recording. The previous part ended with: \"{}\"",
+            previous
+        ));
+    }
+    lines.join("\n")
+}
 
-    let file_name = filename.ok_or((StatusCode::BAD_REQUEST, "无法获取文件名".to_string()))?;
+async fn handle_audio(
+    state: AppState,
+    identity: Option<axum::Extension<UserTokenIdentity>>,
+    multipart: Multipart,
+    task: AudioTask,
+) -> Result<Response, (StatusCode, String)> {
+    let form = parse_form(multipart).await?;
 
     info!(
-        "收到音频转录请求: 文件={}, 大小={} bytes, 模型={}",
-        file_name,
-        audio_bytes.len(),
-        model
+        "收到音频{}请求: 文件={}, 大小={} bytes, 模型={}, 格式={:?}",
+        if task == AudioTask::Translate { "翻译" } else { "转录" },
+        form.file_name,
+        form.audio_data.len(),
+        form.model,
+        form.response_format
     );
 
+    let mapped = crate::proxy::common::model_mapping::resolve_model_route(
+        &form.model,
+        &*state.custom_mapping.read().await,
+    );
+    if let Err(resp) = check_model_access(identity.as_deref(), &form.model, &mapped, ClientProtocol::OpenAI) {
+        return Ok(resp);
+    }
+
     // 2. 检测 MIME 类型
     let mime_type =
-        AudioProcessor::detect_mime_type(&file_name).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+        AudioProcessor::detect_mime_type(&form.file_name).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
 
-    // 3. 验证文件大小
-    if AudioProcessor::exceeds_size_limit(audio_bytes.len()) {
-        let size_mb = audio_bytes.len() as f64 / (1024.0 * 1024.0);
-        return Err((
-            StatusCode::PAYLOAD_TOO_LARGE,
+    // 3. 切分音频 (WAV/FLAC 超过大小或时长上限时分段，其余格式超限直接拒绝)
+    let segments = AudioProcessor::split_segments(&mime_type, &form.audio_data).map_err(|e| {
+        let size_mb = form.audio_data.len() as f64 / (1024.0 * 1024.0);
+        let status = if AudioProcessor::exceeds_size_limit(form.audio_data.len()) {
+            StatusCode::PAYLOAD_TOO_LARGE
+        } else {
+            StatusCode::BAD_REQUEST
+        };
+        (
+            status,
             format!(
-                "音频文件过大 ({:.1} MB)。最大支持 15 MB (约 16 分钟 MP3)。建议: 1) 压缩音频质量 2) 分段上传",
-                size_mb
+                "音频文件过大 ({:.1} MB) 且无法分段: {}。超过 15 MB 的音频仅支持 WAV/FLAC 分段处理，其他格式请压缩或分段上传",
+                size_mb, e
             ),
-        ));
+        )
+    })?;
+    if segments.len() > 1 {
+        info!("音频已切分为 {} 段", segments.len());
     }
 
-    // 4. 使用 Inline Data 方式
-    debug!("使用 Inline Data 方式处理");
-    let base64_audio = AudioProcessor::encode_to_base64(&audio_bytes);
-
-    // 5. 构建 Gemini 请求
-    let gemini_request = json!({
-        "contents": [{
-            "parts": [
-                {"text": prompt},
-                {
-                    "inlineData": {
-                        "mimeType": mime_type,
-                        "data": base64_audio
+    // 4. 逐段转录并拼接
+    let account_groups = UserTokenIdentity::account_groups_of(identity.as_deref());
+    let with_timestamps = form.response_format.needs_timestamps();
+    let mut transcript = Transcript::default();
+    let mut last_email = String::new();
+
+    for (idx, segment) in segments.iter().enumerate() {
+        let previous = transcript.tail(CONTEXT_TAIL_CHARS);
+        let instruction = build_instruction(&form, task, previous.as_deref());
+
+        let mut generation_config = json!({});
+        if let Some(temperature) = form.temperature {
+            generation_config["temperature"] = json!(temperature);
+        }
+        if with_timestamps {
+            crate::proxy::common::structured_output::apply_response_schema(
+                &mut generation_config,
+                &timestamp_schema(form.word_timestamps),
+            );
+        }
+
+        // 5. 构建 Gemini 请求 (Inline Data)
+        let gemini_request = json!({
+            "contents": [{
+                "role": "user",
+                "parts": [
+                    {"text": instruction},
+                    {
+                        "inlineData": {
+                            "mimeType": mime_type,
+                            "data": AudioProcessor::encode_to_base64(&segment.data)
+                        }
                     }
+                ]
+            }],
+            "generationConfig": generation_config
+        });
+
+        let mut last_error = String::new();
+        let mut output: Option<String> = None;
+        for attempt in 0..MAX_SEGMENT_ATTEMPTS {
+            match transcribe_segment(&state, &mapped, &account_groups, &gemini_request, attempt > 0).await {
+                Ok((text, email)) => {
+                    last_email = email;
+                    output = Some(text);
+                    break;
                 }
-            ]
-        }]
-    });
+                Err(e) => {
+                    warn!("音频第 {} 段处理失败 (尝试 {}/{}): {}", idx + 1, attempt + 1, MAX_SEGMENT_ATTEMPTS, e);
+                    last_error = e;
+                }
+            }
+        }
+        let output = output.ok_or_else(|| {
+            (
+                StatusCode::BAD_GATEWAY,
+                format!("音频第 {}/{} 段处理失败: {}", idx + 1, segments.len(), last_error),
+            )
+        })?;
+        debug!("音频第 {} 段完成，返回 {} 字符", idx + 1, output.len());
 
-    // 6. 获取 Token 和上游客户端
-    let token_manager = state.token_manager;
-    let (access_token, project_id, email, account_id, _wait_ms) = token_manager
-        .get_token("text", false, None, &model)
-        .await
-        .map_err(|e| (StatusCode::SERVICE_UNAVAILABLE, e))?;
+        transcript.append(&output, with_timestamps, segment.start_secs, segment.duration_secs);
+    }
+
+    info!("音频处理完成，返回 {} 字符", transcript.text().len());
+
+    // 6. 按 response_format 返回
+    let body = transcript.render(form.response_format, task, form.word_timestamps);
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, form.response_format.content_type().to_string()),
+            (header::HeaderName::from_static("x-account-email"), last_email),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+/// 发送单段音频到上游，返回 (模型输出文本, 使用的账号)
+async fn transcribe_segment(
+    state: &AppState,
+    model: &str,
+    account_groups: &[String],
+    gemini_request: &Value,
+    force_rotate: bool,
+) -> Result<(String, String), String> {
+    let (access_token, project_id, email, account_id, _wait_ms) = state
+        .token_manager
+        .get_token("text", force_rotate, None, model, account_groups)
+        .await?;
 
     info!("使用账号: {}", email);
 
-    // 7. 包装请求为 v1internal 格式
+    // 包装请求为 v1internal 格式
     let wrapped_body = json!({
         "project": project_id,
         "requestId": format!("audio-{}", Uuid::new_v4()),
@@ -114,9 +305,8 @@ pub async fn handle_audio_transcription(
         "requestType": "text"
     });
 
-    // 8. 发送请求到 Gemini
-    let upstream = state.upstream.clone();
-    let response = upstream
+    let response = state
+        .upstream
         .call_v1_internal(
             "generateContent",
             &access_token,
@@ -125,7 +315,7 @@ pub async fn handle_audio_transcription(
             Some(account_id.as_str()),
         )
         .await
-        .map_err(|e| (StatusCode::BAD_GATEWAY, format!("上游请求失败: {}", e)))?
+        .map_err(|e| format!("上游请求失败: {}", e))?
         .response;
 
     if !response.status().is_success() {
@@ -133,38 +323,30 @@ pub async fn handle_audio_transcription(
             .text()
             .await
             .unwrap_or_else(|_| "Unknown error".to_string());
-        return Err((
-            StatusCode::BAD_GATEWAY,
-            format!("Gemini API 错误: {}", error_text),
-        ));
+        return Err(format!("Gemini API 错误: {}", error_text));
     }
 
     let result: Value = response
         .json()
         .await
-        .map_err(|e| (StatusCode::BAD_GATEWAY, format!("解析响应失败: {}", e)))?;
+        .map_err(|e| format!("解析响应失败: {}", e))?;
 
-    // 9. 提取文本响应（解包 v1internal 响应）
+    // 提取文本响应（解包 v1internal 响应，跳过思维链）
     let inner_response = result.get("response").unwrap_or(&result);
     let text = inner_response
         .get("candidates")
         .and_then(|c| c.get(0))
         .and_then(|c| c.get("content"))
         .and_then(|c| c.get("parts"))
-        .and_then(|p| p.get(0))
-        .and_then(|p| p.get("text"))
-        .and_then(|t| t.as_str())
-        .unwrap_or("");
-
-    info!("音频转录完成，返回 {} 字符", text.len());
+        .and_then(|p| p.as_array())
+        .map(|parts| {
+            parts
+                .iter()
+                .filter(|p| !p.get("thought").and_then(|t| t.as_bool()).unwrap_or(false))
+                .filter_map(|p| p.get("text").and_then(|t| t.as_str()))
+                .collect::<String>()
+        })
+        .unwrap_or_default();
 
-    // 10. 返回标准格式响应
-    Ok((
-        StatusCode::OK,
-        [("X-Account-Email", email.as_str())],
-        Json(json!({
-            "text": text
-        })),
-    )
-        .into_response())
+    Ok((text, email))
 }
@@ -19,11 +19,19 @@ use crate::proxy::mappers::claude::{
     models::{Message, MessageContent},
 };
 use crate::proxy::server::AppState;
+use crate::proxy::handlers::common::{check_model_access, filter_permitted_models, ClientProtocol};
+use crate::proxy::middleware::auth::UserTokenIdentity;
 use crate::proxy::mappers::context_manager::ContextManager;
 use crate::proxy::mappers::estimation_calibrator::get_calibrator;
 use crate::proxy::debug_logger;
 use crate::proxy::upstream::client::mask_email;
 use crate::proxy::common::client_adapter::CLIENT_ADAPTERS; // [NEW] Import Adapter Registry
+use crate::proxy::common::background_task::{self, INTERNAL_BACKGROUND_TASK};
+use crate::modules::response_cache;
+use crate::proxy::common::model_fallback;
+use crate::proxy::common::token_count;
+use crate::proxy::common::structured_output;
+use crate::proxy::providers::compatible;
 use axum::http::HeaderMap;
 use std::sync::{atomic::Ordering, Arc};
 use crate::proxy::model_specs; // [NEW]
@@ -131,6 +139,7 @@ fn apply_thinking_hints(
         if request.output_config.is_none() {
             request.output_config = Some(crate::proxy::mappers::claude::models::OutputConfig {
                 effort: Some(level_to_effort(level)),
+                format: None,
             });
             tracing::debug!("[{}] Applied thinking hint: effort={}", trace_id, level);
             applied = true;
@@ -159,10 +168,6 @@ fn apply_thinking_hints(
 
 const MAX_RETRY_ATTEMPTS: usize = 3;
 
-// ===== Model Constants for Background Tasks =====
-// These can be adjusted for performance/cost optimization or overridden by custom_mapping
-const INTERNAL_BACKGROUND_TASK: &str = "internal-background-task";  // Unified virtual ID for all background tasks
-
 // ===== Layer 3: XML Summary Prompt Template =====
 // Borrowed from Practical-Guide-to-Context-Engineering + Claude Code official practice
 // This prompt generates a structured 8-section XML summary for context compression
@@ -245,6 +250,7 @@ use super::common::{determine_retry_strategy, apply_retry_strategy, should_rotat
 pub async fn handle_messages(
     State(state): State<AppState>,
     headers: HeaderMap,
+    identity: Option<axum::Extension<UserTokenIdentity>>,
     Json(body): Json<Value>,
 ) -> Response {
     // [FIX] 保存原始请求体的完整副本，用于日志记录
@@ -289,6 +295,17 @@ pub async fn handle_messages(
         }
     };
 
+    // 用户令牌模型访问控制 (在 z.ai / Google 分流之前统一检查)
+    {
+        let mapped = crate::proxy::common::model_mapping::resolve_model_route(
+            &request.model,
+            &*state.custom_mapping.read().await,
+        );
+        if let Err(resp) = check_model_access(identity.as_deref(), &request.model, &mapped, ClientProtocol::Anthropic) {
+            return resp;
+        }
+    }
+
     // [Task #6] Apply OpenCode variants thinking hints from raw JSON
     // 由于此时还没拿到账号，先用模型默认限额兜底
     let temp_cap = model_specs::get_thinking_budget(&request.model, None);
@@ -324,7 +341,22 @@ pub async fn handle_messages(
                     true
                 } else {
                     // [Issue #703 Fix] 智能判断:检查是否有可用的 Google 账号
-                    let has_available = state.token_manager.has_available_account("claude", &normalized_model).await;
+                    // 配置了模型降级链且存在可用备用模型时，仍走 Google 流程
+                    let has_available = state
+                        .token_manager
+                        .has_available_account(
+                            "claude",
+                            &normalized_model,
+                            &UserTokenIdentity::account_groups_of(identity.as_deref()),
+                        )
+                        .await
+                        || {
+                            let mapping = state.custom_mapping.read().await;
+                            let mapped = crate::proxy::common::model_mapping::resolve_model_route(&request.model, &mapping);
+                            model_fallback::select_fallback(&state.token_manager, &mapping, identity.as_deref(), &request.model, &mapped)
+                                .await
+                                .is_some()
+                        };
                     if !has_available {
                         tracing::info!(
                             "[{}] All Google accounts unavailable (rate-limited or quota-protected for {}), using fallback provider",
@@ -405,7 +437,24 @@ pub async fn handle_messages(
         )
         .await;
     }
-    
+
+    // 通用上游提供商 (OpenAI / Anthropic 兼容后端)
+    {
+        let mapped = crate::proxy::common::model_mapping::resolve_model_route(
+            &request.model,
+            &*state.custom_mapping.read().await,
+        );
+        if let Some(provider) = compatible::select_provider(
+            &state,
+            &request.model,
+            &mapped,
+            &UserTokenIdentity::account_groups_of(identity.as_deref()),
+        )
+        .await {
+            return compatible::handle_claude(&state, &provider, &headers, &request, &trace_id).await;
+        }
+    }
+
     // Google Flow 继续使用 request 对象
     // (后续代码不需要再次 filter_invalid_thinking_blocks)
     
@@ -528,6 +577,44 @@ pub async fn handle_messages(
     let mut last_email: Option<String> = None;
     let mut last_mapped_model: Option<String> = None;
     let mut last_status = StatusCode::SERVICE_UNAVAILABLE; // Default to 503 if no response reached
+    let account_groups = UserTokenIdentity::account_groups_of(identity.as_deref());
+
+    // 结构化输出校验 (proxy.structured_outputs.validate，仅非流式请求)
+    let output_schema = request
+        .output_config
+        .as_ref()
+        .and_then(|c| c.output_schema())
+        .filter(|_| !request.stream)
+        .and_then(|schema| structured_output::validation_retries().map(|r| (schema.clone(), r)));
+    let mut schema_retries_left = output_schema.as_ref().map(|(_, r)| *r).unwrap_or(0);
+    let max_attempts = max_attempts + schema_retries_left as usize;
+
+    // 精确匹配响应缓存 (proxy.response_cache，默认关闭；需要校验输出的请求不走缓存)
+    // 后台任务改写与上下文压缩对相同输入是确定的，因此按改写前的请求计算缓存键
+    // 显式 temperature > 0 的请求结果不确定，不走缓存
+    let cache_key = if response_cache::is_enabled()
+        && output_schema.is_none()
+        && response_cache::is_cacheable(request.temperature)
+    {
+        let mapped = crate::proxy::common::model_mapping::resolve_model_route(
+            &request.model,
+            &*state.custom_mapping.read().await,
+        );
+        let caller = identity.as_deref().map(|id| id.token_id.as_str());
+        serde_json::to_value(&request)
+            .ok()
+            .map(|v| response_cache::cache_key(caller, "anthropic", &mapped, &v))
+    } else {
+        None
+    };
+    let cached = match cache_key.as_deref() {
+        Some(key) => response_cache::lookup(key).await,
+        None => None,
+    };
+    if let Some(cached) = cached {
+        info!("[{}] ✓ Response cache hit ({})", trace_id, cached.model);
+        return replay_cached_messages(&request, cached, &trace_id, client_adapter).await;
+    }
     
     for attempt in 0..max_attempts {
         // 2. 模型路由解析
@@ -535,6 +622,20 @@ pub async fn handle_messages(
             &request_for_body.model,
             &*state.custom_mapping.read().await,
         );
+
+        // 模型降级链: 原模型的账号全部被限流/配额保护时切换到备用模型 (每次重试重新判断)
+        let fallback = model_fallback::select_fallback(
+            &token_manager,
+            &*state.custom_mapping.read().await,
+            identity.as_deref(),
+            &request_for_body.model,
+            &mapped_model,
+        )
+        .await;
+        if let Some(fb) = &fallback {
+            info!("[{}] Model fallback: {} -> {}", trace_id, fb.from, fb.to);
+            mapped_model = fb.to.clone();
+        }
         last_mapped_model = Some(mapped_model.clone());
         
         // 将 Claude 工具转为 Value 数组以便探测联网
@@ -558,7 +659,7 @@ pub async fn handle_messages(
         let session_id = Some(session_id_str.as_str());
 
         let force_rotate_token = attempt > 0;
-        let (access_token, project_id, email, account_id, _wait_ms) = match token_manager.get_token(&config.request_type, force_rotate_token, session_id, &config.final_model).await {
+        let (access_token, project_id, email, account_id, _wait_ms) = match token_manager.get_token(&config.request_type, force_rotate_token, session_id, &config.final_model, &account_groups).await {
             Ok(t) => t,
             Err(e) => {
                 let safe_message = if e.contains("invalid_grant") {
@@ -588,15 +689,21 @@ pub async fn handle_messages(
         
         
         // ===== 【优化】后台任务智能检测与降级 =====
-        // 使用新的检测系统，支持 5 大类关键词和多 Flash 模型策略
-        let background_task_type = detect_background_task_type(&request_for_body);
+        // 规则可配置 (proxy.background_task_rules)，按客户端适配器限定作用范围
+        let background_match = background_task::last_user_message(&request_for_body).and_then(|msg| {
+            let client = client_adapter
+                .as_ref()
+                .map(|a| a.name())
+                .unwrap_or(background_task::DEFAULT_CLIENT);
+            background_task::classify(&msg, client)
+        });
         
         // 传递映射后的模型名
         let mut request_with_mapped = request_for_body.clone();
 
-        if let Some(task_type) = background_task_type {
-            // 检测到后台任务,强制降级到 Flash 模型
-            let virtual_model_id = select_background_model(task_type);
+        if let Some(task) = background_match {
+            // 检测到后台任务,强制降级到规则指定的模型
+            let virtual_model_id = task.target_model.as_str();
             
             // [FIX] 必须根据虚拟 ID Re-resolve 路由，以支持用户自定义映射 (如 internal-task -> gemini-3)
             // 否则会直接使用 generic ID 导致下游无法识别或只能使用静态默认值
@@ -606,9 +713,9 @@ pub async fn handle_messages(
             );
 
             info!(
-                "[{}][AUTO] 检测到后台任务 (类型: {:?}), 路由重定向: {} -> {} (最终物理模型: {})",
+                "[{}][AUTO] 检测到后台任务 (规则: {}), 路由重定向: {} -> {} (最终物理模型: {})",
                 trace_id,
-                task_type,
+                task.rule,
                 mapped_model,
                 virtual_model_id,
                 resolved_model
@@ -737,7 +844,7 @@ pub async fn handle_messages(
                 // Clone token_manager Arc to avoid borrow issues
                 let token_manager_clone = token_manager.clone();
                 
-                match try_compress_with_summary(&request_with_mapped, &trace_id, &token_manager_clone).await {
+                match try_compress_with_summary(&request_with_mapped, &trace_id, &token_manager_clone, &account_groups).await {
                     Ok(forked_request) => {
                         info!(
                             "[{}] [Layer-3] Fork successful: {} → {} messages",
@@ -911,7 +1018,7 @@ pub async fn handle_messages(
         // 成功
         if status.is_success() {
             // [智能限流] 请求成功，重置该账号的连续失败计数
-            token_manager.mark_account_success(&email);
+            token_manager.mark_account_success(&email, Some(&request_with_mapped.model));
             
                 // Determine context limit based on model
                 let context_limit = crate::proxy::mappers::claude::utils::get_context_limit_for_model(&request_with_mapped.model);
@@ -928,13 +1035,24 @@ pub async fn handle_messages(
                     "status": status.as_u16(),
                     "upstream_url": upstream_url,
                 });
-                let gemini_stream = debug_logger::wrap_stream_with_debug(
+                let mut gemini_stream = debug_logger::wrap_stream_with_debug(
                     Box::pin(response.bytes_stream()),
                     debug_cfg.clone(),
                     trace_id.clone(),
                     "upstream_response",
                     meta,
                 );
+                // 降级后的响应不写入原模型的缓存
+                if let Some(key) = cache_key.as_ref().filter(|_| fallback.is_none()) {
+                    gemini_stream = response_cache::tee_stream(
+                        gemini_stream,
+                        response_cache::PendingEntry {
+                            key: key.clone(),
+                            protocol: "anthropic",
+                            model: request_with_mapped.model.clone(),
+                        },
+                    );
+                }
 
                 let current_message_count = request_with_mapped.messages.len();
 
@@ -1022,7 +1140,7 @@ pub async fn handle_messages(
                         // 判断客户端期望的格式
                         if client_wants_stream {
                             // 客户端本就要 Stream，直接返回 SSE
-                            return Response::builder()
+                            return model_fallback::annotate_response(Response::builder()
                                 .status(StatusCode::OK)
                                 .header(header::CONTENT_TYPE, "text/event-stream")
                                 .header(header::CACHE_CONTROL, "no-cache")
@@ -1032,7 +1150,7 @@ pub async fn handle_messages(
                                 .header("X-Mapped-Model", &request_with_mapped.model)
                                 .header("X-Context-Purified", if is_purified { "true" } else { "false" })
                                 .body(Body::from_stream(combined_stream))
-                                .unwrap();
+                                .unwrap(), fallback.as_ref());
                         } else {
                             // 客户端要非 Stream，需要收集完整响应并转换为 JSON
                             use crate::proxy::mappers::claude::collect_stream_to_json;
@@ -1040,14 +1158,36 @@ pub async fn handle_messages(
                             match collect_stream_to_json(combined_stream).await {
                                 Ok(full_response) => {
                                     info!("[{}] ✓ Stream collected and converted to JSON", trace_id);
-                                    return Response::builder()
+                                    if let Some((schema, _)) = &output_schema {
+                                        if let Err(message) = check_structured_output(schema, &full_response) {
+                                            tracing::warn!("[{}] Structured output validation failed: {}", trace_id, message);
+                                            if schema_retries_left > 0 && attempt + 1 < max_attempts {
+                                                schema_retries_left -= 1;
+                                                last_error = message;
+                                                continue;
+                                            }
+                                            return (
+                                                StatusCode::BAD_GATEWAY,
+                                                [("X-Account-Email", email.as_str()), ("X-Mapped-Model", request_with_mapped.model.as_str())],
+                                                Json(json!({
+                                                    "type": "error",
+                                                    "error": {
+                                                        "type": "schema_validation_failed",
+                                                        "message": message
+                                                    }
+                                                })),
+                                            )
+                                                .into_response();
+                                        }
+                                    }
+                                    return model_fallback::annotate_response(Response::builder()
                                         .status(StatusCode::OK)
                                         .header(header::CONTENT_TYPE, "application/json")
                                         .header("X-Account-Email", &email)
                                         .header("X-Mapped-Model", &request_with_mapped.model)
                                         .header("X-Context-Purified", if is_purified { "true" } else { "false" })
                                         .body(Body::from(serde_json::to_string(&full_response).unwrap()))
-                                        .unwrap();
+                                        .unwrap(), fallback.as_ref());
                                 }
                                 Err(e) => {
                                     return (StatusCode::INTERNAL_SERVER_ERROR, format!("Stream collection error: {}", e)).into_response();
@@ -1123,7 +1263,10 @@ pub async fn handle_messages(
                     cache_info
                 );
 
-                return (StatusCode::OK, [("X-Account-Email", email.as_str()), ("X-Mapped-Model", request_with_mapped.model.as_str())], Json(claude_response)).into_response();
+                return model_fallback::annotate_response(
+                    (StatusCode::OK, [("X-Account-Email", email.as_str()), ("X-Mapped-Model", request_with_mapped.model.as_str())], Json(claude_response)).into_response(),
+                    fallback.as_ref(),
+                );
             }
         }
         
@@ -1407,14 +1550,74 @@ pub async fn handle_messages(
     }
 }
 
+/// 回放缓存的上游 SSE 响应 (流式客户端直接输出 SSE，非流式客户端聚合为 JSON)
+async fn replay_cached_messages(
+    request: &ClaudeRequest,
+    cached: response_cache::CachedResponse,
+    trace_id: &str,
+    client_adapter: Option<std::sync::Arc<dyn crate::proxy::common::client_adapter::ClientAdapter>>,
+) -> Response {
+    let registered_tool_names: Vec<String> = request
+        .tools
+        .as_ref()
+        .map(|tools| tools.iter().filter_map(|t| t.name.clone()).collect())
+        .unwrap_or_default();
+
+    let claude_stream = create_claude_sse_stream(
+        response_cache::replay_stream(cached.body),
+        trace_id.to_string(),
+        String::new(),
+        None,
+        false,
+        crate::proxy::mappers::claude::utils::get_context_limit_for_model(&cached.model),
+        None,
+        request.messages.len(),
+        client_adapter,
+        registered_tool_names,
+    )
+    .map(|result| -> Result<Bytes, std::io::Error> {
+        match result {
+            Ok(b) => Ok(b),
+            Err(e) => Ok(Bytes::from(format!("data: {{\"error\":\"{}\"}}\n\n", e))),
+        }
+    });
+
+    if request.stream {
+        return Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, "text/event-stream")
+            .header(header::CACHE_CONTROL, "no-cache")
+            .header("X-Mapped-Model", &cached.model)
+            .header(response_cache::CACHE_HEADER, "HIT")
+            .body(Body::from_stream(claude_stream))
+            .unwrap();
+    }
+
+    use crate::proxy::mappers::claude::collect_stream_to_json;
+    match collect_stream_to_json(Box::pin(claude_stream)).await {
+        Ok(full_response) => Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, "application/json")
+            .header("X-Mapped-Model", &cached.model)
+            .header(response_cache::CACHE_HEADER, "HIT")
+            .body(Body::from(serde_json::to_string(&full_response).unwrap()))
+            .unwrap(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("Cached response replay error: {}", e)).into_response(),
+    }
+}
+
 /// 列出可用模型
-pub async fn handle_list_models(State(state): State<AppState>) -> impl IntoResponse {
+pub async fn handle_list_models(
+    State(state): State<AppState>,
+    identity: Option<axum::Extension<UserTokenIdentity>>,
+) -> impl IntoResponse {
     use crate::proxy::common::model_mapping::get_all_dynamic_models;
 
     let model_ids = get_all_dynamic_models(
         &state.custom_mapping,
         Some(&state.token_manager)
     ).await;
+    let model_ids = filter_permitted_models(&state, identity.as_deref(), model_ids).await;
 
     let data: Vec<_> = model_ids.into_iter().map(|id| {
         json!({
@@ -1435,8 +1638,21 @@ pub async fn handle_list_models(State(state): State<AppState>) -> impl IntoRespo
 pub async fn handle_count_tokens(
     State(state): State<AppState>,
     headers: HeaderMap,
+    identity: Option<axum::Extension<UserTokenIdentity>>,
     Json(body): Json<Value>,
 ) -> Response {
+    // 用户令牌模型访问控制 (在转发 z.ai 或使用账号池计数之前)
+    {
+        let model = body.get("model").and_then(|v| v.as_str()).unwrap_or_default();
+        let mapped = crate::proxy::common::model_mapping::resolve_model_route(
+            model,
+            &*state.custom_mapping.read().await,
+        );
+        if let Err(resp) = check_model_access(identity.as_deref(), model, &mapped, ClientProtocol::Anthropic) {
+            return resp;
+        }
+    }
+
     let zai = state.zai.read().await.clone();
     let zai_enabled = zai.enabled && !matches!(zai.dispatch_mode, crate::proxy::ZaiDispatchMode::Off);
 
@@ -1452,11 +1668,56 @@ pub async fn handle_count_tokens(
         .await;
     }
 
-    Json(json!({
-        "input_tokens": 0,
-        "output_tokens": 0
-    }))
-    .into_response()
+    let request: ClaudeRequest = match serde_json::from_value(body) {
+        Ok(r) => r,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({
+                    "type": "error",
+                    "error": {
+                        "type": "invalid_request_error",
+                        "message": format!("Invalid request body: {}", e)
+                    }
+                })),
+            )
+                .into_response();
+        }
+    };
+
+    let mapped_model = crate::proxy::common::model_mapping::resolve_model_route(
+        &request.model,
+        &*state.custom_mapping.read().await,
+    );
+    // 计数只关心输入，估算时不计入思考预算
+    let mut estimate_input = request.clone();
+    estimate_input.thinking = None;
+    let raw_estimate = ContextManager::estimate_token_usage(&estimate_input);
+
+    let account_groups = UserTokenIdentity::account_groups_of(identity.as_deref());
+    let mut request_with_mapped = request;
+    request_with_mapped.model = mapped_model.clone();
+    let (input_tokens, method) = token_count::count_tokens(
+        &state,
+        &mapped_model,
+        &account_groups,
+        token_count::CountEstimator::ContextManager,
+        raw_estimate,
+        |project_id, account_id, token| {
+            transform_claude_request_in(&request_with_mapped, project_id, false, Some(account_id), "count_tokens", token)
+                .map(|body| body.get("request").cloned().unwrap_or(body))
+        },
+    )
+    .await;
+
+    (
+        [(token_count::COUNT_METHOD_HEADER, method.as_str())],
+        Json(json!({
+            "input_tokens": input_tokens,
+            "count_method": method
+        })),
+    )
+        .into_response()
 }
 
 // 移除已失效的简单单元测试，后续将补全完整的集成测试
@@ -1472,154 +1733,6 @@ mod tests {
 }
 */
 
-// ===== 后台任务检测辅助函数 =====
-
-/// 后台任务类型
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum BackgroundTaskType {
-    TitleGeneration,      // 标题生成
-    SimpleSummary,        // 简单摘要
-    ContextCompression,   // 上下文压缩
-    PromptSuggestion,     // 提示建议
-    SystemMessage,        // 系统消息
-    EnvironmentProbe,     // 环境探测
-}
-
-/// 标题生成关键词
-const TITLE_KEYWORDS: &[&str] = &[
-    "write a 5-10 word title",
-    "Please write a 5-10 word title",
-    "Respond with the title",
-    "Generate a title for",
-    "Create a brief title",
-    "title for the conversation",
-    "conversation title",
-    "生成标题",
-    "为对话起个标题",
-];
-
-/// 摘要生成关键词
-const SUMMARY_KEYWORDS: &[&str] = &[
-    "Summarize this coding conversation",
-    "Summarize the conversation",
-    "Concise summary",
-    "in under 50 characters",
-    "compress the context",
-    "Provide a concise summary",
-    "condense the previous messages",
-    "shorten the conversation history",
-    "extract key points from",
-];
-
-/// 建议生成关键词
-const SUGGESTION_KEYWORDS: &[&str] = &[
-    "prompt suggestion generator",
-    "suggest next prompts",
-    "what should I ask next",
-    "generate follow-up questions",
-    "recommend next steps",
-    "possible next actions",
-];
-
-/// 系统消息关键词
-const SYSTEM_KEYWORDS: &[&str] = &[
-    "Warmup",
-    "<system-reminder>",
-    // Removed: "Caveat: The messages below were generated" - this is a normal Claude Desktop system prompt
-    "This is a system message",
-];
-
-/// 环境探测关键词
-const PROBE_KEYWORDS: &[&str] = &[
-    "check current directory",
-    "list available tools",
-    "verify environment",
-    "test connection",
-];
-
-/// 检测后台任务并返回任务类型
-fn detect_background_task_type(request: &ClaudeRequest) -> Option<BackgroundTaskType> {
-    let last_user_msg = extract_last_user_message_for_detection(request)?;
-    let preview = last_user_msg.chars().take(500).collect::<String>();
-    
-    // 长度过滤：后台任务通常不超过 800 字符
-    if last_user_msg.len() > 800 {
-        return None;
-    }
-    
-    // 按优先级匹配
-    if matches_keywords(&preview, SYSTEM_KEYWORDS) {
-        return Some(BackgroundTaskType::SystemMessage);
-    }
-    
-    if matches_keywords(&preview, TITLE_KEYWORDS) {
-        return Some(BackgroundTaskType::TitleGeneration);
-    }
-    
-    if matches_keywords(&preview, SUMMARY_KEYWORDS) {
-        if preview.contains("in under 50 characters") {
-            return Some(BackgroundTaskType::SimpleSummary);
-        }
-        return Some(BackgroundTaskType::ContextCompression);
-    }
-    
-    if matches_keywords(&preview, SUGGESTION_KEYWORDS) {
-        return Some(BackgroundTaskType::PromptSuggestion);
-    }
-    
-    if matches_keywords(&preview, PROBE_KEYWORDS) {
-        return Some(BackgroundTaskType::EnvironmentProbe);
-    }
-    
-    None
-}
-
-/// 辅助函数：关键词匹配
-fn matches_keywords(text: &str, keywords: &[&str]) -> bool {
-    keywords.iter().any(|kw| text.contains(kw))
-}
-
-/// 辅助函数：提取最后一条用户消息（用于检测）
-fn extract_last_user_message_for_detection(request: &ClaudeRequest) -> Option<String> {
-    request.messages.iter().rev()
-        .filter(|m| m.role == "user")
-        .find_map(|m| {
-            let content = match &m.content {
-                crate::proxy::mappers::claude::models::MessageContent::String(s) => s.to_string(),
-                crate::proxy::mappers::claude::models::MessageContent::Array(arr) => {
-                    arr.iter()
-                        .filter_map(|block| match block {
-                            crate::proxy::mappers::claude::models::ContentBlock::Text { text } => Some(text.as_str()),
-                            _ => None,
-                        })
-                        .collect::<Vec<_>>()
-                        .join(" ")
-                }
-            };
-            
-            if content.trim().is_empty() 
-                || content.starts_with("Warmup") 
-                || content.contains("<system-reminder>") 
-            {
-                None 
-            } else {
-                Some(content)
-            }
-        })
-}
-
-/// 根据后台任务类型选择合适的模型
-fn select_background_model(task_type: BackgroundTaskType) -> &'static str {
-    match task_type {
-        BackgroundTaskType::TitleGeneration => INTERNAL_BACKGROUND_TASK,
-        BackgroundTaskType::SimpleSummary => INTERNAL_BACKGROUND_TASK,
-        BackgroundTaskType::SystemMessage => INTERNAL_BACKGROUND_TASK,
-        BackgroundTaskType::PromptSuggestion => INTERNAL_BACKGROUND_TASK,
-        BackgroundTaskType::EnvironmentProbe => INTERNAL_BACKGROUND_TASK,
-        BackgroundTaskType::ContextCompression => INTERNAL_BACKGROUND_TASK,
-    }
-}
-
 // ===== [Issue #467 Fix] Warmup 请求拦截 =====
 
 /// 检测是否为 Warmup 请求
@@ -1743,6 +1856,24 @@ fn create_warmup_response(request: &ClaudeRequest, is_stream: bool) -> Response
     }
 }
 
+/// 校验非流式响应的文本输出是否符合 output_config.format 的 Schema (含工具调用的响应跳过)
+fn check_structured_output(
+    schema: &Value,
+    response: &crate::proxy::mappers::claude::models::ClaudeResponse,
+) -> Result<(), String> {
+    let value = serde_json::to_value(response).unwrap_or_default();
+    let blocks = value.get("content").and_then(|c| c.as_array()).cloned().unwrap_or_default();
+    if blocks.iter().any(|b| b.get("type").and_then(|t| t.as_str()) == Some("tool_use")) {
+        return Ok(());
+    }
+    let text: String = blocks
+        .iter()
+        .filter(|b| b.get("type").and_then(|t| t.as_str()) == Some("text"))
+        .filter_map(|b| b.get("text").and_then(|t| t.as_str()))
+        .collect();
+    structured_output::check_output(schema, &text)
+}
+
 // ===== [Helper] Synchronous Upstream Call =====
 // Reusable function for making non-streaming calls to Gemini API
 // Used by Layer 3 and potentially other internal operations
@@ -1756,10 +1887,11 @@ async fn call_gemini_sync(
     request: &ClaudeRequest,
     token_manager: &Arc<crate::proxy::TokenManager>,
     trace_id: &str,
+    account_groups: &[String],
 ) -> Result<String, String> {
     // Get token and transform request
     let (access_token, project_id, _, account_id, _wait_ms) = token_manager
-        .get_token("gemini", false, None, model)
+        .get_token("gemini", false, None, model, account_groups)
         .await
         .map_err(|e| format!("Failed to get account: {}", e))?;
     
@@ -1826,6 +1958,7 @@ async fn try_compress_with_summary(
     original_request: &ClaudeRequest,
     trace_id: &str,
     token_manager: &Arc<crate::proxy::TokenManager>,
+    account_groups: &[String],
 ) -> Result<ClaudeRequest, String> {
     info!("[{}] [Layer-3] Starting context compression with XML summary", trace_id);
     
@@ -1881,6 +2014,7 @@ async fn try_compress_with_summary(
         &summary_request,
         token_manager,
         trace_id,
+        account_groups,
     ).await?;
     
     info!("[{}] [Layer-3] Generated XML summary (len: {} chars)", trace_id, xml_summary.len());
@@ -3,6 +3,7 @@ use tracing::{debug, info};
 use axum::{http::StatusCode, response::{IntoResponse, Response}, Json, extract::State};
 use serde_json::{json, Value};
 use crate::proxy::server::AppState;
+use crate::proxy::middleware::auth::UserTokenIdentity;
 
 // ===== 统一重试与退避策略 =====
 
@@ -193,3 +194,85 @@ pub async fn handle_detect_model(
 
     Json(response).into_response()
 }
+
+// ===== 用户令牌模型访问控制 =====
+
+/// 客户端协议 (用于构造符合协议格式的错误响应)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClientProtocol {
+    OpenAI,
+    Anthropic,
+    Gemini,
+}
+
+/// 构造模型被拒绝访问时的 403 响应
+pub fn model_forbidden_response(protocol: ClientProtocol, model: &str) -> Response {
+    let message = format!("This token is not allowed to use model '{}'", model);
+    let body = match protocol {
+        ClientProtocol::OpenAI => json!({
+            "error": {
+                "message": message,
+                "type": "permission_error",
+                "param": "model",
+                "code": "model_not_allowed"
+            }
+        }),
+        ClientProtocol::Anthropic => json!({
+            "type": "error",
+            "error": {
+                "type": "permission_error",
+                "message": message
+            }
+        }),
+        ClientProtocol::Gemini => json!({
+            "error": {
+                "code": 403,
+                "message": message,
+                "status": "PERMISSION_DENIED"
+            }
+        }),
+    };
+    (StatusCode::FORBIDDEN, Json(body)).into_response()
+}
+
+/// 检查用户令牌是否允许使用模型 (在 resolve_model_route 之后调用)
+///
+/// 未携带用户令牌 (如使用全局 API Key) 时不做限制。
+pub fn check_model_access(
+    identity: Option<&UserTokenIdentity>,
+    original_model: &str,
+    mapped_model: &str,
+    protocol: ClientProtocol,
+) -> Result<(), Response> {
+    match identity {
+        Some(id) if !id.is_model_permitted(original_model, mapped_model) => {
+            tracing::warn!(
+                "[ModelACL] Token of user {} denied model {} (mapped: {})",
+                id.username,
+                original_model,
+                mapped_model
+            );
+            Err(model_forbidden_response(protocol, original_model))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// 按用户令牌的模型规则过滤模型列表 (用于 /v1/models 与 /v1beta/models)
+pub async fn filter_permitted_models(
+    state: &AppState,
+    identity: Option<&UserTokenIdentity>,
+    model_ids: Vec<String>,
+) -> Vec<String> {
+    let Some(id) = identity else {
+        return model_ids;
+    };
+    let mapping = state.custom_mapping.read().await;
+    model_ids
+        .into_iter()
+        .filter(|m| {
+            let mapped = crate::proxy::common::model_mapping::resolve_model_route(m, &mapping);
+            id.is_model_permitted(m, &mapped)
+        })
+        .collect()
+}
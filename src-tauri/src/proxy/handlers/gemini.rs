@@ -9,10 +9,14 @@ use serde_json::{json, Value};
 use tracing::{debug, error, info};
 
 use crate::proxy::common::client_adapter::CLIENT_ADAPTERS;
+use crate::proxy::common::token_count;
 use crate::proxy::debug_logger;
 use crate::proxy::handlers::common::{
-    apply_retry_strategy, determine_retry_strategy, should_rotate_account,
+    apply_retry_strategy, check_model_access, determine_retry_strategy, filter_permitted_models,
+    should_rotate_account, ClientProtocol,
 };
+use crate::proxy::middleware::auth::UserTokenIdentity;
+use crate::proxy::providers::compatible;
 use crate::proxy::mappers::gemini::{unwrap_response, wrap_request};
 use crate::proxy::server::AppState;
 use crate::proxy::session_manager::SessionManager;
@@ -27,6 +31,7 @@ pub async fn handle_generate(
     State(state): State<AppState>,
     Path(model_action): Path<String>,
     headers: HeaderMap,          // [NEW] Extract headers for adapter detection
+    identity: Option<axum::Extension<UserTokenIdentity>>,
     Json(mut body): Json<Value>, // 改为 mut 以支持修复提示词注入
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
     // 解析 model:method
@@ -52,6 +57,12 @@ pub async fn handle_generate(
         debug!("[{}] Client Adapter detected", trace_id);
     }
 
+    // models/{model}:countTokens 与 generateContent 共用同一路由
+    if method == "countTokens" {
+        // 模型访问控制在 handle_count_tokens 内完成
+        return handle_count_tokens(State(state), Path(model_name), identity, Json(body)).await;
+    }
+
     // 1. 验证方法
     if method != "generateContent" && method != "streamGenerateContent" {
         return Err((
@@ -85,6 +96,28 @@ pub async fn handle_generate(
         // debug!("[AutoConverter] Converting non-stream request to stream");
     }
 
+    // 用户令牌模型访问控制
+    {
+        let mapped = crate::proxy::common::model_mapping::resolve_model_route(
+            &model_name,
+            &*state.custom_mapping.read().await,
+        );
+        if let Err(resp) = check_model_access(identity.as_deref(), &model_name, &mapped, ClientProtocol::Gemini) {
+            return Ok(resp);
+        }
+
+        // 通用上游提供商 (OpenAI / Anthropic 兼容后端)
+        if let Some(provider) = compatible::select_provider(
+            &state,
+            &model_name,
+            &mapped,
+            &UserTokenIdentity::account_groups_of(identity.as_deref()),
+        )
+        .await {
+            return Ok(compatible::handle_gemini(&state, &provider, &headers, &model_name, &body, client_wants_stream).await);
+        }
+    }
+
     // 2. 获取 UpstreamClient 和 TokenManager
     let upstream = state.upstream.clone();
     let token_manager = state.token_manager;
@@ -93,6 +126,7 @@ pub async fn handle_generate(
 
     let mut last_error = String::new();
     let mut last_email: Option<String> = None;
+    let account_groups = UserTokenIdentity::account_groups_of(identity.as_deref());
 
     for attempt in 0..max_attempts {
         // 3. 模型路由解析
@@ -138,6 +172,7 @@ pub async fn handle_generate(
                 attempt > 0,
                 Some(&session_id),
                 &config.final_model,
+                &account_groups,
             )
             .await
         {
@@ -659,11 +694,13 @@ pub async fn handle_generate(
 
 pub async fn handle_list_models(
     State(state): State<AppState>,
+    identity: Option<axum::Extension<UserTokenIdentity>>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
     use crate::proxy::common::model_mapping::get_all_dynamic_models;
 
     // 获取所有动态模型列表（与 /v1/models 一致）
     let model_ids = get_all_dynamic_models(&state.custom_mapping, Some(&state.token_manager)).await;
+    let model_ids = filter_permitted_models(&state, identity.as_deref(), model_ids).await;
 
     // 转换为 Gemini API 格式
     let models: Vec<_> = model_ids
@@ -696,20 +733,40 @@ pub async fn handle_get_model(Path(model_name): Path<String>) -> impl IntoRespon
 
 pub async fn handle_count_tokens(
     State(state): State<AppState>,
-    Path(_model_name): Path<String>,
-    Json(_body): Json<Value>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let model_group = "gemini";
-    let (_access_token, _project_id, _, _, _wait_ms) = state
-        .token_manager
-        .get_token(model_group, false, None, "gemini")
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::SERVICE_UNAVAILABLE,
-                format!("Token error: {}", e),
-            )
-        })?;
-
-    Ok(Json(json!({"totalTokens": 0})))
+    Path(model_name): Path<String>,
+    identity: Option<axum::Extension<UserTokenIdentity>>,
+    Json(body): Json<Value>,
+) -> Result<axum::response::Response, (StatusCode, String)> {
+    let model_name = model_name.trim_start_matches("models/").trim_end_matches(":countTokens").to_string();
+    let mapped_model = crate::proxy::common::model_mapping::resolve_model_route(
+        &model_name,
+        &*state.custom_mapping.read().await,
+    );
+    // 用户令牌模型访问控制 (计数同样会占用账号池)
+    if let Err(resp) = check_model_access(identity.as_deref(), &model_name, &mapped_model, ClientProtocol::Gemini) {
+        return Ok(resp);
+    }
+    // 支持 {contents} 与 {generateContentRequest} 两种请求形式
+    let request = body.get("generateContentRequest").cloned().unwrap_or(body);
+    let raw_estimate = token_count::estimate_gemini_request(&request);
+
+    let account_groups = UserTokenIdentity::account_groups_of(identity.as_deref());
+    let (total_tokens, method) = token_count::count_tokens(
+        &state,
+        &mapped_model,
+        &account_groups,
+        token_count::CountEstimator::GeminiRequest,
+        raw_estimate,
+        |_, _, _| Ok(request.clone()),
+    )
+    .await;
+
+    Ok((
+        [(token_count::COUNT_METHOD_HEADER, method.as_str())],
+        Json(json!({
+            "totalTokens": total_tokens,
+            "countMethod": method
+        })),
+    )
+        .into_response())
 }
@@ -8,7 +8,7 @@ use serde_json::{json, Value};
 use tracing::{debug, error, info}; // Import Engine trait for encode method
 
 use crate::proxy::mappers::openai::{
-    transform_openai_request, transform_openai_response, OpenAIRequest,
+    transform_openai_request, transform_openai_response, OpenAIRequest, OpenAIResponse,
 };
 // use crate::proxy::upstream::client::UpstreamClient; // 通过 state 获取
 use crate::proxy::debug_logger;
@@ -17,10 +17,19 @@ use crate::proxy::upstream::client::mask_email;
 
 const MAX_RETRY_ATTEMPTS: usize = 3;
 use super::common::{
-    apply_retry_strategy, determine_retry_strategy, should_rotate_account, RetryStrategy,
+    apply_retry_strategy, check_model_access, determine_retry_strategy, filter_permitted_models,
+    should_rotate_account, ClientProtocol, RetryStrategy,
 };
+use crate::proxy::middleware::auth::UserTokenIdentity;
 use crate::proxy::common::client_adapter::CLIENT_ADAPTERS; // [NEW] Adapter Registry
+use crate::proxy::common::model_fallback;
+use crate::proxy::providers::compatible;
+use crate::modules::response_cache;
+use crate::modules::response_store;
+use crate::modules::image_store;
+use crate::proxy::common::structured_output;
 use crate::proxy::session_manager::SessionManager;
+use crate::proxy::token_manager::TokenManager;
 use axum::http::HeaderMap;
 use tokio::time::Duration;
 use crate::modules::account;
@@ -28,13 +37,22 @@ use crate::modules::account;
 pub async fn handle_chat_completions(
     State(state): State<AppState>,
     headers: HeaderMap, // [CHANGED] Extract headers
+    identity: Option<axum::Extension<UserTokenIdentity>>,
     Json(mut body): Json<Value>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
     // [NEW] Check for Image Model Redirection
     let model_name = body.get("model").and_then(|v| v.as_str()).unwrap_or("").to_lowercase();
     if model_name.contains("image") || model_name.contains("dall-e") || model_name.contains("midjourney") {
+        let mapped = crate::proxy::common::model_mapping::resolve_model_route(
+            &model_name,
+            &*state.custom_mapping.read().await,
+        );
+        if let Err(resp) = check_model_access(identity.as_deref(), &model_name, &mapped, ClientProtocol::OpenAI) {
+            return Ok(resp);
+        }
         tracing::info!("[ChatRedirection] Redirecting model {} to image generations", model_name);
-        return intercept_chat_to_image(state, body, &model_name).await;
+        let account_groups = UserTokenIdentity::account_groups_of(identity.as_deref());
+        return intercept_chat_to_image(state, body, &model_name, &account_groups).await;
     }
 
     // [FIX] 保存原始请求体的完整副本，用于日志记录
@@ -48,6 +66,7 @@ pub async fn handle_chat_completions(
 
     if is_responses_format {
         debug!("Detected Responses API format, converting to Chat Completions format");
+        map_responses_text_format(&mut body);
 
         // 转换 instructions 为 system message
         if let Some(instructions) = body.get("instructions").and_then(|v| v.as_str()) {
@@ -146,6 +165,28 @@ pub async fn handle_chat_completions(
         debug!("[{}] Client Adapter detected", trace_id);
     }
 
+    // 通用上游提供商 (OpenAI / Anthropic 兼容后端)
+    {
+        let mapped = crate::proxy::common::model_mapping::resolve_model_route(
+            &openai_req.model,
+            &*state.custom_mapping.read().await,
+        );
+        if let Err(resp) = check_model_access(identity.as_deref(), &openai_req.model, &mapped, ClientProtocol::OpenAI) {
+            return Ok(resp);
+        }
+        if let Some(provider) = compatible::select_provider(
+            &state,
+            &openai_req.model,
+            &mapped,
+            &UserTokenIdentity::account_groups_of(identity.as_deref()),
+        )
+        .await {
+            // Responses 格式已在上方转换为 messages，不能原样透传
+            let raw_body = (!is_responses_format).then(|| original_body.clone());
+            return Ok(compatible::handle_openai(&state, &provider, &headers, &openai_req, raw_body, &trace_id).await);
+        }
+    }
+
     // 1. 获取 UpstreamClient (Clone handle)
     let upstream = state.upstream.clone();
     let token_manager = state.token_manager;
@@ -161,8 +202,59 @@ pub async fn handle_chat_completions(
         &openai_req.model,
         &*state.custom_mapping.read().await,
     );
+    if let Err(resp) = check_model_access(identity.as_deref(), &openai_req.model, &mapped_model, ClientProtocol::OpenAI) {
+        return Ok(resp);
+    }
+    let account_groups = UserTokenIdentity::account_groups_of(identity.as_deref());
+
+    // 结构化输出校验 (proxy.structured_outputs.validate，仅非流式请求)
+    let output_schema = structured_output_check(&openai_req);
+    let mut schema_retries_left = output_schema.as_ref().map(|(_, r)| *r).unwrap_or(0);
+    let max_attempts = max_attempts + schema_retries_left as usize;
+
+    // 精确匹配响应缓存 (proxy.response_cache，默认关闭；需要校验输出的请求不走缓存)
+    // 显式 temperature > 0 的请求结果不确定，不走缓存
+    let cache_key = if response_cache::is_enabled()
+        && output_schema.is_none()
+        && response_cache::is_cacheable(openai_req.temperature)
+    {
+        let caller = identity.as_deref().map(|id| id.token_id.as_str());
+        serde_json::to_value(&openai_req)
+            .ok()
+            .map(|v| response_cache::cache_key(caller, "openai", &mapped_model, &v))
+    } else {
+        None
+    };
+    let cached = match cache_key.as_deref() {
+        Some(key) => response_cache::lookup(key).await,
+        None => None,
+    };
+    if let Some(cached) = cached {
+        info!("[{}] ✓ Response cache hit ({})", trace_id, cached.model);
+        return Ok(replay_cached_chat_completion(&openai_req, cached, &trace_id).await);
+    }
 
     for attempt in 0..max_attempts {
+        // 模型降级链: 原模型的账号全部被限流/配额保护时切换到备用模型 (每次重试重新判断)
+        let fallback = model_fallback::select_fallback(
+            &token_manager,
+            &*state.custom_mapping.read().await,
+            identity.as_deref(),
+            &openai_req.model,
+            &mapped_model,
+        )
+        .await;
+        // 降级后的响应不写入原模型的缓存
+        let cache_entry = cache_key.as_ref().filter(|_| fallback.is_none()).map(|key| response_cache::PendingEntry {
+            key: key.clone(),
+            protocol: "openai",
+            model: mapped_model.clone(),
+        });
+        let mapped_model = match &fallback {
+            Some(fb) => fb.to.clone(),
+            None => mapped_model.clone(),
+        };
+
         // 将 OpenAI 工具转为 Value 数组以便探测联网
         let tools_val: Option<Vec<Value>> = openai_req
             .tools
@@ -189,6 +281,7 @@ pub async fn handle_chat_completions(
                 attempt > 0,
                 Some(&session_id),
                 &mapped_model,
+                &account_groups,
             )
             .await
         {
@@ -352,13 +445,16 @@ pub async fn handle_chat_completions(
                     "status": status.as_u16(),
                     "upstream_url": upstream_url,
                 });
-                let gemini_stream = debug_logger::wrap_stream_with_debug(
+                let mut gemini_stream = debug_logger::wrap_stream_with_debug(
                     Box::pin(response.bytes_stream()),
                     debug_cfg.clone(),
                     trace_id.clone(),
                     "upstream_response",
                     meta,
                 );
+                if let Some(entry) = cache_entry {
+                    gemini_stream = response_cache::tee_stream(gemini_stream, entry);
+                }
 
                 // [P1 FIX] Enhanced Peek logic to handle heartbeats and slow start
                 // Pre-read until we find meaningful content, skip heartbeats
@@ -444,7 +540,7 @@ pub async fn handle_chat_completions(
                 if client_wants_stream {
                     // 客户端请求流式，返回 SSE
                     let body = Body::from_stream(combined_stream);
-                    return Ok(Response::builder()
+                    return Ok(model_fallback::annotate_response(Response::builder()
                         .header("Content-Type", "text/event-stream")
                         .header("Cache-Control", "no-cache")
                         .header("Connection", "keep-alive")
@@ -453,7 +549,7 @@ pub async fn handle_chat_completions(
                         .header("X-Mapped-Model", &mapped_model)
                         .body(body)
                         .unwrap()
-                        .into_response());
+                        .into_response(), fallback.as_ref()));
                 } else {
                     // 客户端请求非流式，但内部强制转为流式
                     // 收集流数据并聚合为 JSON
@@ -462,7 +558,18 @@ pub async fn handle_chat_completions(
                     match collect_stream_to_json(Box::pin(combined_stream)).await {
                         Ok(full_response) => {
                             info!("[{}] ✓ Stream collected and converted to JSON", trace_id);
-                            return Ok((
+                            if let Some((schema, _)) = &output_schema {
+                                if let Err(message) = check_structured_output(schema, &full_response) {
+                                    tracing::warn!("[{}] Structured output validation failed: {}", trace_id, message);
+                                    if schema_retries_left > 0 && attempt + 1 < max_attempts {
+                                        schema_retries_left -= 1;
+                                        last_error = message;
+                                        continue;
+                                    }
+                                    return Ok(schema_validation_error(&email, &mapped_model, &message));
+                                }
+                            }
+                            return Ok(model_fallback::annotate_response((
                                 StatusCode::OK,
                                 [
                                     ("X-Account-Email", email.as_str()),
@@ -470,7 +577,7 @@ pub async fn handle_chat_completions(
                                 ],
                                 Json(full_response),
                             )
-                                .into_response());
+                                .into_response(), fallback.as_ref()));
                         }
                         Err(e) => {
                             error!("[{}] Stream collection error: {}", trace_id, e);
@@ -491,7 +598,7 @@ pub async fn handle_chat_completions(
 
             let openai_response =
                 transform_openai_response(&gemini_resp, Some(&session_id), message_count);
-            return Ok((
+            return Ok(model_fallback::annotate_response((
                 StatusCode::OK,
                 [
                     ("X-Account-Email", email.as_str()),
@@ -499,28 +606,24 @@ pub async fn handle_chat_completions(
                 ],
                 Json(openai_response),
             )
-                .into_response());
+                .into_response(), fallback.as_ref()));
         }
 
-        // 处理特定错误并重试
+        // 处理特定错误并重试 (标记限流并按策略退避)
+        let failure = handle_upstream_failure(
+            &token_manager,
+            response,
+            &email,
+            &mapped_model,
+            attempt,
+            max_attempts,
+            &trace_id,
+        )
+        .await;
         let status_code = status.as_u16();
-        let _retry_after = response
-            .headers()
-            .get("Retry-After")
-            .and_then(|h| h.to_str().ok())
-            .map(|s| s.to_string());
-        let error_text = response
-            .text()
-            .await
-            .unwrap_or_else(|_| format!("HTTP {}", status_code));
+        let error_text = failure.error_text;
         last_error = format!("HTTP {}: {}", status_code, error_text);
 
-        // [New] 打印错误报文日志
-        tracing::error!(
-            "[OpenAI-Upstream] Error Response {}: {}",
-            status_code,
-            error_text
-        );
         if debug_logger::is_enabled(&debug_cfg) {
             let payload = json!({
                 "kind": "upstream_response_error",
@@ -544,25 +647,7 @@ pub async fn handle_chat_completions(
             .await;
         }
 
-        // 确定重试策略
-        let strategy = determine_retry_strategy(status_code, &error_text, false);
-
-        // 3. 标记限流状态(用于 UI 显示)
-        if status_code == 429 || status_code == 529 || status_code == 503 || status_code == 500 {
-            // [FIX] Use async version with model parameter for fine-grained rate limiting
-            token_manager
-                .mark_rate_limited_async(
-                    &email,
-                    status_code,
-                    _retry_after.as_deref(),
-                    &error_text,
-                    Some(&mapped_model),
-                )
-                .await;
-        }
-
-        // 执行退避
-        if apply_retry_strategy(strategy, attempt, max_attempts, status_code, &trace_id).await {
+        if failure.retry {
             // [NEW] Apply Client Adapter "let_it_crash" strategy
             if let Some(adapter) = &client_adapter {
                 if adapter.let_it_crash() && attempt > 0 {
@@ -580,14 +665,6 @@ pub async fn handle_chat_completions(
                 }
             }
 
-            // 判断是否需要轮换账号
-            if !should_rotate_account(status_code) {
-                debug!(
-                    "[{}] Keeping same account for status {} (server-side issue)",
-                    trace_id, status_code
-                );
-            }
-
             // 2. [REMOVED] 不再特殊处理 QUOTA_EXHAUSTED，允许账号轮换
             // if error_text.contains("QUOTA_EXHAUSTED") { ... }
             /*
@@ -650,21 +727,6 @@ pub async fn handle_chat_completions(
             continue; // 重试
         }
 
-        // 只有 403 (权限/地区限制) 和 401 (认证失效) 触发账号轮换
-        if status_code == 403 || status_code == 401 {
-            if apply_retry_strategy(
-                RetryStrategy::FixedDelay(Duration::from_millis(200)),
-                attempt,
-                max_attempts,
-                status_code,
-                &trace_id,
-            )
-            .await
-            {
-                continue;
-            }
-        }
-
         // 只有 403 (权限/地区限制) 和 401 (认证失效) 触发账号轮换
         if status_code == 403 || status_code == 401 {
             // [NEW] 403 时设置 is_forbidden 状态，避免 Claude Code 会话退出
@@ -716,39 +778,337 @@ pub async fn handle_chat_completions(
             "OpenAI Upstream non-retryable error {} on account {}: {}",
             status_code, email, error_text
         );
-        return Ok((
-            status,
-            [
-                ("X-Account-Email", email.as_str()),
-                ("X-Mapped-Model", mapped_model.as_str()),
-            ],
-            // [FIX] Return JSON error for better client compatibility
-            Json(json!({
-                "error": {
-                    "message": error_text,
-                    "type": "upstream_error",
-                    "code": status_code
-                }
-            })),
-        )
-            .into_response());
+        return Ok(upstream_error_response(status, &email, &mapped_model, &error_text));
     }
 
     // 所有尝试均失败
-    if let Some(email) = last_email {
-        Ok((
+    Ok(exhausted_response(last_email, mapped_model, &last_error))
+}
+
+/// 上游非 2xx 响应的处理结果
+struct UpstreamFailure {
+    error_text: String,
+    /// 已按重试策略完成退避，应换号重试
+    retry: bool,
+}
+
+/// 读取上游错误响应、标记限流并按重试策略退避 (chat / completions / embeddings 共用)
+async fn handle_upstream_failure(
+    token_manager: &TokenManager,
+    response: rquest::Response,
+    email: &str,
+    mapped_model: &str,
+    attempt: usize,
+    max_attempts: usize,
+    trace_id: &str,
+) -> UpstreamFailure {
+    let status_code = response.status().as_u16();
+    let retry_after = response
+        .headers()
+        .get("Retry-After")
+        .and_then(|h| h.to_str().ok())
+        .map(|s| s.to_string());
+    let error_text = response
+        .text()
+        .await
+        .unwrap_or_else(|_| format!("HTTP {}", status_code));
+
+    // [New] 打印错误报文日志
+    tracing::error!(
+        "[{}] Upstream Error Response {}: {}",
+        trace_id,
+        status_code,
+        error_text
+    );
+
+    // 标记限流状态(用于 UI 显示)
+    if status_code == 429 || status_code == 529 || status_code == 503 || status_code == 500 {
+        // [FIX] Use async version with model parameter for fine-grained rate limiting
+        token_manager
+            .mark_rate_limited_async(
+                email,
+                status_code,
+                retry_after.as_deref(),
+                &error_text,
+                Some(mapped_model),
+            )
+            .await;
+    }
+
+    // 确定重试策略并执行退避
+    let strategy = determine_retry_strategy(status_code, &error_text, false);
+    let mut retry = apply_retry_strategy(strategy, attempt, max_attempts, status_code, trace_id).await;
+
+    // 只有 403 (权限/地区限制) 和 401 (认证失效) 触发账号轮换
+    if !retry && (status_code == 403 || status_code == 401) {
+        retry = apply_retry_strategy(
+            RetryStrategy::FixedDelay(Duration::from_millis(200)),
+            attempt,
+            max_attempts,
+            status_code,
+            trace_id,
+        )
+        .await;
+    }
+    if retry && !should_rotate_account(status_code) {
+        debug!(
+            "[{}] Keeping same account for status {} (server-side issue)",
+            trace_id, status_code
+        );
+    }
+
+    UpstreamFailure { error_text, retry }
+}
+
+/// 不可重试的上游错误 (JSON 格式，便于客户端解析)
+fn upstream_error_response(
+    status: StatusCode,
+    email: &str,
+    mapped_model: &str,
+    error_text: &str,
+) -> Response {
+    (
+        status,
+        [("X-Account-Email", email), ("X-Mapped-Model", mapped_model)],
+        Json(json!({
+            "error": {
+                "message": error_text,
+                "type": "upstream_error",
+                "code": status.as_u16()
+            }
+        })),
+    )
+        .into_response()
+}
+
+/// 所有尝试均失败时的响应
+fn exhausted_response(last_email: Option<String>, mapped_model: String, last_error: &str) -> Response {
+    let message = format!("All accounts exhausted. Last error: {}", last_error);
+    match last_email {
+        Some(email) => (
             StatusCode::TOO_MANY_REQUESTS,
             [("X-Account-Email", email), ("X-Mapped-Model", mapped_model)],
-            format!("All accounts exhausted. Last error: {}", last_error),
+            message,
         )
-            .into_response())
-    } else {
-        Ok((
+            .into_response(),
+        None => (
             StatusCode::TOO_MANY_REQUESTS,
             [("X-Mapped-Model", mapped_model)],
-            format!("All accounts exhausted. Last error: {}", last_error),
+            message,
+        )
+            .into_response(),
+    }
+}
+
+/// 回放缓存的上游 SSE 响应 (流式客户端直接输出 SSE，非流式客户端聚合为 JSON)
+async fn replay_cached_chat_completion(
+    openai_req: &OpenAIRequest,
+    cached: response_cache::CachedResponse,
+    trace_id: &str,
+) -> Response {
+    use crate::proxy::mappers::openai::streaming::create_openai_sse_stream;
+
+    let session_id = SessionManager::extract_openai_session_id(openai_req);
+    let openai_stream = create_openai_sse_stream(
+        response_cache::replay_stream(cached.body),
+        openai_req.model.clone(),
+        session_id,
+        openai_req.messages.len(),
+    );
+
+    if openai_req.stream {
+        return Response::builder()
+            .header("Content-Type", "text/event-stream")
+            .header("Cache-Control", "no-cache")
+            .header("X-Mapped-Model", &cached.model)
+            .header(response_cache::CACHE_HEADER, "HIT")
+            .body(axum::body::Body::from_stream(openai_stream))
+            .unwrap()
+            .into_response();
+    }
+
+    use crate::proxy::mappers::openai::collector::collect_stream_to_json;
+    match collect_stream_to_json(openai_stream).await {
+        Ok(full_response) => (
+            StatusCode::OK,
+            [
+                ("X-Mapped-Model", cached.model.as_str()),
+                (response_cache::CACHE_HEADER, "HIT"),
+            ],
+            Json(full_response),
         )
-            .into_response())
+            .into_response(),
+        Err(e) => {
+            error!("[{}] Cached response replay error: {}", trace_id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Cached response replay error: {}", e),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// 请求声明的 json_schema 及允许的校验重试次数 (仅非流式请求且开启 proxy.structured_outputs.validate)
+fn structured_output_check(openai_req: &OpenAIRequest) -> Option<(Value, u32)> {
+    openai_req
+        .response_format
+        .as_ref()
+        .and_then(|f| f.output_schema())
+        .filter(|_| !openai_req.stream)
+        .and_then(|schema| structured_output::validation_retries().map(|r| (schema.clone(), r)))
+}
+
+/// 校验聚合后的响应文本是否符合 Schema (工具调用等无文本内容的响应不做校验)
+fn check_structured_output(schema: &Value, response: &OpenAIResponse) -> Result<(), String> {
+    let content = serde_json::to_value(response).ok().and_then(|v| {
+        v.pointer("/choices/0/message/content")
+            .and_then(|c| c.as_str())
+            .map(|c| c.to_string())
+    });
+    match content {
+        Some(content) => structured_output::check_output(schema, &content),
+        None => Ok(()),
+    }
+}
+
+fn schema_validation_error(email: &str, mapped_model: &str, message: &str) -> Response {
+    (
+        StatusCode::BAD_GATEWAY,
+        [("X-Account-Email", email), ("X-Mapped-Model", mapped_model)],
+        Json(json!({
+            "error": {
+                "message": message,
+                "type": "upstream_error",
+                "code": "schema_validation_failed"
+            }
+        })),
+    )
+        .into_response()
+}
+
+/// Responses API 的 text.format 转为 Chat Completions 的 response_format (结构化输出)
+fn map_responses_text_format(body: &mut Value) {
+    if body.get("response_format").is_some() {
+        return;
+    }
+    let Some(format) = body.get("text").and_then(|t| t.get("format")).cloned() else {
+        return;
+    };
+    let response_format = match format.get("type").and_then(|t| t.as_str()) {
+        Some("json_schema") => json!({
+            "type": "json_schema",
+            "json_schema": {
+                "name": format.get("name").and_then(|n| n.as_str()).unwrap_or("response"),
+                "schema": format.get("schema").cloned(),
+                "strict": format.get("strict").and_then(|v| v.as_bool()),
+            }
+        }),
+        Some("json_object") => json!({ "type": "json_object" }),
+        _ => return,
+    };
+    body["response_format"] = response_format;
+}
+
+/// Responses API 错误响应 (OpenAI error 格式)
+fn responses_error(status: StatusCode, message: String, param: Option<&str>, code: Option<&str>) -> Response {
+    (
+        status,
+        Json(json!({
+            "error": {
+                "message": message,
+                "type": "invalid_request_error",
+                "param": param,
+                "code": code
+            }
+        })),
+    )
+        .into_response()
+}
+
+/// 将 Chat 响应转为 Responses API 的 response 对象 (非流式 /v1/responses)
+fn build_responses_object(
+    chat_resp: &OpenAIResponse,
+    previous_response_id: Option<&str>,
+) -> Value {
+    let suffix = uuid::Uuid::new_v4().simple().to_string();
+    let mut output = Vec::new();
+    if let Some(choice) = chat_resp.choices.first() {
+        let text = match &choice.message.content {
+            Some(crate::proxy::mappers::openai::OpenAIContent::String(s)) => s.clone(),
+            _ => String::new(),
+        };
+        let tool_calls = choice.message.tool_calls.as_deref().unwrap_or_default();
+        if !text.is_empty() || tool_calls.is_empty() {
+            output.push(json!({
+                "id": format!("msg-{}", &suffix[..16]),
+                "type": "message",
+                "role": "assistant",
+                "status": "completed",
+                "content": [{ "type": "output_text", "text": text, "annotations": [] }]
+            }));
+        }
+        for call in tool_calls {
+            output.push(json!({
+                "id": format!("fc-{}", call.id.trim_start_matches("call_")),
+                "type": "function_call",
+                "status": "completed",
+                "call_id": call.id,
+                "name": call.function.name,
+                "arguments": call.function.arguments
+            }));
+        }
+    }
+
+    json!({
+        "id": format!("resp-{}", suffix),
+        "object": "response",
+        "created_at": chat_resp.created,
+        "status": "completed",
+        "model": chat_resp.model,
+        "previous_response_id": previous_response_id,
+        "output": output,
+        "usage": chat_resp.usage.as_ref().map(|u| json!({
+            "input_tokens": u.prompt_tokens,
+            "output_tokens": u.completion_tokens,
+            "total_tokens": u.total_tokens
+        }))
+    })
+}
+
+/// GET /v1/responses/:id — 读取已保存的响应
+pub async fn handle_get_response(
+    axum::extract::Path(id): axum::extract::Path<String>,
+    identity: Option<axum::Extension<UserTokenIdentity>>,
+) -> Response {
+    let owner = identity.as_deref().map(|i| i.token_id.as_str());
+    match response_store::get(&id, owner).await {
+        Ok(Some(stored)) => Json(stored.response).into_response(),
+        Ok(None) => responses_error(
+            StatusCode::NOT_FOUND,
+            format!("Response with id '{}' not found.", id),
+            None,
+            None,
+        ),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+/// DELETE /v1/responses/:id — 删除已保存的响应 (之后无法再以它为 previous_response_id 续接)
+pub async fn handle_delete_response(
+    axum::extract::Path(id): axum::extract::Path<String>,
+    identity: Option<axum::Extension<UserTokenIdentity>>,
+) -> Response {
+    let owner = identity.as_deref().map(|i| i.token_id.as_str());
+    match response_store::delete(&id, owner).await {
+        Ok(true) => Json(json!({ "id": id, "object": "response.deleted", "deleted": true })).into_response(),
+        Ok(false) => responses_error(
+            StatusCode::NOT_FOUND,
+            format!("Response with id '{}' not found.", id),
+            None,
+            None,
+        ),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
     }
 }
 
@@ -756,6 +1116,7 @@ pub async fn handle_chat_completions(
 /// 将 Prompt 转换为 Chat Message 格式，复用 handle_chat_completions
 pub async fn handle_completions(
     State(state): State<AppState>,
+    identity: Option<axum::Extension<UserTokenIdentity>>,
     Json(mut body): Json<Value>,
 ) -> Response {
     debug!(
@@ -765,8 +1126,50 @@ pub async fn handle_completions(
 
     let is_codex_style = body.get("input").is_some() || body.get("instructions").is_some();
 
+    // Responses API 服务端状态：previous_response_id 续接历史，store (默认 true) 保存本次响应
+    let mut previous_response_id: Option<String> = None;
+    let mut pending_store: Option<response_store::PendingResponse> = None;
+    if is_codex_style {
+        let owner = identity.as_deref().map(|i| i.token_id.clone());
+        previous_response_id = body
+            .get("previous_response_id")
+            .and_then(|v| v.as_str())
+            .map(String::from);
+        let request_input = response_store::input_items(body.get("input"));
+        if let Some(prev) = &previous_response_id {
+            match response_store::load_history(prev, owner.as_deref()).await {
+                Ok(mut history) => {
+                    debug!(
+                        "[Codex] Restored {} items from response chain {}",
+                        history.len(),
+                        prev
+                    );
+                    history.extend(request_input.iter().cloned());
+                    body["input"] = json!(history);
+                }
+                Err(e) => {
+                    return responses_error(
+                        StatusCode::BAD_REQUEST,
+                        e,
+                        Some("previous_response_id"),
+                        Some("previous_response_not_found"),
+                    );
+                }
+            }
+        }
+        let store = body.get("store").and_then(|v| v.as_bool()).unwrap_or(true);
+        if store && response_store::is_enabled() {
+            pending_store = Some(response_store::PendingResponse {
+                previous_response_id: previous_response_id.clone(),
+                owner,
+                input: request_input,
+            });
+        }
+    }
+
     // 1. Convert Payload to Messages (Shared Chat Format)
     if is_codex_style {
+        map_responses_text_format(&mut body);
         let instructions = body
             .get("instructions")
             .and_then(|v| v.as_str())
@@ -1144,9 +1547,32 @@ pub async fn handle_completions(
         &openai_req.model,
         &*state.custom_mapping.read().await,
     );
+    if let Err(resp) = check_model_access(identity.as_deref(), &openai_req.model, &mapped_model, ClientProtocol::OpenAI) {
+        return resp;
+    }
+    let account_groups = UserTokenIdentity::account_groups_of(identity.as_deref());
     let trace_id = format!("req_{}", chrono::Utc::now().timestamp_subsec_millis());
 
+    // 结构化输出校验 (text.format 已映射为 response_format，与 handle_chat_completions 一致)
+    let output_schema = structured_output_check(&openai_req);
+    let mut schema_retries_left = output_schema.as_ref().map(|(_, r)| *r).unwrap_or(0);
+    let max_attempts = max_attempts + schema_retries_left as usize;
+
     for attempt in 0..max_attempts {
+        // 模型降级链: 原模型的账号全部被限流/配额保护时切换到备用模型 (每次重试重新判断)
+        let fallback = model_fallback::select_fallback(
+            &token_manager,
+            &*state.custom_mapping.read().await,
+            identity.as_deref(),
+            &openai_req.model,
+            &mapped_model,
+        )
+        .await;
+        let mapped_model = match &fallback {
+            Some(fb) => fb.to.clone(),
+            None => mapped_model.clone(),
+        };
+
         // 3. 模型配置解析
         // 将 OpenAI 工具转为 Value 数组以便探测联网
         let tools_val: Option<Vec<Value>> = openai_req
@@ -1177,6 +1603,7 @@ pub async fn handle_completions(
                 force_rotate,
                 session_id,
                 &mapped_model,
+                &account_groups,
             )
             .await
         {
@@ -1251,7 +1678,7 @@ pub async fn handle_completions(
         let status = response.status();
         if status.is_success() {
             // [智能限流] 请求成功，重置该账号的连续失败计数
-            token_manager.mark_account_success(&email);
+            token_manager.mark_account_success(&email, Some(&mapped_model));
 
             if list_response {
                 use axum::body::Body;
@@ -1339,16 +1766,23 @@ pub async fn handle_completions(
                         Ok::<Bytes, String>(first_data_chunk.unwrap())
                     })
                     .chain(openai_stream);
+                    let body = match pending_store.take() {
+                        Some(pending) => Body::from_stream(response_store::record_stream(
+                            Box::pin(combined_stream),
+                            pending,
+                        )),
+                        None => Body::from_stream(combined_stream),
+                    };
 
-                    return Response::builder()
+                    return model_fallback::annotate_response(Response::builder()
                         .header("Content-Type", "text/event-stream")
                         .header("Cache-Control", "no-cache")
                         .header("Connection", "keep-alive")
                         .header("X-Account-Email", &email)
                         .header("X-Mapped-Model", &mapped_model)
-                        .body(Body::from_stream(combined_stream))
+                        .body(body)
                         .unwrap()
-                        .into_response();
+                        .into_response(), fallback.as_ref());
                 } else {
                     // Forced Stream Internal -> Convert to Legacy JSON
                     // Use CHAT SSE Stream (so Collector can parse it)
@@ -1420,6 +1854,33 @@ pub async fn handle_completions(
                     use crate::proxy::mappers::openai::collector::collect_stream_to_json;
                     match collect_stream_to_json(Box::pin(combined_stream)).await {
                         Ok(chat_resp) => {
+                            if let Some((schema, _)) = &output_schema {
+                                if let Err(message) = check_structured_output(schema, &chat_resp) {
+                                    tracing::warn!("[{}] Structured output validation failed: {}", trace_id, message);
+                                    if schema_retries_left > 0 && attempt + 1 < max_attempts {
+                                        schema_retries_left -= 1;
+                                        last_error = message;
+                                        continue;
+                                    }
+                                    return schema_validation_error(&email, &mapped_model, &message);
+                                }
+                            }
+                            if is_codex_style {
+                                let resp_obj = build_responses_object(&chat_resp, previous_response_id.as_deref());
+                                if let Some(pending) = pending_store.take() {
+                                    pending.save_in_background(resp_obj.clone());
+                                }
+                                return model_fallback::annotate_response((
+                                    StatusCode::OK,
+                                    [
+                                        ("X-Account-Email", email.as_str()),
+                                        ("X-Mapped-Model", mapped_model.as_str()),
+                                    ],
+                                    Json(resp_obj),
+                                )
+                                    .into_response(), fallback.as_ref());
+                            }
+
                             // NOW: Convert Chat Response -> Legacy Response (Same logic as below)
                             let choices = chat_resp.choices.iter().map(|c| {
                                 json!({
@@ -1442,7 +1903,7 @@ pub async fn handle_completions(
                                 "usage": chat_resp.usage
                             });
 
-                            return (
+                            return model_fallback::annotate_response((
                                 StatusCode::OK,
                                 [
                                     ("X-Account-Email", email.as_str()),
@@ -1450,7 +1911,7 @@ pub async fn handle_completions(
                                 ],
                                 Json(legacy_resp),
                             )
-                                .into_response();
+                                .into_response(), fallback.as_ref());
                         }
                         Err(e) => {
                             return (
@@ -1499,7 +1960,7 @@ pub async fn handle_completions(
                 "usage": chat_resp.usage
             });
 
-            return (
+            return model_fallback::annotate_response((
                 StatusCode::OK,
                 [
                     ("X-Account-Email", email.as_str()),
@@ -1507,45 +1968,24 @@ pub async fn handle_completions(
                 ],
                 Json(legacy_resp),
             )
-                .into_response();
+                .into_response(), fallback.as_ref());
         }
 
         // Handle errors and retry
-        let status_code = status.as_u16();
-        let retry_after = response
-            .headers()
-            .get("Retry-After")
-            .and_then(|h| h.to_str().ok())
-            .map(|s| s.to_string());
-        let error_text = response
-            .text()
-            .await
-            .unwrap_or_else(|_| format!("HTTP {}", status_code));
-        last_error = format!("HTTP {}: {}", status_code, error_text);
-
-        tracing::error!(
-            "[Codex-Upstream] Error Response {}: {}",
-            status_code,
-            error_text
-        );
-
-        // 3. 标记限流状态(用于 UI 显示)
-        if status_code == 429 || status_code == 529 || status_code == 503 || status_code == 500 {
-            token_manager
-                .mark_rate_limited_async(
-                    &email,
-                    status_code,
-                    retry_after.as_deref(),
-                    &error_text,
-                    Some(&mapped_model),
-                )
-                .await;
-        }
-
-        // 确定重试策略
-        let strategy = determine_retry_strategy(status_code, &error_text, false);
+        let failure = handle_upstream_failure(
+            &token_manager,
+            response,
+            &email,
+            &mapped_model,
+            attempt,
+            max_attempts,
+            &trace_id,
+        )
+        .await;
+        let error_text = failure.error_text;
+        last_error = format!("HTTP {}: {}", status.as_u16(), error_text);
 
-        if apply_retry_strategy(strategy, attempt, max_attempts, status_code, &trace_id).await {
+        if failure.retry {
             // 继续重试 (loop 会增加 attempt, 导致 force_rotate=true)
             continue;
         } else {
@@ -1563,27 +2003,174 @@ pub async fn handle_completions(
     }
 
     // 所有尝试均失败
-    if let Some(email) = last_email {
-        (
-            StatusCode::TOO_MANY_REQUESTS,
-            [("X-Account-Email", email), ("X-Mapped-Model", mapped_model)],
-            format!("All accounts exhausted. Last error: {}", last_error),
-        )
-            .into_response()
-    } else {
-        (
-            StatusCode::TOO_MANY_REQUESTS,
-            [("X-Mapped-Model", mapped_model)],
-            format!("All accounts exhausted. Last error: {}", last_error),
+    exhausted_response(last_email, mapped_model, &last_error)
+}
+
+/// OpenAI Embeddings API: POST /v1/embeddings
+/// 转换为 Gemini batchEmbedContents，复用账号轮换与重试策略
+pub async fn handle_embeddings(
+    State(state): State<AppState>,
+    identity: Option<axum::Extension<UserTokenIdentity>>,
+    Json(body): Json<Value>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    use crate::proxy::mappers::openai::embeddings::{
+        parse_embedding_inputs, transform_embeddings_request, transform_embeddings_response,
+        EmbeddingsRequest,
+    };
+
+    let req: EmbeddingsRequest = serde_json::from_value(body)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid request: {}", e)))?;
+    if let Some(format) = req.encoding_format.as_deref() {
+        if format != "float" && format != "base64" {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("Invalid encoding_format '{}': expected 'float' or 'base64'", format),
+            ));
+        }
+    }
+    let texts = parse_embedding_inputs(&req.input).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let estimated_tokens: u32 = texts
+        .iter()
+        .map(|t| crate::proxy::mappers::context_manager::estimate_tokens_from_str(t))
+        .sum();
+
+    let trace_id = format!("embed_{}", chrono::Utc::now().timestamp_subsec_millis());
+    info!(
+        "[{}] OpenAI Embeddings Request: {} | {} inputs",
+        trace_id,
+        req.model,
+        texts.len()
+    );
+
+    let mapped_model = crate::proxy::common::model_mapping::resolve_model_route(
+        &req.model,
+        &*state.custom_mapping.read().await,
+    );
+    if let Err(resp) = check_model_access(identity.as_deref(), &req.model, &mapped_model, ClientProtocol::OpenAI) {
+        return Ok(resp);
+    }
+    let account_groups = UserTokenIdentity::account_groups_of(identity.as_deref());
+
+    let upstream = state.upstream.clone();
+    let token_manager = state.token_manager;
+    let pool_size = token_manager.len();
+    let max_attempts = MAX_RETRY_ATTEMPTS.min(pool_size.saturating_add(1)).max(2);
+
+    let mut last_error = String::new();
+    let mut last_email: Option<String> = None;
+
+    for attempt in 0..max_attempts {
+        let (access_token, project_id, email, account_id, _wait_ms) = match token_manager
+            .get_token("text", attempt > 0, None, &mapped_model, &account_groups)
+            .await
+        {
+            Ok(t) => t,
+            Err(e) => {
+                let headers = [("X-Mapped-Model", mapped_model.as_str())];
+                return Ok((
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    headers,
+                    format!("Token error: {}", e),
+                )
+                    .into_response());
+            }
+        };
+        last_email = Some(email.clone());
+        info!("✓ Using account: {} (type: embeddings)", email);
+
+        let wrapped_body = transform_embeddings_request(&req, &texts, &project_id, &mapped_model);
+        let response = match upstream
+            .call_v1_internal(
+                "batchEmbedContents",
+                &access_token,
+                wrapped_body,
+                None,
+                Some(account_id.as_str()),
+            )
+            .await
+        {
+            Ok(r) => r.response,
+            Err(e) => {
+                last_error = e.clone();
+                debug!(
+                    "Embeddings request failed on attempt {}/{}: {}",
+                    attempt + 1,
+                    max_attempts,
+                    e
+                );
+                continue;
+            }
+        };
+
+        let status = response.status();
+        if status.is_success() {
+            let gemini_resp: Value = response
+                .json()
+                .await
+                .map_err(|e| (StatusCode::BAD_GATEWAY, format!("Parse error: {}", e)))?;
+            let (openai_response, prompt_tokens) =
+                transform_embeddings_response(&gemini_resp, &req, texts.len(), estimated_tokens)
+                    .map_err(|e| (StatusCode::BAD_GATEWAY, e))?;
+
+            // 嵌入请求只有输入 Token，monitor 中间件仅在输入输出齐全时记账，这里直接写入统计
+            let stats_email = email.clone();
+            let stats_model = req.model.clone();
+            tokio::task::spawn_blocking(move || {
+                if let Err(e) = crate::modules::token_stats::record_usage(
+                    &stats_email,
+                    &stats_model,
+                    prompt_tokens,
+                    0,
+                ) {
+                    tracing::debug!("Failed to record embedding token stats: {}", e);
+                }
+            });
+
+            return Ok((
+                StatusCode::OK,
+                [
+                    ("X-Account-Email", email.as_str()),
+                    ("X-Mapped-Model", mapped_model.as_str()),
+                ],
+                Json(openai_response),
+            )
+                .into_response());
+        }
+
+        let failure = handle_upstream_failure(
+            &token_manager,
+            response,
+            &email,
+            &mapped_model,
+            attempt,
+            max_attempts,
+            &trace_id,
         )
-            .into_response()
+        .await;
+        last_error = format!("HTTP {}: {}", status.as_u16(), failure.error_text);
+        if failure.retry {
+            continue;
+        }
+
+        error!(
+            "Embeddings upstream non-retryable error {} on account {}: {}",
+            status.as_u16(), email, failure.error_text
+        );
+        return Ok(upstream_error_response(status, &email, &mapped_model, &failure.error_text));
     }
+
+    Ok(exhausted_response(last_email, mapped_model, &last_error))
 }
 
-pub async fn handle_list_models(State(state): State<AppState>) -> impl IntoResponse {
+pub async fn handle_list_models(
+    State(state): State<AppState>,
+    identity: Option<axum::Extension<UserTokenIdentity>>,
+) -> impl IntoResponse {
     use crate::proxy::common::model_mapping::get_all_dynamic_models;
 
     let model_ids = get_all_dynamic_models(&state.custom_mapping, Some(&state.token_manager)).await;
+    // 仅列出当前用户令牌允许使用的模型
+    let model_ids = filter_permitted_models(&state, identity.as_deref(), model_ids).await;
 
     let data: Vec<_> = model_ids
         .into_iter()
@@ -1608,15 +2195,17 @@ pub async fn handle_list_models(State(state): State<AppState>) -> impl IntoRespo
 pub async fn handle_chat_redirection(
     State(state): State<AppState>,
     headers: HeaderMap,
+    identity: Option<axum::Extension<UserTokenIdentity>>,
     Json(body): Json<Value>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    handle_chat_completions(State(state), headers, Json(body)).await
+    handle_chat_completions(State(state), headers, identity, Json(body)).await
 }
 
 async fn intercept_chat_to_image(
     state: AppState,
     body: Value,
     model_name: &str,
+    account_groups: &[String],
 ) -> Result<Response, (StatusCode, String)> {
     // 1. Extract prompt from messages
     let mut prompt = String::new();
@@ -1652,7 +2241,7 @@ async fn intercept_chat_to_image(
         "response_format": "url"
     });
 
-    match handle_images_generations_internal(state, img_req).await {
+    match handle_images_generations_internal(state, img_req, account_groups, None).await {
         Ok((email, img_res)) => {
             // Extract URL
             let mut img_markdown = String::new();
@@ -1738,11 +2327,98 @@ async fn intercept_chat_to_image(
     }
 }
 
+/// 图片链接的外部访问地址：优先使用 image_store.public_base_url，否则按请求头推断
+/// X-Forwarded-Host / X-Forwarded-Proto 仅在对端为受信任代理时采用，避免客户端伪造链接域名
+async fn image_base_url(
+    state: &AppState,
+    headers: &HeaderMap,
+    peer: Option<axum::extract::ConnectInfo<std::net::SocketAddr>>,
+) -> String {
+    let configured = image_store::current_config().public_base_url;
+    if !configured.trim().is_empty() {
+        return configured.trim().trim_end_matches('/').to_string();
+    }
+    let trust_forwarded = crate::proxy::middleware::ip_filter::is_trusted_peer(
+        peer.map(|info| info.0.ip()),
+        &state.security.read().await.security_monitor.trusted_proxies,
+    );
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.split(',').next())
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+    };
+    let forwarded = |name: &str| header(name).filter(|_| trust_forwarded);
+    let scheme = forwarded("x-forwarded-proto").unwrap_or("http");
+    let host = forwarded("x-forwarded-host")
+        .or_else(|| header("host"))
+        .unwrap_or("localhost");
+    format!("{}://{}", scheme, host)
+}
+
+/// response_format=url 的图片项：写入本地图片存储并返回短期链接；
+/// 存储关闭、写入失败或没有外部地址时回退为 data URI
+async fn image_url_item(mime_type: &str, data: &str, base_url: Option<&str>) -> Value {
+    if let Some(base_url) = base_url.filter(|_| image_store::current_config().enabled) {
+        // 解码与落盘 (含过期清理) 为阻塞 I/O，放到阻塞线程池执行
+        let (mime, payload) = (mime_type.to_string(), data.to_string());
+        let saved = tokio::task::spawn_blocking(move || image_store::save_base64(&mime, &payload))
+            .await
+            .unwrap_or_else(|e| Err(format!("image store task failed: {}", e)));
+        match saved {
+            Ok(id) => return json!({ "url": format!("{}/v1/files/images/{}", base_url, id) }),
+            Err(e) => tracing::warn!("[Images] Failed to store image, falling back to data URI: {}", e),
+        }
+    }
+    json!({ "url": format!("data:{};base64,{}", mime_type, data) })
+}
+
+/// GET /v1/files/images/:id — 读取生成的图片 (过期后返回 404)
+pub async fn handle_get_image(axum::extract::Path(id): axum::extract::Path<String>) -> Response {
+    let loaded = tokio::task::spawn_blocking(move || image_store::load(&id))
+        .await
+        .unwrap_or_else(|e| Err(e.to_string()));
+    match loaded {
+        Ok(Some(image)) => (
+            [
+                (axum::http::header::CONTENT_TYPE, image.mime_type.to_string()),
+                (
+                    axum::http::header::CACHE_CONTROL,
+                    format!("private, max-age={}", image.expires_in),
+                ),
+            ],
+            image.bytes,
+        )
+            .into_response(),
+        Ok(None) => (StatusCode::NOT_FOUND, "Image not found or expired").into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
 pub async fn handle_images_generations(
     State(state): State<AppState>,
+    peer: Option<axum::extract::ConnectInfo<std::net::SocketAddr>>,
+    headers: HeaderMap,
+    identity: Option<axum::Extension<UserTokenIdentity>>,
     Json(body): Json<Value>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    match handle_images_generations_internal(state, body).await {
+    let model = body
+        .get("model")
+        .and_then(|v| v.as_str())
+        .unwrap_or("gemini-3.1-flash-image");
+    let mapped = crate::proxy::common::model_mapping::resolve_model_route(
+        model,
+        &*state.custom_mapping.read().await,
+    );
+    if let Err(resp) = check_model_access(identity.as_deref(), model, &mapped, ClientProtocol::OpenAI) {
+        return Ok(resp);
+    }
+    let account_groups = UserTokenIdentity::account_groups_of(identity.as_deref());
+
+    let base_url = image_base_url(&state, &headers, peer).await;
+    match handle_images_generations_internal(state, body, &account_groups, Some(&base_url)).await {
         Ok((email_header, openai_response)) => Ok((
             StatusCode::OK,
             [
@@ -1756,9 +2432,12 @@ pub async fn handle_images_generations(
     }
 }
 
+/// `image_base_url` 为 None 时 (聊天接口内联生图) response_format=url 返回 data URI
 pub async fn handle_images_generations_internal(
     state: AppState,
     body: Value,
+    account_groups: &[String],
+    image_base_url: Option<&str>,
 ) -> Result<(String, Value), (StatusCode, String)> {
     // 1. 解析请求参数
     let prompt = body.get("prompt").and_then(|v| v.as_str()).ok_or((
@@ -1844,6 +2523,7 @@ pub async fn handle_images_generations_internal(
         let _response_format = response_format.to_string();
 
         let model_to_use = clean_model_name.clone();
+        let account_groups = account_groups.to_vec();
 
         tasks.push(tokio::spawn(async move {
             let mut last_error = String::new();
@@ -1851,7 +2531,7 @@ pub async fn handle_images_generations_internal(
             for attempt in 0..max_attempts {
                 // 4.1 获取 Token
                 let (access_token, project_id, email, account_id, _wait_ms) = match token_manager
-                    .get_token("image_gen", attempt > 0, None, &model_to_use)
+                    .get_token("image_gen", attempt > 0, None, &model_to_use, &account_groups)
                     .await
                 {
                     Ok(t) => t,
@@ -1977,9 +2657,7 @@ pub async fn handle_images_generations_internal(
                                             .get("mimeType")
                                             .and_then(|v| v.as_str())
                                             .unwrap_or("image/png");
-                                        images.push(json!({
-                                            "url": format!("data:{};base64,{}", mime_type, data)
-                                        }));
+                                        images.push(image_url_item(mime_type, data, image_base_url).await);
                                     } else {
                                         images.push(json!({
                                             "b64_json": data
@@ -2057,9 +2735,13 @@ pub async fn handle_images_generations_internal(
 
 pub async fn handle_images_edits(
     State(state): State<AppState>,
+    peer: Option<axum::extract::ConnectInfo<std::net::SocketAddr>>,
+    headers: HeaderMap,
+    identity: Option<axum::Extension<UserTokenIdentity>>,
     mut multipart: axum::extract::Multipart,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
     tracing::info!("[Images] Received edit request");
+    let base_url = image_base_url(&state, &headers, peer).await;
 
     let mut image_data = None;
     let mut mask_data = None;
@@ -2145,6 +2827,15 @@ pub async fn handle_images_edits(
         return Err((StatusCode::BAD_REQUEST, "Missing prompt".to_string()));
     }
 
+    let mapped = crate::proxy::common::model_mapping::resolve_model_route(
+        &model,
+        &*state.custom_mapping.read().await,
+    );
+    if let Err(resp) = check_model_access(identity.as_deref(), &model, &mapped, ClientProtocol::OpenAI) {
+        return Ok(resp);
+    }
+    let account_groups = UserTokenIdentity::account_groups_of(identity.as_deref());
+
     tracing::info!(
         "[Images] Edit/Ref Request: model={}, prompt={}, n={}, size={}, aspect_ratio={:?}, image_size={:?}, style={:?}, refs={}, has_main_image={}",
         model,
@@ -2239,6 +2930,7 @@ pub async fn handle_images_edits(
         let image_config = image_config.clone();
         let response_format = response_format.clone();
         let model = model.clone();
+        let account_groups = account_groups.clone();
 
         tasks.push(tokio::spawn(async move {
             let mut last_error = String::new();
@@ -2246,7 +2938,7 @@ pub async fn handle_images_edits(
             for attempt in 0..max_attempts {
                 // 4.1 获取 Token
                 let (access_token, project_id, email, account_id, _wait_ms) = match token_manager
-                    .get_token("image_gen", attempt > 0, None, "gemini-3.1-flash-image")
+                    .get_token("image_gen", attempt > 0, None, "gemini-3.1-flash-image", &account_groups)
                     .await
                 {
                     Ok(t) => t,
@@ -2373,9 +3065,7 @@ pub async fn handle_images_edits(
                                             .get("mimeType")
                                             .and_then(|v| v.as_str())
                                             .unwrap_or("image/png");
-                                        images.push(json!({
-                                            "url": format!("data:{};base64,{}", mime_type, data)
-                                        }));
+                                        images.push(image_url_item(mime_type, data, Some(&base_url)).await);
                                     } else {
                                         images.push(json!({
                                             "b64_json": data
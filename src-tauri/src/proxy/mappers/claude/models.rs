@@ -218,12 +218,33 @@ pub struct Metadata {
 }
 
 /// Output Configuration (Claude API v2.0.67+)
-/// Controls effort level for model reasoning
+/// Controls effort level for model reasoning and structured output format
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OutputConfig {
     /// Effort level: "high", "medium", "low"
     #[serde(skip_serializing_if = "Option::is_none")]
     pub effort: Option<String>,
+    /// Structured output format: {"type": "json_schema", "schema": {...}}
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<OutputFormat>,
+}
+
+impl OutputConfig {
+    /// 请求的输出 Schema (仅 json_schema 类型)
+    pub fn output_schema(&self) -> Option<&serde_json::Value> {
+        self.format
+            .as_ref()
+            .filter(|f| f.type_ == "json_schema")
+            .and_then(|f| f.schema.as_ref())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputFormat {
+    #[serde(rename = "type")]
+    pub type_: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<serde_json::Value>,
 }
 
 /// Claude API 响应
@@ -645,6 +645,7 @@ pub fn transform_claude_request_in(
                 }
 
                 gen_obj.remove("responseMimeType");
+                gen_obj.remove("responseSchema");
                 gen_obj.remove("responseModalities");
                 gen_obj.insert("imageConfig".to_string(), image_config);
             }
@@ -1980,6 +1981,11 @@ fn build_generation_config(
         tracing::debug!("[Opus-Alignment] Skipping stopSequences for Opus 4.6 to match OpenAI protocol");
     }
 
+    // 结构化输出: output_config.format (json_schema) -> responseSchema
+    if let Some(schema) = claude_req.output_config.as_ref().and_then(|c| c.output_schema()) {
+        crate::proxy::common::structured_output::apply_response_schema(&mut config, schema);
+    }
+
     config
 }
 
@@ -0,0 +1,223 @@
+// OpenAI Embeddings ↔ Gemini batchEmbedContents 转换
+
+use base64::Engine as _;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// OpenAI /v1/embeddings 请求体
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingsRequest {
+    pub model: String,
+    /// 字符串或字符串数组 (不支持 token id 数组)
+    pub input: Value,
+    #[serde(default)]
+    pub dimensions: Option<u32>,
+    /// "float" (默认) 或 "base64"
+    #[serde(default)]
+    pub encoding_format: Option<String>,
+    #[serde(default)]
+    pub user: Option<String>,
+}
+
+impl EmbeddingsRequest {
+    pub fn wants_base64(&self) -> bool {
+        self.encoding_format.as_deref() == Some("base64")
+    }
+}
+
+/// 解析 `input` 字段为文本列表
+pub fn parse_embedding_inputs(input: &Value) -> Result<Vec<String>, String> {
+    let texts = match input {
+        Value::String(s) => vec![s.clone()],
+        Value::Array(items) => {
+            let mut texts = Vec::with_capacity(items.len());
+            for item in items {
+                match item {
+                    Value::String(s) => texts.push(s.clone()),
+                    Value::Number(_) | Value::Array(_) => {
+                        return Err("Token id arrays are not supported; send input as text.".to_string())
+                    }
+                    _ => return Err("'input' must be a string or an array of strings.".to_string()),
+                }
+            }
+            texts
+        }
+        _ => return Err("'input' must be a string or an array of strings.".to_string()),
+    };
+
+    if texts.is_empty() {
+        return Err("'input' must not be empty.".to_string());
+    }
+    if texts.iter().any(|t| t.is_empty()) {
+        return Err("'input' must not contain empty strings.".to_string());
+    }
+    Ok(texts)
+}
+
+/// 构建 v1internal batchEmbedContents 请求
+pub fn transform_embeddings_request(
+    req: &EmbeddingsRequest,
+    texts: &[String],
+    project_id: &str,
+    mapped_model: &str,
+) -> Value {
+    let model_path = format!("models/{}", mapped_model);
+    let requests: Vec<Value> = texts
+        .iter()
+        .map(|text| {
+            let mut item = json!({
+                "model": model_path,
+                "content": { "parts": [{ "text": text }] }
+            });
+            if let Some(dim) = req.dimensions {
+                item["outputDimensionality"] = json!(dim);
+            }
+            item
+        })
+        .collect();
+
+    json!({
+        "project": project_id,
+        "requestId": format!("embed-{}", uuid::Uuid::new_v4()),
+        "model": mapped_model,
+        "userAgent": "antigravity",
+        "requestType": "text",
+        "request": { "requests": requests }
+    })
+}
+
+/// 将 float32 向量编码为 OpenAI 兼容的 base64 (小端序)
+fn encode_embedding_base64(values: &[f64]) -> String {
+    let mut bytes = Vec::with_capacity(values.len() * 4);
+    for v in values {
+        bytes.extend_from_slice(&(*v as f32).to_le_bytes());
+    }
+    base64::engine::general_purpose::STANDARD.encode(bytes)
+}
+
+/// 将 Gemini 响应转换为 OpenAI embeddings 响应
+///
+/// Gemini 不一定返回用量，此时使用 `estimated_tokens`。返回 (响应体, prompt_tokens)。
+pub fn transform_embeddings_response(
+    gemini_response: &Value,
+    req: &EmbeddingsRequest,
+    expected: usize,
+    estimated_tokens: u32,
+) -> Result<(Value, u32), String> {
+    let inner = gemini_response.get("response").unwrap_or(gemini_response);
+    let embeddings = inner
+        .get("embeddings")
+        .and_then(|e| e.as_array())
+        .ok_or_else(|| "Upstream response has no embeddings".to_string())?;
+    if embeddings.len() != expected {
+        return Err(format!(
+            "Upstream returned {} embeddings for {} inputs",
+            embeddings.len(),
+            expected
+        ));
+    }
+
+    let mut data = Vec::with_capacity(embeddings.len());
+    for (index, embedding) in embeddings.iter().enumerate() {
+        let values: Vec<f64> = embedding
+            .get("values")
+            .and_then(|v| v.as_array())
+            .ok_or_else(|| format!("Embedding {} has no values", index))?
+            .iter()
+            .filter_map(|v| v.as_f64())
+            .collect();
+        let vector = if req.wants_base64() {
+            json!(encode_embedding_base64(&values))
+        } else {
+            json!(values)
+        };
+        data.push(json!({
+            "object": "embedding",
+            "index": index,
+            "embedding": vector
+        }));
+    }
+
+    let prompt_tokens = inner
+        .get("usageMetadata")
+        .and_then(|u| u.get("promptTokenCount"))
+        .and_then(|v| v.as_u64())
+        .map(|v| v as u32)
+        .unwrap_or(estimated_tokens);
+
+    let body = json!({
+        "object": "list",
+        "data": data,
+        "model": req.model,
+        "usage": {
+            "prompt_tokens": prompt_tokens,
+            "total_tokens": prompt_tokens
+        }
+    });
+    Ok((body, prompt_tokens))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(input: Value, encoding_format: Option<&str>) -> EmbeddingsRequest {
+        EmbeddingsRequest {
+            model: "text-embedding-3-small".to_string(),
+            input,
+            dimensions: Some(256),
+            encoding_format: encoding_format.map(|s| s.to_string()),
+            user: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_embedding_inputs() {
+        assert_eq!(parse_embedding_inputs(&json!("hi")).unwrap(), vec!["hi"]);
+        assert_eq!(parse_embedding_inputs(&json!(["a", "b"])).unwrap(), vec!["a", "b"]);
+        assert!(parse_embedding_inputs(&json!([1, 2, 3])).is_err());
+        assert!(parse_embedding_inputs(&json!([[1, 2]])).is_err());
+        assert!(parse_embedding_inputs(&json!([])).is_err());
+        assert!(parse_embedding_inputs(&json!([""])).is_err());
+    }
+
+    #[test]
+    fn test_transform_embeddings_request() {
+        let req = request(json!(["a", "b"]), None);
+        let texts = parse_embedding_inputs(&req.input).unwrap();
+        let body = transform_embeddings_request(&req, &texts, "proj-1", "gemini-embedding-001");
+
+        assert_eq!(body["project"], "proj-1");
+        let requests = body["request"]["requests"].as_array().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1]["model"], "models/gemini-embedding-001");
+        assert_eq!(requests[1]["content"]["parts"][0]["text"], "b");
+        assert_eq!(requests[0]["outputDimensionality"], 256);
+    }
+
+    #[test]
+    fn test_transform_embeddings_response_float_and_base64() {
+        let upstream = json!({
+            "response": { "embeddings": [{ "values": [0.5, -1.0] }, { "values": [0.25, 2.0] }] }
+        });
+
+        let (body, tokens) =
+            transform_embeddings_response(&upstream, &request(json!(["a", "b"]), None), 2, 7).unwrap();
+        assert_eq!(tokens, 7);
+        assert_eq!(body["object"], "list");
+        assert_eq!(body["model"], "text-embedding-3-small");
+        assert_eq!(body["data"][1]["index"], 1);
+        assert_eq!(body["data"][0]["embedding"], json!([0.5, -1.0]));
+        assert_eq!(body["usage"]["prompt_tokens"], 7);
+
+        let (body, _) =
+            transform_embeddings_response(&upstream, &request(json!(["a", "b"]), Some("base64")), 2, 7).unwrap();
+        let encoded = body["data"][0]["embedding"].as_str().unwrap();
+        let bytes = base64::engine::general_purpose::STANDARD.decode(encoded).unwrap();
+        assert_eq!(bytes.len(), 8);
+        assert_eq!(f32::from_le_bytes(bytes[4..8].try_into().unwrap()), -1.0);
+
+        // 数量不匹配视为上游错误
+        assert!(transform_embeddings_response(&upstream, &request(json!("a"), None), 1, 1).is_err());
+    }
+}
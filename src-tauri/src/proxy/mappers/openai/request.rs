@@ -550,7 +550,9 @@ pub fn transform_openai_request(
     }
 
     if let Some(fmt) = &request.response_format {
-        if fmt.r#type == "json_object" {
+        if let Some(schema) = fmt.output_schema() {
+            crate::proxy::common::structured_output::apply_response_schema(&mut gen_config, schema);
+        } else if fmt.r#type == "json_object" || fmt.r#type == "json_schema" {
             gen_config["responseMimeType"] = json!("application/json");
         }
     }
@@ -717,6 +719,7 @@ pub fn transform_openai_request(
                 // [REMOVED] thinkingConfig 拦截已删除，允许图像生成时输出思维链
                 // gen_obj.remove("thinkingConfig");
                 gen_obj.remove("responseMimeType");
+                gen_obj.remove("responseSchema");
                 gen_obj.remove("responseModalities");
                 gen_obj.insert("imageConfig".to_string(), image_config);
             }
@@ -892,6 +895,42 @@ mod tests {
         );
     }
     
+    #[test]
+    fn test_transform_openai_request_json_schema() {
+        let req = OpenAIRequest {
+            model: "gpt-4o".to_string(),
+            messages: vec![OpenAIMessage {
+                role: "user".to_string(),
+                content: Some(OpenAIContent::String("Extract the invoice".to_string())),
+                reasoning_content: None,
+                tool_calls: None,
+                tool_call_id: None,
+                name: None,
+            }],
+            response_format: Some(ResponseFormat {
+                r#type: "json_schema".to_string(),
+                json_schema: Some(JsonSchemaFormat {
+                    name: "invoice".to_string(),
+                    description: None,
+                    schema: Some(json!({
+                        "type": "object",
+                        "properties": {"total": {"type": "number"}},
+                        "required": ["total"],
+                        "additionalProperties": false
+                    })),
+                    strict: Some(true),
+                }),
+            }),
+            ..Default::default()
+        };
+
+        let (result, _, _) = transform_openai_request(&req, "test-v", "gemini-2.5-flash", None);
+        let gen_config = &result["request"]["generationConfig"];
+        assert_eq!(gen_config["responseMimeType"], "application/json");
+        assert_eq!(gen_config["responseSchema"]["properties"]["total"]["type"], "number");
+        assert!(gen_config["responseSchema"].get("additionalProperties").is_none());
+    }
+
     #[test]
     fn test_gemini_pro_thinking_injection() {
         let req = OpenAIRequest {
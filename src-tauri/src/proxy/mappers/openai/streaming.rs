@@ -31,6 +31,25 @@ pub fn store_thought_signature(sig: &str, session_id: &str, message_count: usize
 
 
 
+/// [FIX #1575] 标准化 shell 工具参数名称
+/// Gemini 可能使用 cmd/code/script 等替代参数名，统一为 command
+fn normalize_shell_args(name: &str, args: &mut Value) {
+    if name != "shell" && name != "bash" && name != "local_shell" {
+        return;
+    }
+    if let Some(obj) = args.as_object_mut() {
+        if !obj.contains_key("command") {
+            for alt_key in &["cmd", "code", "script", "shell_command"] {
+                if let Some(val) = obj.remove(*alt_key) {
+                    obj.insert("command".to_string(), val);
+                    debug!("[OpenAI-Stream] Normalized shell arg '{}' -> 'command'", alt_key);
+                    break;
+                }
+            }
+        }
+    }
+}
+
 /// Extract and convert Gemini usageMetadata to OpenAI usage format
 fn extract_usage_metadata(u: &Value) -> Option<super::models::OpenAIUsage> {
     use super::models::{OpenAIUsage, PromptTokensDetails};
@@ -139,23 +158,8 @@ where
                                                                     emitted_tool_calls.insert(call_key);
                                                                     let name = func_call.get("name").and_then(|v| v.as_str()).unwrap_or("unknown");
                                                                     let mut args = func_call.get("args").unwrap_or(&json!({})).clone();
-                                                                    
-                                                                    // [FIX #1575] 标准化 shell 工具参数名称
-                                                                    // Gemini 可能使用 cmd/code/script 等替代参数名，统一为 command
-                                                                    if name == "shell" || name == "bash" || name == "local_shell" {
-                                                                        if let Some(obj) = args.as_object_mut() {
-                                                                            if !obj.contains_key("command") {
-                                                                                for alt_key in &["cmd", "code", "script", "shell_command"] {
-                                                                                    if let Some(val) = obj.remove(*alt_key) {
-                                                                                        obj.insert("command".to_string(), val);
-                                                                                        debug!("[OpenAI-Stream] Normalized shell arg '{}' -> 'command'", alt_key);
-                                                                                        break;
-                                                                                    }
-                                                                                }
-                                                                            }
-                                                                        }
-                                                                    }
-                                                                    
+                                                                    normalize_shell_args(name, &mut args);
+
                                                                     let args_str = serde_json::to_string(&args).unwrap_or_default();
                                                                     let mut hasher = std::collections::hash_map::DefaultHasher::new();
                                                                     use std::hash::{Hash, Hasher};
@@ -475,6 +479,7 @@ where
         yield Ok::<Bytes, String>(Bytes::from(format!("data: {}\n\n", serde_json::to_string(&content_part_added).unwrap())));
 
         let mut emitted_tool_calls = std::collections::HashSet::new();
+        let mut function_call_items: Vec<Value> = Vec::new();
         let mut accumulated_text = String::new();
         let mut heartbeat_interval = tokio::time::interval(std::time::Duration::from_secs(15));
         heartbeat_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
@@ -535,7 +540,30 @@ where
                                                         if let Some(func_call) = part.get("functionCall") {
                                                             let call_key = serde_json::to_string(func_call).unwrap_or_default();
                                                             if !emitted_tool_calls.contains(&call_key) {
+                                                                // function_call 输出项 (call_id 与 Chat 流一致，按 functionCall 内容哈希)
+                                                                let mut hasher = std::collections::hash_map::DefaultHasher::new();
+                                                                use std::hash::{Hash, Hasher};
+                                                                call_key.hash(&mut hasher);
+                                                                let call_id = format!("call_{:x}", hasher.finish());
                                                                 emitted_tool_calls.insert(call_key);
+
+                                                                let name = func_call.get("name").and_then(|v| v.as_str()).unwrap_or("unknown");
+                                                                let mut args = func_call.get("args").cloned().unwrap_or_else(|| json!({}));
+                                                                normalize_shell_args(name, &mut args);
+                                                                let fc_item = json!({
+                                                                    "id": format!("fc-{}", &call_id[5..]),
+                                                                    "type": "function_call",
+                                                                    "status": "completed",
+                                                                    "call_id": &call_id,
+                                                                    "name": name,
+                                                                    "arguments": serde_json::to_string(&args).unwrap_or_default()
+                                                                });
+                                                                let output_index = 1 + function_call_items.len();
+                                                                for ev_type in ["response.output_item.added", "response.output_item.done"] {
+                                                                    let ev = json!({ "type": ev_type, "output_index": output_index, "item": &fc_item });
+                                                                    yield Ok::<Bytes, String>(Bytes::from(format!("data: {}\n\n", serde_json::to_string(&ev).unwrap())));
+                                                                }
+                                                                function_call_items.push(fc_item);
                                                             }
                                                         }
                                                     }
@@ -633,21 +661,23 @@ where
         yield Ok::<Bytes, String>(Bytes::from(format!("data: {}\n\n", serde_json::to_string(&output_item_done).unwrap())));
 
         // 8. response.completed
+        let mut output = vec![json!({
+            "id": &item_id,
+            "type": "message",
+            "role": "assistant",
+            "content": [{
+                "type": "output_text",
+                "text": &accumulated_text
+            }]
+        })];
+        output.extend(function_call_items);
         let completed_ev = json!({
             "type": "response.completed",
             "response": {
                 "id": &response_id,
                 "object": "response",
                 "status": "completed",
-                "output": [{
-                    "id": &item_id,
-                    "type": "message",
-                    "role": "assistant",
-                    "content": [{
-                        "type": "output_text",
-                        "text": &accumulated_text
-                    }]
-                }]
+                "output": output
             }
         });
         yield Ok::<Bytes, String>(Bytes::from(format!("data: {}\n\n", serde_json::to_string(&completed_ev).unwrap())));
@@ -661,6 +691,17 @@ mod tests {
     use futures::stream;
     use serde_json::json;
 
+    #[test]
+    fn test_normalize_shell_args() {
+        let mut args = json!({ "cmd": "ls -la" });
+        normalize_shell_args("shell", &mut args);
+        assert_eq!(args, json!({ "command": "ls -la" }));
+
+        let mut other = json!({ "cmd": "ls" });
+        normalize_shell_args("read_file", &mut other);
+        assert_eq!(other, json!({ "cmd": "ls" }));
+    }
+
     #[tokio::test]
     async fn test_openai_streaming_usage_only_at_end() {
         // Chunk 1: Partial content, no usage
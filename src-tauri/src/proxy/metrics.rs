@@ -0,0 +1,357 @@
+//! Prometheus Metrics
+//! 以 Prometheus 文本格式 (text/plain; version=0.0.4) 导出反代运行指标
+
+use dashmap::DashMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
+use std::sync::OnceLock;
+
+/// 请求耗时直方图桶 (秒)
+const LATENCY_BUCKETS: [f64; 12] = [0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 20.0, 30.0, 60.0, 120.0, 300.0];
+
+static METRICS_ENABLED: AtomicBool = AtomicBool::new(false);
+static GLOBAL_METRICS: OnceLock<ProxyMetrics> = OnceLock::new();
+
+/// 全局指标注册表 (monitor 中间件写入，/metrics 读取)
+pub fn global() -> &'static ProxyMetrics {
+    GLOBAL_METRICS.get_or_init(ProxyMetrics::new)
+}
+
+pub fn set_enabled(enabled: bool) {
+    METRICS_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    METRICS_ENABLED.load(Ordering::Relaxed)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct RequestLabels {
+    protocol: String,
+    model: String,
+    mapped_model: String,
+    account: String,
+    status: String,
+}
+
+#[derive(Debug, Default)]
+struct RequestSeries {
+    count: u64,
+    duration_sum: f64,
+    /// 与 LATENCY_BUCKETS 一一对应的非累积计数
+    buckets: [u64; LATENCY_BUCKETS.len()],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct TokenLabels {
+    protocol: String,
+    model: String,
+    account: String,
+}
+
+#[derive(Debug, Default)]
+struct TokenSeries {
+    input: u64,
+    output: u64,
+}
+
+/// 单条请求的指标样本
+pub struct RequestSample<'a> {
+    pub protocol: Option<&'a str>,
+    pub model: Option<&'a str>,
+    pub mapped_model: Option<&'a str>,
+    pub account: Option<&'a str>,
+    pub status: u16,
+    pub duration_ms: u64,
+    pub input_tokens: Option<u32>,
+    pub output_tokens: Option<u32>,
+}
+
+/// 代理池节点健康状态 (抓取时由调用方提供)
+pub struct ProxyHealthSample {
+    pub id: String,
+    pub name: String,
+    pub enabled: bool,
+    pub healthy: bool,
+    pub latency_ms: Option<u64>,
+    pub in_flight: usize,
+}
+
+/// 流式响应占位，Drop 时递减 in-flight 计数
+pub struct StreamGuard;
+
+impl Drop for StreamGuard {
+    fn drop(&mut self) {
+        global().streams_in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+pub struct ProxyMetrics {
+    requests: DashMap<RequestLabels, RequestSeries>,
+    tokens: DashMap<TokenLabels, TokenSeries>,
+    streams_in_flight: AtomicI64,
+    streams_total: AtomicU64,
+}
+
+fn label_or(value: Option<&str>, fallback: &str) -> String {
+    match value {
+        Some(v) if !v.is_empty() => v.to_string(),
+        _ => fallback.to_string(),
+    }
+}
+
+/// 按 Prometheus 文本格式转义标签值
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+impl ProxyMetrics {
+    pub fn new() -> Self {
+        Self {
+            requests: DashMap::new(),
+            tokens: DashMap::new(),
+            streams_in_flight: AtomicI64::new(0),
+            streams_total: AtomicU64::new(0),
+        }
+    }
+
+    /// 记录一次已完成的请求 (计数、耗时直方图与 Token 用量)
+    pub fn record_request(&self, sample: &RequestSample) {
+        let protocol = label_or(sample.protocol, "unknown");
+        let model = label_or(sample.model, "unknown");
+        let account = label_or(sample.account, "none");
+
+        {
+            let mut series = self
+                .requests
+                .entry(RequestLabels {
+                    protocol: protocol.clone(),
+                    model: model.clone(),
+                    mapped_model: label_or(sample.mapped_model, "unknown"),
+                    account: account.clone(),
+                    status: sample.status.to_string(),
+                })
+                .or_default();
+            let seconds = sample.duration_ms as f64 / 1000.0;
+            series.count += 1;
+            series.duration_sum += seconds;
+            if let Some(idx) = LATENCY_BUCKETS.iter().position(|b| seconds <= *b) {
+                series.buckets[idx] += 1;
+            }
+        }
+
+        if sample.input_tokens.is_some() || sample.output_tokens.is_some() {
+            let mut series = self
+                .tokens
+                .entry(TokenLabels { protocol, model, account })
+                .or_default();
+            series.input += sample.input_tokens.unwrap_or(0) as u64;
+            series.output += sample.output_tokens.unwrap_or(0) as u64;
+        }
+    }
+
+    /// 标记一个流式响应开始，返回的占位需持有到流结束
+    pub fn stream_started(&self) -> StreamGuard {
+        self.streams_in_flight.fetch_add(1, Ordering::Relaxed);
+        self.streams_total.fetch_add(1, Ordering::Relaxed);
+        StreamGuard
+    }
+
+    pub fn streams_in_flight(&self) -> i64 {
+        self.streams_in_flight.load(Ordering::Relaxed)
+    }
+
+    /// 渲染 Prometheus 文本格式
+    ///
+    /// `rate_limited` 为 (原因, 账号数)，`proxies` 为代理池节点状态，二者均在抓取时实时采集。
+    pub fn render(&self, rate_limited: &[(String, usize)], proxies: &[ProxyHealthSample]) -> String {
+        let mut out = String::new();
+
+        // 1. 请求计数
+        let mut requests: Vec<_> = self
+            .requests
+            .iter()
+            .map(|e| {
+                let l = e.key();
+                let labels = format!(
+                    "protocol=\"{}\",model=\"{}\",mapped_model=\"{}\",account=\"{}\",status=\"{}\"",
+                    escape_label(&l.protocol),
+                    escape_label(&l.model),
+                    escape_label(&l.mapped_model),
+                    escape_label(&l.account),
+                    escape_label(&l.status),
+                );
+                let v = e.value();
+                (labels, v.count, v.duration_sum, v.buckets)
+            })
+            .collect();
+        requests.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let _ = writeln!(out, "# HELP antigravity_requests_total Total proxied requests.");
+        let _ = writeln!(out, "# TYPE antigravity_requests_total counter");
+        for (labels, count, _, _) in &requests {
+            let _ = writeln!(out, "antigravity_requests_total{{{}}} {}", labels, count);
+        }
+
+        // 2. 请求耗时直方图 (至响应头返回)
+        let _ = writeln!(out, "# HELP antigravity_request_duration_seconds Time until response headers were returned.");
+        let _ = writeln!(out, "# TYPE antigravity_request_duration_seconds histogram");
+        for (labels, count, sum, buckets) in &requests {
+            let mut cumulative = 0u64;
+            for (bound, n) in LATENCY_BUCKETS.iter().zip(buckets.iter()) {
+                cumulative += n;
+                let _ = writeln!(
+                    out,
+                    "antigravity_request_duration_seconds_bucket{{{},le=\"{}\"}} {}",
+                    labels, bound, cumulative
+                );
+            }
+            let _ = writeln!(out, "antigravity_request_duration_seconds_bucket{{{},le=\"+Inf\"}} {}", labels, count);
+            let _ = writeln!(out, "antigravity_request_duration_seconds_sum{{{}}} {}", labels, sum);
+            let _ = writeln!(out, "antigravity_request_duration_seconds_count{{{}}} {}", labels, count);
+        }
+
+        // 3. Token 用量
+        let mut tokens: Vec<_> = self
+            .tokens
+            .iter()
+            .map(|e| {
+                let l = e.key();
+                let labels = format!(
+                    "protocol=\"{}\",model=\"{}\",account=\"{}\"",
+                    escape_label(&l.protocol),
+                    escape_label(&l.model),
+                    escape_label(&l.account),
+                );
+                (labels, e.value().input, e.value().output)
+            })
+            .collect();
+        tokens.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let _ = writeln!(out, "# HELP antigravity_tokens_total Tokens reported by upstream responses.");
+        let _ = writeln!(out, "# TYPE antigravity_tokens_total counter");
+        for (labels, input, output) in &tokens {
+            let _ = writeln!(out, "antigravity_tokens_total{{{},type=\"input\"}} {}", labels, input);
+            let _ = writeln!(out, "antigravity_tokens_total{{{},type=\"output\"}} {}", labels, output);
+        }
+
+        // 4. 流式响应
+        let _ = writeln!(out, "# HELP antigravity_streams_in_flight Streaming responses currently being relayed.");
+        let _ = writeln!(out, "# TYPE antigravity_streams_in_flight gauge");
+        let _ = writeln!(out, "antigravity_streams_in_flight {}", self.streams_in_flight());
+        let _ = writeln!(out, "# HELP antigravity_streams_total Streaming responses started.");
+        let _ = writeln!(out, "# TYPE antigravity_streams_total counter");
+        let _ = writeln!(out, "antigravity_streams_total {}", self.streams_total.load(Ordering::Relaxed));
+
+        // 5. 限流账号
+        let _ = writeln!(out, "# HELP antigravity_rate_limited_accounts Accounts currently locked out, by reason.");
+        let _ = writeln!(out, "# TYPE antigravity_rate_limited_accounts gauge");
+        for (reason, count) in rate_limited {
+            let _ = writeln!(
+                out,
+                "antigravity_rate_limited_accounts{{reason=\"{}\"}} {}",
+                escape_label(reason),
+                count
+            );
+        }
+
+        // 6. 代理池健康
+        let _ = writeln!(out, "# HELP antigravity_proxy_pool_healthy Whether a pool proxy passed its last health check.");
+        let _ = writeln!(out, "# TYPE antigravity_proxy_pool_healthy gauge");
+        for p in proxies {
+            let _ = writeln!(
+                out,
+                "antigravity_proxy_pool_healthy{{proxy_id=\"{}\",name=\"{}\",enabled=\"{}\"}} {}",
+                escape_label(&p.id),
+                escape_label(&p.name),
+                p.enabled,
+                p.healthy as u8
+            );
+        }
+        let _ = writeln!(out, "# HELP antigravity_proxy_pool_latency_seconds Latency measured by the last health check.");
+        let _ = writeln!(out, "# TYPE antigravity_proxy_pool_latency_seconds gauge");
+        for p in proxies {
+            if let Some(ms) = p.latency_ms {
+                let _ = writeln!(
+                    out,
+                    "antigravity_proxy_pool_latency_seconds{{proxy_id=\"{}\"}} {}",
+                    escape_label(&p.id),
+                    ms as f64 / 1000.0
+                );
+            }
+        }
+        let _ = writeln!(out, "# HELP antigravity_proxy_pool_in_flight Requests currently routed through a pool proxy.");
+        let _ = writeln!(out, "# TYPE antigravity_proxy_pool_in_flight gauge");
+        for p in proxies {
+            let _ = writeln!(
+                out,
+                "antigravity_proxy_pool_in_flight{{proxy_id=\"{}\"}} {}",
+                escape_label(&p.id),
+                p.in_flight
+            );
+        }
+
+        out
+    }
+}
+
+impl Default for ProxyMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(status: u16, duration_ms: u64) -> RequestSample<'static> {
+        RequestSample {
+            protocol: Some("openai"),
+            model: Some("gpt-4"),
+            mapped_model: Some("gemini-2.5-pro"),
+            account: Some("a@example.com"),
+            status,
+            duration_ms,
+            input_tokens: Some(10),
+            output_tokens: Some(5),
+        }
+    }
+
+    #[test]
+    fn test_render_counters_and_histogram() {
+        let metrics = ProxyMetrics::new();
+        metrics.record_request(&sample(200, 300));
+        metrics.record_request(&sample(200, 4000));
+        metrics.record_request(&sample(429, 50));
+
+        let text = metrics.render(&[("QUOTA_EXHAUSTED".to_string(), 2)], &[]);
+        let ok = "protocol=\"openai\",model=\"gpt-4\",mapped_model=\"gemini-2.5-pro\",account=\"a@example.com\",status=\"200\"";
+
+        assert!(text.contains(&format!("antigravity_requests_total{{{}}} 2", ok)));
+        assert!(text.contains(&format!("antigravity_request_duration_seconds_bucket{{{},le=\"0.25\"}} 0", ok)));
+        assert!(text.contains(&format!("antigravity_request_duration_seconds_bucket{{{},le=\"0.5\"}} 1", ok)));
+        assert!(text.contains(&format!("antigravity_request_duration_seconds_bucket{{{},le=\"5\"}} 2", ok)));
+        assert!(text.contains(&format!("antigravity_request_duration_seconds_bucket{{{},le=\"+Inf\"}} 2", ok)));
+        assert!(text.contains("status=\"429\"} 1"));
+        assert!(text.contains(
+            "antigravity_tokens_total{protocol=\"openai\",model=\"gpt-4\",account=\"a@example.com\",type=\"input\"} 30"
+        ));
+        assert!(text.contains("antigravity_rate_limited_accounts{reason=\"QUOTA_EXHAUSTED\"} 2"));
+    }
+
+    #[test]
+    fn test_stream_guard_and_label_escaping() {
+        let metrics = global();
+        let before = metrics.streams_in_flight();
+        let guard = metrics.stream_started();
+        assert_eq!(metrics.streams_in_flight(), before + 1);
+        drop(guard);
+        assert_eq!(metrics.streams_in_flight(), before);
+
+        assert_eq!(escape_label("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+}
@@ -0,0 +1,96 @@
+// 管理接口审计中间件
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::Method,
+    middleware::Next,
+    response::Response,
+};
+use bytes::{Bytes, BytesMut};
+use futures::StreamExt;
+
+use crate::modules::audit_log::{self, AuditEntry};
+use crate::proxy::middleware::auth::AdminActor;
+use crate::proxy::server::AppState;
+
+/// 审计层运行在鉴权之前，只预读这么多字节用于生成摘要，其余部分原样流式转发
+const MAX_AUDIT_BODY_SIZE: usize = 16 * 1024; // 16KB
+
+/// 记录管理接口的变更操作 (非 GET/HEAD/OPTIONS)
+///
+/// 需要挂在 admin_auth 之外，这样鉴权失败的尝试也会以 `unauthenticated` 记录。
+pub async fn audit_middleware(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let method = request.method().clone();
+    if matches!(method, Method::GET | Method::HEAD | Method::OPTIONS) || !audit_log::is_enabled() {
+        return next.run(request).await;
+    }
+
+    let trusted_proxies = state.security.read().await.security_monitor.trusted_proxies.clone();
+    let source_ip = crate::proxy::middleware::ip_filter::extract_client_ip(&request, &trusted_proxies);
+    let user_agent = request
+        .headers()
+        .get("user-agent")
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+    // nest("/api") 后 uri 已去掉前缀，这里补回完整路径
+    let endpoint = format!("/api{}", request.uri().path());
+
+    let (parts, body) = request.into_parts();
+    let mut stream = body.into_data_stream();
+    let mut head = BytesMut::new();
+    let mut complete = true;
+    while head.len() <= MAX_AUDIT_BODY_SIZE {
+        match stream.next().await {
+            Some(Ok(chunk)) => head.extend_from_slice(&chunk),
+            Some(Err(e)) => {
+                // 读取失败交由下游处理，这里只放弃摘要
+                tracing::warn!("[Audit] Failed to read request body for {}: {}", endpoint, e);
+                complete = false;
+                break;
+            }
+            None => break,
+        }
+    }
+    let complete = complete && head.len() <= MAX_AUDIT_BODY_SIZE;
+    let head = head.freeze();
+    // 不完整的 JSON 无法脱敏，超限的请求体只记录大小下限
+    let payload = if complete {
+        audit_log::summarize_payload(&head)
+    } else {
+        Some(format!("[body larger than {} bytes, not captured]", MAX_AUDIT_BODY_SIZE))
+    };
+    let body = futures::stream::once(async move { Ok::<Bytes, axum::Error>(head) }).chain(stream);
+    let request = Request::from_parts(parts, Body::from_stream(body));
+
+    let response = next.run(request).await;
+
+    let status = response.status();
+    let actor = response
+        .extensions()
+        .get::<AdminActor>()
+        .map(|a| a.0.clone())
+        .unwrap_or_else(|| "unauthenticated".to_string());
+    let entry = AuditEntry {
+        id: 0,
+        timestamp: chrono::Utc::now().timestamp(),
+        actor,
+        source_ip,
+        method: method.to_string(),
+        endpoint,
+        payload,
+        status: status.as_u16(),
+        success: status.is_success(),
+        user_agent,
+    };
+    tokio::task::spawn_blocking(move || {
+        if let Err(e) = audit_log::record(&entry) {
+            tracing::error!("[Audit] Failed to record entry: {}", e);
+        }
+    });
+
+    response
+}
@@ -4,11 +4,12 @@ use axum::{
     extract::Request,
     http::{header, StatusCode},
     middleware::Next,
-    response::Response,
+    response::{IntoResponse, Response},
 };
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+use crate::modules::admin_key_db::{self, AdminKey, AdminScope};
 use crate::proxy::{ProxyAuthMode, ProxySecurityConfig};
 
 /// API Key 认证中间件 (代理接口使用，遵循 auth_mode)
@@ -75,18 +76,9 @@ async fn auth_middleware_internal(
                 });
             
             if let Some(token) = api_key {
-                // 尝试验证是否为 User Token（不阻止请求，只记录）
+                // 尝试验证是否为 User Token（不阻止未携带令牌的请求，但令牌自身的预算/RPM/并发限制照常生效）
                 if let Ok(Some(user_token)) = crate::modules::user_token_db::get_token_by_value(token) {
-                    let identity = UserTokenIdentity {
-                        token_id: user_token.id,
-                        token: user_token.token,
-                        username: user_token.username,
-                    };
-                    // 注入 identity 到请求
-                    let (mut parts, body) = request.into_parts();
-                    parts.extensions.insert(identity);
-                    let request = Request::from_parts(parts, body);
-                    return Ok(next.run(request).await);
+                    return Ok(run_with_user_token(user_token, request, next).await);
                 }
             }
             
@@ -106,7 +98,8 @@ async fn auth_middleware_internal(
         // 管理接口 (/api/*)
         // 1. 如果全局鉴权关闭，则管理接口也放行 (除非是强制局域网模式)
         if matches!(effective_mode, ProxyAuthMode::Off) {
-            return Ok(next.run(request).await);
+            let response = next.run(request).await;
+            return Ok(with_admin_actor(response, "anonymous"));
         }
 
         // 2. 健康检查在所有模式下对管理接口放行
@@ -134,6 +127,17 @@ async fn auth_middleware_internal(
                 .and_then(|h| h.to_str().ok())
         });
 
+    // 具名管理密钥：按路由校验权限范围
+    if force_strict {
+        let admin_key = match api_key {
+            Some(secret) => lookup_admin_key(secret).await,
+            None => None,
+        };
+        if let Some(key) = admin_key {
+            return Ok(authorize_admin_key(key, request, next).await);
+        }
+    }
+
     if security.api_key.is_empty() && (security.admin_password.is_none() || security.admin_password.as_ref().unwrap().is_empty()) {
         if force_strict {
              tracing::error!("Admin auth is required but both api_key and admin_password are empty; denying request");
@@ -144,10 +148,12 @@ async fn auth_middleware_internal(
     }
 
     // 认证逻辑
+    let mut admin_actor = "api_key";
     let authorized = if force_strict {
         // 管理接口：优先使用独立的 admin_password，如果没有则回退使用 api_key
         match &security.admin_password {
             Some(pwd) if !pwd.is_empty() => {
+                admin_actor = "admin_password";
                 api_key.map(|k| k == pwd).unwrap_or(false)
             }
             _ => {
@@ -161,50 +167,29 @@ async fn auth_middleware_internal(
     };
 
     if authorized {
-        Ok(next.run(request).await)
+        let response = next.run(request).await;
+        if force_strict {
+            Ok(with_admin_actor(response, admin_actor))
+        } else {
+            Ok(response)
+        }
     } else if !force_strict && api_key.is_some() {
         // 尝试验证 UserToken
         let token = api_key.unwrap();
         
-        // 提取 IP (复用逻辑)
-        let client_ip = request
-            .headers()
-            .get("x-forwarded-for")
-            .and_then(|v| v.to_str().ok())
-            .map(|s| s.split(',').next().unwrap_or(s).trim().to_string())
-            .or_else(|| {
-                request
-                    .headers()
-                    .get("x-real-ip")
-                    .and_then(|v| v.to_str().ok())
-                    .map(|s| s.to_string())
-            })
-            .unwrap_or_else(|| "127.0.0.1".to_string()); // Default fallback
+        // 提取 IP (复用 ip_filter 的受信任代理解析逻辑)
+        let client_ip = crate::proxy::middleware::ip_filter::extract_client_ip(
+            &request,
+            &security.security_monitor.trusted_proxies,
+        )
+        .unwrap_or_else(|| "127.0.0.1".to_string()); // Default fallback
 
         // 验证 Token
         match crate::modules::user_token_db::validate_token(token, &client_ip) {
             Ok((true, _)) => {
                 // Token 有效，查询信息以便传递
                 if let Ok(Some(user_token)) = crate::modules::user_token_db::get_token_by_value(token) {
-                     let identity = UserTokenIdentity {
-                        token_id: user_token.id,
-                        token: user_token.token,
-                        username: user_token.username,
-                    };
-                    
-                    // [FIX] 将身份信息注入到请求 extensions 中，而不是响应
-                    // 这样 monitor_middleware 在处理请求时就能获取到 identity
-                    // 因为中间件执行顺序：auth (外层) -> monitor (内层) -> handler
-                    // 响应返回时：handler -> monitor -> auth
-                    // 如果注入到 response，monitor 执行时 identity 还不存在
-                    let (mut parts, body) = request.into_parts();
-                    parts.extensions.insert(identity);
-                    let request = Request::from_parts(parts, body);
-                    
-                    // 执行请求
-                    let response = next.run(request).await;
-                    
-                    Ok(response)
+                    Ok(run_with_user_token(user_token, request, next).await)
                 } else {
                     Err(StatusCode::UNAUTHORIZED)
                 }
@@ -236,6 +221,229 @@ async fn auth_middleware_internal(
     }
 }
 
+async fn lookup_admin_key(secret: &str) -> Option<AdminKey> {
+    if !secret.starts_with(admin_key_db::KEY_PREFIX) {
+        return None;
+    }
+    let secret = secret.to_string();
+    match tokio::task::spawn_blocking(move || admin_key_db::authenticate(&secret))
+        .await
+        .unwrap_or_else(|e| Err(e.to_string()))
+    {
+        Ok(key) => key,
+        Err(e) => {
+            tracing::error!("Admin key lookup error: {}", e);
+            None
+        }
+    }
+}
+
+/// 校验管理密钥是否具备当前路由所需的权限范围
+async fn authorize_admin_key(key: AdminKey, request: Request, next: Next) -> Response {
+    let actor = format!("admin_key:{}", key.name);
+    let access = required_admin_access(request.method(), request.uri().path());
+    let denied = match access {
+        AdminAccess::Public => None,
+        AdminAccess::Scope(scope) if key.has_scope(scope) => None,
+        AdminAccess::Scope(scope) => Some(format!("Admin key lacks required scope: {}", scope.as_str())),
+        AdminAccess::MasterOnly => Some("This endpoint requires the admin password".to_string()),
+    };
+
+    if let Some(message) = denied {
+        tracing::warn!(
+            "Admin key '{}' denied: {} {} ({})",
+            key.name,
+            request.method(),
+            request.uri().path(),
+            message
+        );
+        let response = (
+            StatusCode::FORBIDDEN,
+            axum::Json(serde_json::json!({ "error": message })),
+        )
+            .into_response();
+        return with_admin_actor(response, &actor);
+    }
+
+    let (mut parts, body) = request.into_parts();
+    parts.extensions.insert(ScopedAdmin(key));
+    let response = next.run(Request::from_parts(parts, body)).await;
+    with_admin_actor(response, &actor)
+}
+
+/// 管理接口路由所需的访问级别
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AdminAccess {
+    /// 无需权限范围 (健康检查)
+    Public,
+    Scope(AdminScope),
+    /// 仅管理密码 / api_key 可访问 (管理密钥自身的管理等)
+    MasterOnly,
+}
+
+/// 根据方法与路径 (nest 后不含 /api 前缀) 计算所需的访问级别
+/// GET/HEAD 为读，其余方法为写；未归类的路由默认仅限主凭据
+pub fn required_admin_access(method: &axum::http::Method, path: &str) -> AdminAccess {
+    let path = path.strip_prefix("/api").filter(|p| p.starts_with('/')).unwrap_or(path);
+    let is_read = matches!(*method, axum::http::Method::GET | axum::http::Method::HEAD);
+    let scoped = |read: AdminScope, write: AdminScope| {
+        AdminAccess::Scope(if is_read { read } else { write })
+    };
+    let accounts = scoped(AdminScope::AccountsRead, AdminScope::AccountsWrite);
+    let config = scoped(AdminScope::ConfigRead, AdminScope::ConfigWrite);
+    let logs = scoped(AdminScope::LogsRead, AdminScope::LogsWrite);
+
+    let mut segments = path.trim_start_matches('/').split('/');
+    let (first, second, third) = (
+        segments.next().unwrap_or(""),
+        segments.next().unwrap_or(""),
+        segments.next().unwrap_or(""),
+    );
+    match (first, second) {
+        // CLI 配置内容包含主 api_key，仅限主凭据
+        ("proxy", "cli" | "opencode" | "droid") if third == "config" => AdminAccess::MasterOnly,
+        ("health", _) => AdminAccess::Public,
+        ("accounts" | "account-groups" | "auth", _) => accounts,
+        ("logs" | "stats" | "audit" | "events", _) | ("debug", "logs") => logs,
+        ("security", _) => scoped(AdminScope::SecurityRead, AdminScope::SecurityWrite),
+        ("user-tokens", _) => scoped(AdminScope::UserTokensRead, AdminScope::UserTokensWrite),
+        ("proxy", "pool" | "rate-limits" | "preferred-account" | "session-bindings") => accounts,
+        ("proxy", "stats") => logs,
+        ("config" | "system" | "debug" | "zai" | "proxy", _) => config,
+        _ => AdminAccess::MasterOnly,
+    }
+}
+
+/// 在响应中标记管理接口调用者 (供审计中间件读取)
+fn with_admin_actor(mut response: Response, actor: &str) -> Response {
+    response.extensions_mut().insert(AdminActor(actor.to_string()));
+    response
+}
+
+/// 以用户令牌身份执行请求：先检查预算 / RPM / 并发限制 (超限返回 429 + Retry-After)，再注入身份
+async fn run_with_user_token(
+    user_token: crate::modules::user_token_db::UserToken,
+    request: Request,
+    next: Next,
+) -> Response {
+    let guard = match enforce_token_limits(&user_token).await {
+        Ok(guard) => guard,
+        Err(response) => return response,
+    };
+
+    let identity = UserTokenIdentity {
+        token_id: user_token.id,
+        token: user_token.token,
+        username: user_token.username,
+        allowed_models: user_token.allowed_models,
+        denied_models: user_token.denied_models,
+        account_groups: user_token.account_groups,
+    };
+
+    // [FIX] 将身份信息注入到请求 extensions 中，而不是响应
+    // 这样 monitor_middleware 在处理请求时就能获取到 identity
+    // 因为中间件执行顺序：auth (外层) -> monitor (内层) -> handler
+    // 响应返回时：handler -> monitor -> auth
+    // 如果注入到 response，monitor 执行时 identity 还不存在
+    let (mut parts, body) = request.into_parts();
+    parts.extensions.insert(identity);
+    let request = Request::from_parts(parts, body);
+
+    // 执行请求 (并发占位持有到响应体传输结束)
+    let response = next.run(request).await;
+    attach_concurrency_guard(response, guard)
+}
+
+/// 检查用户令牌的 Token 预算、RPM 与并发限制
+async fn enforce_token_limits(
+    user_token: &crate::modules::user_token_db::UserToken,
+) -> Result<crate::proxy::middleware::token_limiter::ConcurrencyGuard, Response> {
+    // 预算统计是同步 SQLite 聚合查询，放到阻塞线程池执行，避免占用异步工作线程
+    let budget_token = user_token.clone();
+    let budget = tokio::task::spawn_blocking(move || {
+        crate::modules::user_token_db::check_token_budget(&budget_token)
+    })
+    .await
+    .unwrap_or_else(|e| Err(format!("budget check task failed: {}", e)));
+
+    match budget {
+        Ok(Some(exceeded)) => {
+            tracing::warn!(
+                "UserToken {} exceeded {} budget ({}/{})",
+                user_token.username,
+                exceeded.period.as_str(),
+                exceeded.used,
+                exceeded.limit
+            );
+            return Err(too_many_requests_response(
+                format!(
+                    "Token budget exceeded: {} tokens used of the {} limit of {}.",
+                    exceeded.used,
+                    exceeded.period.as_str(),
+                    exceeded.limit
+                ),
+                "token_budget_exceeded",
+                exceeded.retry_after as u64,
+            ));
+        }
+        Ok(None) => {}
+        Err(e) => {
+            // 统计查询失败时不阻断请求
+            tracing::error!("UserToken budget check error: {}", e);
+        }
+    }
+
+    crate::proxy::middleware::token_limiter::global_limiter()
+        .try_acquire(&user_token.id, user_token.limits.max_rpm, user_token.limits.max_concurrent)
+        .map_err(|rejection| {
+            tracing::warn!("UserToken {} rate limited: {:?}", user_token.username, rejection);
+            let code = match rejection {
+                crate::proxy::middleware::token_limiter::LimitRejection::Rpm { .. } => "token_rate_limited",
+                crate::proxy::middleware::token_limiter::LimitRejection::Concurrency { .. } => "token_concurrency_limited",
+            };
+            too_many_requests_response(rejection.message(), code, rejection.retry_after())
+        })
+}
+
+fn too_many_requests_response(message: String, code: &str, retry_after: u64) -> Response {
+    let body = serde_json::json!({
+        "error": {
+            "message": message,
+            "type": "rate_limit_error",
+            "code": code
+        }
+    });
+    axum::response::Response::builder()
+        .status(StatusCode::TOO_MANY_REQUESTS)
+        .header("Content-Type", "application/json")
+        .header(header::RETRY_AFTER, retry_after.to_string())
+        .body(axum::body::Body::from(body.to_string()))
+        .unwrap()
+}
+
+/// 将并发占位绑定到响应体，流式响应结束或被丢弃时才释放
+fn attach_concurrency_guard(
+    response: Response,
+    guard: crate::proxy::middleware::token_limiter::ConcurrencyGuard,
+) -> Response {
+    use futures::StreamExt;
+
+    let (parts, body) = response.into_parts();
+    let stream = body.into_data_stream().map(move |chunk| {
+        let _ = &guard;
+        chunk
+    });
+    Response::from_parts(parts, axum::body::Body::from_stream(stream))
+}
+
+/// 通过具名管理密钥访问时注入到请求 extensions (主凭据访问时不存在)
+#[derive(Clone, Debug)]
+pub struct ScopedAdmin(pub AdminKey);
+
+/// 管理接口调用者 (admin_password / api_key / anonymous / admin_key:<名称>)，注入到响应 extensions
+#[derive(Clone, Debug)]
+pub struct AdminActor(pub String);
+
 /// 用户令牌身份信息 (传递给 Monitor 使用)
 #[derive(Clone, Debug)]
 pub struct UserTokenIdentity {
@@ -243,6 +451,29 @@ pub struct UserTokenIdentity {
     #[allow(dead_code)] // 保留原始 token 便于审计/调试
     pub token: String,
     pub username: String,
+    /// 模型白名单 (为空表示不限制)
+    pub allowed_models: Vec<String>,
+    /// 模型黑名单 (优先于白名单)
+    pub denied_models: Vec<String>,
+    /// 可调度的账号分组 (为空表示使用全部账号)
+    pub account_groups: Vec<String>,
+}
+
+impl UserTokenIdentity {
+    /// 调用方可调度的账号分组 (无用户令牌或未绑定分组时为空，表示全部账号)
+    pub fn account_groups_of(identity: Option<&Self>) -> Vec<String> {
+        identity.map(|i| i.account_groups.clone()).unwrap_or_default()
+    }
+
+    /// 检查该令牌是否允许使用模型 (原始模型名与路由后的模型名)
+    pub fn is_model_permitted(&self, original_model: &str, mapped_model: &str) -> bool {
+        crate::proxy::common::model_mapping::is_model_permitted(
+            &self.allowed_models,
+            &self.denied_models,
+            original_model,
+            mapped_model,
+        )
+    }
 }
 
 #[cfg(test)]
@@ -272,6 +503,47 @@ mod tests {
         // 我们在 auth_middleware_internal 基础上做了逻辑校验即可
     }
 
+    #[test]
+    fn test_required_admin_access() {
+        use axum::http::Method;
+
+        assert_eq!(required_admin_access(&Method::GET, "/health"), AdminAccess::Public);
+        assert_eq!(
+            required_admin_access(&Method::GET, "/stats/token/daily"),
+            AdminAccess::Scope(AdminScope::LogsRead)
+        );
+        assert_eq!(
+            required_admin_access(&Method::DELETE, "/accounts/abc"),
+            AdminAccess::Scope(AdminScope::AccountsWrite)
+        );
+        assert_eq!(
+            required_admin_access(&Method::POST, "/api/config"),
+            AdminAccess::Scope(AdminScope::ConfigWrite)
+        );
+        assert_eq!(
+            required_admin_access(&Method::GET, "/proxy/rate-limits"),
+            AdminAccess::Scope(AdminScope::AccountsRead)
+        );
+        assert_eq!(
+            required_admin_access(&Method::GET, "/debug/logs"),
+            AdminAccess::Scope(AdminScope::LogsRead)
+        );
+        assert_eq!(
+            required_admin_access(&Method::PATCH, "/user-tokens/t1"),
+            AdminAccess::Scope(AdminScope::UserTokensWrite)
+        );
+        assert_eq!(required_admin_access(&Method::POST, "/admin-keys"), AdminAccess::MasterOnly);
+        assert_eq!(
+            required_admin_access(&Method::POST, "/proxy/opencode/config"),
+            AdminAccess::MasterOnly
+        );
+        assert_eq!(
+            required_admin_access(&Method::POST, "/proxy/cli/status"),
+            AdminAccess::Scope(AdminScope::ConfigWrite)
+        );
+        assert_eq!(required_admin_access(&Method::GET, "/unknown"), AdminAccess::MasterOnly);
+    }
+
     #[test]
     fn test_auth_placeholder() {
         assert!(true);
@@ -4,6 +4,8 @@ use axum::{
     response::{IntoResponse, Response},
     http::StatusCode,
 };
+use axum::http::HeaderMap;
+use std::net::IpAddr;
 use crate::proxy::server::AppState;
 use crate::modules::security_db;
 
@@ -13,15 +15,15 @@ pub async fn ip_filter_middleware(
     request: Request,
     next: Next,
 ) -> Response {
+    // 读取安全配置 (克隆后立即释放读锁，避免请求处理期间阻塞热更新)
+    let security_monitor = state.security.read().await.security_monitor.clone();
+
     // 提取客户端 IP
-    let client_ip = extract_client_ip(&request);
+    let client_ip = extract_client_ip(&request, &security_monitor.trusted_proxies);
     
     if let Some(ip) = &client_ip {
-        // 读取安全配置
-        let security_config = state.security.read().await;
-        
         // 1. 检查白名单 (如果启用白名单模式,只允许白名单 IP)
-        if security_config.security_monitor.whitelist.enabled {
+        if security_monitor.whitelist.enabled {
             match security_db::is_ip_in_whitelist(ip) {
                 Ok(true) => {
                     // 在白名单中,直接放行
@@ -42,7 +44,7 @@ pub async fn ip_filter_middleware(
             }
         } else {
             // 白名单优先模式: 如果在白名单中,跳过黑名单检查
-            if security_config.security_monitor.whitelist.whitelist_priority {
+            if security_monitor.whitelist.whitelist_priority {
                 match security_db::is_ip_in_whitelist(ip) {
                     Ok(true) => {
                         tracing::debug!("[IP Filter] IP {} is in whitelist (priority mode), skipping blacklist check", ip);
@@ -59,7 +61,7 @@ pub async fn ip_filter_middleware(
         }
 
         // 2. 检查黑名单
-        if security_config.security_monitor.blacklist.enabled {
+        if security_monitor.blacklist.enabled {
             match security_db::get_blacklist_entry_for_ip(ip) {
                 Ok(Some(entry)) => {
                     tracing::warn!("[IP Filter] IP {} is in blacklist, blocking", ip);
@@ -144,29 +146,212 @@ pub async fn ip_filter_middleware(
 }
 
 /// 从请求中提取客户端 IP
-fn extract_client_ip(request: &Request) -> Option<String> {
-    // 1. 优先从 X-Forwarded-For 提取 (取第一个 IP)
-    request
-        .headers()
-        .get("x-forwarded-for")
+///
+/// 仅当 TCP 对端地址属于 `trusted_proxies` 时才信任转发头，
+/// 否则直接返回 TCP 对端地址 (ConnectInfo)。
+pub fn extract_client_ip(request: &Request, trusted_proxies: &[String]) -> Option<String> {
+    let peer = request
+        .extensions()
+        .get::<axum::extract::ConnectInfo<std::net::SocketAddr>>()
+        .map(|info| info.0.ip());
+    resolve_client_ip(request.headers(), peer, trusted_proxies)
+}
+
+/// 根据 TCP 对端地址与转发头解析真实客户端 IP
+///
+/// 解析顺序 (仅对端受信任时):
+/// 1. RFC 7239 `Forwarded` 的 `for=` 链
+/// 2. `X-Forwarded-For` 链
+/// 3. `X-Real-IP`
+///
+/// 转发链从右向左遍历，跳过受信任代理，返回第一个不受信任的地址；
+/// 遇到无法解析的条目 (如 `unknown`、混淆标识) 时停止，返回最近一跳受信任代理。
+pub fn resolve_client_ip(
+    headers: &HeaderMap,
+    peer: Option<IpAddr>,
+    trusted_proxies: &[String],
+) -> Option<String> {
+    let peer = match peer {
+        Some(ip) => normalize_ip(ip),
+        None => return None,
+    };
+
+    if !is_trusted_proxy(&peer, trusted_proxies) {
+        return Some(peer.to_string());
+    }
+
+    let chain = forwarded_chain(headers).or_else(|| x_forwarded_for_chain(headers));
+
+    if let Some(chain) = chain {
+        let mut nearest = peer;
+        for entry in chain.iter().rev() {
+            match entry {
+                Some(ip) => {
+                    if !is_trusted_proxy(ip, trusted_proxies) {
+                        return Some(ip.to_string());
+                    }
+                    nearest = *ip;
+                }
+                None => break,
+            }
+        }
+        return Some(nearest.to_string());
+    }
+
+    if let Some(ip) = headers
+        .get("x-real-ip")
         .and_then(|v| v.to_str().ok())
-        .map(|s| s.split(',').next().unwrap_or(s).trim().to_string())
-        .or_else(|| {
-            // 2. 备选从 X-Real-IP 提取
-            request
-                .headers()
-                .get("x-real-ip")
-                .and_then(|v| v.to_str().ok())
-                .map(|s| s.to_string())
-        })
-        .or_else(|| {
-            // 3. 最后尝试从 ConnectInfo 获取 (TCP 连接 IP)
-            // 这可以解决本地开发/测试时没有代理头导致 IP 获取失败的问题
-            request
-                .extensions()
-                .get::<axum::extract::ConnectInfo<std::net::SocketAddr>>()
-                .map(|info| info.0.ip().to_string())
-        })
+        .and_then(parse_forwarded_node)
+    {
+        return Some(ip.to_string());
+    }
+
+    Some(peer.to_string())
+}
+
+/// 判断 IP 是否属于受信任代理列表 (支持单个 IP 与 IPv4/IPv6 CIDR)
+pub fn is_trusted_proxy(ip: &IpAddr, trusted_proxies: &[String]) -> bool {
+    trusted_proxies
+        .iter()
+        .any(|pattern| ip_matches_pattern(ip, pattern.trim()))
+}
+
+/// TCP 对端是否为受信任代理 (对端未知时视为不受信任)
+pub fn is_trusted_peer(peer: Option<IpAddr>, trusted_proxies: &[String]) -> bool {
+    peer.map(|ip| is_trusted_proxy(&normalize_ip(ip), trusted_proxies))
+        .unwrap_or(false)
+}
+
+/// 校验单个 IP 或 CIDR 规则是否可解析 (用于配置校验)
+pub fn is_valid_ip_pattern(pattern: &str) -> bool {
+    let pattern = pattern.trim();
+    let (network, prefix_len) = match pattern.split_once('/') {
+        Some((net, len)) => match len.trim().parse::<u8>() {
+            Ok(len) => (net.trim(), Some(len)),
+            Err(_) => return false,
+        },
+        None => (pattern, None),
+    };
+    match network.parse::<IpAddr>() {
+        Ok(IpAddr::V4(_)) => prefix_len.map_or(true, |len| len <= 32),
+        Ok(IpAddr::V6(_)) => prefix_len.map_or(true, |len| len <= 128),
+        Err(_) => false,
+    }
+}
+
+fn ip_matches_pattern(ip: &IpAddr, pattern: &str) -> bool {
+    let (network, prefix_len) = match pattern.split_once('/') {
+        Some((net, len)) => match len.trim().parse::<u8>() {
+            Ok(len) => (net.trim(), Some(len)),
+            Err(_) => return false,
+        },
+        None => (pattern, None),
+    };
+
+    let network = match network.parse::<IpAddr>() {
+        Ok(net) => normalize_ip(net),
+        Err(_) => return false,
+    };
+
+    match (ip, network) {
+        (IpAddr::V4(ip), IpAddr::V4(net)) => {
+            let len = prefix_len.unwrap_or(32);
+            if len > 32 {
+                return false;
+            }
+            let mask = if len == 0 { 0 } else { u32::MAX << (32 - len) };
+            (u32::from(*ip) & mask) == (u32::from(net) & mask)
+        }
+        (IpAddr::V6(ip), IpAddr::V6(net)) => {
+            let len = prefix_len.unwrap_or(128);
+            if len > 128 {
+                return false;
+            }
+            let mask = if len == 0 { 0 } else { u128::MAX << (128 - len) };
+            (u128::from(*ip) & mask) == (u128::from(net) & mask)
+        }
+        _ => false,
+    }
+}
+
+/// 将 IPv4-mapped IPv6 地址 (::ffff:a.b.c.d) 还原为 IPv4
+fn normalize_ip(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6
+            .to_ipv4_mapped()
+            .map(IpAddr::V4)
+            .unwrap_or(IpAddr::V6(v6)),
+        v4 => v4,
+    }
+}
+
+/// 解析 `X-Forwarded-For` (可能存在多个同名头，按出现顺序拼接)
+///
+/// 含非 ASCII 字节的条目视为无法解析的节点 (遍历到此停止)，不影响同一头中的其他条目
+fn x_forwarded_for_chain(headers: &HeaderMap) -> Option<Vec<Option<IpAddr>>> {
+    let mut chain = Vec::new();
+    for value in headers.get_all("x-forwarded-for") {
+        for element in value.as_bytes().split(|b| *b == b',') {
+            if !element.is_ascii() {
+                chain.push(None);
+                continue;
+            }
+            let element = std::str::from_utf8(element).unwrap_or_default().trim();
+            if !element.is_empty() {
+                chain.push(parse_forwarded_node(element));
+            }
+        }
+    }
+    if chain.is_empty() {
+        None
+    } else {
+        Some(chain)
+    }
+}
+
+/// 解析 RFC 7239 `Forwarded` 头中的 `for=` 参数
+fn forwarded_chain(headers: &HeaderMap) -> Option<Vec<Option<IpAddr>>> {
+    let mut chain = Vec::new();
+    for value in headers.get_all("forwarded") {
+        let value = value.to_str().ok()?;
+        for element in value.split(',') {
+            let node = element.split(';').find_map(|pair| {
+                let (key, val) = pair.split_once('=')?;
+                if key.trim().eq_ignore_ascii_case("for") {
+                    Some(val.trim())
+                } else {
+                    None
+                }
+            });
+            if let Some(node) = node {
+                chain.push(parse_forwarded_node(node));
+            }
+        }
+    }
+    if chain.is_empty() {
+        None
+    } else {
+        Some(chain)
+    }
+}
+
+/// 解析单个转发节点，兼容 `1.2.3.4`、`1.2.3.4:8080`、`"[2001:db8::1]:4711"`、`2001:db8::1`
+fn parse_forwarded_node(node: &str) -> Option<IpAddr> {
+    let node = node.trim().trim_matches('"').trim();
+
+    if let Some(rest) = node.strip_prefix('[') {
+        let end = rest.find(']')?;
+        return rest[..end].parse::<IpAddr>().ok().map(normalize_ip);
+    }
+
+    if let Ok(ip) = node.parse::<IpAddr>() {
+        return Some(normalize_ip(ip));
+    }
+
+    // IPv4 带端口
+    node.rsplit_once(':')
+        .and_then(|(host, _)| host.parse::<std::net::Ipv4Addr>().ok())
+        .map(IpAddr::V4)
 }
 
 /// 创建被封禁的响应
@@ -1,10 +1,12 @@
 // Middleware 模块 - Axum 中间件
 
+pub mod audit;
 pub mod auth;
 pub mod cors;
 pub mod logging;
 pub mod monitor;
 pub mod ip_filter;
+pub mod token_limiter;
 
 pub mod service_status;
 
@@ -12,4 +14,5 @@ pub use cors::cors_layer;
 pub use monitor::monitor_middleware;
 pub use service_status::service_status_middleware;
 pub use auth::{auth_middleware, admin_auth_middleware};
+pub use audit::audit_middleware;
 pub use ip_filter::ip_filter_middleware;
@@ -43,27 +43,17 @@ pub async fn monitor_middleware(
     let method = request.method().to_string();
     let uri = request.uri().to_string();
     
-    if uri.contains("event_logging") || uri.contains("/api/") || uri.starts_with("/internal/") {
+    if uri.contains("event_logging") || uri.contains("/api/") || uri.starts_with("/internal/") || request.uri().path() == "/metrics" {
         return next.run(request).await;
     }
     
     let start = Instant::now();
     
-    // Extract client IP from headers (X-Forwarded-For or X-Real-IP)
+    // Extract client IP (forwarding headers are only honoured from trusted proxies)
     // IMPORTANT: Extract from Request headers, not Response headers (since we want the client's IP)
     // Note: We need to do this BEFORE consuming the request body if possible, or extract it from the original request
-    let client_ip = request
-        .headers()
-        .get("x-forwarded-for")
-        .and_then(|v| v.to_str().ok())
-        .map(|s| s.split(',').next().unwrap_or(s).trim().to_string())
-        .or_else(|| {
-            request
-                .headers()
-                .get("x-real-ip")
-                .and_then(|v| v.to_str().ok())
-                .map(|s| s.to_string())
-        });
+    let trusted_proxies = state.security.read().await.security_monitor.trusted_proxies.clone();
+    let client_ip = crate::proxy::middleware::ip_filter::extract_client_ip(&request, &trusted_proxies);
         
     let user_agent = request
         .headers()
@@ -131,6 +121,12 @@ pub async fn monitor_middleware(
         .and_then(|v| v.to_str().ok())
         .map(|s| s.to_string());
 
+    let cache_hit = response
+        .headers()
+        .get(crate::modules::response_cache::CACHE_HEADER)
+        .map(|v| v == "HIT")
+        .unwrap_or(false);
+
     // Extract mapped model from X-Mapped-Model header if present
     let mapped_model = response
         .headers()
@@ -154,9 +150,15 @@ pub async fn monitor_middleware(
     // Extract username from UserTokenIdentity if present
     let username = user_token_identity.as_ref().map(|identity| identity.username.clone());
 
+    // 重放请求使用预先分配的日志 ID，并将日志回传给重放任务
+    let replay_sink = crate::proxy::replay::log_sink();
+
     let monitor = state.monitor.clone();
     let mut log = ProxyRequestLog {
-        id: uuid::Uuid::new_v4().to_string(),
+        id: replay_sink
+            .as_ref()
+            .map(|s| s.log_id.clone())
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
         timestamp: chrono::Utc::now().timestamp_millis(),
         method,
         url: uri,
@@ -173,6 +175,7 @@ pub async fn monitor_middleware(
         output_tokens: None,
         protocol,
         username,
+        cache_hit,
     };
 
 
@@ -180,6 +183,8 @@ pub async fn monitor_middleware(
         let (parts, body) = response.into_parts();
         let mut stream = body.into_data_stream();
         let (tx, rx) = tokio::sync::mpsc::channel(64);
+        let stream_guard = crate::proxy::metrics::is_enabled()
+            .then(|| crate::proxy::metrics::global().stream_started());
         
         tokio::spawn(async move {
             let mut all_stream_data = Vec::new();
@@ -202,6 +207,7 @@ pub async fn monitor_middleware(
                     let _ = tx.send(Err(axum::Error::new(e))).await;
                 }
             }
+            drop(stream_guard);
             
             // Parse and consolidate stream data into readable format
             if let Ok(full_response) = std::str::from_utf8(&all_stream_data) {
@@ -433,6 +439,9 @@ pub async fn monitor_middleware(
             // Record User Token Usage
             record_user_token_usage(&user_token_identity, &log, user_agent.clone());
 
+            if let Some(sink) = &replay_sink {
+                sink.deliver(&log);
+            }
             monitor.log_request(log).await;
         });
 
@@ -476,6 +485,9 @@ pub async fn monitor_middleware(
                 // Record User Token Usage
                 record_user_token_usage(&user_token_identity, &log, user_agent.clone());
 
+                if let Some(sink) = &replay_sink {
+                    sink.deliver(&log);
+                }
                 monitor.log_request(log).await;
                 Response::from_parts(parts, Body::from(bytes))
             }
@@ -485,6 +497,9 @@ pub async fn monitor_middleware(
                 // Record User Token Usage (even if too large)
                 record_user_token_usage(&user_token_identity, &log, user_agent.clone());
 
+                if let Some(sink) = &replay_sink {
+                    sink.deliver(&log);
+                }
                 monitor.log_request(log).await;
                 Response::from_parts(parts, Body::empty())
             }
@@ -495,6 +510,9 @@ pub async fn monitor_middleware(
         // Record User Token Usage
         record_user_token_usage(&user_token_identity, &log, user_agent);
 
+        if let Some(sink) = &replay_sink {
+            sink.deliver(&log);
+        }
         monitor.log_request(log).await;
         response
     }
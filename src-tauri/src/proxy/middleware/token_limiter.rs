@@ -0,0 +1,189 @@
+//! User Token Rate Limiter
+//! 用户令牌的每分钟请求数 (RPM) 与并发请求数限制
+
+use dashmap::DashMap;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+const RPM_WINDOW: Duration = Duration::from_secs(60);
+
+static GLOBAL_TOKEN_LIMITER: OnceLock<TokenLimiter> = OnceLock::new();
+
+/// 全局令牌限流器 (auth 中间件与统计接口共享)
+pub fn global_limiter() -> &'static TokenLimiter {
+    GLOBAL_TOKEN_LIMITER.get_or_init(TokenLimiter::new)
+}
+
+/// 限流拒绝原因
+#[derive(Debug, Clone, PartialEq)]
+pub enum LimitRejection {
+    /// 超出每分钟请求数，`retry_after` 为秒
+    Rpm { limit: i32, retry_after: u64 },
+    /// 超出并发请求数
+    Concurrency { limit: i32 },
+}
+
+impl LimitRejection {
+    pub fn retry_after(&self) -> u64 {
+        match self {
+            LimitRejection::Rpm { retry_after, .. } => *retry_after,
+            LimitRejection::Concurrency { .. } => 1,
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            LimitRejection::Rpm { limit, .. } => {
+                format!("Rate limit exceeded: this token allows {} requests per minute.", limit)
+            }
+            LimitRejection::Concurrency { limit } => {
+                format!("Too many concurrent requests: this token allows {} at a time.", limit)
+            }
+        }
+    }
+}
+
+/// 并发占位，Drop 时释放
+pub struct ConcurrencyGuard {
+    counter: Arc<AtomicUsize>,
+}
+
+impl Drop for ConcurrencyGuard {
+    fn drop(&mut self) {
+        self.counter.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+pub struct TokenLimiter {
+    /// token_id -> 最近一分钟内的请求时间
+    windows: DashMap<String, VecDeque<Instant>>,
+    /// token_id -> 当前并发数
+    in_flight: DashMap<String, Arc<AtomicUsize>>,
+}
+
+impl TokenLimiter {
+    pub fn new() -> Self {
+        Self {
+            windows: DashMap::new(),
+            in_flight: DashMap::new(),
+        }
+    }
+
+    /// 尝试为一次请求占用额度 (0 表示不限制)
+    ///
+    /// 成功时返回并发占位，调用方需持有到响应体传输结束。
+    pub fn try_acquire(
+        &self,
+        token_id: &str,
+        max_rpm: i32,
+        max_concurrent: i32,
+    ) -> Result<ConcurrencyGuard, LimitRejection> {
+        let counter = self
+            .in_flight
+            .entry(token_id.to_string())
+            .or_insert_with(|| Arc::new(AtomicUsize::new(0)))
+            .clone();
+
+        // 1. 并发检查 (先占位，失败时回退)
+        let previous = counter.fetch_add(1, Ordering::SeqCst);
+        let guard = ConcurrencyGuard { counter };
+        if max_concurrent > 0 && previous >= max_concurrent as usize {
+            return Err(LimitRejection::Concurrency { limit: max_concurrent });
+        }
+
+        // 2. RPM 滑动窗口检查
+        let now = Instant::now();
+        let mut window = self.windows.entry(token_id.to_string()).or_default();
+        while window
+            .front()
+            .map(|t| now.duration_since(*t) >= RPM_WINDOW)
+            .unwrap_or(false)
+        {
+            window.pop_front();
+        }
+        if max_rpm > 0 && window.len() >= max_rpm as usize {
+            let oldest = *window.front().unwrap_or(&now);
+            let retry_after = RPM_WINDOW
+                .saturating_sub(now.duration_since(oldest))
+                .as_secs()
+                .max(1);
+            return Err(LimitRejection::Rpm { limit: max_rpm, retry_after });
+        }
+        window.push_back(now);
+
+        Ok(guard)
+    }
+
+    /// 当前并发请求数
+    pub fn in_flight(&self, token_id: &str) -> usize {
+        self.in_flight
+            .get(token_id)
+            .map(|c| c.load(Ordering::SeqCst))
+            .unwrap_or(0)
+    }
+
+    /// 最近一分钟内的请求数
+    pub fn recent_requests(&self, token_id: &str) -> usize {
+        let now = Instant::now();
+        self.windows
+            .get(token_id)
+            .map(|w| w.iter().filter(|t| now.duration_since(**t) < RPM_WINDOW).count())
+            .unwrap_or(0)
+    }
+}
+
+impl Default for TokenLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rpm_limit() {
+        let limiter = TokenLimiter::new();
+        for _ in 0..3 {
+            assert!(limiter.try_acquire("t1", 3, 0).is_ok());
+        }
+        match limiter.try_acquire("t1", 3, 0) {
+            Err(LimitRejection::Rpm { limit, retry_after }) => {
+                assert_eq!(limit, 3);
+                assert!(retry_after >= 1 && retry_after <= 60);
+            }
+            _ => panic!("expected rpm rejection"),
+        }
+        // 其他令牌不受影响
+        assert!(limiter.try_acquire("t2", 3, 0).is_ok());
+        assert_eq!(limiter.recent_requests("t1"), 3);
+    }
+
+    #[test]
+    fn test_concurrency_limit_released_on_drop() {
+        let limiter = TokenLimiter::new();
+        let g1 = limiter.try_acquire("t1", 0, 2).unwrap();
+        let _g2 = limiter.try_acquire("t1", 0, 2).unwrap();
+        assert_eq!(
+            limiter.try_acquire("t1", 0, 2).err(),
+            Some(LimitRejection::Concurrency { limit: 2 })
+        );
+        // 被拒绝的请求不占用并发
+        assert_eq!(limiter.in_flight("t1"), 2);
+
+        drop(g1);
+        assert_eq!(limiter.in_flight("t1"), 1);
+        assert!(limiter.try_acquire("t1", 0, 2).is_ok());
+    }
+
+    #[test]
+    fn test_rejected_concurrency_does_not_consume_rpm() {
+        let limiter = TokenLimiter::new();
+        let _g = limiter.try_acquire("t1", 2, 1).unwrap();
+        assert!(limiter.try_acquire("t1", 2, 1).is_err());
+        assert_eq!(limiter.recent_requests("t1"), 1);
+    }
+}
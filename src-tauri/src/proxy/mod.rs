@@ -12,15 +12,18 @@ pub mod audio; // 音频处理模块
 pub mod cli_sync; // CLI 配置同步 (v3.3.35)
 pub mod droid_sync; // Droid (Factory CLI) 配置同步
 pub mod common; // 公共工具
+pub mod config_reload; // 配置热重载 (文件变更 / SIGHUP)
 pub mod debug_logger;
 pub mod handlers; // API 端点处理器
 pub mod mappers; // 协议转换器
+pub mod metrics; // Prometheus 指标导出
 pub mod middleware; // Axum 中间件
 pub mod monitor; // 监控
 pub mod opencode_sync; // OpenCode 配置同步
 pub mod providers; // Extra upstream providers (z.ai, etc.)
 pub mod proxy_pool; // 代理池管理器
 pub mod rate_limit; // 限流跟踪
+pub mod replay; // 请求重放 (调试)
 pub mod model_specs; // 模型规格管理 (v4.1.29)
 pub mod session_manager; // 会话指纹管理
 pub mod signature_cache; // Signature Cache (v3.3.16)
@@ -34,6 +37,7 @@ pub use config::update_thinking_budget_config;
 pub use config::update_image_thinking_mode;
 pub use config::ProxyAuthMode;
 pub use config::ProxyConfig;
+#[cfg(feature = "desktop")]
 pub use config::ProxyPoolConfig;
 pub use config::ZaiConfig;
 pub use config::ZaiDispatchMode;
@@ -1,7 +1,6 @@
 use serde::{Serialize, Deserialize};
 use std::collections::VecDeque;
 use tokio::sync::RwLock;
-use tauri::Emitter;
 use std::sync::atomic::{AtomicBool, Ordering};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,6 +22,8 @@ pub struct ProxyRequestLog {
     pub output_tokens: Option<u32>,
     pub protocol: Option<String>,     // 协议类型: "openai", "anthropic", "gemini"
     pub username: Option<String>,     // User token username
+    #[serde(default)]
+    pub cache_hit: bool,              // 响应来自本地响应缓存 (未消耗上游配额)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -37,11 +38,10 @@ pub struct ProxyMonitor {
     pub stats: RwLock<ProxyStats>,
     pub max_logs: usize,
     pub enabled: AtomicBool,
-    app_handle: Option<tauri::AppHandle>,
 }
 
 impl ProxyMonitor {
-    pub fn new(max_logs: usize, app_handle: Option<tauri::AppHandle>) -> Self {
+    pub fn new(max_logs: usize) -> Self {
         // Initialize DB
         if let Err(e) = crate::modules::proxy_db::init_db() {
             tracing::error!("Failed to initialize proxy DB: {}", e);
@@ -66,7 +66,6 @@ impl ProxyMonitor {
             stats: RwLock::new(ProxyStats::default()),
             max_logs,
             enabled: AtomicBool::new(false), // Default to disabled
-            app_handle,
         }
     }
 
@@ -79,6 +78,19 @@ impl ProxyMonitor {
     }
 
     pub async fn log_request(&self, log: ProxyRequestLog) {
+        if crate::proxy::metrics::is_enabled() {
+            crate::proxy::metrics::global().record_request(&crate::proxy::metrics::RequestSample {
+                protocol: log.protocol.as_deref(),
+                model: log.model.as_deref(),
+                mapped_model: log.mapped_model.as_deref(),
+                account: log.account_email.as_deref(),
+                status: log.status,
+                duration_ms: log.duration,
+                input_tokens: log.input_tokens,
+                output_tokens: log.output_tokens,
+            });
+        }
+
         if let (Some(account), Some(input), Some(output)) = (
             &log.account_email,
             log.input_tokens,
@@ -160,7 +172,7 @@ impl ProxyMonitor {
         });
 
         // Emit event (send summary only, without body to reduce memory)
-        if let Some(app) = &self.app_handle {
+        {
             let log_summary = ProxyRequestLog {
                 id: log.id.clone(),
                 timestamp: log.timestamp,
@@ -179,8 +191,9 @@ impl ProxyMonitor {
                 output_tokens: log.output_tokens,
                 protocol: log.protocol.clone(),
                 username: log.username.clone(),
+                cache_hit: log.cache_hit,
             };
-            let _ = app.emit("proxy://request", &log_summary);
+            crate::modules::events::emit("proxy://request", &log_summary);
         }
     }
 
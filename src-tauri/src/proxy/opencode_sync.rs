@@ -1574,7 +1574,7 @@ pub fn read_opencode_config_content(file_name: Option<String>) -> Result<String,
         .map_err(|e| format!("Failed to read config: {}", e))
 }
 
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_opencode_sync_status(proxy_url: String) -> Result<OpencodeStatus, String> {
     let (installed, version) = check_opencode_installed();
     let (is_synced, has_backup, current_base_url) = get_sync_status(&proxy_url);
@@ -1593,7 +1593,7 @@ pub async fn get_opencode_sync_status(proxy_url: String) -> Result<OpencodeStatu
     })
 }
 
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn execute_opencode_sync(
     proxy_url: String,
     api_key: String,
@@ -1603,17 +1603,19 @@ pub async fn execute_opencode_sync(
     sync_opencode_config(&proxy_url, &api_key, sync_accounts.unwrap_or(false), models)
 }
 
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn execute_opencode_restore() -> Result<(), String> {
     restore_opencode_config()
 }
 
+#[cfg(feature = "desktop")]
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GetOpencodeConfigRequest {
     pub file_name: Option<String>,
 }
 
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub async fn get_opencode_config_content(request: GetOpencodeConfigRequest) -> Result<String, String> {
     read_opencode_config_content(request.file_name)
@@ -1733,7 +1735,7 @@ fn cleanup_legacy_provider(provider: &mut Value, proxy_url: &str) {
     }
 }
 
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn execute_opencode_clear(
     proxy_url: Option<String>,
     clear_legacy: Option<bool>,
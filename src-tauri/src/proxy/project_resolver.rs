@@ -3,8 +3,9 @@ use serde_json::Value;
 /// 使用 Antigravity 的 loadCodeAssist API 获取 project_id
 /// 这是获取 cloudaicompanionProject 的正确方式
 pub async fn fetch_project_id(access_token: &str) -> Result<String, String> {
-    // 使用 Sandbox 环境，避免 Prod 环境的 429 错误
-    let url = "https://daily-cloudcode-pa.sandbox.googleapis.com/v1internal:loadCodeAssist";
+    // 使用首个生效的上游端点 (默认 Sandbox，避免 Prod 环境的 429 错误)
+    let base_url = crate::proxy::upstream::client::configured_primary_base_url();
+    let url = format!("{}:loadCodeAssist", base_url);
     
     let request_body = serde_json::json!({
         "metadata": {
@@ -14,7 +15,7 @@ pub async fn fetch_project_id(access_token: &str) -> Result<String, String> {
     
     let client = crate::utils::http::get_client();
     let response = client
-        .post(url)
+        .post(&url)
         .bearer_auth(access_token)
         // .header("Host", "cloudcode-pa.googleapis.com") // 移除 Host header，因为已切换域名
 
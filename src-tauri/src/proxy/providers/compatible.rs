@@ -0,0 +1,534 @@
+// 通用上游提供商 (OpenAI / Anthropic 兼容后端，如 vLLM、llama.cpp)
+// 同协议请求直接透传 (保留原生流式)；跨协议请求经现有 mapper 转为 Gemini 格式后再转换为提供商协议
+
+use axum::{
+    body::Body,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use bytes::Bytes;
+use futures::StreamExt;
+use serde_json::Value;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{OnceLock, RwLock};
+
+use super::translate;
+use super::zai_anthropic::{build_client, join_base_url};
+use crate::proxy::config::{ProviderDispatchMode, ProviderProtocol, UpstreamProviderConfig};
+use crate::proxy::mappers::claude::models::{ClaudeRequest, GeminiResponse};
+use crate::proxy::mappers::openai::OpenAIRequest;
+use crate::proxy::server::AppState;
+
+/// 标记处理该请求的提供商
+pub const PROVIDER_HEADER: &str = "X-Upstream-Provider";
+
+const DEFAULT_ANTHROPIC_VERSION: &str = "2023-06-01";
+/// 跨协议转换时上下文上限未知，仅用于 Claude 流式状态机
+const TRANSLATED_CONTEXT_LIMIT: u32 = 1_000_000;
+
+static GLOBAL_PROVIDERS: OnceLock<RwLock<Vec<UpstreamProviderConfig>>> = OnceLock::new();
+static POOLED_RR: AtomicUsize = AtomicUsize::new(0);
+
+/// 更新全局提供商列表
+pub fn update_providers(providers: Vec<UpstreamProviderConfig>) {
+    let enabled = providers.iter().filter(|p| p.enabled).count();
+    if let Some(lock) = GLOBAL_PROVIDERS.get() {
+        if let Ok(mut cfg) = lock.write() {
+            *cfg = providers;
+        }
+    } else {
+        let _ = GLOBAL_PROVIDERS.set(RwLock::new(providers));
+    }
+    tracing::debug!("[Providers] {} upstream provider(s) enabled", enabled);
+}
+
+fn enabled_providers() -> Vec<UpstreamProviderConfig> {
+    GLOBAL_PROVIDERS
+        .get()
+        .and_then(|lock| lock.read().ok())
+        .map(|cfg| cfg.iter().filter(|p| p.enabled).cloned().collect())
+        .unwrap_or_default()
+}
+
+fn pattern_matches(pattern: &str, model: &str) -> bool {
+    pattern == model
+        || (pattern.contains('*') && crate::proxy::common::model_mapping::wildcard_match(pattern, model))
+}
+
+fn matches_model(provider: &UpstreamProviderConfig, model: &str) -> bool {
+    provider.models.is_empty() || provider.models.iter().any(|p| pattern_matches(p, model))
+}
+
+/// 客户端模型名 -> 上游模型名 (精确匹配优先，其次通配符)
+pub fn upstream_model(provider: &UpstreamProviderConfig, model: &str) -> String {
+    if let Some(target) = provider.model_mapping.get(model) {
+        return target.clone();
+    }
+    provider
+        .model_mapping
+        .iter()
+        .find(|(pattern, _)| pattern.contains('*') && pattern_matches(pattern, model))
+        .map(|(_, target)| target.clone())
+        .unwrap_or_else(|| model.to_string())
+}
+
+/// 提供商显式声明的模型 (用于 /v1/models 列表，不含通配符)
+pub fn listed_models() -> Vec<String> {
+    enabled_providers()
+        .into_iter()
+        .flat_map(|p| p.models.into_iter())
+        .filter(|m| !m.contains('*'))
+        .collect()
+}
+
+/// 按配置顺序选择处理该请求的提供商；返回 None 时走 Google 账号池
+/// `account_groups` 为调用方可调度的账号分组，Fallback 模式只检查这些分组内的账号
+pub async fn select_provider(
+    state: &AppState,
+    client_model: &str,
+    mapped_model: &str,
+    account_groups: &[String],
+) -> Option<UpstreamProviderConfig> {
+    let providers = enabled_providers();
+    if providers.is_empty() {
+        return None;
+    }
+    let google_accounts = state.token_manager.len();
+
+    for provider in providers.into_iter().filter(|p| matches_model(p, client_model)) {
+        let selected = match provider.dispatch_mode {
+            ProviderDispatchMode::Exclusive => true,
+            ProviderDispatchMode::Pooled => {
+                // 与 z.ai Pooled 相同：作为账号池中的一个额外槽位
+                let total = google_accounts.saturating_add(1);
+                POOLED_RR.fetch_add(1, Ordering::Relaxed) % total == 0
+            }
+            ProviderDispatchMode::Fallback => {
+                google_accounts == 0
+                    || !state.token_manager.has_available_account("", mapped_model, account_groups).await
+            }
+        };
+        if selected {
+            tracing::info!(
+                "[Providers] Routing {} to provider {} ({:?})",
+                client_model,
+                provider.id,
+                provider.dispatch_mode
+            );
+            return Some(provider);
+        }
+    }
+    None
+}
+
+fn endpoint_path(protocol: ProviderProtocol) -> &'static str {
+    match protocol {
+        ProviderProtocol::Openai => "/chat/completions",
+        ProviderProtocol::Anthropic => "/v1/messages",
+    }
+}
+
+async fn client_for(state: &AppState) -> Result<reqwest::Client, Response> {
+    let upstream_proxy = state.upstream_proxy.read().await.clone();
+    build_client(Some(upstream_proxy), state.request_timeout)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e).into_response())
+}
+
+/// 发送请求到提供商；失败时直接返回给客户端的错误响应
+async fn send(
+    client: &reqwest::Client,
+    provider: &UpstreamProviderConfig,
+    incoming: &HeaderMap,
+    body: &Value,
+) -> Result<reqwest::Response, Response> {
+    let url = join_base_url(&provider.base_url, endpoint_path(provider.protocol))
+        .map_err(|e| (StatusCode::BAD_REQUEST, e).into_response())?;
+
+    let mut req = client
+        .post(&url)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(serde_json::to_vec(body).unwrap_or_default());
+
+    let api_key = provider.api_key.trim();
+    match provider.protocol {
+        ProviderProtocol::Openai => {
+            if !api_key.is_empty() {
+                req = req.bearer_auth(api_key);
+            }
+        }
+        ProviderProtocol::Anthropic => {
+            if !api_key.is_empty() {
+                req = req.header("x-api-key", api_key);
+            }
+            let version = incoming
+                .get("anthropic-version")
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or(DEFAULT_ANTHROPIC_VERSION);
+            req = req.header("anthropic-version", version);
+            if let Some(beta) = incoming.get("anthropic-beta") {
+                req = req.header("anthropic-beta", beta.clone());
+            }
+        }
+    }
+
+    tracing::debug!("[Providers] Forwarding to {} ({})", provider.id, url);
+    req.send().await.map_err(|e| {
+        (
+            StatusCode::BAD_GATEWAY,
+            format!("Upstream provider {} request failed: {}", provider.id, e),
+        )
+            .into_response()
+    })
+}
+
+fn with_provider_headers(mut response: Response, provider: &UpstreamProviderConfig, model: &str) -> Response {
+    let headers = response.headers_mut();
+    if let Ok(v) = HeaderValue::from_str(&provider.id) {
+        headers.insert(PROVIDER_HEADER, v);
+    }
+    if let Ok(v) = HeaderValue::from_str(model) {
+        headers.insert("X-Mapped-Model", v);
+    }
+    response
+}
+
+/// 同协议透传 (含 SSE 流)
+async fn passthrough(
+    state: &AppState,
+    provider: &UpstreamProviderConfig,
+    incoming: &HeaderMap,
+    body: Value,
+    model: &str,
+) -> Response {
+    let sent = match client_for(state).await {
+        Ok(client) => send(&client, provider, incoming, &body).await,
+        Err(resp) => Err(resp),
+    };
+    let resp = match sent {
+        Ok(r) => r,
+        Err(resp) => return with_provider_headers(resp, provider, model),
+    };
+
+    let status = StatusCode::from_u16(resp.status().as_u16()).unwrap_or(StatusCode::BAD_GATEWAY);
+    let mut out = Response::builder().status(status);
+    if let Some(ct) = resp.headers().get(header::CONTENT_TYPE) {
+        out = out.header(header::CONTENT_TYPE, ct.clone());
+    }
+    let stream = resp.bytes_stream().map(|chunk| match chunk {
+        Ok(b) => Ok::<Bytes, std::io::Error>(b),
+        Err(e) => Ok(Bytes::from(format!("Upstream stream error: {}", e))),
+    });
+    let response = out.body(Body::from_stream(stream)).unwrap_or_else(|_| {
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to build response").into_response()
+    });
+    with_provider_headers(response, provider, model)
+}
+
+/// 跨协议调用：Gemini 请求 -> 提供商协议 (非流式) -> Gemini 响应
+async fn call_translated(
+    client: &reqwest::Client,
+    provider: &UpstreamProviderConfig,
+    incoming: &HeaderMap,
+    gemini_request: &Value,
+    model: &str,
+) -> Result<Value, Response> {
+    let body = match provider.protocol {
+        ProviderProtocol::Openai => translate::gemini_to_openai_request(gemini_request, model),
+        ProviderProtocol::Anthropic => translate::gemini_to_anthropic_request(gemini_request, model),
+    };
+
+    let resp = send(client, provider, incoming, &body).await?;
+    let status = resp.status();
+    let text = resp.text().await.map_err(|e| {
+        (StatusCode::BAD_GATEWAY, format!("Failed to read provider response: {}", e)).into_response()
+    })?;
+    if !status.is_success() {
+        let status = StatusCode::from_u16(status.as_u16()).unwrap_or(StatusCode::BAD_GATEWAY);
+        return Err((status, text).into_response());
+    }
+
+    let json: Value = serde_json::from_str(&text).map_err(|e| {
+        (StatusCode::BAD_GATEWAY, format!("Invalid provider response: {}", e)).into_response()
+    })?;
+    Ok(match provider.protocol {
+        ProviderProtocol::Openai => translate::openai_response_to_gemini(&json, model),
+        ProviderProtocol::Anthropic => translate::anthropic_response_to_gemini(&json, model),
+    })
+}
+
+/// 将完整的 Gemini 响应包装为单个 SSE 数据块，复用现有的流式转换器
+fn single_chunk_stream(
+    gemini_response: &Value,
+) -> std::pin::Pin<Box<impl futures::Stream<Item = Result<Bytes, String>> + Send>> {
+    let chunk = Bytes::from(format!("data: {}\n\n", gemini_response));
+    Box::pin(futures::stream::once(async move { Ok::<Bytes, String>(chunk) }))
+}
+
+fn sse_response(stream: impl futures::Stream<Item = Result<Bytes, String>> + Send + 'static) -> Response {
+    Response::builder()
+        .header(header::CONTENT_TYPE, "text/event-stream")
+        .header(header::CACHE_CONTROL, "no-cache")
+        .header("X-Accel-Buffering", "no")
+        .body(Body::from_stream(stream))
+        .unwrap_or_else(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Failed to build response").into_response())
+}
+
+/// Claude 协议客户端 (/v1/messages)
+pub async fn handle_claude(
+    state: &AppState,
+    provider: &UpstreamProviderConfig,
+    incoming: &HeaderMap,
+    request: &ClaudeRequest,
+    trace_id: &str,
+) -> Response {
+    let model = upstream_model(provider, &request.model);
+
+    if provider.protocol == ProviderProtocol::Anthropic {
+        let mut body = match serde_json::to_value(request) {
+            Ok(v) => v,
+            Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        };
+        body["model"] = Value::String(model.clone());
+        super::zai_anthropic::deep_remove_cache_control(&mut body);
+        return passthrough(state, provider, incoming, body, &model).await;
+    }
+
+    let v1internal = match crate::proxy::mappers::claude::transform_claude_request_in(
+        request, "", false, None, "", None,
+    ) {
+        Ok(b) => b,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+    let translated = match client_for(state).await {
+        Ok(client) => call_translated(&client, provider, incoming, &v1internal["request"], &model).await,
+        Err(resp) => Err(resp),
+    };
+    let gemini = match translated {
+        Ok(g) => g,
+        Err(resp) => return with_provider_headers(resp, provider, &model),
+    };
+
+    let message_count = request.messages.len();
+    let response = if request.stream {
+        let tool_names: Vec<String> = request
+            .tools
+            .as_ref()
+            .map(|tools| tools.iter().filter_map(|t| t.name.clone()).collect())
+            .unwrap_or_default();
+        sse_response(crate::proxy::mappers::claude::create_claude_sse_stream(
+            single_chunk_stream(&gemini),
+            trace_id.to_string(),
+            format!("provider:{}", provider.id),
+            None,
+            false,
+            TRANSLATED_CONTEXT_LIMIT,
+            None,
+            message_count,
+            None,
+            tool_names,
+        ))
+    } else {
+        let parsed: GeminiResponse = match serde_json::from_value(gemini) {
+            Ok(r) => r,
+            Err(e) => return (StatusCode::BAD_GATEWAY, e.to_string()).into_response(),
+        };
+        match crate::proxy::mappers::claude::transform_response(
+            &parsed,
+            false,
+            TRANSLATED_CONTEXT_LIMIT,
+            None,
+            request.model.clone(),
+            message_count,
+        ) {
+            Ok(claude) => Json(claude).into_response(),
+            Err(e) => (StatusCode::BAD_GATEWAY, e).into_response(),
+        }
+    };
+    with_provider_headers(response, provider, &model)
+}
+
+/// OpenAI 协议客户端 (/v1/chat/completions)
+/// `raw_body` 为客户端原始请求体 (透传时保留未建模的字段)
+pub async fn handle_openai(
+    state: &AppState,
+    provider: &UpstreamProviderConfig,
+    incoming: &HeaderMap,
+    request: &OpenAIRequest,
+    raw_body: Option<Value>,
+    _trace_id: &str,
+) -> Response {
+    let model = upstream_model(provider, &request.model);
+
+    if provider.protocol == ProviderProtocol::Openai {
+        let mut body = raw_body
+            .or_else(|| serde_json::to_value(request).ok())
+            .unwrap_or_default();
+        body["model"] = Value::String(model.clone());
+        translate::strip_internal_openai_fields(&mut body);
+        return passthrough(state, provider, incoming, body, &model).await;
+    }
+
+    let (v1internal, session_id, message_count) =
+        crate::proxy::mappers::openai::transform_openai_request(request, "", &model, None);
+    let translated = match client_for(state).await {
+        Ok(client) => call_translated(&client, provider, incoming, &v1internal["request"], &model).await,
+        Err(resp) => Err(resp),
+    };
+    let gemini = match translated {
+        Ok(g) => g,
+        Err(resp) => return with_provider_headers(resp, provider, &model),
+    };
+
+    let response = if request.stream {
+        sse_response(crate::proxy::mappers::openai::streaming::create_openai_sse_stream(
+            single_chunk_stream(&gemini),
+            request.model.clone(),
+            session_id,
+            message_count,
+        ))
+    } else {
+        let mut openai = crate::proxy::mappers::openai::transform_openai_response(
+            &gemini,
+            Some(&session_id),
+            message_count,
+        );
+        openai.model = request.model.clone();
+        Json(openai).into_response()
+    };
+    with_provider_headers(response, provider, &model)
+}
+
+/// Gemini 协议客户端 (/v1beta/models/{model}:generateContent)
+pub async fn handle_gemini(
+    state: &AppState,
+    provider: &UpstreamProviderConfig,
+    incoming: &HeaderMap,
+    client_model: &str,
+    body: &Value,
+    stream: bool,
+) -> Response {
+    let model = upstream_model(provider, client_model);
+    let translated = match client_for(state).await {
+        Ok(client) => call_translated(&client, provider, incoming, body, &model).await,
+        Err(resp) => Err(resp),
+    };
+    let gemini = match translated {
+        Ok(g) => g,
+        Err(resp) => return with_provider_headers(resp, provider, &model),
+    };
+
+    let response = if stream {
+        sse_response(single_chunk_stream(&gemini))
+    } else {
+        Json(gemini).into_response()
+    };
+    with_provider_headers(response, provider, &model)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn provider(protocol: ProviderProtocol, base_url: &str) -> UpstreamProviderConfig {
+        UpstreamProviderConfig {
+            id: "local".to_string(),
+            enabled: true,
+            protocol,
+            base_url: base_url.to_string(),
+            api_key: "sk-local".to_string(),
+            models: vec!["qwen*".to_string(), "llama-3".to_string()],
+            model_mapping: HashMap::from([("qwen*".to_string(), "Qwen/Qwen2.5-7B".to_string())]),
+            dispatch_mode: ProviderDispatchMode::Exclusive,
+        }
+    }
+
+    #[test]
+    fn test_model_matching_and_mapping() {
+        let p = provider(ProviderProtocol::Openai, "http://127.0.0.1:1/v1");
+        assert!(matches_model(&p, "qwen-coder"));
+        assert!(matches_model(&p, "llama-3"));
+        assert!(!matches_model(&p, "claude-sonnet-4-5"));
+        assert_eq!(upstream_model(&p, "qwen-coder"), "Qwen/Qwen2.5-7B");
+        assert_eq!(upstream_model(&p, "llama-3"), "llama-3");
+
+        let all = UpstreamProviderConfig { models: vec![], ..p };
+        assert!(matches_model(&all, "anything"));
+    }
+
+    /// 本地桩服务：记录收到的请求并返回固定的 OpenAI / Anthropic 响应
+    async fn spawn_stub() -> (String, std::sync::Arc<tokio::sync::Mutex<Vec<(String, HeaderMap, Value)>>>) {
+        use axum::{routing::post, Router};
+        let seen = std::sync::Arc::new(tokio::sync::Mutex::new(Vec::new()));
+
+        let s1 = seen.clone();
+        let s2 = seen.clone();
+        let app = Router::new()
+            .route(
+                "/v1/chat/completions",
+                post(move |headers: HeaderMap, Json(body): Json<Value>| async move {
+                    s1.lock().await.push(("openai".to_string(), headers, body));
+                    Json(serde_json::json!({
+                        "id": "chatcmpl-stub",
+                        "choices": [{ "message": { "role": "assistant", "content": "pong" }, "finish_reason": "stop" }],
+                        "usage": { "prompt_tokens": 2, "completion_tokens": 1, "total_tokens": 3 }
+                    }))
+                }),
+            )
+            .route(
+                "/v1/messages",
+                post(move |headers: HeaderMap, Json(body): Json<Value>| async move {
+                    s2.lock().await.push(("anthropic".to_string(), headers, body));
+                    Json(serde_json::json!({
+                        "id": "msg_stub",
+                        "type": "message",
+                        "role": "assistant",
+                        "content": [{ "type": "text", "text": "pong" }],
+                        "stop_reason": "end_turn",
+                        "usage": { "input_tokens": 2, "output_tokens": 1 }
+                    }))
+                }),
+            );
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        (format!("http://{}", addr), seen)
+    }
+
+    #[tokio::test]
+    async fn test_call_translated_against_stub() {
+        let (base, seen) = spawn_stub().await;
+        let client = build_client(None, 30).unwrap();
+        let gemini_request = serde_json::json!({
+            "contents": [{ "role": "user", "parts": [{ "text": "ping" }] }]
+        });
+
+        // OpenAI 兼容后端
+        let openai = provider(ProviderProtocol::Openai, &format!("{}/v1", base));
+        let resp = call_translated(&client, &openai, &HeaderMap::new(), &gemini_request, "Qwen/Qwen2.5-7B")
+            .await
+            .unwrap_or_else(|_| panic!("openai stub call failed"));
+        assert_eq!(resp["candidates"][0]["content"]["parts"][0]["text"], "pong");
+
+        // Anthropic 兼容后端
+        let anthropic = provider(ProviderProtocol::Anthropic, &base);
+        let resp = call_translated(&client, &anthropic, &HeaderMap::new(), &gemini_request, "claude-local")
+            .await
+            .unwrap_or_else(|_| panic!("anthropic stub call failed"));
+        assert_eq!(resp["usageMetadata"]["totalTokenCount"], 3);
+
+        let seen = seen.lock().await;
+        assert_eq!(seen[0].0, "openai");
+        assert_eq!(seen[0].1["authorization"], "Bearer sk-local");
+        assert_eq!(seen[0].2["model"], "Qwen/Qwen2.5-7B");
+        assert_eq!(seen[0].2["messages"][0]["content"], "ping");
+        assert_eq!(seen[1].0, "anthropic");
+        assert_eq!(seen[1].1["x-api-key"], "sk-local");
+        assert_eq!(seen[1].1["anthropic-version"], DEFAULT_ANTHROPIC_VERSION);
+        assert_eq!(seen[1].2["max_tokens"], 8192);
+    }
+}
@@ -1,2 +1,3 @@
+pub mod compatible;
+pub mod translate;
 pub mod zai_anthropic;
-
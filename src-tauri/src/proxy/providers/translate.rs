@@ -0,0 +1,612 @@
+// Gemini ↔ OpenAI Chat / Anthropic Messages 转换
+// 客户端请求先经现有 mapper 转为 Gemini 格式，再由此处转为通用提供商的协议；响应反向转回 Gemini 格式
+
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+
+/// Anthropic 要求必须提供 max_tokens
+const DEFAULT_ANTHROPIC_MAX_TOKENS: u64 = 8192;
+
+/// 是否为 Claude mapper 为 Google 上游注入的系统提示 (转发给第三方时去除)
+fn is_injected_system_text(text: &str) -> bool {
+    text.starts_with("You are Antigravity, a powerful agentic AI coding assistant")
+        || text.trim() == "--- [SYSTEM_PROMPT_END] ---"
+}
+
+fn system_text(gemini: &Value) -> Option<String> {
+    let parts = gemini
+        .get("systemInstruction")
+        .and_then(|s| s.get("parts"))
+        .and_then(|p| p.as_array())?;
+    let texts: Vec<&str> = parts
+        .iter()
+        .filter_map(|p| p.get("text").and_then(|t| t.as_str()))
+        .filter(|t| !is_injected_system_text(t))
+        .collect();
+    let joined = texts.join("\n\n");
+    if joined.trim().is_empty() {
+        None
+    } else {
+        Some(joined)
+    }
+}
+
+/// 为缺少 id 的 functionCall / functionResponse 生成可配对的 id (Gemini 原生客户端不携带 id)
+#[derive(Default)]
+struct ToolCallIds {
+    pending: HashMap<String, VecDeque<String>>,
+    counter: usize,
+}
+
+impl ToolCallIds {
+    fn call_id(&mut self, call: &Value) -> String {
+        let name = call.get("name").and_then(|n| n.as_str()).unwrap_or_default();
+        let id = match call.get("id").and_then(|v| v.as_str()).filter(|s| !s.is_empty()) {
+            Some(id) => id.to_string(),
+            None => {
+                self.counter += 1;
+                format!("call_{}_{}", name, self.counter)
+            }
+        };
+        self.pending.entry(name.to_string()).or_default().push_back(id.clone());
+        id
+    }
+
+    fn response_id(&mut self, resp: &Value) -> String {
+        let name = resp.get("name").and_then(|n| n.as_str()).unwrap_or_default();
+        if let Some(id) = resp.get("id").and_then(|v| v.as_str()).filter(|s| !s.is_empty()) {
+            if let Some(queue) = self.pending.get_mut(name) {
+                queue.retain(|p| p != id);
+            }
+            return id.to_string();
+        }
+        self.pending
+            .get_mut(name)
+            .and_then(|q| q.pop_front())
+            .unwrap_or_else(|| format!("call_{}", name))
+    }
+}
+
+/// functionResponse.response 转为工具结果文本
+fn function_response_text(resp: &Value) -> String {
+    match resp.get("response") {
+        Some(r) => match r.get("result").or_else(|| r.get("output")) {
+            Some(Value::String(s)) => s.clone(),
+            Some(other) => other.to_string(),
+            None => r.to_string(),
+        },
+        None => String::new(),
+    }
+}
+
+fn is_thought(part: &Value) -> bool {
+    part.get("thought").and_then(|t| t.as_bool()).unwrap_or(false)
+}
+
+fn function_declarations(gemini: &Value) -> Vec<Value> {
+    gemini
+        .get("tools")
+        .and_then(|t| t.as_array())
+        .map(|tools| {
+            tools
+                .iter()
+                .filter_map(|t| t.get("functionDeclarations").and_then(|f| f.as_array()))
+                .flatten()
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn function_calling_mode(gemini: &Value) -> Option<&str> {
+    gemini
+        .get("toolConfig")
+        .and_then(|c| c.get("functionCallingConfig"))
+        .and_then(|c| c.get("mode"))
+        .and_then(|m| m.as_str())
+}
+
+// ============================================================================
+// Gemini -> OpenAI Chat Completions
+// ============================================================================
+
+/// 将 Gemini generateContent 请求 (不含 v1internal 包装) 转为 OpenAI Chat 请求
+pub fn gemini_to_openai_request(gemini: &Value, model: &str) -> Value {
+    let mut messages = Vec::new();
+    if let Some(system) = system_text(gemini) {
+        messages.push(json!({ "role": "system", "content": system }));
+    }
+
+    let mut ids = ToolCallIds::default();
+    for content in gemini.get("contents").and_then(|c| c.as_array()).into_iter().flatten() {
+        let role = content.get("role").and_then(|r| r.as_str()).unwrap_or("user");
+        let parts = content.get("parts").and_then(|p| p.as_array()).cloned().unwrap_or_default();
+
+        let mut text = String::new();
+        let mut images = Vec::new();
+        let mut tool_calls = Vec::new();
+        let mut tool_results = Vec::new();
+
+        for part in &parts {
+            if is_thought(part) {
+                continue;
+            }
+            if let Some(t) = part.get("text").and_then(|t| t.as_str()) {
+                text.push_str(t);
+            } else if let Some(inline) = part.get("inlineData") {
+                let mime = inline.get("mimeType").and_then(|m| m.as_str()).unwrap_or("image/png");
+                let data = inline.get("data").and_then(|d| d.as_str()).unwrap_or_default();
+                images.push(json!({
+                    "type": "image_url",
+                    "image_url": { "url": format!("data:{};base64,{}", mime, data) }
+                }));
+            } else if let Some(call) = part.get("functionCall") {
+                let args = call.get("args").cloned().unwrap_or_else(|| json!({}));
+                tool_calls.push(json!({
+                    "id": ids.call_id(call),
+                    "type": "function",
+                    "function": {
+                        "name": call.get("name").and_then(|n| n.as_str()).unwrap_or_default(),
+                        "arguments": args.to_string()
+                    }
+                }));
+            } else if let Some(resp) = part.get("functionResponse") {
+                tool_results.push(json!({
+                    "role": "tool",
+                    "tool_call_id": ids.response_id(resp),
+                    "content": function_response_text(resp)
+                }));
+            }
+        }
+
+        if role == "model" {
+            let mut msg = json!({ "role": "assistant", "content": text });
+            if !tool_calls.is_empty() {
+                msg["tool_calls"] = Value::Array(tool_calls);
+                if text.is_empty() {
+                    msg["content"] = Value::Null;
+                }
+            }
+            messages.push(msg);
+        } else {
+            // 工具结果必须紧跟在 assistant 的 tool_calls 之后
+            messages.extend(tool_results);
+            if !images.is_empty() {
+                let mut blocks = Vec::new();
+                if !text.is_empty() {
+                    blocks.push(json!({ "type": "text", "text": text }));
+                }
+                blocks.extend(images);
+                messages.push(json!({ "role": "user", "content": blocks }));
+            } else if !text.is_empty() {
+                messages.push(json!({ "role": "user", "content": text }));
+            }
+        }
+    }
+
+    let mut body = json!({ "model": model, "messages": messages, "stream": false });
+
+    let gen = gemini.get("generationConfig").cloned().unwrap_or_else(|| json!({}));
+    if let Some(v) = gen.get("temperature") {
+        body["temperature"] = v.clone();
+    }
+    if let Some(v) = gen.get("topP") {
+        body["top_p"] = v.clone();
+    }
+    if let Some(v) = gen.get("maxOutputTokens") {
+        body["max_tokens"] = v.clone();
+    }
+    if let Some(v) = gen.get("stopSequences").filter(|v| v.as_array().map_or(false, |a| !a.is_empty())) {
+        body["stop"] = v.clone();
+    }
+    if gen.get("responseMimeType").and_then(|m| m.as_str()) == Some("application/json") {
+        body["response_format"] = json!({ "type": "json_object" });
+    }
+
+    let declarations = function_declarations(gemini);
+    if !declarations.is_empty() {
+        let tools: Vec<Value> = declarations
+            .iter()
+            .map(|d| {
+                json!({
+                    "type": "function",
+                    "function": {
+                        "name": d.get("name").cloned().unwrap_or(Value::Null),
+                        "description": d.get("description").cloned().unwrap_or_else(|| json!("")),
+                        "parameters": d.get("parameters").cloned().unwrap_or_else(|| json!({ "type": "object", "properties": {} }))
+                    }
+                })
+            })
+            .collect();
+        body["tools"] = Value::Array(tools);
+        match function_calling_mode(gemini) {
+            Some("ANY") => body["tool_choice"] = json!("required"),
+            Some("NONE") => body["tool_choice"] = json!("none"),
+            _ => {}
+        }
+    }
+
+    body
+}
+
+fn openai_finish_reason(reason: Option<&str>) -> &'static str {
+    match reason {
+        Some("length") => "MAX_TOKENS",
+        Some("content_filter") => "SAFETY",
+        _ => "STOP",
+    }
+}
+
+/// 将 OpenAI Chat 非流式响应转为 Gemini generateContent 响应
+pub fn openai_response_to_gemini(resp: &Value, model: &str) -> Value {
+    let mut candidates = Vec::new();
+    for (index, choice) in resp.get("choices").and_then(|c| c.as_array()).into_iter().flatten().enumerate() {
+        let message = choice.get("message").cloned().unwrap_or_else(|| json!({}));
+        let mut parts = Vec::new();
+
+        // vLLM / DeepSeek 等推理模型的思考内容
+        if let Some(reasoning) = message
+            .get("reasoning_content")
+            .or_else(|| message.get("reasoning"))
+            .and_then(|r| r.as_str())
+            .filter(|r| !r.is_empty())
+        {
+            parts.push(json!({ "text": reasoning, "thought": true }));
+        }
+        if let Some(text) = message.get("content").and_then(|c| c.as_str()).filter(|c| !c.is_empty()) {
+            parts.push(json!({ "text": text }));
+        }
+        for call in message.get("tool_calls").and_then(|t| t.as_array()).into_iter().flatten() {
+            let function = call.get("function").cloned().unwrap_or_else(|| json!({}));
+            let args = function
+                .get("arguments")
+                .and_then(|a| a.as_str())
+                .and_then(|a| serde_json::from_str::<Value>(a).ok())
+                .unwrap_or_else(|| json!({}));
+            parts.push(json!({
+                "functionCall": {
+                    "name": function.get("name").cloned().unwrap_or(Value::Null),
+                    "args": args,
+                    "id": call.get("id").cloned().unwrap_or(Value::Null)
+                }
+            }));
+        }
+        if parts.is_empty() {
+            parts.push(json!({ "text": "" }));
+        }
+
+        candidates.push(json!({
+            "content": { "role": "model", "parts": parts },
+            "finishReason": openai_finish_reason(choice.get("finish_reason").and_then(|f| f.as_str())),
+            "index": index
+        }));
+    }
+
+    let usage = resp.get("usage").cloned().unwrap_or_else(|| json!({}));
+    let prompt = usage.get("prompt_tokens").and_then(|v| v.as_u64()).unwrap_or(0);
+    let completion = usage.get("completion_tokens").and_then(|v| v.as_u64()).unwrap_or(0);
+
+    json!({
+        "candidates": candidates,
+        "usageMetadata": {
+            "promptTokenCount": prompt,
+            "candidatesTokenCount": completion,
+            "totalTokenCount": usage.get("total_tokens").and_then(|v| v.as_u64()).unwrap_or(prompt + completion)
+        },
+        "modelVersion": model,
+        "responseId": resp.get("id").cloned().unwrap_or(Value::Null)
+    })
+}
+
+// ============================================================================
+// Gemini -> Anthropic Messages
+// ============================================================================
+
+/// 将 Gemini generateContent 请求转为 Anthropic Messages 请求
+pub fn gemini_to_anthropic_request(gemini: &Value, model: &str) -> Value {
+    let mut messages: Vec<Value> = Vec::new();
+    let mut ids = ToolCallIds::default();
+
+    for content in gemini.get("contents").and_then(|c| c.as_array()).into_iter().flatten() {
+        let role = match content.get("role").and_then(|r| r.as_str()) {
+            Some("model") => "assistant",
+            _ => "user",
+        };
+
+        let mut blocks = Vec::new();
+        for part in content.get("parts").and_then(|p| p.as_array()).into_iter().flatten() {
+            if is_thought(part) {
+                continue;
+            }
+            if let Some(t) = part.get("text").and_then(|t| t.as_str()) {
+                if !t.is_empty() {
+                    blocks.push(json!({ "type": "text", "text": t }));
+                }
+            } else if let Some(inline) = part.get("inlineData") {
+                blocks.push(json!({
+                    "type": "image",
+                    "source": {
+                        "type": "base64",
+                        "media_type": inline.get("mimeType").cloned().unwrap_or_else(|| json!("image/png")),
+                        "data": inline.get("data").cloned().unwrap_or_else(|| json!(""))
+                    }
+                }));
+            } else if let Some(call) = part.get("functionCall") {
+                blocks.push(json!({
+                    "type": "tool_use",
+                    "id": ids.call_id(call),
+                    "name": call.get("name").cloned().unwrap_or(Value::Null),
+                    "input": call.get("args").cloned().unwrap_or_else(|| json!({}))
+                }));
+            } else if let Some(resp) = part.get("functionResponse") {
+                blocks.push(json!({
+                    "type": "tool_result",
+                    "tool_use_id": ids.response_id(resp),
+                    "content": function_response_text(resp)
+                }));
+            }
+        }
+        if blocks.is_empty() {
+            continue;
+        }
+
+        // Anthropic 要求 user / assistant 交替出现，合并相邻的同角色消息
+        match messages.last_mut() {
+            Some(last) if last["role"] == role => {
+                if let Some(existing) = last["content"].as_array_mut() {
+                    existing.extend(blocks);
+                }
+            }
+            _ => messages.push(json!({ "role": role, "content": blocks })),
+        }
+    }
+
+    let gen = gemini.get("generationConfig").cloned().unwrap_or_else(|| json!({}));
+    let mut body = json!({
+        "model": model,
+        "messages": messages,
+        "max_tokens": gen.get("maxOutputTokens").and_then(|v| v.as_u64()).unwrap_or(DEFAULT_ANTHROPIC_MAX_TOKENS),
+        "stream": false
+    });
+    if let Some(system) = system_text(gemini) {
+        body["system"] = json!(system);
+    }
+    if let Some(v) = gen.get("temperature") {
+        body["temperature"] = v.clone();
+    }
+    if let Some(v) = gen.get("topP") {
+        body["top_p"] = v.clone();
+    }
+    if let Some(v) = gen.get("stopSequences").filter(|v| v.as_array().map_or(false, |a| !a.is_empty())) {
+        body["stop_sequences"] = v.clone();
+    }
+
+    let declarations = function_declarations(gemini);
+    if !declarations.is_empty() {
+        let tools: Vec<Value> = declarations
+            .iter()
+            .map(|d| {
+                json!({
+                    "name": d.get("name").cloned().unwrap_or(Value::Null),
+                    "description": d.get("description").cloned().unwrap_or_else(|| json!("")),
+                    "input_schema": d.get("parameters").cloned().unwrap_or_else(|| json!({ "type": "object", "properties": {} }))
+                })
+            })
+            .collect();
+        body["tools"] = Value::Array(tools);
+        match function_calling_mode(gemini) {
+            Some("ANY") => body["tool_choice"] = json!({ "type": "any" }),
+            Some("NONE") => body["tool_choice"] = json!({ "type": "none" }),
+            _ => {}
+        }
+    }
+
+    body
+}
+
+/// 将 Anthropic Messages 非流式响应转为 Gemini generateContent 响应
+pub fn anthropic_response_to_gemini(resp: &Value, model: &str) -> Value {
+    let mut parts = Vec::new();
+    for block in resp.get("content").and_then(|c| c.as_array()).into_iter().flatten() {
+        match block.get("type").and_then(|t| t.as_str()) {
+            Some("text") => parts.push(json!({ "text": block.get("text").cloned().unwrap_or_else(|| json!("")) })),
+            Some("thinking") => parts.push(json!({
+                "text": block.get("thinking").cloned().unwrap_or_else(|| json!("")),
+                "thought": true
+            })),
+            Some("tool_use") => parts.push(json!({
+                "functionCall": {
+                    "name": block.get("name").cloned().unwrap_or(Value::Null),
+                    "args": block.get("input").cloned().unwrap_or_else(|| json!({})),
+                    "id": block.get("id").cloned().unwrap_or(Value::Null)
+                }
+            })),
+            _ => {}
+        }
+    }
+    if parts.is_empty() {
+        parts.push(json!({ "text": "" }));
+    }
+
+    let finish_reason = match resp.get("stop_reason").and_then(|r| r.as_str()) {
+        Some("max_tokens") => "MAX_TOKENS",
+        Some("refusal") => "SAFETY",
+        _ => "STOP",
+    };
+
+    let usage = resp.get("usage").cloned().unwrap_or_else(|| json!({}));
+    let input = usage.get("input_tokens").and_then(|v| v.as_u64()).unwrap_or(0);
+    let output = usage.get("output_tokens").and_then(|v| v.as_u64()).unwrap_or(0);
+
+    json!({
+        "candidates": [{
+            "content": { "role": "model", "parts": parts },
+            "finishReason": finish_reason,
+            "index": 0
+        }],
+        "usageMetadata": {
+            "promptTokenCount": input,
+            "candidatesTokenCount": output,
+            "totalTokenCount": input + output
+        },
+        "modelVersion": model,
+        "responseId": resp.get("id").cloned().unwrap_or(Value::Null)
+    })
+}
+
+/// 透传请求体：移除值为 null 的字段 (部分兼容后端会拒绝)
+pub fn strip_nulls(body: &mut Value) {
+    if let Some(obj) = body.as_object_mut() {
+        let keys: Vec<String> = obj
+            .iter()
+            .filter(|(_, v)| v.is_null())
+            .map(|(k, _)| k.clone())
+            .collect();
+        for k in keys {
+            obj.remove(&k);
+        }
+    }
+}
+
+/// 透传 OpenAI 请求前移除本服务内部使用的扩展字段 (Codex / 图像参数)，并清理 null 值
+pub fn strip_internal_openai_fields(body: &mut Value) {
+    const INTERNAL_FIELDS: [&str; 7] = [
+        "prompt",
+        "instructions",
+        "input",
+        "size",
+        "quality",
+        "personGeneration",
+        "imageSize",
+    ];
+    if let Some(obj) = body.as_object_mut() {
+        for k in INTERNAL_FIELDS {
+            obj.remove(k);
+        }
+    }
+    strip_nulls(body);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_gemini_request() -> Value {
+        json!({
+            "systemInstruction": { "role": "user", "parts": [
+                { "text": "You are Antigravity, a powerful agentic AI coding assistant designed by the Google Deepmind team." },
+                { "text": "Be terse." },
+                { "text": "\n--- [SYSTEM_PROMPT_END] ---" }
+            ]},
+            "contents": [
+                { "role": "user", "parts": [{ "text": "weather in Paris?" }] },
+                { "role": "model", "parts": [
+                    { "text": "thinking...", "thought": true },
+                    { "functionCall": { "name": "get_weather", "args": { "city": "Paris" } } }
+                ]},
+                { "role": "user", "parts": [
+                    { "functionResponse": { "name": "get_weather", "response": { "result": "sunny" } } }
+                ]}
+            ],
+            "generationConfig": { "temperature": 0.2, "maxOutputTokens": 256, "stopSequences": [] },
+            "tools": [
+                { "functionDeclarations": [{ "name": "get_weather", "description": "Weather", "parameters": { "type": "object" } }] },
+                { "googleSearch": {} }
+            ],
+            "toolConfig": { "functionCallingConfig": { "mode": "ANY" } }
+        })
+    }
+
+    #[test]
+    fn test_gemini_to_openai_request() {
+        let body = gemini_to_openai_request(&sample_gemini_request(), "qwen2.5-coder");
+        let messages = body["messages"].as_array().unwrap();
+
+        assert_eq!(body["model"], "qwen2.5-coder");
+        assert_eq!(messages[0], json!({ "role": "system", "content": "Be terse." }));
+        assert_eq!(messages[1]["content"], "weather in Paris?");
+
+        // 生成的 tool_call id 与工具结果配对
+        let call_id = messages[2]["tool_calls"][0]["id"].as_str().unwrap();
+        assert_eq!(messages[2]["content"], Value::Null);
+        assert_eq!(messages[2]["tool_calls"][0]["function"]["arguments"], "{\"city\":\"Paris\"}");
+        assert_eq!(messages[3]["role"], "tool");
+        assert_eq!(messages[3]["tool_call_id"], call_id);
+        assert_eq!(messages[3]["content"], "sunny");
+
+        assert_eq!(body["max_tokens"], 256);
+        assert!(body.get("stop").is_none());
+        assert_eq!(body["tools"].as_array().unwrap().len(), 1);
+        assert_eq!(body["tool_choice"], "required");
+    }
+
+    #[test]
+    fn test_gemini_to_anthropic_request() {
+        let body = gemini_to_anthropic_request(&sample_gemini_request(), "claude-local");
+        let messages = body["messages"].as_array().unwrap();
+
+        assert_eq!(body["system"], "Be terse.");
+        assert_eq!(body["max_tokens"], 256);
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[1]["role"], "assistant");
+        assert_eq!(messages[1]["content"][0]["type"], "tool_use");
+        assert_eq!(messages[2]["content"][0]["tool_use_id"], messages[1]["content"][0]["id"]);
+        assert_eq!(body["tools"][0]["input_schema"], json!({ "type": "object" }));
+        assert_eq!(body["tool_choice"], json!({ "type": "any" }));
+    }
+
+    #[test]
+    fn test_openai_response_to_gemini() {
+        let resp = json!({
+            "id": "chatcmpl-1",
+            "choices": [{
+                "message": {
+                    "role": "assistant",
+                    "content": "Hi",
+                    "reasoning_content": "hmm",
+                    "tool_calls": [{ "id": "c1", "type": "function", "function": { "name": "f", "arguments": "{\"a\":1}" } }]
+                },
+                "finish_reason": "tool_calls"
+            }],
+            "usage": { "prompt_tokens": 3, "completion_tokens": 4, "total_tokens": 7 }
+        });
+        let gemini = openai_response_to_gemini(&resp, "qwen");
+        let parts = gemini["candidates"][0]["content"]["parts"].as_array().unwrap();
+
+        assert_eq!(parts[0], json!({ "text": "hmm", "thought": true }));
+        assert_eq!(parts[1]["text"], "Hi");
+        assert_eq!(parts[2]["functionCall"]["args"], json!({ "a": 1 }));
+        assert_eq!(parts[2]["functionCall"]["id"], "c1");
+        assert_eq!(gemini["candidates"][0]["finishReason"], "STOP");
+        assert_eq!(gemini["usageMetadata"]["totalTokenCount"], 7);
+    }
+
+    #[test]
+    fn test_anthropic_response_to_gemini() {
+        let resp = json!({
+            "id": "msg_1",
+            "content": [
+                { "type": "thinking", "thinking": "plan", "signature": "sig" },
+                { "type": "text", "text": "Done" },
+                { "type": "tool_use", "id": "toolu_1", "name": "f", "input": { "x": true } }
+            ],
+            "stop_reason": "max_tokens",
+            "usage": { "input_tokens": 10, "output_tokens": 5 }
+        });
+        let gemini = anthropic_response_to_gemini(&resp, "claude-local");
+        let parts = gemini["candidates"][0]["content"]["parts"].as_array().unwrap();
+
+        assert_eq!(parts[0]["thought"], true);
+        assert_eq!(parts[1]["text"], "Done");
+        assert_eq!(parts[2]["functionCall"]["id"], "toolu_1");
+        assert_eq!(gemini["candidates"][0]["finishReason"], "MAX_TOKENS");
+        assert_eq!(gemini["usageMetadata"]["totalTokenCount"], 15);
+    }
+
+    #[test]
+    fn test_strip_internal_openai_fields() {
+        let mut body = json!({ "model": "m", "messages": [], "size": null, "instructions": "x", "seed": 1 });
+        strip_internal_openai_fields(&mut body);
+        assert_eq!(body, json!({ "model": "m", "messages": [], "seed": 1 }));
+    }
+}
@@ -36,7 +36,7 @@ fn map_model_for_zai(original: &str, state: &crate::proxy::ZaiConfig) -> String
     state.models.sonnet.clone()
 }
 
-fn join_base_url(base: &str, path: &str) -> Result<String, String> {
+pub(crate) fn join_base_url(base: &str, path: &str) -> Result<String, String> {
     let base = base.trim_end_matches('/');
     let path = if path.starts_with('/') {
         path.to_string()
@@ -46,7 +46,7 @@ fn join_base_url(base: &str, path: &str) -> Result<String, String> {
     Ok(format!("{}{}", base, path))
 }
 
-fn build_client(
+pub(crate) fn build_client(
     upstream_proxy: Option<crate::proxy::config::UpstreamProxyConfig>,
     timeout_secs: u64,
 ) -> Result<reqwest::Client, String> {
@@ -33,12 +33,29 @@ pub struct PoolProxyConfig {
     pub entry_id: String,
 }
 
+/// 代理在途请求租约
+/// 创建时在途计数 +1，Drop 时 -1 (随上游响应体一起释放)
+#[derive(Debug)]
+pub struct ProxyLease {
+    counter: Arc<AtomicUsize>,
+    pub entry_id: String,
+}
+
+impl Drop for ProxyLease {
+    fn drop(&mut self) {
+        self.counter.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
 /// 代理池管理器
 pub struct ProxyPoolManager {
     config: Arc<RwLock<ProxyPoolConfig>>,
     
-    /// 代理使用计数 (proxy_id -> count)
+    /// 代理累计分配次数 (proxy_id -> count)
     usage_counter: Arc<DashMap<String, usize>>,
+
+    /// 代理在途请求数 (proxy_id -> count)，由 ProxyLease 维护
+    in_flight: Arc<DashMap<String, Arc<AtomicUsize>>>,
     
     /// 账号到代理的绑定 (account_id -> proxy_id)
     account_bindings: Arc<DashMap<String, String>>,
@@ -66,6 +83,7 @@ impl ProxyPoolManager {
         Self {
             config,
             usage_counter: Arc::new(DashMap::new()),
+            in_flight: Arc::new(DashMap::new()),
             account_bindings,
             round_robin_index: Arc::new(AtomicUsize::new(0)),
         }
@@ -257,6 +275,9 @@ impl ProxyPoolManager {
             ProxySelectionStrategy::WeightedRoundRobin => {
                 self.select_weighted(&healthy_proxies)
             }
+            ProxySelectionStrategy::LatencyAware => {
+                self.select_latency_aware(&healthy_proxies)
+            }
         };
         
         if let Some(entry) = selected {
@@ -287,14 +308,81 @@ impl ProxyPoolManager {
     }
     
     fn select_least_connections<'a>(&self, proxies: &[&'a ProxyEntry]) -> Option<&'a ProxyEntry> {
-        proxies.iter().min_by_key(|p| {
-            self.usage_counter.get(&p.id).map(|v| *v).unwrap_or(0)
-        }).copied()
+        // 在途请求数相同时按 priority 决胜
+        proxies.iter().min_by_key(|p| (self.in_flight_count(&p.id), p.priority)).copied()
     }
     
     fn select_weighted<'a>(&self, proxies: &[&'a ProxyEntry]) -> Option<&'a ProxyEntry> {
-        // 简单实现: 类似优先级的加权, 这里暂用 Priority 代替
-        self.select_by_priority(proxies)
+        let total: u64 = proxies.iter().map(|p| p.weight as u64).sum();
+        if total == 0 {
+            // 所有代理权重均为 0 时退化为优先级策略
+            return self.select_by_priority(proxies);
+        }
+
+        use rand::Rng;
+        let mut point = rand::thread_rng().gen_range(0..total);
+        for p in proxies {
+            let w = p.weight as u64;
+            if point < w {
+                return Some(*p);
+            }
+            point -= w;
+        }
+        None
+    }
+
+    /// 延迟感知: score = 延迟 × (在途请求数 + 1) / 权重，取最小值
+    /// 尚未完成健康检查 (无延迟数据) 的代理按已知最大延迟的 2 倍估算
+    fn select_latency_aware<'a>(&self, proxies: &[&'a ProxyEntry]) -> Option<&'a ProxyEntry> {
+        let candidates: Vec<&'a ProxyEntry> = proxies.iter().copied().filter(|p| p.weight > 0).collect();
+        if candidates.is_empty() {
+            return self.select_by_priority(proxies);
+        }
+
+        let unknown_latency = candidates
+            .iter()
+            .filter_map(|p| p.latency)
+            .max()
+            .map(|max| max.saturating_mul(2))
+            .unwrap_or(1000)
+            .max(1);
+
+        let score = |p: &ProxyEntry| -> f64 {
+            let latency = p.latency.unwrap_or(unknown_latency).max(1) as f64;
+            let load = (self.in_flight_count(&p.id) + 1) as f64;
+            latency * load / p.weight as f64
+        };
+
+        candidates
+            .into_iter()
+            .min_by(|a, b| {
+                score(a)
+                    .partial_cmp(&score(b))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+                    .then(a.priority.cmp(&b.priority))
+            })
+    }
+
+    /// 获取代理当前在途请求数
+    pub fn in_flight_count(&self, proxy_id: &str) -> usize {
+        self.in_flight
+            .get(proxy_id)
+            .map(|c| c.load(Ordering::Relaxed))
+            .unwrap_or(0)
+    }
+
+    /// 为指定代理登记一个在途请求，返回的租约在 Drop 时自动释放
+    pub fn acquire_lease(&self, proxy_id: &str) -> ProxyLease {
+        let counter = self
+            .in_flight
+            .entry(proxy_id.to_string())
+            .or_insert_with(|| Arc::new(AtomicUsize::new(0)))
+            .clone();
+        counter.fetch_add(1, Ordering::Relaxed);
+        ProxyLease {
+            counter,
+            entry_id: proxy_id.to_string(),
+        }
     }
 
     /// 构建 reqwest::Proxy 配置
@@ -527,3 +615,125 @@ impl ProxyPoolManager {
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str, priority: i32, weight: u32, latency: Option<u64>) -> ProxyEntry {
+        ProxyEntry {
+            id: id.to_string(),
+            name: id.to_string(),
+            url: format!("http://{}.local:8080", id),
+            auth: None,
+            enabled: true,
+            priority,
+            tags: vec![],
+            max_accounts: None,
+            health_check_url: None,
+            last_check_time: None,
+            is_healthy: true,
+            latency,
+            weight,
+        }
+    }
+
+    fn manager() -> ProxyPoolManager {
+        ProxyPoolManager::new(Arc::new(RwLock::new(ProxyPoolConfig::default())))
+    }
+
+    #[test]
+    fn test_weighted_selection_respects_weights() {
+        let pool = manager();
+        let a = entry("a", 0, 9, None);
+        let b = entry("b", 1, 1, None);
+        let zero = entry("zero", 2, 0, None);
+        let proxies = vec![&a, &b, &zero];
+
+        let mut hits_a = 0;
+        for _ in 0..2000 {
+            let picked = pool.select_weighted(&proxies).unwrap();
+            assert_ne!(picked.id, "zero", "weight 0 must never be selected");
+            if picked.id == "a" {
+                hits_a += 1;
+            }
+        }
+        // 期望约 90%，留足随机波动余量
+        assert!(hits_a > 1600 && hits_a < 1950, "unexpected distribution: {}", hits_a);
+    }
+
+    #[test]
+    fn test_weighted_selection_all_zero_falls_back_to_priority() {
+        let pool = manager();
+        let a = entry("a", 5, 0, None);
+        let b = entry("b", 1, 0, None);
+        assert_eq!(pool.select_weighted(&[&a, &b]).unwrap().id, "b");
+    }
+
+    #[test]
+    fn test_least_connections_tracks_in_flight_leases() {
+        let pool = manager();
+        let a = entry("a", 0, 1, None);
+        let b = entry("b", 1, 1, None);
+        let proxies = vec![&a, &b];
+
+        // 无在途请求时按 priority 决胜
+        assert_eq!(pool.select_least_connections(&proxies).unwrap().id, "a");
+
+        let lease = pool.acquire_lease("a");
+        assert_eq!(pool.in_flight_count("a"), 1);
+        assert_eq!(pool.select_least_connections(&proxies).unwrap().id, "b");
+
+        // 租约释放后计数归零
+        drop(lease);
+        assert_eq!(pool.in_flight_count("a"), 0);
+        assert_eq!(pool.select_least_connections(&proxies).unwrap().id, "a");
+    }
+
+    #[test]
+    fn test_latency_aware_prefers_fast_and_spreads_load() {
+        let pool = manager();
+        let fast = entry("fast", 1, 1, Some(100));
+        let slow = entry("slow", 0, 1, Some(250));
+        let proxies = vec![&fast, &slow];
+
+        assert_eq!(pool.select_latency_aware(&proxies).unwrap().id, "fast");
+
+        // fast 有 2 个在途请求后得分 300 > slow 的 250
+        let _l1 = pool.acquire_lease("fast");
+        let _l2 = pool.acquire_lease("fast");
+        assert_eq!(pool.select_latency_aware(&proxies).unwrap().id, "slow");
+    }
+
+    #[test]
+    fn test_latency_aware_weight_and_unknown_latency() {
+        let pool = manager();
+        let heavy = entry("heavy", 1, 4, Some(300));
+        let light = entry("light", 0, 1, Some(100));
+        let unknown = entry("unknown", 0, 1, None);
+        let proxies = vec![&heavy, &light, &unknown];
+
+        // heavy: 300 / 4 = 75 < light: 100 < unknown: 600
+        assert_eq!(pool.select_latency_aware(&proxies).unwrap().id, "heavy");
+    }
+
+    #[test]
+    fn test_proxy_entry_weight_defaults_to_one() {
+        let json = serde_json::json!({
+            "id": "p1",
+            "name": "p1",
+            "url": "http://127.0.0.1:7890",
+            "auth": null,
+            "enabled": true,
+            "priority": 0,
+            "tags": [],
+            "max_accounts": null,
+            "health_check_url": null,
+            "last_check_time": null,
+            "is_healthy": true,
+            "latency": null
+        });
+        let entry: ProxyEntry = serde_json::from_value(json).unwrap();
+        assert_eq!(entry.weight, 1);
+    }
+}
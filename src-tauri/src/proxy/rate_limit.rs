@@ -1,6 +1,9 @@
 use dashmap::DashMap;
-use std::time::{SystemTime, Duration};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{SystemTime, Duration, UNIX_EPOCH};
 use regex::Regex;
+use crate::modules::rate_limit_db::{self, PersistedLockout};
 
 /// 限流原因类型
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -17,6 +20,29 @@ pub enum RateLimitReason {
     Unknown,
 }
 
+impl RateLimitReason {
+    /// 持久化使用的字符串表示
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RateLimitReason::QuotaExhausted => "QUOTA_EXHAUSTED",
+            RateLimitReason::RateLimitExceeded => "RATE_LIMIT_EXCEEDED",
+            RateLimitReason::ModelCapacityExhausted => "MODEL_CAPACITY_EXHAUSTED",
+            RateLimitReason::ServerError => "SERVER_ERROR",
+            RateLimitReason::Unknown => "UNKNOWN",
+        }
+    }
+
+    pub fn from_db_str(s: &str) -> Self {
+        match s {
+            "QUOTA_EXHAUSTED" => RateLimitReason::QuotaExhausted,
+            "RATE_LIMIT_EXCEEDED" => RateLimitReason::RateLimitExceeded,
+            "MODEL_CAPACITY_EXHAUSTED" => RateLimitReason::ModelCapacityExhausted,
+            "SERVER_ERROR" => RateLimitReason::ServerError,
+            _ => RateLimitReason::Unknown,
+        }
+    }
+}
+
 /// 限流信息
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
@@ -41,11 +67,96 @@ pub struct RateLimitInfo {
 /// 失败计数过期时间：1小时（超过此时间未失败则重置计数）
 const FAILURE_COUNT_EXPIRY_SECONDS: u64 = 3600;
 
+/// 持久化的最短锁定时长：短时软避让 (5-15秒) 在重启后必然已过期，不值得写盘
+const PERSIST_MIN_LOCKOUT_SECONDS: u64 = 30;
+
+fn to_unix_secs(t: SystemTime) -> i64 {
+    t.duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0)
+}
+
+fn from_unix_secs(secs: i64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(secs.max(0) as u64)
+}
+
+/// 持久化写操作 (由后台线程按顺序执行)
+enum PersistOp {
+    SaveLockout(PersistedLockout),
+    DeleteLockout(String),
+    SaveFailureCount { account_id: String, count: u32, last_failure: i64 },
+    DeleteFailureCount(String),
+    Prune { now: i64, failure_expiry_before: i64 },
+    ClearLockouts,
+}
+
+/// 后台写线程：持有唯一的数据库连接，请求路径只负责投递写操作，不做阻塞 I/O
+///
+/// Drop 时关闭通道并等待队列写完，保证进程退出 (或测试重建跟踪器) 前数据已落盘。
+struct PersistWriter {
+    tx: Option<mpsc::Sender<PersistOp>>,
+    handle: Option<std::thread::JoinHandle<()>>,
+}
+
+impl PersistWriter {
+    fn spawn(db_path: &Path) -> Result<Self, String> {
+        let conn = rate_limit_db::connect_db(db_path)?;
+        let (tx, rx) = mpsc::channel::<PersistOp>();
+        let handle = std::thread::Builder::new()
+            .name("rate-limit-db".to_string())
+            .spawn(move || {
+                for op in rx {
+                    let (what, result) = match op {
+                        PersistOp::SaveLockout(row) => ("持久化限流记录", rate_limit_db::save_lockout(&conn, &row)),
+                        PersistOp::DeleteLockout(key) => ("删除持久化限流记录", rate_limit_db::delete_lockout(&conn, &key)),
+                        PersistOp::SaveFailureCount { account_id, count, last_failure } => (
+                            "持久化失败计数",
+                            rate_limit_db::save_failure_count(&conn, &account_id, count, last_failure),
+                        ),
+                        PersistOp::DeleteFailureCount(account_id) => {
+                            ("删除持久化失败计数", rate_limit_db::delete_failure_count(&conn, &account_id))
+                        }
+                        PersistOp::Prune { now, failure_expiry_before } => (
+                            "清理持久化限流记录",
+                            rate_limit_db::prune_expired(&conn, now, failure_expiry_before).map(|_| ()),
+                        ),
+                        PersistOp::ClearLockouts => ("清空持久化限流记录", rate_limit_db::clear_lockouts(&conn)),
+                    };
+                    if let Err(e) = result {
+                        tracing::warn!("{}失败: {}", what, e);
+                    }
+                }
+            })
+            .map_err(|e| e.to_string())?;
+        Ok(Self {
+            tx: Some(tx),
+            handle: Some(handle),
+        })
+    }
+
+    fn send(&self, op: PersistOp) {
+        if let Some(tx) = &self.tx {
+            let _ = tx.send(op);
+        }
+    }
+}
+
+impl Drop for PersistWriter {
+    fn drop(&mut self) {
+        self.tx.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
 /// 限流跟踪器
 pub struct RateLimitTracker {
     limits: DashMap<String, RateLimitInfo>,
     /// 连续失败计数（用于智能指数退避），带时间戳用于自动过期
     failure_counts: DashMap<String, (u32, SystemTime)>,
+    /// 持久化数据库路径 (None 表示仅内存，例如单元测试)
+    db_path: Option<PathBuf>,
+    /// 持久化写线程 (与 db_path 同时存在)
+    writer: Option<PersistWriter>,
 }
 
 impl RateLimitTracker {
@@ -53,8 +164,110 @@ impl RateLimitTracker {
         Self {
             limits: DashMap::new(),
             failure_counts: DashMap::new(),
+            db_path: None,
+            writer: None,
+        }
+    }
+
+    /// 创建带 SQLite 持久化的跟踪器，并恢复尚未过期的锁定记录
+    ///
+    /// 数据库初始化失败时降级为纯内存模式，不影响代理启动。
+    pub fn with_persistence(db_path: PathBuf) -> Self {
+        let mut tracker = Self::new();
+        match rate_limit_db::init_db(&db_path).and_then(|_| PersistWriter::spawn(&db_path)) {
+            Ok(writer) => {
+                tracker.db_path = Some(db_path);
+                tracker.writer = Some(writer);
+                tracker.restore_persisted();
+            }
+            Err(e) => {
+                tracing::warn!("限流数据库初始化失败，仅使用内存记录: {}", e);
+            }
+        }
+        tracker
+    }
+
+    /// 投递持久化写操作 (纯内存模式下忽略)
+    fn persist(&self, op: PersistOp) {
+        if let Some(writer) = &self.writer {
+            writer.send(op);
         }
     }
+
+    /// 从数据库恢复未过期的锁定记录与失败计数
+    ///
+    /// 内存中已存在且更晚到期的记录不会被覆盖。返回恢复的锁定数量。
+    pub fn restore_persisted(&self) -> usize {
+        let Some(db_path) = &self.db_path else {
+            return 0;
+        };
+        let now = SystemTime::now();
+
+        let lockouts = match rate_limit_db::load_active_lockouts(db_path, to_unix_secs(now)) {
+            Ok(l) => l,
+            Err(e) => {
+                tracing::warn!("加载持久化限流记录失败: {}", e);
+                return 0;
+            }
+        };
+
+        let mut restored = 0;
+        for row in lockouts {
+            let reset_time = from_unix_secs(row.reset_time);
+            if let Some(existing) = self.limits.get(&row.key) {
+                if existing.reset_time >= reset_time {
+                    continue;
+                }
+            }
+            let info = RateLimitInfo {
+                reset_time,
+                retry_after_sec: reset_time
+                    .duration_since(now)
+                    .map(|d| d.as_secs())
+                    .unwrap_or(0),
+                detected_at: from_unix_secs(row.detected_at),
+                reason: RateLimitReason::from_db_str(&row.reason),
+                model: row.model,
+            };
+            self.limits.insert(row.key, info);
+            restored += 1;
+        }
+
+        match rate_limit_db::load_failure_counts(db_path) {
+            Ok(counts) => {
+                for row in counts {
+                    self.failure_counts
+                        .entry(row.account_id)
+                        .or_insert((row.count, from_unix_secs(row.last_failure)));
+                }
+            }
+            Err(e) => tracing::warn!("加载持久化失败计数失败: {}", e),
+        }
+
+        if restored > 0 {
+            tracing::info!("从数据库恢复了 {} 个未过期的限流记录", restored);
+        }
+        restored
+    }
+
+    /// 将锁定记录写入数据库 (仅持久化较长的锁定)
+    fn persist_lockout(&self, key: &str, account_id: &str, info: &RateLimitInfo) {
+        if info.retry_after_sec < PERSIST_MIN_LOCKOUT_SECONDS {
+            return;
+        }
+        self.persist(PersistOp::SaveLockout(PersistedLockout {
+            key: key.to_string(),
+            account_id: account_id.to_string(),
+            model: info.model.clone(),
+            reason: info.reason.as_str().to_string(),
+            reset_time: to_unix_secs(info.reset_time),
+            detected_at: to_unix_secs(info.detected_at),
+        }));
+    }
+
+    fn unpersist_lockout(&self, key: &str) {
+        self.persist(PersistOp::DeleteLockout(key.to_string()));
+    }
     
     /// 生成限流 Key
     /// - 账号级: "account_id"
@@ -95,15 +308,24 @@ impl RateLimitTracker {
     /// 
     /// 当账号成功完成请求后调用此方法，将其失败计数归零，
     /// 这样下次失败时会从最短的锁定时间（60秒）开始。
-    pub fn mark_success(&self, account_id: &str) {
+    pub fn mark_success(&self, account_id: &str, model: Option<&str>) {
         if self.failure_counts.remove(account_id).is_some() {
             tracing::debug!("账号 {} 请求成功，已重置失败计数", account_id);
+            self.persist(PersistOp::DeleteFailureCount(account_id.to_string()));
         }
-        // 清除账号级限流
-        self.limits.remove(account_id);
+        // 清除账号级限流，以及本次成功请求所用模型的模型级限流
         // 注意：我们暂时无法清除该账号下的所有模型级锁，因为我们不知道哪些模型被锁了
         // 除非遍历 limits。考虑到模型级锁通常是 QuotaExhausted，让其自然过期也是可以接受的。
-        // 或者我们可以引入索引，但为了简单，暂时只清除 Account 级锁。
+        // 或者我们可以引入索引，但为了简单，暂时只清除 Account 级锁与当前模型的锁。
+        let mut keys = vec![account_id.to_string()];
+        if let Some(m) = model.filter(|m| !m.is_empty()) {
+            keys.push(self.get_limit_key(account_id, Some(m)));
+        }
+        for key in keys {
+            if self.limits.remove(&key).is_some() {
+                self.persist(PersistOp::DeleteLockout(key));
+            }
+        }
     }
     
     /// 精确锁定账号到指定时间点
@@ -129,6 +351,7 @@ impl RateLimitTracker {
         };
         
         let key = self.get_limit_key(account_id, model.as_deref());
+        self.persist_lockout(&key, account_id, &info);
         self.limits.insert(key, info);
         
         if let Some(m) = &model {
@@ -243,7 +466,15 @@ impl RateLimitTracker {
                     }
                     entry.0 += 1;
                     entry.1 = now;
-                    entry.0
+                    let count = entry.0;
+                    drop(entry);
+
+                    self.persist(PersistOp::SaveFailureCount {
+                        account_id: account_id.to_string(),
+                        count,
+                        last_failure: to_unix_secs(now),
+                    });
+                    count
                 } else {
                     // ServerError (5xx) 使用固定值 1，不累加，避免污染 429 的退避阶梯
                     1
@@ -314,6 +545,7 @@ impl RateLimitTracker {
             account_id.to_string()
         };
 
+        self.persist_lockout(&key, account_id, &info);
         self.limits.insert(key, info.clone());
         
         tracing::warn!(
@@ -523,6 +755,27 @@ impl RateLimitTracker {
         }
     }
     
+    /// 按原因统计当前处于锁定中的账号数 (同一账号的多个模型级锁定只计一次)
+    pub fn active_accounts_by_reason(&self) -> Vec<(String, usize)> {
+        let now = SystemTime::now();
+        let mut accounts: std::collections::BTreeMap<&'static str, std::collections::HashSet<String>> =
+            std::collections::BTreeMap::new();
+        for entry in self.limits.iter() {
+            if entry.value().reset_time <= now {
+                continue;
+            }
+            let account_id = entry.key().split(':').next().unwrap_or_default().to_string();
+            accounts
+                .entry(entry.value().reason.as_str())
+                .or_default()
+                .insert(account_id);
+        }
+        accounts
+            .into_iter()
+            .map(|(reason, ids)| (reason.to_string(), ids.len()))
+            .collect()
+    }
+
     /// 清除过期的限流记录
     #[allow(dead_code)]
     pub fn cleanup_expired(&self) -> usize {
@@ -541,13 +794,26 @@ impl RateLimitTracker {
         if count > 0 {
             tracing::debug!("清除了 {} 个过期的限流记录", count);
         }
+
+        // 同步清理数据库中已过期的锁定与失败计数
+        let failure_expiry = now
+            .checked_sub(Duration::from_secs(FAILURE_COUNT_EXPIRY_SECONDS))
+            .unwrap_or(UNIX_EPOCH);
+        self.persist(PersistOp::Prune {
+            now: to_unix_secs(now),
+            failure_expiry_before: to_unix_secs(failure_expiry),
+        });
         
         count
     }
     
     /// 清除指定账号的限流记录
     pub fn clear(&self, account_id: &str) -> bool {
-        self.limits.remove(account_id).is_some()
+        let removed = self.limits.remove(account_id).is_some();
+        if removed {
+            self.unpersist_lockout(account_id);
+        }
+        removed
     }
     
     /// 清除所有限流记录 (乐观重置策略)
@@ -557,6 +823,7 @@ impl RateLimitTracker {
     pub fn clear_all(&self) {
         let count = self.limits.len();
         self.limits.clear();
+        self.persist(PersistOp::ClearLockouts);
         tracing::warn!("🔄 Optimistic reset: Cleared all {} rate limit record(s)", count);
     }
 }
@@ -679,4 +946,102 @@ mod tests {
         let info = tracker.parse_from_error("acc2", 429, None, quota_body, None, &backoff_steps);
         assert_eq!(info.unwrap().retry_after_sec, 7200);
     }
+
+    fn temp_db_path() -> PathBuf {
+        std::env::temp_dir().join(format!("rate_limits_test_{}.db", uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn test_lockouts_survive_restart() {
+        let db_path = temp_db_path();
+        let backoff_steps = vec![60, 300, 1800, 7200];
+        let quota_body = r#"{"error":{"details":[{"reason":"QUOTA_EXHAUSTED"}]}}"#;
+
+        {
+            let tracker = RateLimitTracker::with_persistence(db_path.clone());
+            tracker.parse_from_error("acc1", 429, None, quota_body, Some("gemini-3-pro".to_string()), &backoff_steps);
+            tracker.set_lockout_until("acc2", SystemTime::now() + Duration::from_secs(600), RateLimitReason::QuotaExhausted, None);
+            // 短时软避让不会写盘
+            tracker.parse_from_error("acc3", 503, None, "", None, &backoff_steps);
+        }
+
+        // 模拟重启
+        let tracker = RateLimitTracker::with_persistence(db_path.clone());
+        assert!(tracker.is_rate_limited("acc1", Some("gemini-3-pro")));
+        assert!(!tracker.is_rate_limited("acc1", Some("claude-sonnet-4-5")));
+        assert!(tracker.is_rate_limited("acc2", None));
+        assert!(!tracker.is_rate_limited("acc3", None));
+        assert_eq!(tracker.get("acc2").unwrap().reason, RateLimitReason::QuotaExhausted);
+
+        // 失败计数也被恢复：第 2 次配额耗尽应进入下一档退避
+        let info = tracker.parse_from_error("acc1", 429, None, quota_body, Some("gemini-3-pro".to_string()), &backoff_steps);
+        assert_eq!(info.unwrap().retry_after_sec, 300);
+
+        let _ = std::fs::remove_file(&db_path);
+    }
+
+    #[test]
+    fn test_cleared_and_expired_lockouts_are_not_restored() {
+        let db_path = temp_db_path();
+
+        {
+            let tracker = RateLimitTracker::with_persistence(db_path.clone());
+            tracker.set_lockout_until("acc1", SystemTime::now() + Duration::from_secs(600), RateLimitReason::QuotaExhausted, None);
+            tracker.set_lockout_until("acc2", SystemTime::now() + Duration::from_secs(600), RateLimitReason::QuotaExhausted, None);
+            tracker.set_lockout_until("acc2", SystemTime::now() + Duration::from_secs(600), RateLimitReason::QuotaExhausted, Some("gemini-3-pro".to_string()));
+            assert!(tracker.clear("acc1"));
+            tracker.set_lockout_until("acc2", SystemTime::now() + Duration::from_secs(600), RateLimitReason::QuotaExhausted, Some("claude-opus-4-6".to_string()));
+            // 成功请求清除账号级锁定与该模型的锁定，其他模型的锁定保留
+            tracker.mark_success("acc2", Some("gemini-3-pro"));
+            assert!(!tracker.is_rate_limited("acc2", Some("gemini-3-pro")));
+            assert!(tracker.is_rate_limited("acc2", Some("claude-opus-4-6")));
+        }
+        {
+            let tracker = RateLimitTracker::with_persistence(db_path.clone());
+            assert!(!tracker.is_rate_limited("acc1", None));
+            assert!(!tracker.is_rate_limited("acc2", None));
+            assert!(!tracker.is_rate_limited("acc2", Some("gemini-3-pro")));
+            assert!(tracker.is_rate_limited("acc2", Some("claude-opus-4-6")));
+        }
+
+        // 直接写入一条已过期的记录，清理循环应将其删除
+        let conn = rate_limit_db::connect_db(&db_path).unwrap();
+        rate_limit_db::save_lockout(&conn, &PersistedLockout {
+            key: "acc3".to_string(),
+            account_id: "acc3".to_string(),
+            model: None,
+            reason: "QUOTA_EXHAUSTED".to_string(),
+            reset_time: to_unix_secs(SystemTime::now()) - 10,
+            detected_at: to_unix_secs(SystemTime::now()) - 100,
+        }).unwrap();
+        let tracker = RateLimitTracker::with_persistence(db_path.clone());
+        assert!(!tracker.is_rate_limited("acc3", None));
+        tracker.cleanup_expired();
+        // 等待后台写线程处理完队列
+        drop(tracker);
+        let remaining = rate_limit_db::load_active_lockouts(&db_path, 0).unwrap();
+        let keys: Vec<&str> = remaining.iter().map(|l| l.key.as_str()).collect();
+        assert_eq!(keys, vec!["acc2:claude-opus-4-6"]);
+
+        let _ = std::fs::remove_file(&db_path);
+    }
+
+    #[test]
+    fn test_active_accounts_by_reason() {
+        let tracker = RateLimitTracker::new();
+        let later = SystemTime::now() + Duration::from_secs(600);
+        tracker.set_lockout_until("acc1", later, RateLimitReason::QuotaExhausted, None);
+        tracker.set_lockout_until("acc1", later, RateLimitReason::QuotaExhausted, Some("gemini-2.5-pro".to_string()));
+        tracker.set_lockout_until("acc2", later, RateLimitReason::QuotaExhausted, None);
+        tracker.set_lockout_until("acc3", later, RateLimitReason::RateLimitExceeded, None);
+
+        let counts = tracker.active_accounts_by_reason();
+        assert_eq!(
+            counts,
+            vec![
+                ("QUOTA_EXHAUSTED".to_string(), 2),
+                ("RATE_LIMIT_EXCEEDED".to_string(), 1),
+            ]
+        );
+    }
 }
@@ -0,0 +1,436 @@
+// 请求重放 (调试工具)
+// 按日志 ID 将已记录的请求重新发送到反代路由，可指定账号 / 映射模型 / 流式模式。
+// 重放结果照常写入请求日志，并与原始日志关联，附带两次响应的结构化差异。
+
+use axum::body::Body;
+use axum::http::{header, Method, Request};
+use axum::Router;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+use tower::ServiceExt;
+
+use crate::proxy::monitor::ProxyRequestLog;
+use crate::proxy::server::AppState;
+
+/// 差异比较时忽略的字段 (每次生成都会变化)
+const VOLATILE_KEYS: &[&str] = &["id", "created"];
+/// 等待监控中间件回传重放日志的超时时间
+const LOG_WAIT_SECS: u64 = 30;
+
+tokio::task_local! {
+    static REPLAY_CONTEXT: ReplayContext;
+}
+
+#[derive(Clone)]
+struct ReplayContext {
+    account_email: Option<String>,
+    mapped_model: Option<String>,
+    sink: ReplayLogSink,
+}
+
+/// 当前任务是否为重放请求
+pub fn is_active() -> bool {
+    REPLAY_CONTEXT.try_with(|_| ()).is_ok()
+}
+
+/// 重放请求固定使用的账号邮箱
+pub fn forced_account() -> Option<String> {
+    REPLAY_CONTEXT
+        .try_with(|ctx| ctx.account_email.clone())
+        .ok()
+        .flatten()
+}
+
+/// 重放请求覆盖的映射模型
+pub fn mapped_model_override() -> Option<String> {
+    REPLAY_CONTEXT
+        .try_with(|ctx| ctx.mapped_model.clone())
+        .ok()
+        .flatten()
+}
+
+/// 监控中间件通过该句柄回传重放请求的日志 (使用预先分配的日志 ID)
+#[derive(Clone)]
+pub struct ReplayLogSink {
+    pub log_id: String,
+    sender: Arc<Mutex<Option<oneshot::Sender<ProxyRequestLog>>>>,
+}
+
+impl ReplayLogSink {
+    pub fn deliver(&self, log: &ProxyRequestLog) {
+        if let Some(tx) = self.sender.lock().ok().and_then(|mut s| s.take()) {
+            let _ = tx.send(log.clone());
+        }
+    }
+}
+
+pub fn log_sink() -> Option<ReplayLogSink> {
+    REPLAY_CONTEXT.try_with(|ctx| ctx.sink.clone()).ok()
+}
+
+/// 重放选项
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReplayOptions {
+    /// 使用原请求的账号
+    #[serde(default)]
+    pub same_account: bool,
+    /// 指定账号 (账号 ID 或邮箱)，优先于 same_account；都未指定时按正常调度选择账号
+    #[serde(default)]
+    pub account_id: Option<String>,
+    /// 覆盖映射后的模型
+    #[serde(default)]
+    pub mapped_model: Option<String>,
+    /// 强制流式 (true) / 非流式 (false)，为空时保持原请求
+    #[serde(default)]
+    pub stream: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// 单处差异 (path 形如 `content`、`tool_calls[0].function.name`，空字符串表示整体)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DiffEntry {
+    pub path: String,
+    pub kind: DiffKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<Value>,
+}
+
+/// 原始响应与重放响应的差异
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponseDiff {
+    pub identical: bool,
+    /// 状态码、映射模型、账号与 token 用量
+    pub metadata: Vec<DiffEntry>,
+    /// 响应体 (均为 JSON 时逐字段比较)
+    pub body: Vec<DiffEntry>,
+}
+
+/// 一次重放记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayRecord {
+    /// 重放请求的日志 ID
+    pub replay_id: String,
+    pub original_id: String,
+    pub created_at: i64,
+    pub options: ReplayOptions,
+    pub diff: ResponseDiff,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayResult {
+    pub record: ReplayRecord,
+    pub log: ProxyRequestLog,
+}
+
+fn join_key(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+fn diff_into(path: &str, before: &Value, after: &Value, out: &mut Vec<DiffEntry>) {
+    match (before, after) {
+        (Value::Object(a), Value::Object(b)) => {
+            for (k, va) in a {
+                if VOLATILE_KEYS.contains(&k.as_str()) {
+                    continue;
+                }
+                match b.get(k) {
+                    Some(vb) => diff_into(&join_key(path, k), va, vb, out),
+                    None => out.push(DiffEntry {
+                        path: join_key(path, k),
+                        kind: DiffKind::Removed,
+                        before: Some(va.clone()),
+                        after: None,
+                    }),
+                }
+            }
+            for (k, vb) in b {
+                if !a.contains_key(k) && !VOLATILE_KEYS.contains(&k.as_str()) {
+                    out.push(DiffEntry {
+                        path: join_key(path, k),
+                        kind: DiffKind::Added,
+                        before: None,
+                        after: Some(vb.clone()),
+                    });
+                }
+            }
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            for i in 0..a.len().max(b.len()) {
+                let item_path = format!("{}[{}]", path, i);
+                match (a.get(i), b.get(i)) {
+                    (Some(va), Some(vb)) => diff_into(&item_path, va, vb, out),
+                    (Some(va), None) => out.push(DiffEntry {
+                        path: item_path,
+                        kind: DiffKind::Removed,
+                        before: Some(va.clone()),
+                        after: None,
+                    }),
+                    (None, Some(vb)) => out.push(DiffEntry {
+                        path: item_path,
+                        kind: DiffKind::Added,
+                        before: None,
+                        after: Some(vb.clone()),
+                    }),
+                    (None, None) => {}
+                }
+            }
+        }
+        _ => {
+            if before != after {
+                out.push(DiffEntry {
+                    path: path.to_string(),
+                    kind: DiffKind::Changed,
+                    before: Some(before.clone()),
+                    after: Some(after.clone()),
+                });
+            }
+        }
+    }
+}
+
+/// 结构化比较两个 JSON 值
+pub fn diff_values(before: &Value, after: &Value) -> Vec<DiffEntry> {
+    let mut out = Vec::new();
+    diff_into("", before, after, &mut out);
+    out
+}
+
+fn parse_body(body: Option<&str>) -> Value {
+    match body {
+        Some(s) => serde_json::from_str(s).unwrap_or_else(|_| Value::String(s.to_string())),
+        None => Value::Null,
+    }
+}
+
+fn log_metadata(log: &ProxyRequestLog) -> Value {
+    json!({
+        "status": log.status,
+        "mapped_model": log.mapped_model,
+        "account_email": log.account_email,
+        "input_tokens": log.input_tokens,
+        "output_tokens": log.output_tokens,
+    })
+}
+
+pub fn diff_logs(original: &ProxyRequestLog, replay: &ProxyRequestLog) -> ResponseDiff {
+    let metadata = diff_values(&log_metadata(original), &log_metadata(replay));
+    let body = diff_values(
+        &parse_body(original.response_body.as_deref()),
+        &parse_body(replay.response_body.as_deref()),
+    );
+    ResponseDiff {
+        identical: metadata.is_empty() && body.is_empty(),
+        metadata,
+        body,
+    }
+}
+
+/// 按需切换流式模式：Gemini 原生协议通过 URL 方法名区分，其余协议修改请求体的 stream 字段
+fn apply_stream_override(url: &str, body: &mut Value, stream: bool) -> String {
+    if !url.contains("/v1beta/models/") {
+        if let Some(obj) = body.as_object_mut() {
+            obj.insert("stream".to_string(), Value::Bool(stream));
+        }
+        return url.to_string();
+    }
+
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let path = if stream {
+        path.replace(":generateContent", ":streamGenerateContent")
+    } else {
+        path.replace(":streamGenerateContent", ":generateContent")
+    };
+    let mut params: Vec<&str> = query
+        .split('&')
+        .filter(|p| !p.is_empty() && !p.starts_with("alt="))
+        .collect();
+    if stream {
+        params.push("alt=sse");
+    }
+    if params.is_empty() {
+        path
+    } else {
+        format!("{}?{}", path, params.join("&"))
+    }
+}
+
+fn resolve_account(
+    state: &AppState,
+    original: &ProxyRequestLog,
+    options: &ReplayOptions,
+) -> Result<Option<String>, String> {
+    let email = match (&options.account_id, options.same_account) {
+        (Some(id), _) if id.contains('@') => id.clone(),
+        (Some(id), _) => state
+            .token_manager
+            .get_token_by_id(id)
+            .map(|t| t.email)
+            .ok_or_else(|| format!("Account not found in pool: {}", id))?,
+        (None, true) => original
+            .account_email
+            .clone()
+            .ok_or("Original request has no account to reuse")?,
+        (None, false) => return Ok(None),
+    };
+    if state.token_manager.get_account_id_by_email(&email).is_none() {
+        return Err(format!("Account not found in pool: {}", email));
+    }
+    Ok(Some(email))
+}
+
+/// 重放一条已记录的请求
+/// `router` 为不含鉴权层的反代路由 (需包含监控中间件，以便记录新日志)
+pub async fn replay_log(
+    state: &AppState,
+    router: Router,
+    log_id: &str,
+    options: ReplayOptions,
+) -> Result<ReplayResult, String> {
+    let original = crate::modules::proxy_db::get_log_detail(log_id)?;
+    if original.method != "POST" {
+        return Err(format!("Only POST requests can be replayed (got {})", original.method));
+    }
+    let mut body: Value = original
+        .request_body
+        .as_deref()
+        .and_then(|s| serde_json::from_str(s).ok())
+        .ok_or("Only logged JSON requests can be replayed")?;
+
+    let account_email = resolve_account(state, &original, &options)?;
+    let url = match options.stream {
+        Some(stream) => apply_stream_override(&original.url, &mut body, stream),
+        None => original.url.clone(),
+    };
+
+    let (tx, rx) = oneshot::channel();
+    let replay_log_id = uuid::Uuid::new_v4().to_string();
+    let ctx = ReplayContext {
+        account_email,
+        mapped_model: options.mapped_model.clone().filter(|m| !m.trim().is_empty()),
+        sink: ReplayLogSink {
+            log_id: replay_log_id.clone(),
+            sender: Arc::new(Mutex::new(Some(tx))),
+        },
+    };
+
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri(&url)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .map_err(|e| e.to_string())?;
+
+    tracing::info!(
+        "[Replay] Replaying log {} -> {} ({} {})",
+        log_id,
+        replay_log_id,
+        original.method,
+        url
+    );
+
+    let response = REPLAY_CONTEXT
+        .scope(ctx, async move { router.oneshot(request).await })
+        .await
+        .map_err(|e| e.to_string())?;
+    // 读完响应体，流式响应在此之后才会生成日志
+    axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .map_err(|e| format!("Failed to read replay response: {}", e))?;
+
+    let log = tokio::time::timeout(std::time::Duration::from_secs(LOG_WAIT_SECS), rx)
+        .await
+        .map_err(|_| "Timed out waiting for the replay log".to_string())?
+        .map_err(|_| "Replay request was not logged".to_string())?;
+
+    // 监控关闭时日志不会落盘，重放结果仍需保存以便关联
+    if !state.monitor.is_enabled() {
+        crate::modules::proxy_db::save_log(&log)?;
+    }
+
+    let record = ReplayRecord {
+        replay_id: log.id.clone(),
+        original_id: original.id.clone(),
+        created_at: chrono::Utc::now().timestamp_millis(),
+        options,
+        diff: diff_logs(&original, &log),
+    };
+    crate::modules::proxy_db::save_replay(&record)?;
+
+    Ok(ReplayResult { record, log })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_values_reports_paths() {
+        let before = json!({
+            "id": "msg_1",
+            "content": "hello",
+            "tool_calls": [{"function": {"name": "read", "arguments": "{}"}}],
+            "thinking": "...",
+        });
+        let after = json!({
+            "id": "msg_2",
+            "content": "hello",
+            "tool_calls": [
+                {"function": {"name": "write", "arguments": "{}"}},
+                {"function": {"name": "read", "arguments": "{}"}}
+            ],
+            "output_tokens": 12,
+        });
+
+        let diff = diff_values(&before, &after);
+        let summary: Vec<(&str, &DiffKind)> = diff.iter().map(|d| (d.path.as_str(), &d.kind)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("tool_calls[0].function.name", &DiffKind::Changed),
+                ("tool_calls[1]", &DiffKind::Added),
+                ("thinking", &DiffKind::Removed),
+                ("output_tokens", &DiffKind::Added),
+            ]
+        );
+        assert!(diff_values(&before, &before).is_empty());
+        // 非 JSON 响应整体比较
+        assert_eq!(diff_values(&json!("a"), &json!("b"))[0].path, "");
+    }
+
+    #[test]
+    fn test_apply_stream_override() {
+        let mut body = json!({"model": "claude-sonnet-4-5", "stream": true});
+        assert_eq!(apply_stream_override("/v1/messages", &mut body, false), "/v1/messages");
+        assert_eq!(body["stream"], json!(false));
+
+        let mut gemini = json!({"contents": []});
+        assert_eq!(
+            apply_stream_override("/v1beta/models/gemini-3-flash:generateContent", &mut gemini, true),
+            "/v1beta/models/gemini-3-flash:streamGenerateContent?alt=sse"
+        );
+        assert_eq!(
+            apply_stream_override(
+                "/v1beta/models/gemini-3-flash:streamGenerateContent?alt=sse&key=k",
+                &mut gemini,
+                false
+            ),
+            "/v1beta/models/gemini-3-flash:generateContent?key=k"
+        );
+        assert!(gemini.get("stream").is_none());
+    }
+}
@@ -1,9 +1,10 @@
 use crate::models::AppConfig;
-use crate::modules::{account, config, logger, migration, proxy_db, security_db, token_stats};
+use crate::modules::{account, config, logger, migration, proxy_db, quota_history, security_db, token_stats};
+use crate::proxy::middleware::auth::ScopedAdmin;
 use crate::proxy::TokenManager;
 use axum::{
     extract::{DefaultBodyLimit, Path, Query, State},
-    http::{HeaderMap, StatusCode},
+    http::{header, HeaderMap, StatusCode},
     response::{Html, IntoResponse, Json, Response},
     routing::{any, delete, get, post},
     Router,
@@ -210,26 +211,127 @@ fn to_account_response(
     }
 }
 
+/// AI 代理路由 (不含中间件层)
+/// 请求重放复用同一套路由，保证重放走与真实请求完全相同的处理链路
+pub(crate) fn ai_routes() -> Router<AppState> {
+    use crate::proxy::handlers;
+
+    Router::new()
+        .route("/health", get(health_check_handler))
+        .route("/healthz", get(health_check_handler))
+        .route("/metrics", get(metrics_handler))
+        // OpenAI Protocol
+        .route("/v1/models", get(handlers::openai::handle_list_models))
+        .route(
+            "/v1/chat/completions",
+            post(handlers::openai::handle_chat_completions),
+        )
+        .route(
+            "/v1/completions",
+            post(handlers::openai::handle_completions),
+        )
+        .route("/v1/responses", post(handlers::openai::handle_completions)) // 兼容 Codex CLI
+        .route(
+            "/v1/responses/:id",
+            get(handlers::openai::handle_get_response).delete(handlers::openai::handle_delete_response),
+        )
+        .route("/v1/embeddings", post(handlers::openai::handle_embeddings))
+        .route(
+            "/v1/images/generations",
+            post(handlers::openai::handle_images_generations),
+        ) // 图像生成 API
+        .route(
+            "/v1/images/edits",
+            post(handlers::openai::handle_images_edits),
+        ) // 图像编辑 API
+        .route(
+            "/v1/files/images/:id",
+            get(handlers::openai::handle_get_image),
+        ) // 生成图片的短期链接
+        .route(
+            "/v1/audio/transcriptions",
+            post(handlers::audio::handle_audio_transcription),
+        ) // 音频转录 API
+        .route(
+            "/v1/audio/translations",
+            post(handlers::audio::handle_audio_translation),
+        ) // 音频翻译 API (输出英文)
+        // Claude Protocol
+        .route("/v1/messages", post(handlers::claude::handle_messages))
+        .route(
+            "/v1/messages/count_tokens",
+            post(handlers::claude::handle_count_tokens),
+        )
+        .route(
+            "/v1/models/claude",
+            get(handlers::claude::handle_list_models),
+        )
+        // z.ai MCP (optional reverse-proxy)
+        .route(
+            "/mcp/web_search_prime/mcp",
+            any(handlers::mcp::handle_web_search_prime),
+        )
+        .route("/mcp/web_reader/mcp", any(handlers::mcp::handle_web_reader))
+        .route(
+            "/mcp/zai-mcp-server/mcp",
+            any(handlers::mcp::handle_zai_mcp_server),
+        )
+        // Gemini Protocol (Native)
+        .route("/v1beta/models", get(handlers::gemini::handle_list_models))
+        // Handle both GET (get info) and POST (generateContent with colon) at the same route
+        .route(
+            "/v1beta/models/:model",
+            get(handlers::gemini::handle_get_model).post(handlers::gemini::handle_generate),
+        )
+        .route(
+            "/v1beta/models/:model/countTokens",
+            post(handlers::gemini::handle_count_tokens),
+        ) // Specific route priority
+        .route(
+            "/v1/models/detect",
+            post(handlers::common::handle_detect_model),
+        )
+        .route("/internal/warmup", post(handlers::warmup::handle_warmup)) // 内部预热端点
+        .route("/v1/api/event_logging/batch", post(silent_ok_handler))
+        .route("/v1/api/event_logging", post(silent_ok_handler))
+}
+
+/// 重放一条已记录的请求 (跳过鉴权与 IP 过滤，保留监控中间件以记录新日志)
+pub async fn replay_logged_request(
+    state: &AppState,
+    log_id: &str,
+    options: crate::proxy::replay::ReplayOptions,
+) -> Result<crate::proxy::replay::ReplayResult, String> {
+    let router = ai_routes()
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            crate::proxy::middleware::monitor_middleware,
+        ))
+        .layer(DefaultBodyLimit::disable())
+        .with_state(state.clone());
+    crate::proxy::replay::replay_log(state, router, log_id, options).await
+}
+
 /// Axum 服务器实例
 #[derive(Clone)]
+#[cfg_attr(not(feature = "desktop"), allow(dead_code))] // 热更新/停服方法仅供 Tauri 命令调用
 pub struct AxumServer {
     shutdown_tx: Arc<tokio::sync::Mutex<Option<oneshot::Sender<()>>>>,
     custom_mapping: Arc<tokio::sync::RwLock<std::collections::HashMap<String, String>>>,
-    proxy_state: Arc<tokio::sync::RwLock<crate::proxy::config::UpstreamProxyConfig>>,
     upstream: Arc<crate::proxy::upstream::client::UpstreamClient>,
     security_state: Arc<RwLock<crate::proxy::ProxySecurityConfig>>,
-    zai_state: Arc<RwLock<crate::proxy::ZaiConfig>>,
-    experimental: Arc<RwLock<crate::proxy::config::ExperimentalConfig>>,
-    debug_logging: Arc<RwLock<crate::proxy::config::DebugLoggingConfig>>,
     #[allow(dead_code)] // 预留给 cloudflared 运行状态查询与后续控制
     pub cloudflared_state: Arc<crate::commands::cloudflared::CloudflaredState>,
     pub is_running: Arc<RwLock<bool>>,
     pub token_manager: Arc<TokenManager>, // [NEW] 暴露出 TokenManager 供反代服务复用
     pub proxy_pool_state: Arc<tokio::sync::RwLock<crate::proxy::config::ProxyPoolConfig>>, // [NEW] 代理池配置状态
     pub proxy_pool_manager: Arc<crate::proxy::proxy_pool::ProxyPoolManager>, // [NEW] 暴露代理池管理器供命令调用
+    app_state: AppState, // 统一热更新入口使用
+    config_watcher: Arc<crate::proxy::config_reload::ConfigWatcher>, // 配置文件/SIGHUP 监听，停服时中止
 }
 
 impl AxumServer {
+    #[cfg(feature = "desktop")]
     pub async fn update_mapping(&self, config: &crate::proxy::config::ProxyConfig) {
         {
             let mut m = self.custom_mapping.write().await;
@@ -238,49 +340,38 @@ impl AxumServer {
         tracing::debug!("模型映射 (Custom) 已全量热更新");
     }
 
-    /// 更新代理配置
-    pub async fn update_proxy(&self, new_config: crate::proxy::config::UpstreamProxyConfig) {
-        let mut proxy = self.proxy_state.write().await;
-        *proxy = new_config;
-        tracing::info!("上游代理配置已热更新");
-    }
-
-    /// 更新代理池配置
-    pub async fn update_proxy_pool(&self, new_config: crate::proxy::config::ProxyPoolConfig) {
-        let mut pool = self.proxy_pool_state.write().await;
-        *pool = new_config;
-        tracing::info!("代理池配置已热更新");
-    }
-
+    #[cfg(feature = "desktop")]
     pub async fn update_security(&self, config: &crate::proxy::config::ProxyConfig) {
         let mut sec = self.security_state.write().await;
         *sec = crate::proxy::ProxySecurityConfig::from_proxy_config(config);
         tracing::info!("反代服务安全配置已热更新");
     }
 
-    pub async fn update_zai(&self, config: &crate::proxy::config::ProxyConfig) {
-        let mut zai = self.zai_state.write().await;
-        *zai = config.zai.clone();
-        tracing::info!("z.ai 配置已热更新");
+    /// 更新 Prometheus 指标导出开关
+    pub async fn update_metrics(&self, config: &crate::proxy::config::ProxyConfig) {
+        crate::proxy::metrics::set_enabled(config.metrics.enabled);
+        tracing::info!("Prometheus 指标导出已{}", if config.metrics.enabled { "启用" } else { "禁用" });
     }
 
-    pub async fn update_experimental(&self, config: &crate::proxy::config::ProxyConfig) {
-        let mut exp = self.experimental.write().await;
-        *exp = config.experimental.clone();
-        tracing::info!("实验性配置已热更新");
+    pub async fn update_upstream_endpoints(&self, config: &crate::proxy::config::ProxyConfig) {
+        self.upstream.set_endpoints(&config.upstream_endpoints).await;
+        tracing::info!("上游端点配置已热更新");
     }
 
-    pub async fn update_debug_logging(&self, config: &crate::proxy::config::ProxyConfig) {
-        let mut dbg_cfg = self.debug_logging.write().await;
-        *dbg_cfg = config.debug_logging.clone();
-        tracing::info!("调试日志配置已热更新");
+    /// 重放一条已记录的请求
+    #[cfg(feature = "desktop")]
+    pub async fn replay_log(
+        &self,
+        log_id: &str,
+        options: crate::proxy::replay::ReplayOptions,
+    ) -> Result<crate::proxy::replay::ReplayResult, String> {
+        replay_logged_request(&self.app_state, log_id, options).await
     }
 
-    pub async fn update_user_agent(&self, config: &crate::proxy::config::ProxyConfig) {
-        self.upstream
-            .set_user_agent_override(config.user_agent_override.clone())
-            .await;
-        tracing::info!("User-Agent 配置已热更新: {:?}", config.user_agent_override);
+    /// 将完整配置热更新到运行中的服务 (监听地址与端口除外)
+    #[cfg(feature = "desktop")]
+    pub async fn apply_config(&self, config: &AppConfig) {
+        crate::proxy::config_reload::apply_to_state(&self.app_state, config).await;
     }
 
     pub async fn set_running(&self, running: bool) {
@@ -362,77 +453,13 @@ impl AxumServer {
         };
 
         // 构建路由 - 使用新架构的 handlers！
-        use crate::proxy::handlers;
         use crate::proxy::middleware::{
-            admin_auth_middleware, auth_middleware, cors_layer, ip_filter_middleware,
-            monitor_middleware, service_status_middleware,
+            admin_auth_middleware, audit_middleware, auth_middleware, cors_layer,
+            ip_filter_middleware, monitor_middleware, service_status_middleware,
         };
 
         // 1. 构建主 AI 代理路由 (遵循 auth_mode 配置)
-        let proxy_routes = Router::new()
-            .route("/health", get(health_check_handler))
-            .route("/healthz", get(health_check_handler))
-            // OpenAI Protocol
-            .route("/v1/models", get(handlers::openai::handle_list_models))
-            .route(
-                "/v1/chat/completions",
-                post(handlers::openai::handle_chat_completions),
-            )
-            .route(
-                "/v1/completions",
-                post(handlers::openai::handle_completions),
-            )
-            .route("/v1/responses", post(handlers::openai::handle_completions)) // 兼容 Codex CLI
-            .route(
-                "/v1/images/generations",
-                post(handlers::openai::handle_images_generations),
-            ) // 图像生成 API
-            .route(
-                "/v1/images/edits",
-                post(handlers::openai::handle_images_edits),
-            ) // 图像编辑 API
-            .route(
-                "/v1/audio/transcriptions",
-                post(handlers::audio::handle_audio_transcription),
-            ) // 音频转录 API
-            // Claude Protocol
-            .route("/v1/messages", post(handlers::claude::handle_messages))
-            .route(
-                "/v1/messages/count_tokens",
-                post(handlers::claude::handle_count_tokens),
-            )
-            .route(
-                "/v1/models/claude",
-                get(handlers::claude::handle_list_models),
-            )
-            // z.ai MCP (optional reverse-proxy)
-            .route(
-                "/mcp/web_search_prime/mcp",
-                any(handlers::mcp::handle_web_search_prime),
-            )
-            .route("/mcp/web_reader/mcp", any(handlers::mcp::handle_web_reader))
-            .route(
-                "/mcp/zai-mcp-server/mcp",
-                any(handlers::mcp::handle_zai_mcp_server),
-            )
-            // Gemini Protocol (Native)
-            .route("/v1beta/models", get(handlers::gemini::handle_list_models))
-            // Handle both GET (get info) and POST (generateContent with colon) at the same route
-            .route(
-                "/v1beta/models/:model",
-                get(handlers::gemini::handle_get_model).post(handlers::gemini::handle_generate),
-            )
-            .route(
-                "/v1beta/models/:model/countTokens",
-                post(handlers::gemini::handle_count_tokens),
-            ) // Specific route priority
-            .route(
-                "/v1/models/detect",
-                post(handlers::common::handle_detect_model),
-            )
-            .route("/internal/warmup", post(handlers::warmup::handle_warmup)) // 内部预热端点
-            .route("/v1/api/event_logging/batch", post(silent_ok_handler))
-            .route("/v1/api/event_logging", post(silent_ok_handler))
+        let proxy_routes = ai_routes()
             // 应用 AI 服务特定的层
             // 注意：Axum layer 执行顺序是从下往上（洋葱模型）
             // 请求: ip_filter -> auth -> monitor -> handler
@@ -502,6 +529,12 @@ impl AxumServer {
             .route("/stats/accounts", get(admin_get_token_stats_by_account))
             .route("/stats/models", get(admin_get_token_stats_by_model))
             .route("/config", get(admin_get_config).post(admin_save_config))
+            .route("/config/validate", post(admin_validate_config))
+            .route(
+                "/proxy/background-rules",
+                get(admin_get_background_task_rules).post(admin_save_background_task_rules),
+            )
+            .route("/proxy/background-rules/test", post(admin_test_background_task_rules))
             .route("/proxy/cli/status", post(admin_get_cli_sync_status))
             .route("/proxy/cli/sync", post(admin_execute_cli_sync))
             .route("/proxy/cli/restore", post(admin_execute_cli_restore))
@@ -570,12 +603,19 @@ impl AxumServer {
             .route("/logs/count", get(admin_get_proxy_logs_count_filtered))
             .route("/logs/clear", post(admin_clear_proxy_logs))
             .route("/logs/:logId", get(admin_get_proxy_log_detail))
+            .route("/logs/:logId/replay", post(admin_replay_proxy_log))
+            .route("/logs/:logId/replays", get(admin_get_proxy_log_replays))
+            // 审计日志
+            .route("/audit/logs", get(admin_get_audit_logs))
+            .route("/audit/export", get(admin_export_audit_logs))
             // Debug Console (Log Bridge)
             .route("/debug/enable", post(admin_enable_debug_console))
             .route("/debug/disable", post(admin_disable_debug_console))
             .route("/debug/enabled", get(admin_is_debug_console_enabled))
             .route("/debug/logs", get(admin_get_debug_console_logs))
             .route("/debug/logs/clear", post(admin_clear_debug_console_logs))
+            // 后台事件流 (SSE)，与桌面版 Tauri 事件同名
+            .route("/events", get(admin_events_stream))
             .route("/stats/token/clear", post(admin_clear_token_stats))
             .route("/stats/token/hourly", get(admin_get_token_stats_hourly))
             .route("/stats/token/daily", get(admin_get_token_stats_daily))
@@ -602,9 +642,17 @@ impl AxumServer {
                 "/stats/token/account-trend/daily",
                 get(admin_get_token_stats_account_trend_daily),
             )
+            .route("/stats/quota/history", get(admin_get_quota_history))
+            .route("/stats/quota/forecast", get(admin_get_quota_forecast))
+            .route("/stats/quota/pool-forecast", get(admin_get_pool_quota_forecast))
             .route("/accounts/bulk-delete", post(admin_delete_accounts))
             .route("/accounts/export", post(admin_export_accounts))
             .route("/accounts/reorder", post(admin_reorder_accounts))
+            .route(
+                "/account-groups",
+                get(admin_list_account_groups).post(admin_save_account_group),
+            )
+            .route("/account-groups/:name", delete(admin_delete_account_group))
             .route("/accounts/:accountId/quota", get(admin_fetch_account_quota))
             .route(
                 "/accounts/:accountId/toggle-proxy",
@@ -655,12 +703,20 @@ impl AxumServer {
             .route("/user-tokens/summary", get(admin_get_user_token_summary))
             .route("/user-tokens/:id/renew", post(admin_renew_user_token))
             .route("/user-tokens/:id", delete(admin_delete_user_token).patch(admin_update_user_token))
+            // 具名管理密钥 (仅主凭据可管理)
+            .route("/admin-keys", get(admin_list_admin_keys).post(admin_create_admin_key))
+            .route("/admin-keys/:id", delete(admin_revoke_admin_key))
             // OAuth (Web) - Admin 接口
             .route("/auth/url", get(admin_prepare_oauth_url_web))
             // 应用管理特定鉴权层 (强制校验)
             .layer(axum::middleware::from_fn_with_state(
                 state.clone(),
                 admin_auth_middleware,
+            ))
+            // 审计层在鉴权之外，鉴权失败的变更尝试同样会被记录
+            .layer(axum::middleware::from_fn_with_state(
+                state.clone(),
+                audit_middleware,
             ));
 
         // 3. 整合并应用全局层
@@ -707,20 +763,23 @@ impl AxumServer {
         // 创建关闭通道
         let (shutdown_tx, mut shutdown_rx) = oneshot::channel::<()>();
 
+        // 监听 gui_config.json 变更与 SIGHUP，自动重载配置 (绑定到本实例的 state)
+        let config_watcher = Arc::new(crate::proxy::config_reload::spawn_config_watcher(
+            state.clone(),
+        ));
+
         let server_instance = Self {
             shutdown_tx: Arc::new(tokio::sync::Mutex::new(Some(shutdown_tx))),
             custom_mapping: custom_mapping_state.clone(),
-            proxy_state,
             upstream: state.upstream.clone(),
             security_state,
-            zai_state,
-            experimental: experimental_state.clone(),
-            debug_logging: debug_logging_state.clone(),
             cloudflared_state,
             is_running: is_running_state,
             token_manager: token_manager.clone(),
             proxy_pool_state,
             proxy_pool_manager,
+            app_state: state.clone(),
+            config_watcher: config_watcher.clone(),
         };
 
         // 在新任务中启动服务器
@@ -767,13 +826,16 @@ impl AxumServer {
                     }
                 }
             }
+            config_watcher.abort();
         });
 
         Ok((server_instance, handle))
     }
 
     /// 停止服务器
+    #[cfg(feature = "desktop")]
     pub fn stop(&self) {
+        self.config_watcher.abort();
         let tx_mutex = self.shutdown_tx.clone();
         tokio::spawn(async move {
             let mut lock = tx_mutex.lock().await;
@@ -796,6 +858,40 @@ async fn health_check_handler() -> Response {
     .into_response()
 }
 
+/// Prometheus 指标导出 (未启用时返回 404)
+async fn metrics_handler(State(state): State<AppState>) -> Response {
+    if !crate::proxy::metrics::is_enabled() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let rate_limited = state.token_manager.rate_limited_accounts_by_reason();
+    let proxies: Vec<crate::proxy::metrics::ProxyHealthSample> = {
+        let pool = state.proxy_pool_state.read().await;
+        if pool.enabled {
+            pool.proxies
+                .iter()
+                .map(|p| crate::proxy::metrics::ProxyHealthSample {
+                    id: p.id.clone(),
+                    name: p.name.clone(),
+                    enabled: p.enabled,
+                    healthy: p.is_healthy,
+                    latency_ms: p.latency,
+                    in_flight: state.proxy_pool_manager.in_flight_count(&p.id),
+                })
+                .collect()
+        } else {
+            Vec::new()
+        }
+    };
+
+    let body = crate::proxy::metrics::global().render(&rate_limited, &proxies);
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")],
+        body,
+    )
+        .into_response()
+}
+
 /// 静默成功处理器 (用于拦截遥测日志等)
 async fn silent_ok_handler() -> Response {
     StatusCode::OK.into_response()
@@ -1281,13 +1377,25 @@ async fn admin_get_logs(
     })))
 }
 
-async fn admin_get_config() -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    let cfg = config::load_app_config().map_err(|e| {
+/// 管理密钥看到的主凭据占位符
+const MASKED_CREDENTIAL: &str = "********";
+
+async fn admin_get_config(
+    scoped: Option<axum::Extension<ScopedAdmin>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let mut cfg = config::load_app_config().map_err(|e| {
         (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(ErrorResponse { error: e }),
         )
     })?;
+    // 管理密钥不能读取主凭据，避免借此提升权限
+    if scoped.is_some() {
+        cfg.proxy.api_key = MASKED_CREDENTIAL.to_string();
+        if cfg.proxy.admin_password.is_some() {
+            cfg.proxy.admin_password = Some(MASKED_CREDENTIAL.to_string());
+        }
+    }
     Ok(Json(cfg))
 }
 
@@ -1299,10 +1407,29 @@ struct SaveConfigWrapper {
 
 async fn admin_save_config(
     State(state): State<AppState>,
+    scoped: Option<axum::Extension<ScopedAdmin>>,
     Json(payload): Json<SaveConfigWrapper>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    let new_config = payload.config;
-    // 1. 持久化
+    let mut new_config = payload.config;
+    // 管理密钥不能修改主凭据，始终沿用当前值
+    if let Some(axum::Extension(ScopedAdmin(key))) = &scoped {
+        tracing::info!("Config saved by admin key '{}', keeping master credentials", key.name);
+        let current = crate::proxy::config_reload::running_config();
+        new_config.proxy.api_key = current.proxy.api_key;
+        new_config.proxy.admin_password = current.proxy.admin_password;
+    }
+    // 1. 校验 (存在错误时拒绝保存)
+    let issues = crate::proxy::config_reload::validate(&new_config);
+    if crate::modules::config_validation::has_errors(&issues) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: crate::modules::config_validation::summarize_errors(&issues),
+            }),
+        ));
+    }
+
+    // 2. 持久化
     config::save_app_config(&new_config).map_err(|e| {
         (
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -1310,51 +1437,77 @@ async fn admin_save_config(
         )
     })?;
 
-    // 2. 热更新内存状态
-    // 这里我们直接复用内部组件的 update 方法
-    // 注意：AppState 本身持有各个组件的 Arc<RwLock> 或直接持有引用
+    // 3. 热更新内存状态
+    crate::proxy::config_reload::apply_to_state(&state, &new_config).await;
 
-    // 我们需要一个方式获取到当前的 AxumServer 实例来进行热更新，
-    // 或者直接操作 AppState 里的各状态。
-    // 在本重构中，各个状态已经在 AppState 中了。
+    Ok(StatusCode::OK)
+}
 
-    // 更新模型映射
-    {
-        let mut mapping = state.custom_mapping.write().await;
-        *mapping = new_config.clone().proxy.custom_mapping;
-    }
+/// 配置预检 (dry-run)：返回字段级校验结果与相对运行中配置的差异，不写盘
+async fn admin_validate_config(
+    Json(payload): Json<SaveConfigWrapper>,
+) -> impl IntoResponse {
+    let report = crate::modules::config_validation::dry_run(
+        &crate::proxy::config_reload::running_config(),
+        &payload.config,
+        &crate::proxy::config_reload::known_models(),
+    );
+    Json(report)
+}
 
-    // 更新上游代理
-    {
-        let mut proxy = state.upstream_proxy.write().await;
-        *proxy = new_config.clone().proxy.upstream_proxy;
-    }
+async fn admin_get_background_task_rules() -> impl IntoResponse {
+    Json(crate::proxy::config_reload::running_config().proxy.background_task_rules)
+}
 
-    // 更新安全策略
-    {
-        let mut security = state.security.write().await;
-        *security = crate::proxy::ProxySecurityConfig::from_proxy_config(&new_config.proxy);
-    }
+#[derive(Deserialize)]
+struct SaveBackgroundRulesRequest {
+    rules: Vec<crate::proxy::config::BackgroundTaskRule>,
+}
 
-    // 更新 z.ai 配置
-    {
-        let mut zai = state.zai.write().await;
-        *zai = new_config.clone().proxy.zai;
-    }
+/// 替换后台任务规则：与整体保存配置走相同的校验、持久化与热更新流程
+async fn admin_save_background_task_rules(
+    State(state): State<AppState>,
+    Json(payload): Json<SaveBackgroundRulesRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let mut new_config = config::load_app_config().map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse { error: e }),
+        )
+    })?;
+    new_config.proxy.background_task_rules = payload.rules;
 
-    // 更新实验性配置
-    {
-        let mut exp = state.experimental.write().await;
-        *exp = new_config.clone().proxy.experimental;
+    let issues = crate::proxy::config_reload::validate(&new_config);
+    if crate::modules::config_validation::has_errors(&issues) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: crate::modules::config_validation::summarize_errors(&issues),
+            }),
+        ));
     }
 
-    // 更新代理池配置（Web/Docker 保存配置时热更新）
-    {
-        let mut pool = state.proxy_pool_state.write().await;
-        *pool = new_config.clone().proxy.proxy_pool;
-    }
+    config::save_app_config(&new_config).map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse { error: e }),
+        )
+    })?;
+    crate::proxy::config_reload::apply_to_state(&state, &new_config).await;
 
-    Ok(StatusCode::OK)
+    Ok(Json(new_config.proxy.background_task_rules))
+}
+
+async fn admin_test_background_task_rules(
+    Json(payload): Json<crate::proxy::common::background_task::RuleTestRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let report = crate::proxy::common::background_task::test_rules(payload).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse { error: e }),
+        )
+    })?;
+    Ok(Json(report))
 }
 
 // [FIX Web Mode] Get proxy pool config
@@ -1715,6 +1868,91 @@ async fn admin_get_proxy_log_detail(
     }
 }
 
+async fn admin_replay_proxy_log(
+    State(state): State<AppState>,
+    Path(log_id): Path<String>,
+    Json(options): Json<crate::proxy::replay::ReplayOptions>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    replay_logged_request(&state, &log_id, options)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })))
+}
+
+async fn admin_get_proxy_log_replays(
+    Path(log_id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let res = tokio::task::spawn_blocking(move || proxy_db::get_replays(&log_id)).await;
+
+    match res {
+        Ok(Ok(records)) => Ok(Json(records)),
+        Ok(Err(e)) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse { error: e }),
+        )),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )),
+    }
+}
+
+async fn admin_get_audit_logs(
+    Query(query): Query<crate::modules::audit_log::AuditQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let res = tokio::task::spawn_blocking(move || crate::modules::audit_log::query_entries(&query)).await;
+
+    match res {
+        Ok(Ok(entries)) => Ok(Json(entries)),
+        Ok(Err(e)) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse { error: e }),
+        )),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )),
+    }
+}
+
+/// 导出审计日志 (JSON 附件，忽略分页参数)
+async fn admin_export_audit_logs(
+    Query(query): Query<crate::modules::audit_log::AuditQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let entries = tokio::task::spawn_blocking(move || crate::modules::audit_log::export_entries(&query))
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|r| r)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: e })))?;
+
+    let json = serde_json::to_string_pretty(&entries).map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+    let filename = format!(
+        "audit_log_{}.json",
+        chrono::Local::now().format("%Y%m%d_%H%M%S")
+    );
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/json".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        json,
+    ))
+}
+
 #[derive(Deserialize, Debug, Default)]
 #[serde(rename_all = "camelCase")]
 struct LogsFilterQuery {
@@ -1728,6 +1966,25 @@ struct LogsFilterQuery {
     offset: usize,
 }
 
+/// 将事件总线上的事件以 SSE 推送给 Web 端 (event 字段为事件名，data 为 JSON 负载)
+async fn admin_events_stream() -> Response {
+    use axum::response::sse::{Event, KeepAlive, Sse};
+    use futures::StreamExt;
+
+    let stream = tokio_stream::wrappers::BroadcastStream::new(crate::modules::events::subscribe())
+        .filter_map(|item| async move {
+            // 客户端消费过慢时 (Lagged) 跳过丢失的事件
+            let event = item.ok()?;
+            Event::default()
+                .event(event.name)
+                .json_data(event.payload)
+                .ok()
+                .map(Ok::<_, std::convert::Infallible>)
+        });
+
+    Sse::new(stream).keep_alive(KeepAlive::default()).into_response()
+}
+
 async fn admin_get_proxy_logs_filtered(
     Query(params): Query<LogsFilterQuery>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
@@ -1772,6 +2029,40 @@ async fn admin_get_data_dir_path() -> impl IntoResponse {
 
 // --- User Token Handlers ---
 
+async fn admin_list_admin_keys() -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let keys = crate::commands::admin_key::list_admin_keys().await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse { error: e }),
+        )
+    })?;
+    Ok(Json(keys))
+}
+
+async fn admin_create_admin_key(
+    Json(payload): Json<crate::commands::admin_key::CreateAdminKeyRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let created = crate::commands::admin_key::create_admin_key(payload).await.map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse { error: e }),
+        )
+    })?;
+    Ok(Json(created))
+}
+
+async fn admin_revoke_admin_key(
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    crate::commands::admin_key::revoke_admin_key(id).await.map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse { error: e }),
+        )
+    })?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
 async fn admin_list_user_tokens() -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
     let tokens = crate::commands::user_token::list_user_tokens().await.map_err(|e| {
         (
@@ -2141,6 +2432,80 @@ async fn admin_get_token_stats_account_trend_daily(
     }
 }
 
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QuotaHistoryQuery {
+    account_id: Option<String>,
+    model: Option<String>,
+    hours: Option<i64>,
+}
+
+async fn admin_get_quota_history(
+    Query(p): Query<QuotaHistoryQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let hours = p.hours.unwrap_or(24);
+    let res = tokio::task::spawn_blocking(move || {
+        quota_history::get_quota_history(p.account_id.as_deref(), p.model.as_deref(), hours)
+    })
+    .await;
+
+    match res {
+        Ok(Ok(history)) => Ok(Json(history)),
+        Ok(Err(e)) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse { error: e }),
+        )),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )),
+    }
+}
+
+async fn admin_get_quota_forecast(
+    Query(p): Query<QuotaHistoryQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let res = tokio::task::spawn_blocking(move || {
+        quota_history::get_quota_forecast(p.account_id.as_deref())
+    })
+    .await;
+
+    match res {
+        Ok(Ok(forecast)) => Ok(Json(forecast)),
+        Ok(Err(e)) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse { error: e }),
+        )),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )),
+    }
+}
+
+async fn admin_get_pool_quota_forecast(
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let res = tokio::task::spawn_blocking(quota_history::get_pool_quota_forecast).await;
+
+    match res {
+        Ok(Ok(forecast)) => Ok(Json(forecast)),
+        Ok(Err(e)) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse { error: e }),
+        )),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )),
+    }
+}
+
 async fn admin_clear_token_stats() -> impl IntoResponse {
     let res = tokio::task::spawn_blocking(|| {
         // Clear databases (brute force)
@@ -2272,6 +2637,40 @@ async fn admin_reorder_accounts(
     Ok(StatusCode::OK)
 }
 
+async fn admin_list_account_groups() -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let groups = crate::modules::account::list_account_groups().map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse { error: e }),
+        )
+    })?;
+    Ok(Json(groups))
+}
+
+async fn admin_save_account_group(
+    Json(payload): Json<crate::models::AccountGroup>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let group = crate::modules::account::save_account_group(payload).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse { error: e }),
+        )
+    })?;
+    Ok(Json(group))
+}
+
+async fn admin_delete_account_group(
+    Path(name): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    crate::modules::account::delete_account_group(&name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse { error: e }),
+        )
+    })?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
 async fn admin_fetch_account_quota(
     Path(account_id): Path<String>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
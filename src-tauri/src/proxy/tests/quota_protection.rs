@@ -40,6 +40,8 @@ mod tests {
             validation_url: None,
             model_quotas: std::collections::HashMap::new(),
             model_limits: std::collections::HashMap::new(),
+            exhausting_models: std::collections::HashSet::new(),
+            groups: std::collections::HashSet::new(),
         }
     }
 
@@ -1145,6 +1147,8 @@ mod tests {
             validation_url: None,
             model_quotas: std::collections::HashMap::new(),
             model_limits: std::collections::HashMap::new(),
+            exhausting_models: std::collections::HashSet::new(),
+            groups: std::collections::HashSet::new(),
         }
     }
 }
@@ -0,0 +1,197 @@
+//! Trusted Proxy Tests
+//! 受信任代理与客户端 IP 解析测试
+//!
+//! 测试目标:
+//! 1. 非受信任对端的转发头一律忽略 (防止伪造 X-Forwarded-For)
+//! 2. 转发链从右向左遍历，跳过受信任代理
+//! 3. 支持 RFC 7239 `Forwarded` 头
+//! 4. 支持 IPv4/IPv6 CIDR 匹配
+
+#[cfg(test)]
+mod client_ip_resolution_tests {
+    use crate::proxy::middleware::ip_filter::{is_trusted_proxy, resolve_client_ip};
+    use axum::http::{HeaderMap, HeaderValue};
+    use std::net::IpAddr;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in pairs {
+            map.append(*name, HeaderValue::from_static(value));
+        }
+        map
+    }
+
+    fn ip(s: &str) -> Option<IpAddr> {
+        Some(s.parse().unwrap())
+    }
+
+    fn trusted(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    // ============================================================================
+    // 测试类别 1: 未配置 / 非受信任对端
+    // ============================================================================
+
+    #[test]
+    fn test_untrusted_peer_ignores_forwarding_headers() {
+        // 局域网客户端伪造 X-Forwarded-For，应直接使用 TCP 对端地址
+        let h = headers(&[
+            ("x-forwarded-for", "1.2.3.4"),
+            ("x-real-ip", "5.6.7.8"),
+            ("forwarded", "for=9.9.9.9"),
+        ]);
+        let resolved = resolve_client_ip(&h, ip("192.168.1.50"), &[]);
+        assert_eq!(resolved.as_deref(), Some("192.168.1.50"));
+
+        let resolved = resolve_client_ip(&h, ip("192.168.1.50"), &trusted(&["10.0.0.0/8"]));
+        assert_eq!(resolved.as_deref(), Some("192.168.1.50"));
+    }
+
+    #[test]
+    fn test_missing_peer_returns_none() {
+        let h = headers(&[("x-forwarded-for", "1.2.3.4")]);
+        assert_eq!(resolve_client_ip(&h, None, &trusted(&["127.0.0.1"])), None);
+    }
+
+    // ============================================================================
+    // 测试类别 2: X-Forwarded-For 链
+    // ============================================================================
+
+    #[test]
+    fn test_xff_walks_right_to_left() {
+        // 客户端伪造最左侧条目，nginx (10.0.0.2) 追加了真实地址 203.0.113.7
+        let h = headers(&[("x-forwarded-for", "6.6.6.6, 203.0.113.7, 10.0.0.2")]);
+        let resolved = resolve_client_ip(&h, ip("127.0.0.1"), &trusted(&["127.0.0.1", "10.0.0.0/8"]));
+        assert_eq!(resolved.as_deref(), Some("203.0.113.7"));
+    }
+
+    #[test]
+    fn test_xff_multiple_header_lines() {
+        let h = headers(&[
+            ("x-forwarded-for", "198.51.100.1"),
+            ("x-forwarded-for", "10.1.2.3"),
+        ]);
+        let resolved = resolve_client_ip(&h, ip("10.0.0.1"), &trusted(&["10.0.0.0/8"]));
+        assert_eq!(resolved.as_deref(), Some("198.51.100.1"));
+    }
+
+    #[test]
+    fn test_xff_all_trusted_returns_leftmost() {
+        let h = headers(&[("x-forwarded-for", "10.0.0.5, 10.0.0.6")]);
+        let resolved = resolve_client_ip(&h, ip("10.0.0.7"), &trusted(&["10.0.0.0/8"]));
+        assert_eq!(resolved.as_deref(), Some("10.0.0.5"));
+    }
+
+    #[test]
+    fn test_xff_garbage_entry_stops_walk() {
+        // 无法解析的条目终止遍历，返回最近一跳受信任代理
+        let h = headers(&[("x-forwarded-for", "1.2.3.4, not-an-ip, 10.0.0.9")]);
+        let resolved = resolve_client_ip(&h, ip("10.0.0.1"), &trusted(&["10.0.0.0/8"]));
+        assert_eq!(resolved.as_deref(), Some("10.0.0.9"));
+    }
+
+    #[test]
+    fn test_xff_non_ascii_entry_stops_walk() {
+        // 非 ASCII 条目与其他无法解析的条目一样终止遍历，不会跨过它取更左侧的地址
+        let mut h = HeaderMap::new();
+        h.append(
+            "x-forwarded-for",
+            HeaderValue::from_bytes(b"1.2.3.4, caf\xc3\xa9, 10.0.0.9").unwrap(),
+        );
+        let resolved = resolve_client_ip(&h, ip("10.0.0.1"), &trusted(&["10.0.0.0/8"]));
+        assert_eq!(resolved.as_deref(), Some("10.0.0.9"));
+
+        // 单个坏条目不影响链中右侧的有效地址
+        let mut h = HeaderMap::new();
+        h.append("x-forwarded-for", HeaderValue::from_bytes(b"\xff\xfe").unwrap());
+        h.append("x-forwarded-for", HeaderValue::from_static("203.0.113.5, 10.0.0.9"));
+        let resolved = resolve_client_ip(&h, ip("10.0.0.1"), &trusted(&["10.0.0.0/8"]));
+        assert_eq!(resolved.as_deref(), Some("203.0.113.5"));
+    }
+
+    #[test]
+    fn test_xff_entries_with_ports() {
+        let h = headers(&[("x-forwarded-for", "203.0.113.9:51234, [2001:db8::1]:443")]);
+        let resolved = resolve_client_ip(&h, ip("127.0.0.1"), &trusted(&["127.0.0.1"]));
+        assert_eq!(resolved.as_deref(), Some("2001:db8::1"));
+    }
+
+    #[test]
+    fn test_x_real_ip_used_when_no_chain() {
+        let h = headers(&[("x-real-ip", "198.51.100.20")]);
+        let resolved = resolve_client_ip(&h, ip("127.0.0.1"), &trusted(&["127.0.0.1"]));
+        assert_eq!(resolved.as_deref(), Some("198.51.100.20"));
+    }
+
+    #[test]
+    fn test_trusted_peer_without_headers_returns_peer() {
+        let h = HeaderMap::new();
+        let resolved = resolve_client_ip(&h, ip("127.0.0.1"), &trusted(&["127.0.0.1"]));
+        assert_eq!(resolved.as_deref(), Some("127.0.0.1"));
+    }
+
+    // ============================================================================
+    // 测试类别 3: RFC 7239 Forwarded
+    // ============================================================================
+
+    #[test]
+    fn test_forwarded_header_basic() {
+        let h = headers(&[("forwarded", "for=192.0.2.60;proto=http;by=203.0.113.43")]);
+        let resolved = resolve_client_ip(&h, ip("127.0.0.1"), &trusted(&["127.0.0.1"]));
+        assert_eq!(resolved.as_deref(), Some("192.0.2.60"));
+    }
+
+    #[test]
+    fn test_forwarded_header_chain_and_ipv6() {
+        let h = headers(&[(
+            "forwarded",
+            r#"for=6.6.6.6, For="[2001:db8:cafe::17]:4711", for=10.0.0.3"#,
+        )]);
+        let resolved = resolve_client_ip(&h, ip("10.0.0.1"), &trusted(&["10.0.0.0/8"]));
+        assert_eq!(resolved.as_deref(), Some("2001:db8:cafe::17"));
+    }
+
+    #[test]
+    fn test_forwarded_takes_precedence_over_xff() {
+        let h = headers(&[
+            ("forwarded", "for=192.0.2.1"),
+            ("x-forwarded-for", "192.0.2.2"),
+        ]);
+        let resolved = resolve_client_ip(&h, ip("127.0.0.1"), &trusted(&["127.0.0.1"]));
+        assert_eq!(resolved.as_deref(), Some("192.0.2.1"));
+    }
+
+    #[test]
+    fn test_forwarded_obfuscated_identifier() {
+        // `for=unknown` / `for=_hidden` 无法作为客户端 IP，返回最近一跳受信任代理
+        let h = headers(&[("forwarded", "for=unknown, for=10.0.0.4")]);
+        let resolved = resolve_client_ip(&h, ip("10.0.0.1"), &trusted(&["10.0.0.0/8"]));
+        assert_eq!(resolved.as_deref(), Some("10.0.0.4"));
+    }
+
+    // ============================================================================
+    // 测试类别 4: 受信任代理匹配
+    // ============================================================================
+
+    #[test]
+    fn test_trusted_proxy_matching() {
+        let list = trusted(&["127.0.0.1", "10.0.0.0/8", " 172.16.0.0/12 ", "fd00::/8", "bogus", "1.2.3.4/40"]);
+
+        assert!(is_trusted_proxy(&"127.0.0.1".parse().unwrap(), &list));
+        assert!(!is_trusted_proxy(&"127.0.0.2".parse().unwrap(), &list));
+        assert!(is_trusted_proxy(&"10.255.1.1".parse().unwrap(), &list));
+        assert!(is_trusted_proxy(&"172.31.255.255".parse().unwrap(), &list));
+        assert!(!is_trusted_proxy(&"172.32.0.1".parse().unwrap(), &list));
+        assert!(is_trusted_proxy(&"fd12:3456::1".parse().unwrap(), &list));
+        assert!(!is_trusted_proxy(&"2001:db8::1".parse().unwrap(), &list));
+        assert!(!is_trusted_proxy(&"1.2.3.4".parse().unwrap(), &list));
+    }
+
+    #[test]
+    fn test_ipv4_mapped_peer_is_normalized() {
+        let h = headers(&[("x-forwarded-for", "198.51.100.3")]);
+        let resolved = resolve_client_ip(&h, ip("::ffff:127.0.0.1"), &trusted(&["127.0.0.1"]));
+        assert_eq!(resolved.as_deref(), Some("198.51.100.3"));
+    }
+}
@@ -55,6 +55,8 @@ fn create_test_token(
         validation_url: None,
         model_quotas,
         model_limits: std::collections::HashMap::new(),
+        exhausting_models: HashSet::new(),
+        groups: HashSet::new(),
     }
 }
 
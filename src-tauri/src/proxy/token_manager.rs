@@ -36,12 +36,14 @@ pub struct ProxyToken {
     pub validation_url: Option<String>,    // [NEW] Validation URL (#1522)
     pub model_quotas: HashMap<String, i32>, // [OPTIMIZATION] In-memory cache for model-specific quotas
     pub model_limits: HashMap<String, u64>, // [NEW] max_output_tokens per model from quota data
+    pub exhausting_models: HashSet<String>, // 按配额历史预测将在刷新前耗尽的模型 (标准 ID)
+    pub groups: HashSet<String>,            // 所属账号分组 (用户令牌按分组路由)
 }
 
 pub struct TokenManager {
     tokens: Arc<DashMap<String, ProxyToken>>, // account_id -> ProxyToken
     current_index: Arc<AtomicUsize>,
-    last_used_account: Arc<tokio::sync::Mutex<Option<(String, std::time::Instant)>>>,
+    last_used_account: Arc<tokio::sync::Mutex<HashMap<String, (String, std::time::Instant)>>>, // 分组范围 -> 60s 锁定账号
     data_dir: PathBuf,
     rate_limit_tracker: Arc<RateLimitTracker>, // 新增: 限流跟踪器
     sticky_config: Arc<tokio::sync::RwLock<StickySessionConfig>>, // 新增：调度配置
@@ -60,9 +62,12 @@ impl TokenManager {
         Self {
             tokens: Arc::new(DashMap::new()),
             current_index: Arc::new(AtomicUsize::new(0)),
-            last_used_account: Arc::new(tokio::sync::Mutex::new(None)),
+            last_used_account: Arc::new(tokio::sync::Mutex::new(HashMap::new())),
+            // 限流锁定持久化到 rate_limits.db，重启后不会立即重试已耗尽配额的账号
+            rate_limit_tracker: Arc::new(RateLimitTracker::with_persistence(
+                data_dir.join("rate_limits.db"),
+            )),
             data_dir,
-            rate_limit_tracker: Arc::new(RateLimitTracker::new()),
             sticky_config: Arc::new(tokio::sync::RwLock::new(StickySessionConfig::default())),
             session_accounts: Arc::new(DashMap::new()),
             preferred_account_id: Arc::new(tokio::sync::RwLock::new(None)), // [FIX #820]
@@ -125,13 +130,15 @@ impl TokenManager {
         self.current_index.store(0, Ordering::SeqCst);
         {
             let mut last_used = self.last_used_account.lock().await;
-            *last_used = None;
+            last_used.clear();
         }
 
         let entries = std::fs::read_dir(&accounts_dir)
             .map_err(|e| format!("读取账号目录失败: {}", e))?;
 
         let mut count = 0;
+        // 账号分组只需读取一次索引
+        let memberships = crate::modules::account::group_memberships_in_dir(&self.data_dir);
 
         for entry in entries {
             let entry = entry.map_err(|e| format!("读取目录项失败: {}", e))?;
@@ -142,7 +149,7 @@ impl TokenManager {
             }
 
             // 尝试加载账号
-            match self.load_single_account(&path).await {
+            match self.load_single_account(&path, &memberships).await {
                 Ok(Some(token)) => {
                     let account_id = token.account_id.clone();
                     self.tokens.insert(account_id, token);
@@ -157,6 +164,9 @@ impl TokenManager {
             }
         }
 
+        // 恢复持久化的限流锁定 (已在内存中的更晚记录不会被覆盖)
+        self.rate_limit_tracker.restore_persisted();
+
         Ok(count)
     }
 
@@ -170,7 +180,8 @@ impl TokenManager {
             return Err(format!("账号文件不存在: {:?}", path));
         }
 
-        match self.load_single_account(&path).await {
+        let memberships = crate::modules::account::group_memberships_in_dir(&self.data_dir);
+        match self.load_single_account(&path, &memberships).await {
             Ok(Some(token)) => {
                 self.tokens.insert(account_id.to_string(), token);
                 // [NEW] 重新加载账号时自动清除该账号的限流记录
@@ -291,8 +302,12 @@ impl TokenManager {
         OnDiskAccountState::Unknown
     }
 
-    /// 加载单个账号
-    async fn load_single_account(&self, path: &PathBuf) -> Result<Option<ProxyToken>, String> {
+    /// 加载单个账号 (memberships: 账号 ID -> 所属分组)
+    async fn load_single_account(
+        &self,
+        path: &PathBuf,
+        memberships: &HashMap<String, HashSet<String>>,
+    ) -> Result<Option<ProxyToken>, String> {
         let content = std::fs::read_to_string(path).map_err(|e| format!("读取文件失败: {}", e))?;
 
         let mut account: serde_json::Value =
@@ -434,13 +449,14 @@ impl TokenManager {
         let token_obj = account["token"].as_object()
             .ok_or("缺少 token 字段")?;
 
-        let access_token = token_obj["access_token"].as_str()
-            .ok_or("缺少 access_token")?
-            .to_string();
+        // 凭据落盘加密，读取时解密 (兼容旧版明文)
+        let access_token = crate::utils::crypto::open_credential(
+            token_obj["access_token"].as_str().ok_or("缺少 access_token")?,
+        )?;
 
-        let refresh_token = token_obj["refresh_token"].as_str()
-            .ok_or("缺少 refresh_token")?
-            .to_string();
+        let refresh_token = crate::utils::crypto::open_credential(
+            token_obj["refresh_token"].as_str().ok_or("缺少 refresh_token")?,
+        )?;
 
         let expires_in = token_obj["expires_in"].as_i64()
             .ok_or("缺少 expires_in")?;
@@ -519,6 +535,10 @@ impl TokenManager {
             }
         }
 
+        // 按配额历史预测将在刷新前耗尽的模型 (排序降权)
+        let exhausting_models = crate::modules::quota_history::exhausting_models(&self.data_dir, &account_id);
+        let groups = memberships.get(&account_id).cloned().unwrap_or_default();
+
         Ok(Some(ProxyToken {
             account_id,
             access_token,
@@ -538,6 +558,8 @@ impl TokenManager {
             validation_url: account.get("validation_url").and_then(|v| v.as_str()).map(|s| s.to_string()),
             model_quotas,
             model_limits,
+            exhausting_models,
+            groups,
         }))
     }
 
@@ -953,7 +975,7 @@ impl TokenManager {
     const P2C_POOL_SIZE: usize = 5;
 
     /// Power of 2 Choices (P2C) 选择算法
-    /// 从前 5 个候选中随机选 2 个，选择配额更高的 -> 避免热点
+    /// 从前 5 个候选中随机选 2 个，选择配额更高的 (预测将耗尽的账号降权) -> 避免热点
     /// 返回选中的索引
     ///
     /// # 参数
@@ -995,8 +1017,12 @@ impl TokenManager {
         let c1 = available[pick1];
         let c2 = available[pick2];
 
-        // 选择配额更高的
-        let selected = if c1.remaining_quota.unwrap_or(0) >= c2.remaining_quota.unwrap_or(0) {
+        // 优先选择不会在刷新前耗尽的账号，其次选择配额更高的
+        let c1_exhausting = c1.exhausting_models.contains(normalized_target);
+        let c2_exhausting = c2.exhausting_models.contains(normalized_target);
+        let selected = if c1_exhausting != c2_exhausting {
+            if c1_exhausting { c2 } else { c1 }
+        } else if c1.remaining_quota.unwrap_or(0) >= c2.remaining_quota.unwrap_or(0) {
             c1
         } else {
             c2
@@ -1061,12 +1087,14 @@ impl TokenManager {
     /// 参数 `force_rotate` 为 true 时将忽略锁定，强制切换账号
     /// 参数 `session_id` 用于跨请求维持会话粘性
     /// 参数 `target_model` 用于检查配额保护 (Issue #621)
+    /// 参数 `account_groups` 限定只从这些账号分组中调度 (为空表示全部账号)
     pub async fn get_token(
         &self,
         quota_group: &str,
         force_rotate: bool,
         session_id: Option<&str>,
         target_model: &str,
+        account_groups: &[String],
     ) -> Result<(String, String, String, String, u64), String> {
         // [FIX] 检查并处理待重新加载的账号（配额保护同步）
         let pending_reload = crate::proxy::server::take_pending_reload_accounts();
@@ -1091,11 +1119,16 @@ impl TokenManager {
             );
         }
 
+        // 请求重放时固定使用指定账号 (忽略限流与分组限制，便于复现问题)
+        if let Some(email) = crate::proxy::replay::forced_account() {
+            return self.get_token_by_email(&email).await;
+        }
+
         // 【优化 Issue #284】添加 5 秒超时，防止死锁
         let timeout_duration = std::time::Duration::from_secs(5);
         match tokio::time::timeout(
             timeout_duration,
-            self.get_token_internal(quota_group, force_rotate, session_id, target_model),
+            self.get_token_internal(quota_group, force_rotate, session_id, target_model, account_groups),
         )
         .await
         {
@@ -1113,14 +1146,42 @@ impl TokenManager {
         force_rotate: bool,
         session_id: Option<&str>,
         target_model: &str,
+        account_groups: &[String],
     ) -> Result<(String, String, String, String, u64), String> {
         let mut tokens_snapshot: Vec<ProxyToken> =
             self.tokens.iter().map(|e| e.value().clone()).collect();
-        let mut total = tokens_snapshot.len();
-        if total == 0 {
+        if tokens_snapshot.is_empty() {
             return Err("Token pool is empty".to_string());
         }
 
+        // 账号分组过滤：绑定了分组的用户令牌只能调度组内账号
+        if !account_groups.is_empty() {
+            tokens_snapshot.retain(|t| account_groups.iter().any(|g| t.groups.contains(g)));
+            if tokens_snapshot.is_empty() {
+                return Err(format!(
+                    "No accounts available in account groups: {}",
+                    account_groups.join(", ")
+                ));
+            }
+        }
+        let mut total = tokens_snapshot.len();
+
+        // 粘性会话与 60s 锁定按分组范围隔离，避免不同分组互相抢占绑定
+        let scope = {
+            let mut sorted: Vec<&str> = account_groups.iter().map(|g| g.as_str()).collect();
+            sorted.sort_unstable();
+            sorted.dedup();
+            sorted.join(",")
+        };
+        let session_key = session_id.map(|sid| {
+            if scope.is_empty() {
+                sid.to_string()
+            } else {
+                format!("{}|{}", scope, sid)
+            }
+        });
+        let session_id = session_key.as_deref();
+
         // [NEW] 1. 动态能力过滤 (Capability Filter)
         
         // 定义常量
@@ -1166,6 +1227,13 @@ impl TokenManager {
                 return tier_cmp;
             }
 
+            // Priority 0.5: 按当前消耗速率预计在刷新前耗尽目标模型配额的账号降权
+            let exhaust_cmp = a.exhausting_models.contains(&normalized_target)
+                .cmp(&b.exhausting_models.contains(&normalized_target));
+            if exhaust_cmp != std::cmp::Ordering::Equal {
+                return exhaust_cmp;
+            }
+
             // Priority 1: 目标模型的 quota (higher is better) -> 保护低配额账号
             // 经过过滤，key 肯定存在
             let quota_a = a.model_quotas.get(&normalized_target).copied().unwrap_or(0);
@@ -1361,7 +1429,7 @@ impl TokenManager {
         // 预先获取 last_used_account 的快照，避免在循环中多次加锁
         let last_used_account_id = if quota_group != "image_gen" {
             let last_used = self.last_used_account.lock().await;
-            last_used.clone()
+            last_used.get(&scope).cloned()
         } else {
             None
         };
@@ -1723,9 +1791,9 @@ impl TokenManager {
                     let mut last_used = self.last_used_account.lock().await;
                     if new_account_id.is_empty() {
                         // 空字符串表示需要清除锁定
-                        *last_used = None;
+                        last_used.remove(&scope);
                     } else {
-                        *last_used = Some((new_account_id, new_time));
+                        last_used.insert(scope.clone(), (new_account_id, new_time));
                     }
                 }
             }
@@ -1798,7 +1866,9 @@ impl TokenManager {
 
         let now = chrono::Utc::now().timestamp();
 
-        content["token"]["access_token"] = serde_json::Value::String(token_response.access_token.clone());
+        content["token"]["access_token"] = serde_json::Value::String(
+            crate::utils::crypto::seal_credential(&token_response.access_token)?,
+        );
         content["token"]["expires_in"] = serde_json::Value::Number(token_response.expires_in.into());
         content["token"]["expiry_timestamp"] = serde_json::Value::Number((now + token_response.expires_in).into());
 
@@ -1953,6 +2023,11 @@ impl TokenManager {
         self.rate_limit_tracker.get_reset_seconds(account_id)
     }
 
+    /// 按原因统计当前被限流的账号数 (用于 /metrics)
+    pub fn rate_limited_accounts_by_reason(&self) -> Vec<(String, usize)> {
+        self.rate_limit_tracker.active_accounts_by_reason()
+    }
+
     /// 清除过期的限流记录
     #[allow(dead_code)]
     pub fn clean_expired_rate_limits(&self) {
@@ -1982,8 +2057,10 @@ impl TokenManager {
     ///
     /// 在请求成功完成后调用，将该账号的失败计数归零，
     /// 下次失败时从最短的锁定时间开始（智能限流）。
-    pub fn mark_account_success(&self, account_id: &str) {
-        self.rate_limit_tracker.mark_success(account_id);
+    /// model 为本次请求使用的模型，仅清除该模型的模型级锁定
+    pub fn mark_account_success(&self, email: &str, model: Option<&str>) {
+        let account_id = self.email_to_account_id(email).unwrap_or_else(|| email.to_string());
+        self.rate_limit_tracker.mark_success(&account_id, model);
     }
 
     /// 检查是否有可用的 Google 账号
@@ -1993,6 +2070,7 @@ impl TokenManager {
     /// # 参数
     /// - `quota_group`: 配额组("claude" 或 "gemini"),暂未使用但保留用于未来扩展
     /// - `target_model`: 目标模型名称(已归一化),用于配额保护检查
+    /// - `account_groups`: 调用方可调度的账号分组(为空表示全部账号),与 `get_token` 的分组过滤一致
     ///
     /// # 返回值
     /// - `true`: 至少有一个可用账号(未限流且未被配额保护)
@@ -2001,12 +2079,12 @@ impl TokenManager {
     /// # 示例
     /// ```ignore
     /// // 检查是否有可用账号处理 claude-sonnet 请求
-    /// let has_available = token_manager.has_available_account("claude", "claude-sonnet-4-20250514").await;
+    /// let has_available = token_manager.has_available_account("claude", "claude-sonnet-4-20250514", &[]).await;
     /// if !has_available {
     ///     // 切换到外部提供商
     /// }
     /// ```
-    pub async fn has_available_account(&self, _quota_group: &str, target_model: &str) -> bool {
+    pub async fn has_available_account(&self, _quota_group: &str, target_model: &str, account_groups: &[String]) -> bool {
         // 检查配额保护是否启用
         let quota_protection_enabled = crate::modules::config::load_app_config()
             .map(|cfg| cfg.quota_protection.enabled)
@@ -2016,8 +2094,13 @@ impl TokenManager {
         for entry in self.tokens.iter() {
             let token = entry.value();
 
-            // 1. 检查是否被限流
-            if self.is_rate_limited(&token.account_id, None).await {
+            // 0. 只考虑调用方可调度的账号分组
+            if !account_groups.is_empty() && !account_groups.iter().any(|g| token.groups.contains(g)) {
+                continue;
+            }
+
+            // 1. 检查是否被限流 (账号级或该模型的锁定)
+            if self.is_rate_limited(&token.account_id, Some(target_model)).await {
                 tracing::debug!(
                     "[Fallback Check] Account {} is rate-limited, skipping",
                     token.email
@@ -2648,6 +2731,36 @@ mod tests {
     use super::*;
     use std::cmp::Ordering;
 
+    /// 写入一个仅含 gemini-1.5-flash 配额的可用账号文件
+    fn write_test_account(accounts_dir: &std::path::Path, id: &str, email: &str, percentage: i64) {
+        let now = chrono::Utc::now().timestamp();
+        let json = serde_json::json!({
+            "id": id,
+            "email": email,
+            "token": {
+                "access_token": format!("atk-{}", id),
+                "refresh_token": format!("rtk-{}", id),
+                "expires_in": 3600,
+                "expiry_timestamp": now + 3600,
+                "project_id": format!("pid-{}", id)
+            },
+            "quota": {
+                "models": [
+                    { "name": "gemini-1.5-flash", "percentage": percentage }
+                ]
+            },
+            "disabled": false,
+            "proxy_disabled": false,
+            "created_at": now,
+            "last_used": now
+        });
+        std::fs::write(
+            accounts_dir.join(format!("{}.json", id)),
+            serde_json::to_string_pretty(&json).unwrap(),
+        )
+        .unwrap();
+    }
+
     #[tokio::test]
     async fn test_reload_account_purges_cache_when_account_becomes_proxy_disabled() {
         let tmp_root = std::env::temp_dir().join(format!(
@@ -2753,7 +2866,7 @@ mod tests {
         write_account("acc1", "a@test.com", true);
 
         let (_token, _project_id, email, account_id, _wait_ms) = manager
-            .get_token("gemini", false, Some("sid1"), "gemini-1.5-flash")
+            .get_token("gemini", false, Some("sid1"), "gemini-1.5-flash", &[])
             .await
             .unwrap();
 
@@ -2812,7 +2925,7 @@ mod tests {
 
         // Prime: first request should bind the session to acc1.
         let (_token, _project_id, _email, account_id, _wait_ms) = manager
-            .get_token("gemini", false, Some("sid1"), "gemini-1.5-flash")
+            .get_token("gemini", false, Some("sid1"), "gemini-1.5-flash", &[])
             .await
             .unwrap();
         assert_eq!(account_id, "acc1");
@@ -2825,7 +2938,7 @@ mod tests {
         write_account("acc1", "a@test.com", 90, true);
 
         let (_token, _project_id, email, account_id, _wait_ms) = manager
-            .get_token("gemini", false, Some("sid1"), "gemini-1.5-flash")
+            .get_token("gemini", false, Some("sid1"), "gemini-1.5-flash", &[])
             .await
             .unwrap();
 
@@ -2841,6 +2954,140 @@ mod tests {
         let _ = std::fs::remove_dir_all(&tmp_root);
     }
 
+    #[tokio::test]
+    async fn test_forecast_exhaustion_deprioritises_account() {
+        let tmp_root = std::env::temp_dir().join(format!(
+            "antigravity-token-manager-test-forecast-{}",
+            uuid::Uuid::new_v4()
+        ));
+        let accounts_dir = tmp_root.join("accounts");
+        std::fs::create_dir_all(&accounts_dir).unwrap();
+
+        let now = chrono::Utc::now().timestamp();
+        let reset = (chrono::Utc::now() + chrono::Duration::hours(24)).to_rfc3339();
+
+        let write_account = |id: &str, email: &str, percentage: i64| {
+            let json = serde_json::json!({
+                "id": id,
+                "email": email,
+                "token": {
+                    "access_token": format!("atk-{}", id),
+                    "refresh_token": format!("rtk-{}", id),
+                    "expires_in": 3600,
+                    "expiry_timestamp": now + 3600,
+                    "project_id": format!("pid-{}", id)
+                },
+                "quota": {
+                    "models": [
+                        { "name": "gemini-1.5-flash", "percentage": percentage }
+                    ]
+                },
+                "disabled": false,
+                "proxy_disabled": false,
+                "created_at": now,
+                "last_used": now
+            });
+            std::fs::write(
+                accounts_dir.join(format!("{}.json", id)),
+                serde_json::to_string_pretty(&json).unwrap(),
+            )
+            .unwrap();
+        };
+
+        // acc1 配额更高，但按最近两次刷新的速率 (10%/h) 会在 24h 后的刷新前耗尽
+        write_account("acc1", "a@test.com", 90);
+        write_account("acc2", "b@test.com", 60);
+        let db_path = tmp_root.join("quota_history.db");
+        for (ts, pct) in [(now - 3600, 100), (now, 90)] {
+            let quota: crate::models::QuotaData = serde_json::from_value(serde_json::json!({
+                "models": [{ "name": "gemini-1.5-flash", "percentage": pct, "reset_time": reset }],
+                "last_updated": ts
+            }))
+            .unwrap();
+            crate::modules::quota_history::record_quota_snapshot_at(&db_path, "acc1", "a@test.com", &quota)
+                .unwrap();
+        }
+
+        let manager = TokenManager::new(tmp_root.clone());
+        manager.load_accounts().await.unwrap();
+
+        let (_token, _project_id, _email, account_id, _wait_ms) = manager
+            .get_token("gemini", false, Some("sid1"), "gemini-1.5-flash", &[])
+            .await
+            .unwrap();
+        assert_eq!(account_id, "acc2");
+
+        let _ = std::fs::remove_dir_all(&tmp_root);
+    }
+
+    #[tokio::test]
+    async fn test_account_groups_restrict_selection() {
+        let tmp_root = std::env::temp_dir().join(format!(
+            "antigravity-token-manager-test-groups-{}",
+            uuid::Uuid::new_v4()
+        ));
+        let accounts_dir = tmp_root.join("accounts");
+        std::fs::create_dir_all(&accounts_dir).unwrap();
+
+
+        // acc1 配额更高，但只属于 team-b
+        write_test_account(&accounts_dir, "acc1", "a@test.com", 90);
+        write_test_account(&accounts_dir, "acc2", "b@test.com", 10);
+        let index = serde_json::json!({
+            "version": "2.0",
+            "accounts": [],
+            "current_account_id": null,
+            "groups": [
+                { "name": "team-a", "account_ids": ["acc2"] },
+                { "name": "team-b", "account_ids": ["acc1"] }
+            ]
+        });
+        std::fs::write(tmp_root.join("accounts.json"), index.to_string()).unwrap();
+
+        let manager = TokenManager::new(tmp_root.clone());
+        manager.load_accounts().await.unwrap();
+
+        let team_a = vec!["team-a".to_string()];
+        let (_token, _project_id, _email, account_id, _wait_ms) = manager
+            .get_token("gemini", false, Some("sid1"), "gemini-1.5-flash", &team_a)
+            .await
+            .unwrap();
+        assert_eq!(account_id, "acc2");
+
+        // 同一 session id 在不同分组范围内各自绑定
+        let (_token, _project_id, _email, account_id, _wait_ms) = manager
+            .get_token("gemini", false, Some("sid1"), "gemini-1.5-flash", &[])
+            .await
+            .unwrap();
+        assert_eq!(account_id, "acc1");
+        let (_token, _project_id, _email, account_id, _wait_ms) = manager
+            .get_token("gemini", false, Some("sid1"), "gemini-1.5-flash", &team_a)
+            .await
+            .unwrap();
+        assert_eq!(account_id, "acc2");
+
+        let missing = manager
+            .get_token("gemini", false, None, "gemini-1.5-flash", &["team-c".to_string()])
+            .await;
+        assert!(missing.unwrap_err().contains("team-c"));
+
+        // 可用性检查同样只看调用方分组内的账号
+        assert!(manager.has_available_account("", "gemini-1.5-flash", &team_a).await);
+        assert!(!manager.has_available_account("", "gemini-1.5-flash", &["team-c".to_string()]).await);
+
+        // 模型级锁定只影响该模型
+        manager.rate_limit_tracker.set_lockout_until(
+            "acc2",
+            std::time::SystemTime::now() + std::time::Duration::from_secs(600),
+            crate::proxy::rate_limit::RateLimitReason::QuotaExhausted,
+            Some("gemini-1.5-flash".to_string()),
+        );
+        assert!(!manager.has_available_account("", "gemini-1.5-flash", &team_a).await);
+        assert!(manager.has_available_account("", "gemini-3-flash", &team_a).await);
+
+        let _ = std::fs::remove_dir_all(&tmp_root);
+    }
+
     /// 创建测试用的 ProxyToken
     fn create_test_token(
         email: &str,
@@ -2868,6 +3115,8 @@ mod tests {
             validation_url: None,
             model_quotas: HashMap::new(),
             model_limits: HashMap::new(),
+            exhausting_models: HashSet::new(),
+            groups: HashSet::new(),
         }
     }
 
@@ -3126,6 +3375,8 @@ mod tests {
             validation_url: None,
             model_quotas: HashMap::new(),
             model_limits: HashMap::new(),
+            exhausting_models: HashSet::new(),
+            groups: HashSet::new(),
         }
     }
 
@@ -50,17 +50,37 @@ const V1_INTERNAL_BASE_URL_DAILY: &str = "https://daily-cloudcode-pa.googleapis.
 const V1_INTERNAL_BASE_URL_SANDBOX: &str =
     "https://daily-cloudcode-pa.sandbox.googleapis.com/v1internal";
 
-const V1_INTERNAL_BASE_URL_FALLBACKS: [&str; 3] = [
+/// 默认端点顺序，可被 `ProxyConfig.upstream_endpoints` 或 ABV_UPSTREAM_ENDPOINTS 覆盖
+pub const DEFAULT_V1_INTERNAL_BASE_URLS: [&str; 3] = [
     V1_INTERNAL_BASE_URL_SANDBOX, // 优先级 1: Sandbox (已知有效且稳定)
     V1_INTERNAL_BASE_URL_DAILY,   // 优先级 2: Daily (备用)
     V1_INTERNAL_BASE_URL_PROD,    // 优先级 3: Prod (仅作为兜底)
 ];
 
+/// 覆盖上游端点的环境变量 (逗号分隔，优先级高于配置文件)
+const UPSTREAM_ENDPOINTS_ENV: &str = "ABV_UPSTREAM_ENDPOINTS";
+
+/// 按当前配置解析生效的 v1internal 端点 (供配额查询、项目解析等不经过 UpstreamClient 的调用使用)
+pub fn configured_base_urls() -> Vec<String> {
+    // 读取运行中的配置快照，避免每次配额查询/项目解析都读盘
+    let endpoints = crate::proxy::config_reload::running_config().proxy.upstream_endpoints;
+    UpstreamClient::resolve_base_urls(&endpoints, std::env::var(UPSTREAM_ENDPOINTS_ENV).ok().as_deref())
+}
+
+/// 首个生效的 v1internal 端点 (单次调用、不做降级的场景使用)
+pub fn configured_primary_base_url() -> String {
+    configured_base_urls()
+        .into_iter()
+        .next()
+        .unwrap_or_else(|| DEFAULT_V1_INTERNAL_BASE_URLS[0].to_string())
+}
+
 pub struct UpstreamClient {
     default_client: Client,
     proxy_pool: Option<Arc<crate::proxy::proxy_pool::ProxyPoolManager>>,
     client_cache: DashMap<String, Client>, // proxy_id -> Client
     user_agent_override: RwLock<Option<String>>,
+    base_urls: RwLock<Vec<String>>, // 已启用的 v1internal 端点 (按降级顺序)
 }
 
 impl UpstreamClient {
@@ -93,7 +113,55 @@ impl UpstreamClient {
             proxy_pool,
             client_cache: DashMap::new(),
             user_agent_override: RwLock::new(None),
+            base_urls: RwLock::new(Self::resolve_base_urls(
+                &crate::proxy::config::default_upstream_endpoints(),
+                std::env::var(UPSTREAM_ENDPOINTS_ENV).ok().as_deref(),
+            )),
+        }
+    }
+
+    /// 计算生效的端点列表
+    ///
+    /// 优先级：环境变量 > 配置中已启用的端点 > 内置默认端点
+    fn resolve_base_urls(
+        endpoints: &[crate::proxy::config::UpstreamEndpoint],
+        env_override: Option<&str>,
+    ) -> Vec<String> {
+        let normalize = |url: &str| url.trim().trim_end_matches('/').to_string();
+
+        if let Some(raw) = env_override {
+            let urls: Vec<String> = raw
+                .split(',')
+                .map(normalize)
+                .filter(|u| !u.is_empty())
+                .collect();
+            if !urls.is_empty() {
+                return urls;
+            }
+        }
+
+        let urls: Vec<String> = endpoints
+            .iter()
+            .filter(|e| e.enabled)
+            .map(|e| normalize(&e.url))
+            .filter(|u| !u.is_empty())
+            .collect();
+        if urls.is_empty() {
+            tracing::warn!("No upstream endpoint enabled, falling back to built-in defaults");
+            return DEFAULT_V1_INTERNAL_BASE_URLS.iter().map(|u| u.to_string()).collect();
         }
+        urls
+    }
+
+    /// Set upstream v1internal endpoints (ABV_UPSTREAM_ENDPOINTS still takes precedence)
+    pub async fn set_endpoints(&self, endpoints: &[crate::proxy::config::UpstreamEndpoint]) {
+        let urls = Self::resolve_base_urls(
+            endpoints,
+            std::env::var(UPSTREAM_ENDPOINTS_ENV).ok().as_deref(),
+        );
+        let mut lock = self.base_urls.write().await;
+        *lock = urls;
+        tracing::debug!("UpstreamClient endpoints updated: {:?}", lock);
     }
 
     /// Internal helper to build a client with optional upstream proxy config
@@ -172,7 +240,14 @@ impl UpstreamClient {
     }
 
     /// Get client for a specific account (or default if no proxy bound)
-    pub async fn get_client(&self, account_id: Option<&str>) -> Client {
+    ///
+    /// When the client routes through a proxy-pool entry, an in-flight lease for
+    /// that entry is returned alongside it. The lease must be kept alive until
+    /// the upstream response body has been fully consumed.
+    pub async fn get_client(
+        &self,
+        account_id: Option<&str>,
+    ) -> (Client, Option<crate::proxy::proxy_pool::ProxyLease>) {
         if let Some(pool) = &self.proxy_pool {
             if let Some(acc_id) = account_id {
                 // Try to get per-account proxy
@@ -180,7 +255,7 @@ impl UpstreamClient {
                     Ok(Some(proxy_cfg)) => {
                         // Check cache
                         if let Some(client) = self.client_cache.get(&proxy_cfg.entry_id) {
-                            return client.clone();
+                            return (client.clone(), Some(pool.acquire_lease(&proxy_cfg.entry_id)));
                         }
                         // Build new client and cache it
                         match self.build_client_with_proxy(proxy_cfg.clone()) {
@@ -192,7 +267,7 @@ impl UpstreamClient {
                                     proxy_cfg.entry_id,
                                     acc_id
                                 );
-                                return client;
+                                return (client, Some(pool.acquire_lease(&proxy_cfg.entry_id)));
                             }
                             Err(e) => {
                                 tracing::error!("Failed to build client for proxy {}: {}, falling back to default", proxy_cfg.entry_id, e);
@@ -213,7 +288,48 @@ impl UpstreamClient {
             }
         }
         // Fallback to default client
-        self.default_client.clone()
+        (self.default_client.clone(), None)
+    }
+
+    /// Tie a proxy-pool lease to the response body so the in-flight count is
+    /// released only once the body stream is finished or dropped.
+    fn attach_lease(
+        resp: Response,
+        lease: Option<crate::proxy::proxy_pool::ProxyLease>,
+    ) -> Response {
+        let Some(lease) = lease else {
+            return resp;
+        };
+
+        use futures::StreamExt;
+        use rquest::ResponseBuilderExt;
+
+        let mut builder = axum::http::Response::builder()
+            .status(resp.status())
+            .version(resp.version())
+            .url(resp.url().clone());
+        if let Some(headers) = builder.headers_mut() {
+            *headers = resp.headers().clone();
+        }
+
+        // Build the head before consuming the body so a failure can fall back to the original response
+        let parts = match builder.body(()) {
+            Ok(head) => head.into_parts().0,
+            Err(e) => {
+                tracing::warn!("Failed to attach proxy lease to upstream response: {}", e);
+                return resp;
+            }
+        };
+
+        let body = resp.bytes_stream().map(move |chunk| {
+            let _ = &lease;
+            chunk
+        });
+
+        Response::from(axum::http::Response::from_parts(
+            parts,
+            rquest::Body::wrap_stream(body),
+        ))
     }
 
     /// Build v1internal URL
@@ -268,7 +384,7 @@ impl UpstreamClient {
         account_id: Option<&str>, // [NEW] Account ID
     ) -> Result<UpstreamCallResult, String> {
         // [NEW] Get client based on account (cached in proxy pool manager)
-        let client = self.get_client(account_id).await;
+        let (client, lease) = self.get_client(account_id).await;
 
         // 构建 Headers (所有端点复用)
         let mut headers = header::HeaderMap::new();
@@ -333,9 +449,10 @@ impl UpstreamClient {
         let mut fallback_attempts: Vec<FallbackAttemptLog> = Vec::new();
 
         // 遍历所有端点，失败时自动切换
-        for (idx, base_url) in V1_INTERNAL_BASE_URL_FALLBACKS.iter().enumerate() {
+        let base_urls = self.base_urls.read().await.clone();
+        for (idx, base_url) in base_urls.iter().enumerate() {
             let url = Self::build_url(base_url, method, query_string);
-            let has_next = idx + 1 < V1_INTERNAL_BASE_URL_FALLBACKS.len();
+            let has_next = idx + 1 < base_urls.len();
 
             let response = client
                 .post(&url)
@@ -353,7 +470,7 @@ impl UpstreamClient {
                                 "✓ Upstream fallback succeeded | Endpoint: {} | Status: {} | Next endpoints available: {}",
                                 base_url,
                                 status,
-                                V1_INTERNAL_BASE_URL_FALLBACKS.len() - idx - 1
+                                base_urls.len() - idx - 1
                             );
                         } else {
                             tracing::debug!(
@@ -363,7 +480,7 @@ impl UpstreamClient {
                             );
                         }
                         return Ok(UpstreamCallResult {
-                            response: resp,
+                            response: Self::attach_lease(resp, lease),
                             fallback_attempts,
                         });
                     }
@@ -389,7 +506,7 @@ impl UpstreamClient {
 
                     // 不可重试的错误或已是最后一个端点，直接返回
                     return Ok(UpstreamCallResult {
-                        response: resp,
+                        response: Self::attach_lease(resp, lease),
                         fallback_attempts,
                     });
                 }
@@ -483,4 +600,36 @@ mod tests {
             "https://cloudcode-pa.googleapis.com/v1internal:streamGenerateContent?alt=sse"
         );
     }
+
+    #[test]
+    fn test_resolve_base_urls() {
+        use crate::proxy::config::{default_upstream_endpoints, UpstreamEndpoint};
+
+        // 默认顺序: Sandbox → Daily → Prod
+        let urls = UpstreamClient::resolve_base_urls(&default_upstream_endpoints(), None);
+        assert_eq!(urls, DEFAULT_V1_INTERNAL_BASE_URLS.to_vec());
+
+        // 禁用的端点被跳过，尾部斜杠被去除
+        let endpoints = vec![
+            UpstreamEndpoint { url: "https://a.example/v1internal/".to_string(), enabled: true },
+            UpstreamEndpoint { url: "https://b.example/v1internal".to_string(), enabled: false },
+        ];
+        let urls = UpstreamClient::resolve_base_urls(&endpoints, None);
+        assert_eq!(urls, vec!["https://a.example/v1internal"]);
+
+        // 环境变量优先于配置
+        let urls = UpstreamClient::resolve_base_urls(
+            &endpoints,
+            Some(" http://127.0.0.1:9000/v1internal , ,http://127.0.0.1:9001/v1internal"),
+        );
+        assert_eq!(
+            urls,
+            vec!["http://127.0.0.1:9000/v1internal", "http://127.0.0.1:9001/v1internal"]
+        );
+
+        // 空环境变量或全部禁用时回退到默认端点
+        let disabled = vec![UpstreamEndpoint { url: "https://a.example".to_string(), enabled: false }];
+        let urls = UpstreamClient::resolve_base_urls(&disabled, Some(""));
+        assert_eq!(urls, DEFAULT_V1_INTERNAL_BASE_URLS.to_vec());
+    }
 }
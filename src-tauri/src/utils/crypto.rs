@@ -3,12 +3,22 @@ use aes_gcm::{
     Aes256Gcm, Nonce,
 };
 use base64::{engine::general_purpose, Engine as _};
+use rand::RngCore;
 use serde::{Deserialize, Deserializer, Serializer};
 use sha2::Digest;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
 
 const FIXED_NONCE: &[u8; 12] = b"antigravsalt";
 const ENCRYPTED_PREFIX: &str = "ag_enc_";
 
+/// 账号凭据密文前缀，格式: `ag_seal1:<密钥来源>:<base64(salt | nonce | ciphertext)>`
+const SEALED_PREFIX: &str = "ag_seal1:";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const CREDENTIAL_KDF_CONTEXT: &[u8] = b"antigravity-credential-v1";
+const CREDENTIAL_SALT_FILE: &str = "credential.salt";
+
 /// 生成加密密钥 (基于设备 ID)
 fn get_encryption_key() -> [u8; 32] {
     // 使用设备唯一标识生成密钥
@@ -111,6 +121,250 @@ pub fn decrypt_string(encrypted: &str) -> Result<String, String> {
     }
 }
 
+// ============================================================================
+// 账号凭据 (refresh_token / access_token) 落盘加密
+// ============================================================================
+
+/// 凭据密钥来源 (优先级: 密钥文件 > 口令 > 设备 ID)
+#[derive(Clone)]
+enum CredentialKeySource {
+    /// ABV_CREDENTIAL_KEYFILE 指向的文件内容
+    Keyfile(Vec<u8>),
+    /// ABV_CREDENTIAL_PASSPHRASE，经 Argon2id 派生
+    Passphrase(String),
+    /// 回退: machine-uid
+    Machine(String),
+}
+
+impl CredentialKeySource {
+    fn tag(&self) -> &'static str {
+        match self {
+            CredentialKeySource::Keyfile(_) => "k",
+            CredentialKeySource::Passphrase(_) => "p",
+            CredentialKeySource::Machine(_) => "m",
+        }
+    }
+
+    fn secret(&self) -> &[u8] {
+        match self {
+            CredentialKeySource::Keyfile(secret) => secret,
+            CredentialKeySource::Passphrase(passphrase) => passphrase.as_bytes(),
+            CredentialKeySource::Machine(device_id) => device_id.as_bytes(),
+        }
+    }
+
+    fn derive_key(&self, salt: &[u8]) -> Result<[u8; 32], String> {
+        let mut key = [0u8; 32];
+        match self {
+            CredentialKeySource::Passphrase(passphrase) => {
+                argon2::Argon2::default()
+                    .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+                    .map_err(|e| format!("Key derivation failed: {}", e))?;
+            }
+            CredentialKeySource::Keyfile(_) | CredentialKeySource::Machine(_) => {
+                key.copy_from_slice(&credential_digest(self.secret(), salt));
+            }
+        }
+        Ok(key)
+    }
+}
+
+/// 高熵密钥材料 (密钥文件 / 设备 ID) 直接哈希派生
+fn credential_digest(secret: &[u8], salt: &[u8]) -> Vec<u8> {
+    let mut hasher = sha2::Sha256::new();
+    hasher.update(CREDENTIAL_KDF_CONTEXT);
+    hasher.update(salt);
+    hasher.update(secret);
+    hasher.finalize().to_vec()
+}
+
+fn machine_key_source() -> CredentialKeySource {
+    CredentialKeySource::Machine(machine_uid::get().unwrap_or_else(|_| "default".to_string()))
+}
+
+/// 读取环境变量确定当前写入使用的密钥来源 (进程内只解析一次)
+fn primary_key_source() -> &'static CredentialKeySource {
+    static SOURCE: OnceLock<CredentialKeySource> = OnceLock::new();
+    SOURCE.get_or_init(|| {
+        if let Some(path) = std::env::var("ABV_CREDENTIAL_KEYFILE")
+            .ok()
+            .filter(|p| !p.trim().is_empty())
+        {
+            match std::fs::read(path.trim()) {
+                Ok(bytes) if !bytes.is_empty() => {
+                    tracing::info!("[Credentials] Using keyfile for account credential encryption");
+                    return CredentialKeySource::Keyfile(bytes);
+                }
+                Ok(_) => tracing::error!("[Credentials] Keyfile {} is empty, ignoring", path),
+                Err(e) => tracing::error!("[Credentials] Failed to read keyfile {}: {}", path, e),
+            }
+        }
+
+        if let Some(passphrase) = std::env::var("ABV_CREDENTIAL_PASSPHRASE")
+            .ok()
+            .filter(|p| !p.is_empty())
+        {
+            tracing::info!("[Credentials] Using passphrase for account credential encryption");
+            return CredentialKeySource::Passphrase(passphrase);
+        }
+
+        if machine_uid::get().is_err() {
+            tracing::warn!(
+                "[Credentials] Machine ID unavailable; set ABV_CREDENTIAL_PASSPHRASE or ABV_CREDENTIAL_KEYFILE to protect account credentials"
+            );
+        }
+        machine_key_source()
+    })
+}
+
+/// 按密文标记选择解密密钥来源
+fn key_source_for_tag(tag: &str) -> Result<CredentialKeySource, String> {
+    let primary = primary_key_source();
+    if primary.tag() == tag {
+        return Ok(primary.clone());
+    }
+    match tag {
+        "m" => Ok(machine_key_source()),
+        "k" => Err("Credential was sealed with a keyfile; set ABV_CREDENTIAL_KEYFILE".to_string()),
+        "p" => Err("Credential was sealed with a passphrase; set ABV_CREDENTIAL_PASSPHRASE".to_string()),
+        other => Err(format!("Unknown credential key source: {}", other)),
+    }
+}
+
+/// 本机凭据盐值，持久化在数据目录，避免每次启动都为口令重新派生密钥
+fn credential_salt() -> &'static [u8; SALT_LEN] {
+    static SALT: OnceLock<[u8; SALT_LEN]> = OnceLock::new();
+    SALT.get_or_init(|| {
+        // 测试中注入固定盐值，不在真实数据目录写入 credential.salt
+        if cfg!(test) {
+            return [0x5A; SALT_LEN];
+        }
+
+        let path = crate::modules::account::get_data_dir()
+            .ok()
+            .map(|dir| dir.join(CREDENTIAL_SALT_FILE));
+
+        if let Some(existing) = path
+            .as_ref()
+            .and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|s| general_purpose::STANDARD.decode(s.trim()).ok())
+            .filter(|b| b.len() == SALT_LEN)
+        {
+            let mut salt = [0u8; SALT_LEN];
+            salt.copy_from_slice(&existing);
+            return salt;
+        }
+
+        let mut salt = [0u8; SALT_LEN];
+        rand::rngs::OsRng.fill_bytes(&mut salt);
+        if let Some(p) = path {
+            if let Err(e) = std::fs::write(&p, general_purpose::STANDARD.encode(salt)) {
+                tracing::warn!("[Credentials] Failed to persist credential salt: {}", e);
+            }
+        }
+        salt
+    })
+}
+
+/// 派生结果缓存 (口令模式下 Argon2 开销较大)，以密钥材料摘要 + 盐值为键
+fn cached_key(source: &CredentialKeySource, salt: &[u8]) -> Result<[u8; 32], String> {
+    static CACHE: OnceLock<Mutex<HashMap<Vec<u8>, [u8; 32]>>> = OnceLock::new();
+    let cache = CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+    let mut cache_key = sha2::Sha256::new();
+    cache_key.update(source.tag());
+    cache_key.update(source.secret());
+    cache_key.update(salt);
+    let cache_key = cache_key.finalize().to_vec();
+
+    if let Some(key) = cache.lock().ok().and_then(|c| c.get(&cache_key).copied()) {
+        return Ok(key);
+    }
+    let key = source.derive_key(salt)?;
+    if let Ok(mut c) = cache.lock() {
+        c.insert(cache_key, key);
+    }
+    Ok(key)
+}
+
+fn seal_with(source: &CredentialKeySource, salt: &[u8], plaintext: &str) -> Result<String, String> {
+    let key = cached_key(source, salt)?;
+    let cipher = Aes256Gcm::new(&key.into());
+
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::rngs::OsRng.fill_bytes(&mut nonce);
+
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext.as_bytes())
+        .map_err(|e| format!("Encryption failed: {}", e))?;
+
+    let mut payload = Vec::with_capacity(salt.len() + NONCE_LEN + ciphertext.len());
+    payload.extend_from_slice(salt);
+    payload.extend_from_slice(&nonce);
+    payload.extend_from_slice(&ciphertext);
+
+    Ok(format!(
+        "{}{}:{}",
+        SEALED_PREFIX,
+        source.tag(),
+        general_purpose::STANDARD.encode(payload)
+    ))
+}
+
+fn open_with(source: &CredentialKeySource, encoded: &str) -> Result<String, String> {
+    let payload = general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|e| format!("Base64 decode failed: {}", e))?;
+    if payload.len() <= SALT_LEN + NONCE_LEN {
+        return Err("Sealed credential is truncated".to_string());
+    }
+    let (salt, rest) = payload.split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+
+    let key = cached_key(source, salt)?;
+    let cipher = Aes256Gcm::new(&key.into());
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "Credential decryption failed (wrong key or corrupted data)".to_string())?;
+
+    String::from_utf8(plaintext).map_err(|e| format!("UTF-8 conversion failed: {}", e))
+}
+
+fn split_sealed(value: &str) -> Option<(&str, &str)> {
+    value.strip_prefix(SEALED_PREFIX)?.split_once(':')
+}
+
+/// 是否为已加密的凭据
+pub fn is_sealed_credential(value: &str) -> bool {
+    split_sealed(value).is_some()
+}
+
+/// 是否需要 (重新) 加密：明文，或由非当前密钥来源加密
+pub fn credential_needs_reseal(value: &str) -> bool {
+    if value.is_empty() {
+        return false;
+    }
+    match split_sealed(value) {
+        Some((tag, _)) => tag != primary_key_source().tag(),
+        None => true,
+    }
+}
+
+/// 使用当前密钥来源加密凭据 (每条记录使用随机 nonce)；空串与已加密的值原样返回
+pub fn seal_credential(plaintext: &str) -> Result<String, String> {
+    if plaintext.is_empty() || is_sealed_credential(plaintext) {
+        return Ok(plaintext.to_string());
+    }
+    seal_with(primary_key_source(), credential_salt(), plaintext)
+}
+
+/// 解密凭据；旧版明文原样返回
+pub fn open_credential(value: &str) -> Result<String, String> {
+    match split_sealed(value) {
+        Some((tag, encoded)) => open_with(&key_source_for_tag(tag)?, encoded),
+        None => Ok(value.to_string()),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -143,4 +397,42 @@ mod tests {
         let decrypted = decrypt_string(&legacy_encrypted).unwrap();
         assert_eq!(password, decrypted);
     }
+
+    #[test]
+    fn test_seal_open_credential_random_nonce() {
+        let source = CredentialKeySource::Passphrase("correct horse".to_string());
+        let salt = [7u8; SALT_LEN];
+
+        let a = seal_with(&source, &salt, "1//refresh-token").unwrap();
+        let b = seal_with(&source, &salt, "1//refresh-token").unwrap();
+        assert!(a.starts_with("ag_seal1:p:"));
+        assert_ne!(a, b, "each record must use a fresh nonce");
+
+        let (_, encoded) = split_sealed(&a).unwrap();
+        assert_eq!(open_with(&source, encoded).unwrap(), "1//refresh-token");
+
+        // 错误口令无法解密
+        let wrong = CredentialKeySource::Passphrase("wrong".to_string());
+        assert!(open_with(&wrong, encoded).is_err());
+    }
+
+    #[test]
+    fn test_credential_legacy_plaintext_and_machine_fallback() {
+        // 旧版明文原样返回，且需要加密
+        assert_eq!(open_credential("ya29.plain").unwrap(), "ya29.plain");
+        assert!(credential_needs_reseal("ya29.plain"));
+        assert!(!credential_needs_reseal(""));
+
+        let sealed = seal_credential("1//refresh").unwrap();
+        assert!(is_sealed_credential(&sealed));
+        assert!(!credential_needs_reseal(&sealed));
+        assert_eq!(seal_credential(&sealed).unwrap(), sealed, "no double sealing");
+        assert_eq!(open_credential(&sealed).unwrap(), "1//refresh");
+
+        // 篡改密文后解密失败
+        let mut tampered = sealed.clone();
+        tampered.pop();
+        tampered.push(if sealed.ends_with('A') { 'B' } else { 'A' });
+        assert!(open_credential(&tampered).is_err());
+    }
 }
@@ -1,4 +1,5 @@
 /// Protobuf Varint Encoding
+#[cfg(feature = "desktop")]
 pub fn encode_varint(mut value: u64) -> Vec<u8> {
     let mut buf = Vec::new();
     while value >= 0x80 {
@@ -57,6 +58,7 @@ pub fn skip_field(data: &[u8], offset: usize, wire_type: u8) -> Result<usize, St
 }
 
 /// Remove specified Protobuf field
+#[cfg(feature = "desktop")]
 pub fn remove_field(data: &[u8], field_num: u32) -> Result<Vec<u8>, String> {
     let mut result = Vec::new();
     let mut offset = 0;
@@ -117,6 +119,7 @@ pub fn find_field(data: &[u8], target_field: u32) -> Result<Option<Vec<u8>>, Str
 ///     optional string refresh_token = 3;
 ///     optional Timestamp expiry = 4;
 /// }
+#[cfg(feature = "desktop")]
 pub fn create_oauth_field(access_token: &str, refresh_token: &str, expiry: i64) -> Vec<u8> {
     // Field 1: access_token (string, wire_type = 2)
     let tag1 = (1 << 3) | 2;
@@ -177,6 +180,7 @@ pub fn create_oauth_field(access_token: &str, refresh_token: &str, expiry: i64)
 
 
 /// Create Email (Field 2)
+#[cfg(feature = "desktop")]
 pub fn create_email_field(email: &str) -> Vec<u8> {
     let tag = (2 << 3) | 2;
     let mut f = encode_varint(tag);
@@ -186,6 +190,7 @@ pub fn create_email_field(email: &str) -> Vec<u8> {
 }
 
 /// 编码长度分隔字段 (wire_type = 2)
+#[cfg(feature = "desktop")]
 pub fn encode_len_delim_field(field_num: u32, data: &[u8]) -> Vec<u8> {
     let tag = (field_num << 3) | 2;
     let mut f = encode_varint(tag as u64);
@@ -195,11 +200,13 @@ pub fn encode_len_delim_field(field_num: u32, data: &[u8]) -> Vec<u8> {
 }
 
 /// 编码字符串字段 (wire_type = 2)
+#[cfg(feature = "desktop")]
 pub fn encode_string_field(field_num: u32, value: &str) -> Vec<u8> {
     encode_len_delim_field(field_num, value.as_bytes())
 }
 
 /// 编码 varint 字段 (wire_type = 0)
+#[cfg(feature = "desktop")]
 pub fn encode_varint_field(field_num: u32, value: u64) -> Vec<u8> {
     let tag = (field_num << 3) | 0;
     let mut f = encode_varint(tag as u64);
@@ -208,6 +215,7 @@ pub fn encode_varint_field(field_num: u32, value: u64) -> Vec<u8> {
 }
 
 /// 创建 OAuthTokenInfo 消息（不包含 Field 6 包装，用于新格式）
+#[cfg(feature = "desktop")]
 pub fn create_oauth_info(
     access_token: &str,
     refresh_token: &str,
@@ -301,6 +309,7 @@ fn decode_legacy_unified_state_entry(outer_blob: &[u8]) -> Result<(String, Vec<u
 }
 
 /// 创建统一状态同步条目：Topic(Field 1 data map) -> DataEntry(Field 1 key, Field 2 Row) -> Row(Field 1 base64 payload)
+#[cfg(feature = "desktop")]
 pub fn create_unified_state_entry(sentinel_key: &str, payload: &[u8]) -> String {
     use base64::{engine::general_purpose, Engine as _};
 
@@ -348,6 +357,7 @@ pub fn find_varint_field(data: &[u8], target_field: u32) -> Result<Option<u64>,
 }
 
 /// 创建 unified-state stringValue payload
+#[cfg(feature = "desktop")]
 pub fn create_string_value_payload(value: &str) -> Vec<u8> {
     // Matches the upstream `fs` message: { value: { case: "stringValue", value } }
     encode_string_field(3, value)
@@ -357,6 +367,7 @@ pub fn create_string_value_payload(value: &str) -> Vec<u8> {
 ///
 /// Antigravity 的认证链路要求 `uss-userStatus` 里至少存在 sentinel key；
 /// 账号展示和会话绑定依赖名字和邮箱，因此这里写入最小身份信息即可。
+#[cfg(feature = "desktop")]
 pub fn create_minimal_user_status_payload(email: &str) -> Vec<u8> {
     [encode_string_field(3, email), encode_string_field(7, email)].concat()
 }
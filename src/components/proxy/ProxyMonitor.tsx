@@ -28,6 +28,7 @@ interface ProxyRequestLog {
     output_tokens?: number;
     account_email?: string;
     protocol?: string;  // "openai" | "anthropic" | "gemini"
+    cache_hit?: boolean; // 响应来自本地响应缓存
 }
 
 interface ProxyStats {
@@ -103,7 +104,9 @@ const LogTable: React.FC<LogTableProps> = ({
                                 )}
                             </td>
                             <td className="text-gray-600 dark:text-gray-400 truncate text-[10px]" style={{ width: '140px', maxWidth: '140px' }} title={log.account_email || ''}>
-                                {log.account_email ? log.account_email.replace(/(.{3}).*(@.*)/, '$1***$2') : '-'}
+                                {log.cache_hit
+                                    ? <span className="badge badge-xs bg-gray-400 text-white border-none">{t('monitor.table.cache_hit')}</span>
+                                    : log.account_email ? log.account_email.replace(/(.{3}).*(@.*)/, '$1***$2') : '-'}
                             </td>
                             <td className="truncate" style={{ width: '180px', maxWidth: '180px' }}>{log.url}</td>
                             <td className="text-right text-[9px]" style={{ width: '90px' }}>
@@ -1,7 +1,7 @@
 import React, { useEffect, useState } from 'react';
 import { useTranslation } from 'react-i18next';
 import { request as invoke } from '../../utils/request';
-import { Save, AlertTriangle, Shield, ShieldCheck } from 'lucide-react';
+import { Save, AlertTriangle, Shield, ShieldCheck, Network } from 'lucide-react';
 import { showToast } from '../common/ToastContainer';
 
 interface IpBlacklistConfig {
@@ -17,6 +17,7 @@ interface IpWhitelistConfig {
 interface SecurityMonitorConfig {
     blacklist: IpBlacklistConfig;
     whitelist: IpWhitelistConfig;
+    trusted_proxies: string[];
 }
 
 export const SecurityConfig: React.FC = () => {
@@ -170,6 +171,31 @@ export const SecurityConfig: React.FC = () => {
                     </div>
                 </div>
             </div>
+
+            {/* Trusted Proxies */}
+            <div className="card bg-base-100 border border-gray-200 dark:border-base-300 shadow-sm">
+                <div className="card-body">
+                    <h3 className="card-title flex items-center gap-2 text-blue-500">
+                        <Network size={24} />
+                        {t('security.config.trusted_proxies_title')}
+                    </h3>
+                    <p className="text-sm text-gray-500 mb-4">{t('security.config.trusted_proxies_desc')}</p>
+
+                    <textarea
+                        className="textarea textarea-bordered w-full font-mono text-sm"
+                        rows={4}
+                        placeholder={t('security.config.trusted_proxies_placeholder')}
+                        value={(config.trusted_proxies || []).join('\n')}
+                        onChange={(e) => setConfig({
+                            ...config,
+                            trusted_proxies: e.target.value
+                                .split('\n')
+                                .map((line) => line.trim())
+                                .filter((line, idx, arr) => line.length > 0 || idx === arr.length - 1)
+                        })}
+                    />
+                </div>
+            </div>
         </div>
     );
 };
@@ -206,6 +206,8 @@ export default function ProxyPoolSettings({ config, onChange }: ProxyPoolSetting
                                 <option value="round_robin">{t('settings.proxy_pool.strategy_round_robin', 'Round Robin')}</option>
                                 <option value="random">{t('settings.proxy_pool.strategy_random', 'Random')}</option>
                                 <option value="least_connections">{t('settings.proxy_pool.strategy_least_connections', 'Least Connections')}</option>
+                                <option value="weighted_round_robin">{t('settings.proxy_pool.strategy_weighted', 'Weighted')}</option>
+                                <option value="latency_aware">{t('settings.proxy_pool.strategy_latency_aware', 'Latency Aware')}</option>
                             </select>
                         </div>
 
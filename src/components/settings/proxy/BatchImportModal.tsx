@@ -73,6 +73,7 @@ export default function BatchImportModal({ isOpen, onClose, onImport }: BatchImp
                     url: url,
                     enabled: true,
                     priority: 1,
+                    weight: 1,
                     tags: ['imported'],
                     is_healthy: false,
                     latency: undefined
@@ -22,13 +22,55 @@ interface UserToken {
     last_used_at?: number;
     total_requests: number;
     total_tokens_used: number;
+    allowed_models?: string[];
+    denied_models?: string[];
+    account_groups?: string[];
+    limits?: TokenLimits;
 }
 
+// 预算与速率限制 (0 = 不限制)
+interface TokenLimits {
+    daily_token_limit: number;
+    weekly_token_limit: number;
+    monthly_token_limit: number;
+    max_rpm: number;
+    max_concurrent: number;
+}
+
+const EMPTY_LIMITS: TokenLimits = {
+    daily_token_limit: 0,
+    weekly_token_limit: 0,
+    monthly_token_limit: 0,
+    max_rpm: 0,
+    max_concurrent: 0,
+};
+
+interface BudgetUsage {
+    period: 'daily' | 'weekly' | 'monthly';
+    used: number;
+    limit: number;
+    resets_at: number;
+}
+
+interface TokenBudgetSummary {
+    token_id: string;
+    username: string;
+    usage: BudgetUsage[];
+    limits: TokenLimits;
+    in_flight: number;
+    recent_rpm: number;
+}
+
+// 模型规则输入框使用逗号分隔
+const parseModelList = (value: string): string[] =>
+    value.split(',').map(s => s.trim()).filter(Boolean);
+
 interface UserTokenStats {
     total_tokens: number;
     active_tokens: number;
     total_users: number;
     today_requests: number;
+    budgets?: TokenBudgetSummary[];
 }
 
 // interface CreateTokenRequest omitted as it's not explicitly used for typing variables
@@ -49,6 +91,10 @@ const UserToken: React.FC = () => {
     const [editMaxIps, setEditMaxIps] = useState(0);
     const [editCurfewStart, setEditCurfewStart] = useState('');
     const [editCurfewEnd, setEditCurfewEnd] = useState('');
+    const [editAllowedModels, setEditAllowedModels] = useState('');
+    const [editDeniedModels, setEditDeniedModels] = useState('');
+    const [editAccountGroups, setEditAccountGroups] = useState('');
+    const [editLimits, setEditLimits] = useState<TokenLimits>(EMPTY_LIMITS);
     const [updating, setUpdating] = useState(false);
 
     // Create Form State
@@ -59,6 +105,10 @@ const UserToken: React.FC = () => {
     const [newCurfewStart, setNewCurfewStart] = useState('');
     const [newCurfewEnd, setNewCurfewEnd] = useState('');
     const [newCustomExpires, setNewCustomExpires] = useState(''); // datetime-local value
+    const [newAllowedModels, setNewAllowedModels] = useState('');
+    const [newDeniedModels, setNewDeniedModels] = useState('');
+    const [newAccountGroups, setNewAccountGroups] = useState('');
+    const [newLimits, setNewLimits] = useState<TokenLimits>(EMPTY_LIMITS);
 
     const loadData = async () => {
         setLoading(true);
@@ -108,7 +158,11 @@ const UserToken: React.FC = () => {
                     max_ips: newMaxIps,
                     curfew_start: newCurfewStart || null,
                     curfew_end: newCurfewEnd || null,
-                    custom_expires_at: customExpiresAt || null
+                    custom_expires_at: customExpiresAt || null,
+                    allowed_models: parseModelList(newAllowedModels),
+                    denied_models: parseModelList(newDeniedModels),
+                    account_groups: parseModelList(newAccountGroups),
+                    limits: newLimits
                 }
             });
             showToast(t('common.create_success') || 'Created successfully', 'success');
@@ -120,6 +174,10 @@ const UserToken: React.FC = () => {
             setNewCurfewStart('');
             setNewCurfewEnd('');
             setNewCustomExpires('');
+            setNewAllowedModels('');
+            setNewDeniedModels('');
+            setNewAccountGroups('');
+            setNewLimits(EMPTY_LIMITS);
             loadData();
         } catch (e) {
             console.error('Failed to create token', e);
@@ -147,6 +205,10 @@ const UserToken: React.FC = () => {
         setEditMaxIps(token.max_ips ?? 0);  // 使用 ?? 确保 null/undefined 变为 0
         setEditCurfewStart(token.curfew_start ?? '');
         setEditCurfewEnd(token.curfew_end ?? '');
+        setEditAllowedModels((token.allowed_models ?? []).join(', '));
+        setEditDeniedModels((token.denied_models ?? []).join(', '));
+        setEditAccountGroups((token.account_groups ?? []).join(', '));
+        setEditLimits({ ...EMPTY_LIMITS, ...(token.limits ?? {}) });
         setShowEditModal(true);
     };
 
@@ -167,7 +229,11 @@ const UserToken: React.FC = () => {
                     max_ips: editMaxIps,
                     // 使用双层包装: undefined = 不更新, null = 清空, string = 设置值
                     curfew_start: editCurfewStart === '' ? null : editCurfewStart,
-                    curfew_end: editCurfewEnd === '' ? null : editCurfewEnd
+                    curfew_end: editCurfewEnd === '' ? null : editCurfewEnd,
+                    allowed_models: parseModelList(editAllowedModels),
+                    denied_models: parseModelList(editDeniedModels),
+                    account_groups: parseModelList(editAccountGroups),
+                    limits: editLimits
                 }
             });
             showToast(t('common.update_success') || 'Updated successfully', 'success');
@@ -386,6 +452,14 @@ const UserToken: React.FC = () => {
                                         <div className="text-[10px] text-gray-400 mt-0.5">
                                             {(token.total_tokens_used / 1000).toFixed(1)}k tokens
                                         </div>
+                                        {stats?.budgets?.find(b => b.token_id === token.id)?.usage.map(u => (
+                                            <div
+                                                key={u.period}
+                                                className={`text-[10px] mt-0.5 ${u.used >= u.limit ? 'text-red-500 font-medium' : 'text-gray-400'}`}
+                                            >
+                                                {t(`user_token.budget_${u.period}`, { defaultValue: u.period })}: {(u.used / 1000).toFixed(1)}k / {(u.limit / 1000).toFixed(1)}k
+                                            </div>
+                                        ))}
                                     </td>
                                     <td>
                                         {token.max_ips === 0
@@ -566,6 +640,115 @@ const UserToken: React.FC = () => {
                             </label>
                         </div>
 
+                        <div className="form-control w-full mb-3">
+                            <label className="label">
+                                <span className="label-text">{t('user_token.allowed_models', { defaultValue: 'Allowed Models' })}</span>
+                            </label>
+                            <input
+                                type="text"
+                                className="input input-bordered w-full font-mono text-sm"
+                                placeholder="gemini-*, claude-sonnet-*"
+                                value={newAllowedModels}
+                                onChange={e => setNewAllowedModels(e.target.value)}
+                            />
+                            <label className="label">
+                                <span className="label-text-alt text-gray-500">{t('user_token.hint_allowed_models', { defaultValue: 'Comma separated, * wildcard supported. Leave empty to allow all models.' })}</span>
+                            </label>
+                        </div>
+
+                        <div className="form-control w-full mb-3">
+                            <label className="label">
+                                <span className="label-text">{t('user_token.denied_models', { defaultValue: 'Denied Models' })}</span>
+                            </label>
+                            <input
+                                type="text"
+                                className="input input-bordered w-full font-mono text-sm"
+                                placeholder="claude-opus-*, *-image*"
+                                value={newDeniedModels}
+                                onChange={e => setNewDeniedModels(e.target.value)}
+                            />
+                            <label className="label">
+                                <span className="label-text-alt text-gray-500">{t('user_token.hint_denied_models', { defaultValue: 'Denied rules take precedence over allowed rules.' })}</span>
+                            </label>
+                        </div>
+
+                        <div className="form-control w-full mb-3">
+                            <label className="label">
+                                <span className="label-text">{t('user_token.account_groups', { defaultValue: 'Account Groups' })}</span>
+                            </label>
+                            <input
+                                type="text"
+                                className="input input-bordered w-full font-mono text-sm"
+                                placeholder="team-a, team-b"
+                                value={newAccountGroups}
+                                onChange={e => setNewAccountGroups(e.target.value)}
+                            />
+                            <label className="label">
+                                <span className="label-text-alt text-gray-500">{t('user_token.hint_account_groups', { defaultValue: 'Only accounts in these groups serve this token. Leave empty to use all accounts.' })}</span>
+                            </label>
+                        </div>
+
+                        <div className="form-control w-full mb-3">
+                            <label className="label">
+                                <span className="label-text">{t('user_token.limits', { defaultValue: 'Budgets & Rate Limits' })}</span>
+                            </label>
+                            <div className="grid grid-cols-2 gap-2">
+                                <label className="flex flex-col gap-1">
+                                    <span className="text-xs text-gray-500">{t('user_token.budget_daily', { defaultValue: 'Daily Token Budget' })}</span>
+                                    <input
+                                        type="number"
+                                        min={0}
+                                        className="input input-bordered input-sm w-full"
+                                        value={newLimits.daily_token_limit}
+                                        onChange={e => setNewLimits({ ...newLimits, daily_token_limit: Math.max(0, parseInt(e.target.value) || 0) })}
+                                    />
+                                </label>
+                                <label className="flex flex-col gap-1">
+                                    <span className="text-xs text-gray-500">{t('user_token.budget_weekly', { defaultValue: 'Weekly Token Budget' })}</span>
+                                    <input
+                                        type="number"
+                                        min={0}
+                                        className="input input-bordered input-sm w-full"
+                                        value={newLimits.weekly_token_limit}
+                                        onChange={e => setNewLimits({ ...newLimits, weekly_token_limit: Math.max(0, parseInt(e.target.value) || 0) })}
+                                    />
+                                </label>
+                                <label className="flex flex-col gap-1">
+                                    <span className="text-xs text-gray-500">{t('user_token.budget_monthly', { defaultValue: 'Monthly Token Budget' })}</span>
+                                    <input
+                                        type="number"
+                                        min={0}
+                                        className="input input-bordered input-sm w-full"
+                                        value={newLimits.monthly_token_limit}
+                                        onChange={e => setNewLimits({ ...newLimits, monthly_token_limit: Math.max(0, parseInt(e.target.value) || 0) })}
+                                    />
+                                </label>
+                                <label className="flex flex-col gap-1">
+                                    <span className="text-xs text-gray-500">{t('user_token.max_rpm', { defaultValue: 'Requests / Minute' })}</span>
+                                    <input
+                                        type="number"
+                                        min={0}
+                                        className="input input-bordered input-sm w-full"
+                                        value={newLimits.max_rpm}
+                                        onChange={e => setNewLimits({ ...newLimits, max_rpm: Math.max(0, parseInt(e.target.value) || 0) })}
+                                    />
+                                </label>
+                                <label className="flex flex-col gap-1">
+                                    <span className="text-xs text-gray-500">{t('user_token.max_concurrent', { defaultValue: 'Concurrent Requests' })}</span>
+                                    <input
+                                        type="number"
+                                        min={0}
+                                        className="input input-bordered input-sm w-full"
+                                        value={newLimits.max_concurrent}
+                                        onChange={e => setNewLimits({ ...newLimits, max_concurrent: Math.max(0, parseInt(e.target.value) || 0) })}
+                                    />
+                                </label>
+                            </div>
+                            <label className="label">
+                                <span className="label-text-alt text-gray-500">{t('user_token.hint_limits', { defaultValue: '0 = Unlimited. Over-limit requests receive 429 with Retry-After.' })}</span>
+                            </label>
+                        </div>
+
                         <div className="modal-action">
                             <button className="px-4 py-2 hover:bg-gray-100 dark:hover:bg-base-200 rounded-lg text-sm transition-colors" onClick={() => setShowCreateModal(false)}>
                                 {t('common.cancel', { defaultValue: 'Cancel' })}
@@ -656,6 +839,115 @@ const UserToken: React.FC = () => {
                             </label>
                         </div>
 
+                        <div className="form-control w-full mb-3">
+                            <label className="label">
+                                <span className="label-text">{t('user_token.allowed_models', { defaultValue: 'Allowed Models' })}</span>
+                            </label>
+                            <input
+                                type="text"
+                                className="input input-bordered w-full font-mono text-sm"
+                                placeholder="gemini-*, claude-sonnet-*"
+                                value={editAllowedModels}
+                                onChange={e => setEditAllowedModels(e.target.value)}
+                            />
+                            <label className="label">
+                                <span className="label-text-alt text-gray-500">{t('user_token.hint_allowed_models', { defaultValue: 'Comma separated, * wildcard supported. Leave empty to allow all models.' })}</span>
+                            </label>
+                        </div>
+
+                        <div className="form-control w-full mb-3">
+                            <label className="label">
+                                <span className="label-text">{t('user_token.denied_models', { defaultValue: 'Denied Models' })}</span>
+                            </label>
+                            <input
+                                type="text"
+                                className="input input-bordered w-full font-mono text-sm"
+                                placeholder="claude-opus-*, *-image*"
+                                value={editDeniedModels}
+                                onChange={e => setEditDeniedModels(e.target.value)}
+                            />
+                            <label className="label">
+                                <span className="label-text-alt text-gray-500">{t('user_token.hint_denied_models', { defaultValue: 'Denied rules take precedence over allowed rules.' })}</span>
+                            </label>
+                        </div>
+
+                        <div className="form-control w-full mb-3">
+                            <label className="label">
+                                <span className="label-text">{t('user_token.account_groups', { defaultValue: 'Account Groups' })}</span>
+                            </label>
+                            <input
+                                type="text"
+                                className="input input-bordered w-full font-mono text-sm"
+                                placeholder="team-a, team-b"
+                                value={editAccountGroups}
+                                onChange={e => setEditAccountGroups(e.target.value)}
+                            />
+                            <label className="label">
+                                <span className="label-text-alt text-gray-500">{t('user_token.hint_account_groups', { defaultValue: 'Only accounts in these groups serve this token. Leave empty to use all accounts.' })}</span>
+                            </label>
+                        </div>
+
+                        <div className="form-control w-full mb-3">
+                            <label className="label">
+                                <span className="label-text">{t('user_token.limits', { defaultValue: 'Budgets & Rate Limits' })}</span>
+                            </label>
+                            <div className="grid grid-cols-2 gap-2">
+                                <label className="flex flex-col gap-1">
+                                    <span className="text-xs text-gray-500">{t('user_token.budget_daily', { defaultValue: 'Daily Token Budget' })}</span>
+                                    <input
+                                        type="number"
+                                        min={0}
+                                        className="input input-bordered input-sm w-full"
+                                        value={editLimits.daily_token_limit}
+                                        onChange={e => setEditLimits({ ...editLimits, daily_token_limit: Math.max(0, parseInt(e.target.value) || 0) })}
+                                    />
+                                </label>
+                                <label className="flex flex-col gap-1">
+                                    <span className="text-xs text-gray-500">{t('user_token.budget_weekly', { defaultValue: 'Weekly Token Budget' })}</span>
+                                    <input
+                                        type="number"
+                                        min={0}
+                                        className="input input-bordered input-sm w-full"
+                                        value={editLimits.weekly_token_limit}
+                                        onChange={e => setEditLimits({ ...editLimits, weekly_token_limit: Math.max(0, parseInt(e.target.value) || 0) })}
+                                    />
+                                </label>
+                                <label className="flex flex-col gap-1">
+                                    <span className="text-xs text-gray-500">{t('user_token.budget_monthly', { defaultValue: 'Monthly Token Budget' })}</span>
+                                    <input
+                                        type="number"
+                                        min={0}
+                                        className="input input-bordered input-sm w-full"
+                                        value={editLimits.monthly_token_limit}
+                                        onChange={e => setEditLimits({ ...editLimits, monthly_token_limit: Math.max(0, parseInt(e.target.value) || 0) })}
+                                    />
+                                </label>
+                                <label className="flex flex-col gap-1">
+                                    <span className="text-xs text-gray-500">{t('user_token.max_rpm', { defaultValue: 'Requests / Minute' })}</span>
+                                    <input
+                                        type="number"
+                                        min={0}
+                                        className="input input-bordered input-sm w-full"
+                                        value={editLimits.max_rpm}
+                                        onChange={e => setEditLimits({ ...editLimits, max_rpm: Math.max(0, parseInt(e.target.value) || 0) })}
+                                    />
+                                </label>
+                                <label className="flex flex-col gap-1">
+                                    <span className="text-xs text-gray-500">{t('user_token.max_concurrent', { defaultValue: 'Concurrent Requests' })}</span>
+                                    <input
+                                        type="number"
+                                        min={0}
+                                        className="input input-bordered input-sm w-full"
+                                        value={editLimits.max_concurrent}
+                                        onChange={e => setEditLimits({ ...editLimits, max_concurrent: Math.max(0, parseInt(e.target.value) || 0) })}
+                                    />
+                                </label>
+                            </div>
+                            <label className="label">
+                                <span className="label-text-alt text-gray-500">{t('user_token.hint_limits', { defaultValue: '0 = Unlimited. Over-limit requests receive 429 with Retry-After.' })}</span>
+                            </label>
+                        </div>
+
                         <div className="modal-action">
                             <button className="px-4 py-2 hover:bg-gray-100 dark:hover:bg-base-200 rounded-lg text-sm transition-colors" onClick={() => setShowEditModal(false)}>
                                 {t('common.cancel', { defaultValue: 'Cancel' })}
@@ -1,5 +1,5 @@
 import { request as invoke } from '../utils/request';
-import { AppConfig } from '../types/config';
+import { AppConfig, ConfigValidationReport } from '../types/config';
 
 export async function loadConfig(): Promise<AppConfig> {
     return await invoke('load_config');
@@ -8,3 +8,8 @@ export async function loadConfig(): Promise<AppConfig> {
 export async function saveConfig(config: AppConfig): Promise<void> {
     return await invoke('save_config', { config });
 }
+
+/** 预检配置：返回校验问题与相对当前生效配置的差异 (不写盘) */
+export async function validateConfig(config: AppConfig): Promise<ConfigValidationReport> {
+    return await invoke('validate_config', { config });
+}
@@ -14,6 +14,7 @@ export interface Account {
     proxy_disabled_at?: number;
     protected_models?: string[];
     custom_label?: string;  // 用户自定义标签
+    groups?: string[];  // 所属账号分组
     validation_blocked?: boolean;
     validation_blocked_until?: number;
     validation_blocked_reason?: string;
@@ -54,6 +55,46 @@ export interface ModelQuota {
     supported_mime_types?: Record<string, boolean>;
 }
 
+/** 配额历史快照 */
+export interface QuotaSnapshot {
+    timestamp: number;
+    account_id: string;
+    email: string;
+    model: string;
+    percentage: number;
+    reset_time?: number;
+}
+
+/** 单账号单模型消耗预测 */
+export interface QuotaForecast {
+    account_id: string;
+    email: string;
+    model: string;
+    current_percentage: number;
+    burn_rate_per_hour: number; // 每小时消耗百分点
+    projected_exhaustion?: number; // Unix 秒
+    reset_time?: number;
+    exhausts_before_reset: boolean;
+    sample_count: number;
+}
+
+/** 账号池单模型消耗预测 */
+export interface PoolQuotaForecast {
+    model: string;
+    account_count: number;
+    total_remaining_percentage: number;
+    total_burn_rate_per_hour: number;
+    projected_exhaustion?: number;
+    next_reset?: number;
+    accounts_exhausting_before_reset: number;
+}
+
+export interface AccountGroup {
+    name: string;
+    description?: string;
+    account_ids: string[];
+}
+
 export interface DeviceProfile {
     machine_id: string;
     mac_machine_id: string;
@@ -12,11 +12,14 @@ export interface ProxyConfig {
     admin_password?: string;
     auto_start: boolean;
     custom_mapping?: Record<string, string>;
+    model_fallbacks?: Record<string, string[]>; // 模型降级链 (所有账号耗尽时按顺序切换)
     request_timeout: number;
     enable_logging: boolean;
     debug_logging?: DebugLoggingConfig;
     upstream_proxy: UpstreamProxyConfig;
     zai?: ZaiConfig;
+    providers?: UpstreamProviderConfig[]; // 通用 OpenAI / Anthropic 兼容上游
+    background_task_rules?: BackgroundTaskRule[]; // 后台任务识别规则 (按顺序匹配)
     scheduling?: StickySessionConfig;
     experimental?: ExperimentalConfig;
     user_agent_override?: string;
@@ -25,6 +28,92 @@ export interface ProxyConfig {
     global_system_prompt?: GlobalSystemPromptConfig;
     image_thinking_mode?: 'enabled' | 'disabled'; // [NEW] 图像思维模式开关
     proxy_pool?: ProxyPoolConfig;
+    upstream_endpoints?: UpstreamEndpoint[];
+    metrics?: MetricsConfig;
+    response_cache?: ResponseCacheConfig;
+    audit_log?: AuditLogConfig;
+    structured_outputs?: StructuredOutputConfig;
+    response_store?: ResponseStoreConfig;
+    image_store?: ImageStoreConfig;
+}
+
+/** 配置校验问题 */
+export interface ValidationIssue {
+    field: string;
+    message: string;
+    severity: 'error' | 'warning';
+}
+
+/** 配置差异项 (敏感字段以 *** 显示) */
+export interface ConfigChange {
+    path: string;
+    before: any;
+    after: any;
+    requires_restart: boolean;
+}
+
+export interface ConfigValidationReport {
+    valid: boolean;
+    issues: ValidationIssue[];
+    changes: ConfigChange[];
+}
+
+/** Prometheus 指标导出 (GET /metrics) */
+export interface MetricsConfig {
+    enabled: boolean;
+}
+
+/** 精确匹配响应缓存 (命中时回放上次的上游响应，不消耗配额) */
+export interface ResponseCacheConfig {
+    enabled: boolean;
+    ttl_secs: number;
+    max_size_mb: number;
+}
+
+/** 管理接口审计日志 */
+export interface AuditLogConfig {
+    enabled: boolean;
+    retention_days: number; // 0 表示永久保留
+}
+
+/** 结构化输出 (json_schema) 校验，仅作用于非流式请求 */
+export interface StructuredOutputConfig {
+    validate: boolean;
+    max_retries: number;
+}
+
+/** Responses API 响应存储 (previous_response_id 续接) */
+export interface ResponseStoreConfig {
+    enabled: boolean;
+    ttl_secs: number;
+}
+
+/** 生成图片存储 (图片接口 response_format=url 返回短期链接) */
+export interface ImageStoreConfig {
+    enabled: boolean;
+    ttl_secs: number;
+    max_size_mb: number;
+    public_base_url: string;
+}
+
+/** 审计记录 */
+export interface AuditEntry {
+    id: number;
+    timestamp: number;
+    actor: string;
+    source_ip?: string;
+    method: string;
+    endpoint: string;
+    payload?: string;
+    status: number;
+    success: boolean;
+    user_agent?: string;
+}
+
+/** 上游 v1internal 端点 (按顺序降级) */
+export interface UpstreamEndpoint {
+    url: string;
+    enabled: boolean;
 }
 
 // ============================================================================
@@ -96,6 +185,44 @@ export interface ZaiConfig {
     mcp: ZaiMcpConfig;
 }
 
+export type ProviderProtocol = 'openai' | 'anthropic';
+
+export type ProviderDispatchMode = 'exclusive' | 'pooled' | 'fallback';
+
+export interface UpstreamProviderConfig {
+    id: string;
+    enabled: boolean;
+    protocol: ProviderProtocol;
+    base_url: string;
+    api_key: string;
+    models: string[]; // 支持 * 通配符，为空表示匹配全部模型
+    model_mapping?: Record<string, string>;
+    dispatch_mode: ProviderDispatchMode;
+}
+
+export interface BackgroundTaskRule {
+    name: string;
+    enabled: boolean;
+    keywords: string[];
+    regex?: string;
+    max_length: number;
+    clients: string[]; // 客户端适配器名 (如 "opencode"，"default" 表示未匹配适配器)，为空表示全部
+    target_model: string;
+}
+
+export interface BackgroundRuleEvaluation {
+    rule: string;
+    outcome: 'matched' | 'disabled' | 'client_mismatch' | 'too_long' | 'invalid_regex' | 'no_match';
+    matched?: string;
+}
+
+export interface BackgroundRuleTestReport {
+    client: string;
+    message?: string;
+    matched?: { rule: string; target_model: string };
+    evaluations: BackgroundRuleEvaluation[];
+}
+
 export interface ScheduledWarmupConfig {
     enabled: boolean;
     monitored_models: string[];
@@ -190,11 +317,12 @@ export interface ProxyEntry {
     last_check_time?: number;
     is_healthy: boolean;
     latency?: number; // [NEW] 延迟 (毫秒)
+    weight?: number; // 权重 (加权/延迟感知策略使用, 默认 1)
 }
 
 // export type ProxyPoolMode = 'global' | 'per_account' | 'hybrid'; // [REMOVED]
 
-export type ProxySelectionStrategy = 'round_robin' | 'random' | 'priority' | 'least_connections' | 'weighted_round_robin';
+export type ProxySelectionStrategy = 'round_robin' | 'random' | 'priority' | 'least_connections' | 'weighted_round_robin' | 'latency_aware';
 
 export interface ProxyPoolConfig {
     enabled: boolean;
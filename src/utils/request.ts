@@ -14,6 +14,9 @@ const COMMAND_MAPPING: Record<string, { url: string; method: 'GET' | 'POST' | 'D
   'refresh_account_quota': { url: '/api/accounts/:accountId/quota', method: 'GET' },
   'refresh_all_quotas': { url: '/api/accounts/refresh', method: 'POST' },
   'reorder_accounts': { url: '/api/accounts/reorder', method: 'POST' },
+  'list_account_groups': { url: '/api/account-groups', method: 'GET' },
+  'save_account_group': { url: '/api/account-groups', method: 'POST' },
+  'delete_account_group': { url: '/api/account-groups/:name', method: 'DELETE' },
   'toggle_proxy_status': { url: '/api/accounts/:accountId/toggle-proxy', method: 'POST' },
   'warm_up_accounts': { url: '/api/accounts/warmup', method: 'POST' },
   'warm_up_all_accounts': { url: '/api/accounts/warmup', method: 'POST' },
@@ -45,6 +48,7 @@ const COMMAND_MAPPING: Record<string, { url: string; method: 'GET' | 'POST' | 'D
   'fetch_zai_models': { url: '/api/zai/models/fetch', method: 'POST' },
   'load_config': { url: '/api/config', method: 'GET' },
   'save_config': { url: '/api/config', method: 'POST' },
+  'validate_config': { url: '/api/config/validate', method: 'POST' },
   'get_proxy_stats': { url: '/api/proxy/stats', method: 'GET' },
   'set_proxy_monitor_enabled': { url: '/api/proxy/monitor/toggle', method: 'POST' },
 
@@ -53,6 +57,9 @@ const COMMAND_MAPPING: Record<string, { url: string; method: 'GET' | 'POST' | 'D
   'get_proxy_logs_count_filtered': { url: '/api/logs/count', method: 'GET' },
   'clear_proxy_logs': { url: '/api/logs/clear', method: 'POST' },
   'get_proxy_log_detail': { url: '/api/logs/:logId', method: 'GET' },
+  'replay_proxy_log': { url: '/api/logs/:logId/replay', method: 'POST' },
+  'get_proxy_log_replays': { url: '/api/logs/:logId/replays', method: 'GET' },
+  'get_audit_logs': { url: '/api/audit/logs', method: 'GET' },
 
   // Debug Console
   'enable_debug_console': { url: '/api/debug/enable', method: 'POST' },
@@ -86,6 +93,10 @@ const COMMAND_MAPPING: Record<string, { url: string; method: 'GET' | 'POST' | 'D
   'get_token_stats_account_trend_hourly': { url: '/api/stats/token/account-trend/hourly', method: 'GET' },
   'get_token_stats_account_trend_daily': { url: '/api/stats/token/account-trend/daily', method: 'GET' },
   'clear_token_stats': { url: '/api/stats/token/clear', method: 'POST' },
+  'get_quota_history': { url: '/api/stats/quota/history', method: 'GET' },
+  'get_quota_forecast': { url: '/api/stats/quota/forecast', method: 'GET' },
+  'get_pool_quota_forecast': { url: '/api/stats/quota/pool-forecast', method: 'GET' },
+  'test_background_task_rules': { url: '/api/proxy/background-rules/test', method: 'POST' },
 
   // System
   'get_data_dir_path': { url: '/api/system/data-dir', method: 'GET' },
@@ -155,6 +166,9 @@ const COMMAND_MAPPING: Record<string, { url: string; method: 'GET' | 'POST' | 'D
   'renew_user_token': { url: '/api/user-tokens/:id/renew', method: 'POST' },
   'delete_user_token': { url: '/api/user-tokens/:id', method: 'DELETE' },
   'update_user_token': { url: '/api/user-tokens/:id', method: 'PATCH' },
+  'list_admin_keys': { url: '/api/admin-keys', method: 'GET' },
+  'create_admin_key': { url: '/api/admin-keys', method: 'POST' },
+  'revoke_admin_key': { url: '/api/admin-keys/:id', method: 'DELETE' },
 
   // Proxy Pool (Web Mode Fix)
   'get_proxy_pool_config': { url: '/api/proxy/pool/config', method: 'GET' },